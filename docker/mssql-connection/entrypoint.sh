@@ -0,0 +1,16 @@
+#!/bin/bash
+
+# SQL Server's image has no init directory, so start the server and run the
+# setup scripts once it accepts connections
+/opt/mssql/bin/sqlservr &
+
+for i in {1..60}; do
+    /opt/mssql-tools18/bin/sqlcmd -S localhost -U sa -P "$MSSQL_SA_PASSWORD" -C -Q "SELECT 1" > /dev/null 2>&1 && break
+    sleep 1
+done
+
+for script in /docker-entrypoint-initdb.d/*.sql; do
+    /opt/mssql-tools18/bin/sqlcmd -S localhost -U sa -P "$MSSQL_SA_PASSWORD" -C -i "$script"
+done
+
+wait
@@ -0,0 +1,70 @@
+IF DB_ID('mssql-connection') IS NULL CREATE DATABASE [mssql-connection];
+GO
+
+USE [mssql-connection];
+GO
+
+DROP TABLE IF EXISTS [dbo].[all_native_data_types];
+
+CREATE TABLE [dbo].[all_native_data_types] (
+    [id] INT IDENTITY(1,1) PRIMARY KEY,
+    [tinyint_col] TINYINT,
+    [smallint_col] SMALLINT,
+    [int_col] INT,
+    [bigint_col] BIGINT,
+    [bit_col] BIT,
+    [decimal_col] DECIMAL(10,2),
+    [numeric_col] NUMERIC(10,2),
+    [money_col] MONEY,
+    [smallmoney_col] SMALLMONEY,
+    [float_col] FLOAT,
+    [real_col] REAL,
+    [date_col] DATE,
+    [time_col] TIME,
+    [datetime_col] DATETIME,
+    [datetime2_col] DATETIME2,
+    [smalldatetime_col] SMALLDATETIME,
+    [datetimeoffset_col] DATETIMEOFFSET,
+    [char_col] CHAR(10),
+    [varchar_col] VARCHAR(255),
+    [text_col] TEXT,
+    [nchar_col] NCHAR(10),
+    [nvarchar_col] NVARCHAR(255),
+    [ntext_col] NTEXT,
+    [binary_col] BINARY(10),
+    [varbinary_col] VARBINARY(255),
+    [uniqueidentifier_col] UNIQUEIDENTIFIER,
+    [xml_col] XML
+);
+
+INSERT INTO [dbo].[all_native_data_types] (
+    [tinyint_col], [smallint_col], [int_col], [bigint_col], [bit_col],
+    [decimal_col], [numeric_col], [money_col], [smallmoney_col], [float_col],
+    [real_col], [date_col], [time_col], [datetime_col], [datetime2_col],
+    [smalldatetime_col], [datetimeoffset_col], [char_col], [varchar_col], [text_col],
+    [nchar_col], [nvarchar_col], [ntext_col], [binary_col], [varbinary_col],
+    [uniqueidentifier_col], [xml_col]
+) VALUES (
+    255, 32767, 2147483647, 9223372036854775807, 1,
+    12345.67, 12345.67, 12345.67, 123.45, 123456789.123456,
+    123.45, '2024-05-28', '12:34:56', '2024-05-28 12:34:56', '2024-05-28 12:34:56',
+    '2024-05-28 12:34:56', '2024-05-28 12:34:56 +02:00', 'char_data', 'varchar_data', 'text_data',
+    N'nchar_data', N'nvarchar_data', N'ntext_data', CAST('bin_data' AS BINARY(10)), CAST('varbin_data' AS VARBINARY(255)),
+    '6F9619FF-8B86-D011-B42D-00C04FC964FF', '<key>value</key>'
+);
+
+INSERT INTO [dbo].[all_native_data_types] DEFAULT VALUES;
+GO
+
+-- read-only connections run in snapshot transactions with a login that can
+-- only read
+ALTER DATABASE [mssql-connection] SET ALLOW_SNAPSHOT_ISOLATION ON;
+
+IF SUSER_ID('reader') IS NULL
+    CREATE LOGIN [reader] WITH PASSWORD = 'yourStrong(!)Password', CHECK_POLICY = OFF;
+
+IF USER_ID('reader') IS NULL
+    CREATE USER [reader] FOR LOGIN [reader];
+
+ALTER ROLE [db_datareader] ADD MEMBER [reader];
+GO
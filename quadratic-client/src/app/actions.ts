@@ -174,6 +174,11 @@ export const rerunAction = {
   isAvailable: isAvailableBecauseCanEditFile,
 };
 
+export const recalculateAction = {
+  label: 'Recalculate volatile formulas (eg, RAND and NOW)',
+  isAvailable: isAvailableBecauseCanEditFile,
+};
+
 export const rerunSheetAction = {
   label: 'Run all code cells in the current sheet',
   isAvailable: isAvailableBecauseCanEditFile,
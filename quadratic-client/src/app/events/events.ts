@@ -2,6 +2,7 @@ import { EditingCell } from '@/app/gridGL/HTMLGrid/hoverCell/HoverCell';
 import { PanMode } from '@/app/gridGL/pixiApp/PixiAppSettings';
 import { SheetPosTS } from '@/app/gridGL/types/size';
 import {
+  CommentThread,
   JsCodeCell,
   JsHtmlOutput,
   JsRenderBorders,
@@ -11,6 +12,7 @@ import {
   JsRowHeight,
   JsSheetFill,
   JsValidationWarning,
+  Protection,
   Selection,
   SheetBounds,
   SheetInfo,
@@ -19,7 +21,11 @@ import {
 import type { CodeRun } from '@/app/web-workers/CodeRun';
 import { LanguageState } from '@/app/web-workers/languageTypes';
 import { MultiplayerState } from '@/app/web-workers/multiplayerWebWorker/multiplayerClientMessages';
-import { CellEdit, MultiplayerUser } from '@/app/web-workers/multiplayerWebWorker/multiplayerTypes';
+import {
+  CellEdit,
+  MultiplayerUser,
+  ReceiveCommentMention,
+} from '@/app/web-workers/multiplayerWebWorker/multiplayerTypes';
 import {
   CoreClientImage,
   CoreClientImportProgress,
@@ -88,6 +94,7 @@ interface EventTypes {
   multiplayerChangeSheet: () => void;
   multiplayerCursor: () => void;
   multiplayerState: (state: MultiplayerState) => void;
+  commentMention: (mention: ReceiveCommentMention) => void;
   multiplayerCellEdit: (cellEdit: CellEdit, player: MultiplayerUser) => void;
   multiplayerFollow: () => void;
   multiplayerCodeRunning: (multiplayerUser: MultiplayerUser) => void;
@@ -108,6 +115,9 @@ interface EventTypes {
   insertCodeEditorText: (text: string) => void;
 
   sheetValidations: (sheetId: string, validations: Validation[]) => void;
+  sheetComments: (sheetId: string, comments: CommentThread[]) => void;
+  sheetProtections: (sheetId: string, protections: Protection[]) => void;
+  protectedEdit: (sheetId: string, protection: Protection) => void;
   renderValidationWarnings: (
     sheetId: string,
     hashX: number | undefined,
@@ -4,7 +4,12 @@ import { inlineEditorHandler } from '@/app/gridGL/HTMLGrid/inlineEditor/inlineEd
 import { inlineEditorKeyboard } from '@/app/gridGL/HTMLGrid/inlineEditor/inlineEditorKeyboard';
 import { CURSOR_THICKNESS } from '@/app/gridGL/UI/Cursor';
 import { CellAlign, CellVerticalAlign, CellWrap } from '@/app/quadratic-core-types';
-import { provideCompletionItems, provideHover } from '@/app/quadratic-rust-client/quadratic_rust_client';
+import {
+  provideCompletionItems,
+  provideDocumentFormattingEdits,
+  provideHover,
+  provideSignatureHelp,
+} from '@/app/quadratic-rust-client/quadratic_rust_client';
 import { FormulaLanguageConfig, FormulaTokenizerConfig } from '@/app/ui/menus/CodeEditor/FormulaLanguageModel';
 import * as monaco from 'monaco-editor';
 import { editor } from 'monaco-editor';
@@ -316,6 +321,11 @@ class InlineEditorMonaco {
       provideCompletionItems,
     });
     monaco.languages.registerHoverProvider('Formula', { provideHover });
+    monaco.languages.registerSignatureHelpProvider('Formula', {
+      provideSignatureHelp,
+      signatureHelpTriggerCharacters: ['(', ','],
+    });
+    monaco.languages.registerDocumentFormattingEditProvider('Formula', { provideDocumentFormattingEdits });
 
     this.editor = editor.create(div.childNodes[0] as HTMLDivElement, {
       automaticLayout: false,
@@ -35,6 +35,12 @@ export function keyboardCode(
     return true;
   }
 
+  // Recalculate volatile formulas
+  if (matchShortcut('recalculate', event)) {
+    quadraticCore.recalculate(sheets.getCursorPosition());
+    return true;
+  }
+
   // Insert cell reference
   if (editorInteractionState.showCodeEditor && matchShortcut('insert_cell_reference', event)) {
     insertCellRef(editorInteractionState);
@@ -31,6 +31,7 @@ export const ActionEnum = z.enum([
   'execute_code',
   'rerun_sheet_code',
   'rerun_all_code',
+  'recalculate',
   'insert_cell_reference',
   'move_cursor_up',
   'jump_cursor_content_top',
@@ -224,6 +224,13 @@ export const defaultShortcuts: Shortcuts = [
       windows: ['Ctrl + Shift + Enter'],
     },
   },
+  {
+    action: 'recalculate',
+    shortcuts: {
+      mac: ['F9'],
+      windows: ['F9'],
+    },
+  },
   {
     action: 'insert_cell_reference',
     shortcuts: {
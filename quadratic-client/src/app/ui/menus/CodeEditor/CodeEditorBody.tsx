@@ -1,5 +1,10 @@
 import { CodeCellLanguage } from '@/app/quadratic-core-types';
-import { provideCompletionItems, provideHover } from '@/app/quadratic-rust-client/quadratic_rust_client';
+import {
+  provideCompletionItems,
+  provideDocumentFormattingEdits,
+  provideHover,
+  provideSignatureHelp,
+} from '@/app/quadratic-rust-client/quadratic_rust_client';
 import Editor, { Monaco } from '@monaco-editor/react';
 import * as monaco from 'monaco-editor';
 import { useCallback, useEffect, useRef, useState } from 'react';
@@ -157,6 +162,11 @@ export const CodeEditorBody = (props: Props) => {
           provideCompletionItems,
         });
         monaco.languages.registerHoverProvider('formula', { provideHover });
+        monaco.languages.registerSignatureHelpProvider('formula', {
+          provideSignatureHelp,
+          signatureHelpTriggerCharacters: ['(', ','],
+        });
+        monaco.languages.registerDocumentFormattingEditProvider('formula', { provideDocumentFormattingEdits });
         registered.Formula = true;
       }
 
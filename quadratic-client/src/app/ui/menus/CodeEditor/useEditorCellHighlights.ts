@@ -1,7 +1,12 @@
 import { Coordinate } from '@/app/gridGL/types/size';
 import { parsePython as parseCellsAccessed } from '@/app/helpers/parseEditorPythonCell';
 import { CodeCellLanguage, SheetRect } from '@/app/quadratic-core-types';
-import { parseFormula } from '@/app/quadratic-rust-client/quadratic_rust_client';
+import { quadraticCore } from '@/app/web-workers/quadraticCore/quadraticCore';
+import {
+  parseFormula,
+  provideDiagnostics,
+  setLspContext,
+} from '@/app/quadratic-rust-client/quadratic_rust_client';
 import monaco, { editor } from 'monaco-editor';
 import { useEffect, useRef } from 'react';
 import { useRecoilValue } from 'recoil';
@@ -97,6 +102,14 @@ export const useEditorCellHighlights = (
       }
 
       if (language === 'Formula') {
+        setLspContext(await quadraticCore.getLspContext());
+        const markers = await provideDiagnostics(
+          modelValue,
+          editorInteractionState.selectedCell.x,
+          editorInteractionState.selectedCell.y
+        );
+        monacoInst.editor.setModelMarkers(model, 'formula', markers);
+
         parsed = (await parseFormula(
           modelValue,
           editorInteractionState.selectedCell.x,
@@ -1,6 +1,6 @@
 import { sheets } from '@/app/grid/controller/Sheets';
 import { quadraticCore } from '@/app/web-workers/quadraticCore/quadraticCore';
-import { rerunAction, rerunCellAction, rerunSheetAction } from '../../../../actions';
+import { recalculateAction, rerunAction, rerunCellAction, rerunSheetAction } from '../../../../actions';
 import { KeyboardSymbols } from '../../../../helpers/keyboardSymbols';
 import { CommandGroup, CommandPaletteListItem } from '../CommandPaletteListItem';
 
@@ -58,6 +58,19 @@ const commands: CommandGroup = {
         );
       },
     },
+    {
+      label: recalculateAction.label,
+      isAvailable: recalculateAction.isAvailable,
+      Component: (props) => {
+        return (
+          <CommandPaletteListItem
+            {...props}
+            action={() => quadraticCore.recalculate(sheets.getCursorPosition())}
+            shortcut="F9"
+          />
+        );
+      },
+    },
   ],
 };
 
@@ -120,6 +120,10 @@ export class Multiplayer {
         events.emit('needRefresh', 'force');
         break;
 
+      case 'multiplayerClientCommentMention':
+        events.emit('commentMention', e.data.mention);
+        break;
+
       case 'multiplayerClientRefreshJwt':
         await this.addJwtCookie(true);
         this.send({ type: 'clientMultiplayerRefreshJwt', id: e.data.id });
@@ -1,5 +1,5 @@
 import { User } from '@auth0/auth0-spa-js';
-import { CellEdit, ReceiveRoom, UserUpdate } from './multiplayerTypes';
+import { CellEdit, ReceiveCommentMention, ReceiveRoom, UserUpdate } from './multiplayerTypes';
 
 export type MultiplayerState =
   | 'startup'
@@ -84,6 +84,11 @@ export interface MultiplayerClientReload {
   type: 'multiplayerClientReload';
 }
 
+export interface MultiplayerClientCommentMention {
+  type: 'multiplayerClientCommentMention';
+  mention: ReceiveCommentMention;
+}
+
 export interface MultiplayerClientRefreshJwt {
   type: 'multiplayerClientRefreshJwt';
   id: number;
@@ -100,7 +105,8 @@ export type MultiplayerClientMessage =
   | MultiplayerClientUsersInRoom
   | MultiplayerClientUserUpdate
   | MultiplayerClientReload
-  | MultiplayerClientRefreshJwt;
+  | MultiplayerClientRefreshJwt
+  | MultiplayerClientCommentMention;
 
 export type ClientMultiplayerMessage =
   | ClientMultiplayerInit
@@ -1,4 +1,4 @@
-import { ReceiveTransaction } from './multiplayerTypes';
+import { ReceiveCatchUp, ReceiveTransaction } from './multiplayerTypes';
 
 export interface MultiplayerCoreSequenceNum {
   type: 'multiplayerCoreSequenceNum';
@@ -16,6 +16,11 @@ export interface MultiplayerCoreReceiveTransactions {
   transactions: ReceiveTransaction[];
 }
 
+export interface MultiplayerCoreReceiveCatchUp {
+  type: 'multiplayerCoreReceiveCatchUp';
+  catchUp: ReceiveCatchUp;
+}
+
 export interface MultiplayerCoreReceiveTransaction {
   type: 'multiplayerCoreReceiveTransaction';
   transaction: ReceiveTransaction;
@@ -34,6 +39,7 @@ export interface CoreMultiplayerRequestTransactions {
 export type MultiplayerCoreMessage =
   | MultiplayerCoreSequenceNum
   | MultiplayerCoreReceiveTransactions
+  | MultiplayerCoreReceiveCatchUp
   | MultiplayerCoreReceiveTransaction
   | MultiplayerCoreReceiveCurrentTransaction;
 
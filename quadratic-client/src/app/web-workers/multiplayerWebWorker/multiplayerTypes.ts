@@ -90,7 +90,7 @@ export interface ReceiveTransaction {
   type: 'Transaction';
   id: string;
   file_id: string;
-  operations: string | Buffer;
+  operations: string | Uint8Array;
   sequence_num: number;
 }
 
@@ -99,7 +99,7 @@ export interface SendTransaction {
   id: string;
   session_id: string;
   file_id: string;
-  operations: string;
+  operations: string | Uint8Array;
 }
 
 export interface SendGetTransactions {
@@ -114,6 +114,14 @@ export interface ReceiveTransactions {
   transactions: ReceiveTransaction[];
 }
 
+export interface ReceiveCatchUp {
+  type: 'CatchUp';
+  file_id: string;
+  checkpoint_sequence_num: number;
+  checkpoint_url: string;
+  transactions: ReceiveTransaction[];
+}
+
 export interface Heartbeat {
   type: 'Heartbeat';
   session_id: string;
@@ -129,6 +137,17 @@ export interface ReceiveCurrentTransaction {
   sequence_num: number;
 }
 
+export interface ReceiveCommentMention {
+  type: 'CommentMention';
+  file_id: string;
+  sheet_id: string;
+  thread_id: string;
+  comment_id: string;
+  author: string;
+  body: string;
+  mentions: string[];
+}
+
 export interface ReceiveError {
   type: 'Error';
   error: string | Record<string, string[]>;
@@ -141,8 +160,10 @@ export type ReceiveMessages =
   | ReceiveTransaction
   | ReceiveEmpty
   | ReceiveTransactions
+  | ReceiveCatchUp
   | ReceiveEnterRoom
   | ReceiveError
-  | ReceiveCurrentTransaction;
+  | ReceiveCurrentTransaction
+  | ReceiveCommentMention;
 
 export type MultiplayerServerMessage = SendTransaction | SendEnterRoom | SendGetTransactions;
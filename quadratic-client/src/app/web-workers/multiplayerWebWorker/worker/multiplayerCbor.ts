@@ -0,0 +1,19 @@
+/**
+ * Binary messages with the quadratic-multiplayer server.
+ *
+ * The server sends binary CBOR messages when the websocket negotiates the
+ * `quadratic-binary` subprotocol. They are encoded and decoded by
+ * quadratic-core's `compression` functions, the same as on the server, so the
+ * core wasm must be loaded before connecting.
+ */
+
+import initCore from '@/app/quadratic-core/quadratic_core';
+
+export { decodeCbor, encodeCbor } from '@/app/quadratic-core/quadratic_core';
+
+export const BINARY_PROTOCOL = 'quadratic-binary';
+export const JSON_PROTOCOL = 'quadratic-json';
+
+export const initCbor = async () => {
+  await initCore();
+};
@@ -4,7 +4,7 @@
 
 import { debugWebWorkersMessages } from '@/app/debugFlags';
 import { ClientMultiplayerMessage, MultiplayerClientMessage, MultiplayerState } from '../multiplayerClientMessages';
-import { MessageUserUpdate, ReceiveRoom } from '../multiplayerTypes';
+import { MessageUserUpdate, ReceiveCommentMention, ReceiveRoom } from '../multiplayerTypes';
 import { multiplayerCore } from './multiplayerCore';
 import { cellEditDefault, multiplayerServer } from './multiplayerServer';
 
@@ -93,6 +93,13 @@ class MultiplayerClient {
     });
   }
 
+  sendCommentMention(mention: ReceiveCommentMention) {
+    this.send({
+      type: 'multiplayerClientCommentMention',
+      mention,
+    });
+  }
+
   sendState(data: MultiplayerState) {
     this.send({
       type: 'multiplayerClientState',
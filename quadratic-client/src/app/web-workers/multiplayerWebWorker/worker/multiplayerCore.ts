@@ -4,7 +4,7 @@
 
 import { debugWebWorkersMessages } from '@/app/debugFlags';
 import { CoreMultiplayerMessage, MultiplayerCoreMessage } from '../multiplayerCoreMessages';
-import { ReceiveTransaction, ReceiveTransactions } from '../multiplayerTypes';
+import { ReceiveCatchUp, ReceiveTransaction, ReceiveTransactions } from '../multiplayerTypes';
 import { multiplayerServer } from './multiplayerServer';
 
 class MultiplayerCore {
@@ -57,6 +57,13 @@ class MultiplayerCore {
       transactions: receive_transactions.transactions,
     });
   }
+
+  receiveCatchUp(catchUp: ReceiveCatchUp) {
+    this.send({
+      type: 'multiplayerCoreReceiveCatchUp',
+      catchUp,
+    });
+  }
 }
 
 export const multiplayerCore = new MultiplayerCore();
@@ -22,6 +22,7 @@ import {
   UserUpdate,
   Version,
 } from '../multiplayerTypes';
+import { BINARY_PROTOCOL, JSON_PROTOCOL, decodeCbor, encodeCbor, initCbor } from './multiplayerCbor';
 import { multiplayerClient } from './multiplayerClient';
 import { multiplayerCore } from './multiplayerCore';
 
@@ -122,7 +123,11 @@ export class MultiplayerServer {
       await multiplayerClient.sendRefreshJwt();
     }
 
-    this.websocket = new WebSocket(import.meta.env.VITE_QUADRATIC_MULTIPLAYER_URL);
+    // prefer binary CBOR messages, falling back to JSON if the server doesn't
+    // support them
+    await initCbor();
+    this.websocket = new WebSocket(import.meta.env.VITE_QUADRATIC_MULTIPLAYER_URL, [BINARY_PROTOCOL, JSON_PROTOCOL]);
+    this.websocket.binaryType = 'arraybuffer';
     this.websocket.addEventListener('message', this.handleMessage);
 
     this.websocket.addEventListener('close', () => {
@@ -207,7 +212,7 @@ export class MultiplayerServer {
         file_id: this.fileId,
         update: this.userUpdate,
       };
-      this.websocket!.send(JSON.stringify(message));
+      this.websocket!.send(this.encode(message));
       this.userUpdate = {};
       this.lastHeartbeat = now;
     }
@@ -220,7 +225,7 @@ export class MultiplayerServer {
         session_id: this.sessionId,
         file_id: this.fileId!,
       };
-      this.websocket!.send(JSON.stringify(heartbeat));
+      this.websocket!.send(this.encode(heartbeat));
       this.lastHeartbeat = now;
     }
   };
@@ -229,8 +234,10 @@ export class MultiplayerServer {
    * Receive Messages from Multiplayer Server *
    ********************************************/
 
-  private handleMessage = (e: MessageEvent<string>) => {
-    const data: ReceiveMessages = JSON.parse(e.data);
+  private handleMessage = (e: MessageEvent<string | ArrayBuffer>) => {
+    const data = (
+      typeof e.data === 'string' ? JSON.parse(e.data) : decodeCbor(new Uint8Array(e.data))
+    ) as ReceiveMessages;
     switch (data.type) {
       case 'UsersInRoom':
         this.receiveUsersInRoom(data);
@@ -248,6 +255,10 @@ export class MultiplayerServer {
         multiplayerCore.receiveTransactions(data);
         break;
 
+      case 'CatchUp':
+        multiplayerCore.receiveCatchUp(data);
+        break;
+
       case 'EnterRoom':
         if (data.file_id !== this.fileId) throw new Error('Expected file_id to match in EnterRoom');
         multiplayerCore.receiveCurrentTransaction(data.sequence_num);
@@ -257,6 +268,10 @@ export class MultiplayerServer {
         multiplayerCore.receiveCurrentTransaction(data.sequence_num);
         break;
 
+      case 'CommentMention':
+        multiplayerClient.sendCommentMention(data);
+        break;
+
       case 'Error':
         if (data.error_level === 'Error') {
           // If the server is missing transactions, reload the page
@@ -289,9 +304,18 @@ export class MultiplayerServer {
     multiplayerClient.sendUsersInRoom(room);
   }
 
+  // whether the server agreed to binary CBOR messages
+  private get binary(): boolean {
+    return this.websocket?.protocol === BINARY_PROTOCOL;
+  }
+
+  private encode(message: MultiplayerServerMessage | MessageUserUpdate | Heartbeat): string | Uint8Array {
+    return this.binary ? encodeCbor(message) : JSON.stringify(message);
+  }
+
   private send(message: MultiplayerServerMessage) {
     if (!this.websocket) throw new Error('Expected websocket to be defined in sendTransaction');
-    this.websocket.send(JSON.stringify(message));
+    this.websocket.send(this.encode(message));
   }
 
   sendTransaction(transactionMessage: CoreMultiplayerTransaction) {
@@ -305,7 +329,9 @@ export class MultiplayerServer {
       id: transactionMessage.transaction_id,
       session_id: this.sessionId!,
       file_id: this.fileId!,
-      operations: Buffer.from(transactionMessage.operations).toString('base64'),
+      operations: this.binary
+        ? new Uint8Array(transactionMessage.operations)
+        : Buffer.from(transactionMessage.operations).toString('base64'),
     };
     this.send(message);
   }
@@ -5,6 +5,8 @@ import {
   CellVerticalAlign,
   CellWrap,
   CodeCellLanguage,
+  CommentThread,
+  EditUser,
   Format,
   JsCodeCell,
   JsHtmlOutput,
@@ -16,6 +18,7 @@ import {
   JsSheetFill,
   JsValidationWarning,
   MinMax,
+  Protection,
   SearchOptions,
   Selection,
   SheetBounds,
@@ -474,6 +477,11 @@ export interface ClientCoreRerunCodeCells {
   cursor: string;
 }
 
+export interface ClientCoreRecalculate {
+  type: 'clientCoreRecalculate';
+  cursor: string;
+}
+
 export interface ClientCoreSetRegionBorders {
   type: 'clientCoreSetRegionBorders';
   sheetId: string;
@@ -516,6 +524,11 @@ export interface ClientCoreUpdateValidation {
   cursor: string;
 }
 
+export interface ClientCoreSetEditUser {
+  type: 'clientCoreSetEditUser';
+  editUser: EditUser;
+}
+
 export interface ClientCoreRemoveValidation {
   type: 'clientCoreRemoveValidation';
   sheetId: string;
@@ -899,6 +912,24 @@ export interface CoreClientSheetValidations {
   validations: Validation[];
 }
 
+export interface CoreClientSheetComments {
+  type: 'coreClientSheetComments';
+  sheetId: string;
+  comments: CommentThread[];
+}
+
+export interface CoreClientSheetProtections {
+  type: 'coreClientSheetProtections';
+  sheetId: string;
+  protections: Protection[];
+}
+
+export interface CoreClientProtectedEdit {
+  type: 'coreClientProtectedEdit';
+  sheetId: string;
+  protection: Protection;
+}
+
 export interface CoreClientGetValidationFromPos {
   type: 'coreClientGetValidationFromPos';
   id: number;
@@ -919,6 +950,17 @@ export interface CoreClientGetValidationList {
   validations: string[];
 }
 
+export interface ClientCoreGetLspContext {
+  type: 'clientCoreGetLspContext';
+  id: number;
+}
+
+export interface CoreClientGetLspContext {
+  type: 'coreClientGetLspContext';
+  id: number;
+  context: string;
+}
+
 export interface ClientCoreGetDisplayCell {
   type: 'clientCoreGetDisplayCell';
   sheetId: string;
@@ -999,6 +1041,7 @@ export type ClientCoreMessage =
   | ClientCoreExport
   | ClientCoreSearch
   | ClientCoreRerunCodeCells
+  | ClientCoreRecalculate
   | ClientCoreHasRenderCells
   | ClientCoreCopyToClipboard
   | ClientCoreCutToClipboard
@@ -1026,10 +1069,12 @@ export type ClientCoreMessage =
   | ClientCoreGetValidation
   | ClientCoreGetValidations
   | ClientCoreUpdateValidation
+  | ClientCoreSetEditUser
   | ClientCoreRemoveValidation
   | ClientCoreRemoveValidations
   | ClientCoreGetValidationFromPos
   | ClientCoreGetValidationList
+  | ClientCoreGetLspContext
   | ClientCoreGetDisplayCell
   | ClientCoreValidateInput;
 
@@ -1087,9 +1132,13 @@ export type CoreClientMessage =
   | CoreClientOfflineTransactionsApplied
   | CoreClientGetValidations
   | CoreClientSheetValidations
+  | CoreClientSheetComments
+  | CoreClientSheetProtections
+  | CoreClientProtectedEdit
   | CoreClientGetValidationFromPos
   | CoreClientResizeRowHeights
   | CoreClientGetValidationList
+  | CoreClientGetLspContext
   | CoreClientGetDisplayCell
   | CoreClientRenderValidationWarnings
   | CoreClientResizeRowHeights
@@ -16,6 +16,7 @@ import {
   CellVerticalAlign,
   CellWrap,
   CodeCellLanguage,
+  EditUser,
   Format,
   JsCodeCell,
   JsRenderCell,
@@ -51,6 +52,7 @@ import {
   CoreClientGetJwt,
   CoreClientGetRenderCell,
   CoreClientGetRowsBounds,
+  CoreClientGetLspContext,
   CoreClientGetValidationList,
   CoreClientHasRenderCells,
   CoreClientLoad,
@@ -171,6 +173,15 @@ class QuadraticCore {
     } else if (e.data.type === 'coreClientSheetValidations') {
       events.emit('sheetValidations', e.data.sheetId, e.data.validations);
       return;
+    } else if (e.data.type === 'coreClientSheetComments') {
+      events.emit('sheetComments', e.data.sheetId, e.data.comments);
+      return;
+    } else if (e.data.type === 'coreClientSheetProtections') {
+      events.emit('sheetProtections', e.data.sheetId, e.data.protections);
+      return;
+    } else if (e.data.type === 'coreClientProtectedEdit') {
+      events.emit('protectedEdit', e.data.sheetId, e.data.protection);
+      return;
     } else if (e.data.type === 'coreClientResizeRowHeights') {
       events.emit('resizeRowHeights', e.data.sheetId, e.data.rowHeights);
       return;
@@ -694,6 +705,10 @@ class QuadraticCore {
     });
   }
 
+  recalculate(cursor: string) {
+    this.send({ type: 'clientCoreRecalculate', cursor });
+  }
+
   //#region Sheet Operations
 
   addSheet(cursor?: string) {
@@ -1049,6 +1064,10 @@ class QuadraticCore {
     });
   }
 
+  setEditUser(editUser: EditUser) {
+    this.send({ type: 'clientCoreSetEditUser', editUser });
+  }
+
   removeValidation(sheetId: string, validationId: string, cursor: string) {
     this.send({
       type: 'clientCoreRemoveValidation',
@@ -1082,6 +1101,18 @@ class QuadraticCore {
     });
   }
 
+  // Returns the JSON-serialized sheet and table names used by the formula
+  // language server
+  getLspContext(): Promise<string> {
+    return new Promise((resolve) => {
+      const id = this.id++;
+      this.waitingForResponse[id] = (message: CoreClientGetLspContext) => {
+        resolve(message.context);
+      };
+      this.send({ type: 'clientCoreGetLspContext', id });
+    });
+  }
+
   validateInput(sheetId: string, x: number, y: number, input: string): Promise<string | undefined> {
     return new Promise((resolve) => {
       const id = this.id++;
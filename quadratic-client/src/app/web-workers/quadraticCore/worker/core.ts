@@ -12,6 +12,7 @@ import {
   CellVerticalAlign,
   CellWrap,
   CodeCellLanguage,
+  EditUser,
   Format,
   JsCodeCell,
   JsCodeResult,
@@ -25,6 +26,7 @@ import {
 } from '@/app/quadratic-core-types';
 import initCore, { GridController } from '@/app/quadratic-core/quadratic_core';
 import {
+  MultiplayerCoreReceiveCatchUp,
   MultiplayerCoreReceiveTransaction,
   MultiplayerCoreReceiveTransactions,
 } from '@/app/web-workers/multiplayerWebWorker/multiplayerCoreMessages';
@@ -337,6 +339,41 @@ class Core {
     });
   }
 
+  // Replaces the grid with the server's checkpoint and the transactions after
+  // it, while keeping our unsaved transactions.
+  receiveCatchUp(message: MultiplayerCoreReceiveCatchUp) {
+    return new Promise((resolve) => {
+      this.clientQueue.push(async () => {
+        if (!this.gridController) throw new Error('Expected gridController to be defined');
+        const { checkpoint_url, checkpoint_sequence_num, transactions } = message.catchUp;
+
+        const res = await fetch(checkpoint_url);
+        if (!res.ok) throw new Error(`Unable to download checkpoint: ${res.status}`);
+        const checkpoint = new Uint8Array(await res.arrayBuffer());
+
+        const transactionsJson = JSON.stringify(
+          transactions.map((data) => ({
+            id: data.id,
+            file_id: data.file_id,
+            sequence_num: data.sequence_num,
+            operations: Array.from(
+              typeof data.operations === 'string' ? Buffer.from(data.operations, 'base64') : data.operations
+            ),
+          }))
+        );
+        this.gridController.receiveCatchUp(checkpoint, checkpoint_sequence_num, transactionsJson);
+        transactions.forEach((data) => offline.markTransactionSent(data.id));
+
+        if (await offline.unsentTransactionsCount()) {
+          coreClient.sendMultiplayerState('syncing');
+        } else {
+          coreClient.sendMultiplayerState('connected');
+        }
+        resolve(undefined);
+      });
+    });
+  }
+
   summarizeSelection(message: ClientCoreSummarizeSelection): Promise<SummarizeSelectionResult | undefined> {
     return new Promise((resolve) => {
       this.clientQueue.push(() => {
@@ -888,6 +925,11 @@ class Core {
     this.gridController.connectionComplete(transactionId, data as Uint8Array, std_out, std_err, extra);
   }
 
+  connectionCancelled(transactionId: string) {
+    if (!this.gridController) throw new Error('Expected gridController to be defined');
+    this.gridController.connectionCancelled(transactionId);
+  }
+
   getCells(transactionId: string, x: number, y: number, w: number, h?: number, sheet?: string, lineNumber?: number) {
     if (!this.gridController) throw new Error('Expected gridController to be defined');
     return this.gridController.calculationGetCells(transactionId, x, y, w, h, sheet, lineNumber);
@@ -921,6 +963,11 @@ class Core {
     }
   }
 
+  recalculate(cursor?: string) {
+    if (!this.gridController) throw new Error('Expected gridController to be defined');
+    this.gridController.recalculate(cursor);
+  }
+
   cancelExecution(transactionId: string) {
     if (!this.gridController) throw new Error('Expected gridController to be defined');
     const codeResult: JsCodeResult = {
@@ -990,6 +1037,11 @@ class Core {
     this.gridController.updateValidation(JSON.stringify(validation, bigIntReplacer), cursor);
   }
 
+  setEditUser(editUser: EditUser) {
+    if (!this.gridController) throw new Error('Expected gridController to be defined');
+    this.gridController.setEditUser(JSON.stringify(editUser));
+  }
+
   removeValidation(sheetId: string, validationId: string, cursor: string) {
     if (!this.gridController) throw new Error('Expected gridController to be defined');
     this.gridController.removeValidation(sheetId, validationId, cursor);
@@ -1019,6 +1071,11 @@ class Core {
     return JSON.parse(list);
   }
 
+  getLspContext(): string {
+    if (!this.gridController) throw new Error('Expected gridController to be defined');
+    return this.gridController.getLspContext();
+  }
+
   getDisplayCell(sheetId: string, x: number, y: number) {
     if (!this.gridController) throw new Error('Expected gridController to be defined');
     return this.gridController.getDisplayValue(sheetId, posToPos(x, y));
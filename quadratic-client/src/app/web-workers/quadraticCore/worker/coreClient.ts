@@ -8,6 +8,7 @@
 import { debugWebWorkers, debugWebWorkersMessages } from '@/app/debugFlags';
 import { getLanguage } from '@/app/helpers/codeCellLanguage';
 import {
+  CommentThread,
   JsCodeCell,
   JsHtmlOutput,
   JsRenderBorders,
@@ -17,6 +18,7 @@ import {
   JsRowHeight,
   JsSheetFill,
   JsValidationWarning,
+  Protection,
   Selection,
   SheetBounds,
   SheetInfo,
@@ -85,6 +87,7 @@ declare var self: WorkerGlobalScope &
     sendUndoRedo: (undo: boolean, redo: boolean) => void;
     sendImage: (sheetId: string, x: number, y: number, image?: string, w?: string, h?: string) => void;
     sendSheetValidations: (sheetId: string, validations: Validation[]) => void;
+    sendSheetComments: (sheetId: string, comments: CommentThread[]) => void;
     sendResizeRowHeightsClient(sheetId: string, rowHeights: string): void;
     sendRenderValidationWarnings: (
       sheetId: string,
@@ -125,6 +128,9 @@ class CoreClient {
     self.sendUndoRedo = coreClient.sendUndoRedo;
     self.sendImage = coreClient.sendImage;
     self.sendSheetValidations = coreClient.sendSheetValidations;
+    self.sendSheetComments = coreClient.sendSheetComments;
+    self.sendSheetProtections = coreClient.sendSheetProtections;
+    self.sendProtectedEdit = coreClient.sendProtectedEdit;
     self.sendResizeRowHeightsClient = coreClient.sendResizeRowHeights;
     self.sendRenderValidationWarnings = coreClient.sendRenderValidationWarnings;
     self.sendMultiplayerSynced = coreClient.sendMultiplayerSynced;
@@ -469,6 +475,10 @@ class CoreClient {
         core.rerunCodeCells(e.data.sheetId, e.data.x, e.data.y, e.data.cursor);
         return;
 
+      case 'clientCoreRecalculate':
+        core.recalculate(e.data.cursor);
+        return;
+
       case 'clientCoreCancelExecution':
         const language = getLanguage(e.data.language);
         if (language === 'Python') {
@@ -522,6 +532,10 @@ class CoreClient {
         });
         return;
 
+      case 'clientCoreSetEditUser':
+        core.setEditUser(e.data.editUser);
+        return;
+
       case 'clientCoreRemoveValidation':
         core.removeValidation(e.data.sheetId, e.data.validationId, e.data.cursor);
         return;
@@ -546,6 +560,14 @@ class CoreClient {
         });
         return;
 
+      case 'clientCoreGetLspContext':
+        this.send({
+          type: 'coreClientGetLspContext',
+          id: e.data.id,
+          context: core.getLspContext(),
+        });
+        return;
+
       case 'clientCoreGetDisplayCell':
         this.send({
           type: 'coreClientGetDisplayCell',
@@ -725,6 +747,18 @@ class CoreClient {
     this.send({ type: 'coreClientSheetValidations', sheetId, validations });
   };
 
+  sendSheetComments = (sheetId: string, comments: CommentThread[]) => {
+    this.send({ type: 'coreClientSheetComments', sheetId, comments });
+  };
+
+  sendSheetProtections = (sheetId: string, protections: Protection[]) => {
+    this.send({ type: 'coreClientSheetProtections', sheetId, protections });
+  };
+
+  sendProtectedEdit = (sheetId: string, protection: Protection) => {
+    this.send({ type: 'coreClientProtectedEdit', sheetId, protection });
+  };
+
   sendResizeRowHeights = (sheetId: string, rowHeightsString: string) => {
     try {
       const rowHeights = JSON.parse(rowHeightsString) as JsRowHeight[];
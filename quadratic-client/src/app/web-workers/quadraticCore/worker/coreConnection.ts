@@ -1,5 +1,5 @@
 import { debugWebWorkers } from '@/app/debugFlags';
-import { ConnectionKind } from '@/app/quadratic-core-types';
+import { ConnectionKind, SqlParameter } from '@/app/quadratic-core-types';
 import { LanguageState } from '@/app/web-workers/languageTypes';
 import { core } from '@/app/web-workers/quadraticCore/worker/core';
 import { coreClient } from '@/app/web-workers/quadraticCore/worker/coreClient';
@@ -13,6 +13,7 @@ declare var self: WorkerGlobalScope &
       y: number,
       sheetId: string,
       code: string,
+      parameters: SqlParameter[],
       connector_type: ConnectionKind,
       connection_id: String
     ) => void;
@@ -21,6 +22,10 @@ declare var self: WorkerGlobalScope &
 class CoreConnection {
   controller: AbortController = new AbortController();
 
+  // transaction id of the query that is currently running, which is also the
+  // id the connection service uses to cancel it
+  private currentTransactionId?: string;
+
   start() {
     self.sendConnection = this.sendConnection;
 
@@ -46,6 +51,7 @@ class CoreConnection {
     y: number,
     sheetId: string,
     code: string,
+    parameters: SqlParameter[],
     connector_type: ConnectionKind,
     connection_id: String
   ) => {
@@ -56,10 +62,11 @@ class CoreConnection {
     const body = {
       connection_id,
       query: code,
+      parameters,
+      query_id: transactionId,
     };
 
     let buffer = new ArrayBuffer(0);
-    let std_out = undefined;
     let std_err = undefined;
     let extra = undefined;
     let codeRun: CodeRun = {
@@ -70,6 +77,7 @@ class CoreConnection {
     let signal = this.controller.signal;
 
     try {
+      this.currentTransactionId = transactionId;
       this.sendConnectionState('running', { current: codeRun });
 
       const response = await fetch(url, {
@@ -86,19 +94,43 @@ class CoreConnection {
         std_err = await response.text();
         console.warn(std_err);
       } else {
+        // whether the results were cut short is in the parquet file's footer,
+        // which core reads
         buffer = await response.arrayBuffer();
-
-        const headers = response.headers;
-        const isOverTheLimit = headers.get('over-the-limit') === 'true';
-        std_out = isOverTheLimit ? 'Exceeded maximum allowed bytes, not all available records returned.' : '';
-        extra = ` in ${headers.get('elapsed-total-ms')}ms`;
+        extra = ` in ${response.headers.get('elapsed-total-ms')}ms`;
       }
 
       // send the parquet bytes to core
-      core.connectionComplete(transactionId, buffer, std_out, std_err?.replace(/\\/g, '').replace(/"/g, ''), extra);
+      this.currentTransactionId = undefined;
+      core.connectionComplete(transactionId, buffer, undefined, std_err?.replace(/\\/g, '').replace(/"/g, ''), extra);
       this.sendConnectionState('ready');
+    } catch (e: any) {
+      // cancelled queries are completed by cancelExecution
+      if (e?.name !== 'AbortError') {
+        console.error(`Error fetching ${url}`, e);
+
+        // eg, the query failed after its results started streaming
+        if (this.currentTransactionId === transactionId) {
+          this.currentTransactionId = undefined;
+          core.connectionComplete(transactionId, new ArrayBuffer(0), undefined, `${e}`, undefined);
+          this.sendConnectionState('ready');
+        }
+      }
+    }
+  };
+
+  // ask the connection service to stop a running query
+  private cancelQuery = async (queryId: string) => {
+    const base = coreClient.env.VITE_QUADRATIC_CONNECTION_URL;
+    const jwt = await coreClient.getJwt();
+
+    try {
+      await fetch(`${base}/cancel/${queryId}`, {
+        method: 'POST',
+        headers: { Authorization: `Bearer ${jwt}` },
+      });
     } catch (e) {
-      console.error(`Error fetching ${url}`, e);
+      console.warn(`Error cancelling query ${queryId}`, e);
     }
   };
 
@@ -113,6 +145,14 @@ class CoreConnection {
     }
 
     this.controller = new AbortController();
+
+    const transactionId = this.currentTransactionId;
+    if (transactionId) {
+      this.currentTransactionId = undefined;
+      this.cancelQuery(transactionId);
+      core.connectionCancelled(transactionId);
+    }
+
     this.sendConnectionState('ready');
   }
 }
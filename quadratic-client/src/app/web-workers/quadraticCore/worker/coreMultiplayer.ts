@@ -43,6 +43,10 @@ class CoreMultiplayer {
         core.receiveTransactions(e.data);
         break;
 
+      case 'multiplayerCoreReceiveCatchUp':
+        core.receiveCatchUp(e.data);
+        break;
+
       default:
         console.warn('[coreMultiplayer] Unhandled message type', e.data);
     }
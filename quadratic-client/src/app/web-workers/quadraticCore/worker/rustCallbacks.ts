@@ -1,6 +1,7 @@
 // this file cannot include any non-type imports; see https://rustwasm.github.io/wasm-bindgen/reference/js-snippets.html#caveats
 
 import {
+  CommentThread,
   ConnectionKind,
   JsCodeCell,
   JsHtmlOutput,
@@ -10,9 +11,11 @@ import {
   JsRenderFill,
   JsSheetFill,
   JsValidationWarning,
+  Protection,
   Selection,
   SheetBounds,
   SheetInfo,
+  SqlParameter,
   TransactionName,
   Validation,
 } from '@/app/quadratic-core-types';
@@ -82,11 +85,15 @@ declare var self: WorkerGlobalScope &
       y: number,
       sheetId: string,
       code: string,
+      parameters: SqlParameter[],
       connector_type: ConnectionKind,
       connection_id: String
     ) => void;
     sendImage: (sheetId: string, x: number, y: number, image?: string, w?: string, h?: string) => void;
     sendSheetValidations: (sheetId: string, validations: Validation[]) => void;
+    sendSheetComments: (sheetId: string, comments: CommentThread[]) => void;
+    sendSheetProtections: (sheetId: string, protections: Protection[]) => void;
+    sendProtectedEdit: (sheetId: string, protection: Protection) => void;
     sendRequestRowHeights: (transactionId: string, sheetId: string, rows: string) => void;
     sendResizeRowHeightsClient: (sheetId: string, rowHeights: string) => void;
     sendResizeRowHeightsRender: (sheetId: string, rowHeights: string) => void;
@@ -252,10 +259,12 @@ export const jsConnection = (
   y: number,
   sheetId: string,
   code: string,
+  parameters: string,
   connector_type: ConnectionKind,
   connection_id: String
 ) => {
-  self.sendConnection(transactionId, x, y, sheetId, code, connector_type, connection_id);
+  const parametersParsed = JSON.parse(parameters) as SqlParameter[];
+  self.sendConnection(transactionId, x, y, sheetId, code, parametersParsed, connector_type, connection_id);
 };
 
 export const jsSendImage = (sheetId: string, x: number, y: number, image?: string, w?: string, h?: string) => {
@@ -272,6 +281,21 @@ export const jsSheetValidations = (sheetId: string, validations: string) => {
   self.sendSheetValidations(sheetId, validationsParsed);
 };
 
+export const jsSheetComments = (sheetId: string, comments: string) => {
+  const commentsParsed = JSON.parse(comments) as CommentThread[];
+  self.sendSheetComments(sheetId, commentsParsed);
+};
+
+export const jsSheetProtections = (sheetId: string, protections: string) => {
+  const protectionsParsed = JSON.parse(protections) as Protection[];
+  self.sendSheetProtections(sheetId, protectionsParsed);
+};
+
+export const jsProtectedEdit = (sheetId: string, protection: string) => {
+  const protectionParsed = JSON.parse(protection) as Protection;
+  self.sendProtectedEdit(sheetId, protectionParsed);
+};
+
 export const jsRequestRowHeights = (transactionId: string, sheetId: string, rows: string) => {
   self.sendRequestRowHeights(transactionId, sheetId, rows);
 };
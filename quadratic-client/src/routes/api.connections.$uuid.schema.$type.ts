@@ -3,13 +3,14 @@ import { LoaderFunctionArgs } from 'react-router';
 
 export const loader = async ({ request, params }: LoaderFunctionArgs) => {
   const { uuid, type } = params;
+  const refresh = new URL(request.url).searchParams.get('refresh') === 'true';
 
   try {
     // We don't have precise types here because the connection service just
     // tells us that `type` is a string. But we know this will throw if wrong
     // so this is ok for now.
     // @ts-expect-error
-    const data = await connectionClient.schemas.get(type, uuid);
+    const data = await connectionClient.schemas.get(type, uuid, refresh);
     return { ok: true, data };
   } catch (e) {
     console.error(e);
@@ -79,6 +79,10 @@ export const loader = async ({ request, params }: LoaderFunctionArgs): Promise<F
       // @ts-expect-error hard reload via `true` only works in some browsers
       window.location.reload(true);
     }
+    // edits to protected cells are checked against the user and their file permissions
+    const user = await authClient.user();
+    quadraticCore.setEditUser({ user_id: user?.sub ?? '', roles: data.userMakingRequest.filePermissions });
+
     if (!data.file.thumbnail && data.userMakingRequest.filePermissions.includes('FILE_EDIT')) {
       thumbnail.generateThumbnail();
     }
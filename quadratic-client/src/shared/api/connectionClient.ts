@@ -21,11 +21,25 @@ const SqlSchema = z.object({
     z.object({
       name: z.string(),
       schema: z.string(), // public or ...?
+      kind: z.enum(['table', 'view']),
+      row_estimate: z.number().nullable(),
+      comment: z.string().nullable(),
       columns: z.array(
         z.object({
           name: z.string(),
           type: z.string(),
           is_nullable: z.boolean(),
+          is_primary_key: z.boolean(),
+          comment: z.string().nullable(),
+        })
+      ),
+      foreign_keys: z.array(
+        z.object({
+          name: z.string(),
+          columns: z.array(z.string()),
+          referenced_schema: z.string(),
+          referenced_table: z.string(),
+          referenced_columns: z.array(z.string()),
         })
       ),
     })
@@ -40,8 +54,14 @@ type StaticIpsResponse = z.infer<typeof StaticIpsSchema>;
 
 export const connectionClient = {
   schemas: {
-    get: async (connectionType: 'postgres' | 'mysql', connectionId: string): Promise<SqlSchemaResponse | null> => {
-      const res = await fetch(`${API_URL}/${connectionType}/schema/${connectionId}`, {
+    get: async (
+      connectionType: 'postgres' | 'mysql',
+      connectionId: string,
+      refresh = false
+    ): Promise<SqlSchemaResponse | null> => {
+      // the connection service caches schemas unless asked to read them again
+      const query = refresh ? '?refresh=true' : '';
+      const res = await fetch(`${API_URL}/${connectionType}/schema/${connectionId}${query}`, {
         method: 'GET',
         headers: new Headers(await jwtHeader()),
       });
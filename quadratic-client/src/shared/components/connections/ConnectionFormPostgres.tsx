@@ -1,6 +1,15 @@
 import { ConnectionInputPassword } from '@/shared/components/connections/ConnectionInputPassword';
 import { ConnectionFormComponent, UseConnectionForm } from '@/shared/components/connections/connectionsByType';
-import { Form, FormControl, FormField, FormItem, FormLabel, FormMessage } from '@/shared/shadcn/ui/form';
+import { Checkbox } from '@/shared/shadcn/ui/checkbox';
+import {
+  Form,
+  FormControl,
+  FormDescription,
+  FormField,
+  FormItem,
+  FormLabel,
+  FormMessage,
+} from '@/shared/shadcn/ui/form';
 import { Input } from '@/shared/shadcn/ui/input';
 import { zodResolver } from '@hookform/resolvers/zod';
 import {
@@ -33,6 +42,7 @@ export const useConnectionForm: UseConnectionForm<FormValues> = (connection) =>
     database: String(connection?.typeDetails?.database || ''),
     username: String(connection?.typeDetails?.username || ''),
     password: String(connection?.typeDetails?.password || ''),
+    readOnly: Boolean(connection?.typeDetails?.readOnly),
   };
 
   const form = useForm<FormValues>({
@@ -130,6 +140,21 @@ export const ConnectionForm: ConnectionFormComponent<FormValues> = ({ form, chil
             )}
           />
         </div>
+        <FormField
+          control={form.control}
+          name="readOnly"
+          render={({ field }) => (
+            <FormItem className="flex flex-row items-start space-x-3 space-y-0 pt-2">
+              <FormControl>
+                <Checkbox checked={field.value} onCheckedChange={field.onChange} />
+              </FormControl>
+              <div className="space-y-1 leading-none">
+                <FormLabel>Read-only</FormLabel>
+                <FormDescription>Only allow queries that read data, e.g. for a production replica</FormDescription>
+              </div>
+            </FormItem>
+          )}
+        />
         {children}
       </form>
     </Form>
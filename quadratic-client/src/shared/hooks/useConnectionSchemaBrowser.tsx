@@ -37,7 +37,7 @@ export const useConnectionSchemaBrowser = ({ type, uuid }: { uuid: string | unde
 
   const reloadSchema = () => {
     mixpanel.track('[Connections].schemaViewer.refresh');
-    fetcher.load(fetcherUrl);
+    fetcher.load(`${fetcherUrl}?refresh=true`);
   };
 
   return {
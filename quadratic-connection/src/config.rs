@@ -21,7 +21,44 @@ pub(crate) struct Config {
     pub(crate) quadratic_api_uri: String,
     pub(crate) m2m_auth_token: String,
     pub(crate) max_response_bytes: u64,
+    /// The longest a query can run, and its timeout if it doesn't have one
+    #[serde(default = "default_query_timeout_ms")]
+    pub(crate) query_timeout_ms: u64,
     pub(crate) static_ips: Vec<String>,
+    /// Where SQLite and DuckDB database files are
+    #[serde(default = "default_local_database_dir")]
+    pub(crate) local_database_dir: String,
+
+    /// Domains and CIDRs the proxy can request, any public address if empty
+    #[serde(default)]
+    pub(crate) proxy_allow_list: Vec<String>,
+    /// Domains and CIDRs the proxy can't request
+    #[serde(default)]
+    pub(crate) proxy_deny_list: Vec<String>,
+    pub(crate) proxy_max_response_bytes: u64,
+    pub(crate) proxy_requests_per_minute: u32,
+
+    /// How long query results are cached, the cache is disabled if zero
+    #[serde(default)]
+    pub(crate) query_cache_ttl_seconds: u64,
+    /// The largest the cache gets, the oldest results are evicted first
+    #[serde(default = "default_query_cache_max_bytes")]
+    pub(crate) query_cache_max_bytes: u64,
+    /// Where cached results are stored, in memory if empty
+    #[serde(default)]
+    pub(crate) query_cache_dir: String,
+}
+
+fn default_local_database_dir() -> String {
+    "./databases".into()
+}
+
+fn default_query_timeout_ms() -> u64 {
+    5 * 60 * 1000
+}
+
+fn default_query_cache_max_bytes() -> u64 {
+    100 * 1024 * 1024
 }
 
 /// Load the global configuration from the environment into Config.
@@ -1,7 +1,15 @@
 use quadratic_rust_shared::quadratic_api::{get_connection, Connection};
+use quadratic_rust_shared::ssh::{SshConfig, SshTunnel};
+use tokio::net::lookup_host;
 use uuid::Uuid;
 
-use crate::{error::Result, state::State};
+use crate::{
+    error::{ConnectionError, Result},
+    proxy::rules::canonical_ip,
+    state::{tunnels::local_port, State},
+};
+
+const LOCALHOST: &str = "127.0.0.1";
 
 pub(crate) async fn get_api_connection(
     state: &State,
@@ -14,3 +22,105 @@ pub(crate) async fn get_api_connection(
 
     Ok(connection)
 }
+
+/// The host and port to connect to a database at.  When the connection is
+/// through an SSH bastion, this is the local end of a tunnel to the database,
+/// which is opened if there isn't already one.
+pub(crate) async fn tunnel_address(
+    state: &State,
+    ssh: Option<&SshConfig>,
+    host: &str,
+    port: Option<&str>,
+    default_port: u16,
+) -> Result<(String, Option<String>)> {
+    let Some(ssh) = ssh else {
+        check_direct_host(state, host).await?;
+        return Ok((host.to_owned(), port.map(str::to_owned)));
+    };
+
+    let port = remote_port(port, default_port)?;
+    let local_port = local_port(&state.ssh_tunnels, ssh, host, port).await?;
+
+    Ok((LOCALHOST.into(), Some(local_port.to_string())))
+}
+
+/// Like `tunnel_address`, for testing a connection that hasn't been saved.
+/// The tunnel isn't shared, and is closed when it's dropped.
+pub(crate) async fn test_tunnel_address(
+    state: &State,
+    ssh: Option<&SshConfig>,
+    host: &str,
+    port: Option<&str>,
+    default_port: u16,
+) -> Result<(String, Option<String>, Option<SshTunnel>)> {
+    let Some(ssh) = ssh else {
+        check_direct_host(state, host).await?;
+        return Ok((host.to_owned(), port.map(str::to_owned), None));
+    };
+
+    let port = remote_port(port, default_port)?;
+    let tunnel = SshTunnel::open(ssh, host, port).await?;
+    let local_port = tunnel.local_port().to_string();
+
+    Ok((LOCALHOST.into(), Some(local_port), Some(tunnel)))
+}
+
+fn remote_port(port: Option<&str>, default_port: u16) -> Result<u16> {
+    match port {
+        Some(port) => port.parse::<u16>().map_err(|_| {
+            ConnectionError::Connection(format!("Could not parse port {port} into a number"))
+        }),
+        None => Ok(default_port),
+    }
+}
+
+/// The local ends of SSH tunnels listen on this host, so connections that
+/// aren't through a bastion can't be to it, eg, `127.0.0.1` and the port of
+/// another connection's tunnel.  Local databases are allowed in development.
+async fn check_direct_host(state: &State, host: &str) -> Result<()> {
+    if state.settings.allow_loopback_databases {
+        return Ok(());
+    }
+
+    // the port isn't used, but the lookup needs one
+    let mut ips = lookup_host((host, 0))
+        .await
+        .map_err(|e| ConnectionError::Connection(format!("Could not resolve {host}: {e}")))?
+        .map(|address| canonical_ip(&address.ip()));
+
+    match ips.any(|ip| ip.is_loopback() || ip.is_unspecified()) {
+        true => Err(ConnectionError::Forbidden(format!(
+            "Connections to {host} are not allowed"
+        ))),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::new_state;
+
+    #[tokio::test]
+    async fn rejects_loopback_hosts() {
+        let mut state = new_state().await;
+        state.settings.allow_loopback_databases = false;
+
+        for host in [
+            "127.0.0.1",
+            "localhost",
+            "0.0.0.0",
+            "::1",
+            "::ffff:127.0.0.1",
+        ] {
+            let result = tunnel_address(&state, None, host, Some("5432"), 5432).await;
+            assert!(
+                matches!(result, Err(ConnectionError::Forbidden(_))),
+                "{host} should be rejected"
+            );
+        }
+
+        let address = tunnel_address(&state, None, "10.0.0.1", Some("5432"), 5432).await;
+        assert_eq!(address.unwrap(), ("10.0.0.1".into(), Some("5432".into())));
+    }
+}
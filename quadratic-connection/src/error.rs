@@ -9,7 +9,7 @@ use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
 };
-use quadratic_rust_shared::SharedError;
+use quadratic_rust_shared::{SharedError, Sql};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -20,12 +20,18 @@ pub enum ConnectionError {
     #[error("Authentication error: {0}")]
     Authentication(String),
 
+    #[error("Query cancelled: {0}")]
+    Cancelled(String),
+
     #[error("Config error: {0}")]
     Config(String),
 
     #[error("Connection error: {0}")]
     Connection(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Internal server error: {0}")]
     InternalServer(String),
 
@@ -38,12 +44,21 @@ pub enum ConnectionError {
     #[error("Query error: {0}")]
     Query(String),
 
+    #[error("Too many requests: {0}")]
+    RateLimited(String),
+
+    #[error("Read-only connection: {0}")]
+    ReadOnly(String),
+
     #[error("Error requesting data: {0}")]
     Request(String),
 
     #[error("Error serializing or deserializing: {0}")]
     Serialization(String),
 
+    #[error("Query timed out: {0}")]
+    Timeout(String),
+
     #[error("unknown error: {0}")]
     Unknown(String),
 }
@@ -67,8 +82,12 @@ impl From<SharedError> for ConnectionError {
     fn from(error: SharedError) -> Self {
         match error {
             SharedError::Auth(error) => ConnectionError::Authentication(error.to_string()),
+            SharedError::Sql(error @ Sql::ReadOnly(_)) => {
+                ConnectionError::ReadOnly(error.to_string())
+            }
             SharedError::Sql(error) => ConnectionError::Query(error.to_string()),
             SharedError::QuadraticApi(error) => ConnectionError::Connection(error.to_string()),
+            SharedError::Ssh(error) => ConnectionError::Connection(error),
             _ => ConnectionError::Unknown(error.to_string()),
         }
     }
@@ -109,7 +128,16 @@ impl IntoResponse for ConnectionError {
             ConnectionError::Authentication(error) | ConnectionError::InvalidToken(error) => {
                 (StatusCode::UNAUTHORIZED, clean_errors(error))
             }
-            ConnectionError::Query(error) => (StatusCode::BAD_REQUEST, clean_errors(error)),
+            ConnectionError::Query(error) | ConnectionError::Cancelled(error) => {
+                (StatusCode::BAD_REQUEST, clean_errors(error))
+            }
+            ConnectionError::ReadOnly(error) | ConnectionError::Forbidden(error) => {
+                (StatusCode::FORBIDDEN, clean_errors(error))
+            }
+            ConnectionError::RateLimited(error) => {
+                (StatusCode::TOO_MANY_REQUESTS, clean_errors(error))
+            }
+            ConnectionError::Timeout(error) => (StatusCode::GATEWAY_TIMEOUT, clean_errors(error)),
             ConnectionError::Connection(error) => (StatusCode::NOT_FOUND, clean_errors(error)),
             ConnectionError::Proxy(error) => (StatusCode::BAD_REQUEST, clean_errors(error)),
             _ => (StatusCode::INTERNAL_SERVER_ERROR, "Unknown".into()),
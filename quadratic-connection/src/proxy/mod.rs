@@ -0,0 +1,279 @@
+//! Proxy
+//!
+//! Forward requests from code cells to the URL in the `proxy` header.  The
+//! proxy is exposed to untrusted code, so requests are rate limited per user,
+//! responses are capped at `PROXY_MAX_RESPONSE_BYTES`, and only addresses
+//! allowed by `rules::ProxyRules` can be requested.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    body::Body,
+    extract::Request,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use futures::StreamExt;
+use http::{HeaderName, HeaderValue};
+use reqwest::{redirect::Policy, Client, Method, RequestBuilder, Url};
+
+use crate::auth::Claims;
+use crate::error::{proxy_error, ConnectionError, Result};
+use crate::state::State;
+
+use self::rules::{ProxyResolver, ProxyRules};
+
+pub(crate) mod rules;
+
+const REQUEST_TIMEOUT_SEC: u64 = 15;
+const PROXY_HEADER: &str = "proxy";
+const MAX_REDIRECTS: usize = 5;
+
+/// The client that proxied requests are sent with.  Every address it
+/// connects to, including after a redirect, is checked against `rules`.  It
+/// doesn't keep cookies, as it's shared by every user, and ignores the
+/// `HTTP_PROXY` environment variables, which would skip the resolver.
+pub(crate) fn new_proxy_client(rules: &Arc<ProxyRules>) -> Result<Client> {
+    let redirect_rules = Arc::clone(rules);
+    let redirect = Policy::custom(move |attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("Too many redirects")
+        } else if let Err(error) = redirect_rules.check_redirect(attempt.url()) {
+            attempt.error(error)
+        } else {
+            attempt.follow()
+        }
+    });
+
+    Client::builder()
+        .no_proxy()
+        .dns_resolver(Arc::new(ProxyResolver(Arc::clone(rules))))
+        .redirect(redirect)
+        .build()
+        .map_err(proxy_error)
+}
+
+pub(crate) async fn axum_to_reqwest(
+    url: &str,
+    req: Request<Body>,
+    client: Client,
+) -> Result<RequestBuilder> {
+    let method_bytes = req.method().as_str().as_bytes();
+    let method = Method::from_bytes(method_bytes).map_err(proxy_error)?;
+
+    let mut headers = reqwest::header::HeaderMap::with_capacity(req.headers().len());
+    let headers_to_ignore = ["host", PROXY_HEADER, "authorization"];
+
+    for (name, value) in req
+        .headers()
+        .into_iter()
+        .filter(|(name, _)| !headers_to_ignore.contains(&name.as_str()))
+    {
+        let name = reqwest::header::HeaderName::from_bytes(name.as_ref()).map_err(proxy_error)?;
+        let value =
+            reqwest::header::HeaderValue::from_bytes(value.as_ref()).map_err(proxy_error)?;
+        headers.insert(name, value);
+    }
+
+    let body = axum::body::to_bytes(req.into_body(), usize::MAX)
+        .await
+        .map_err(proxy_error)?;
+
+    let reqwest = reqwest::Request::new(method, url.parse().map_err(proxy_error)?);
+    let reqwest = reqwest::RequestBuilder::from_parts(client, reqwest)
+        .headers(headers)
+        .body(reqwest::Body::from(body))
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SEC));
+
+    Ok(reqwest)
+}
+
+/// Stream the response back, ending it with an error once it's over
+/// `max_bytes`.  Responses that say they're too large aren't read.
+pub(crate) fn reqwest_to_axum(
+    reqwest_response: reqwest::Response,
+    max_bytes: u64,
+) -> Result<Response<Body>> {
+    let too_large =
+        move || ConnectionError::Proxy(format!("Response is larger than {max_bytes} bytes"));
+
+    if reqwest_response
+        .content_length()
+        .is_some_and(|length| length > max_bytes)
+    {
+        return Err(too_large());
+    }
+
+    let mut response_builder = Response::builder().status(reqwest_response.status().as_u16());
+
+    for (name, value) in reqwest_response.headers().into_iter() {
+        let name = HeaderName::from_bytes(name.as_ref()).map_err(proxy_error)?;
+        let value = HeaderValue::from_bytes(value.as_ref()).map_err(proxy_error)?;
+        response_builder = response_builder.header(name, value);
+    }
+
+    let mut bytes = 0;
+    let body = reqwest_response.bytes_stream().map(move |chunk| {
+        let chunk = chunk.map_err(proxy_error)?;
+        bytes += chunk.len() as u64;
+
+        match bytes > max_bytes {
+            true => Err(too_large()),
+            false => Ok(chunk),
+        }
+    });
+
+    let response = response_builder
+        .body(Body::from_stream(body))
+        .map_err(proxy_error)?;
+
+    Ok(response)
+}
+
+pub(crate) async fn proxy(
+    state: Extension<State>,
+    claims: Claims,
+    req: Request<Body>,
+) -> Result<impl IntoResponse> {
+    tracing::info!(?req);
+
+    state.proxy_rate_limits.lock().await.check(&claims.sub)?;
+
+    let headers = req.headers().clone();
+    let url = headers
+        .get(PROXY_HEADER)
+        .ok_or_else(|| ConnectionError::Proxy("No proxy header found".to_string()))?
+        .to_str()
+        .map_err(proxy_error)?
+        .parse::<Url>()
+        .map_err(proxy_error)?;
+
+    state.proxy_rules.check_url(&url).await?;
+
+    let request_builder = axum_to_reqwest(url.as_str(), req, state.proxy_client.clone()).await?;
+    let reqwest_response = request_builder.send().await.map_err(proxy_error)?;
+
+    let response = reqwest_to_axum(reqwest_response, state.settings.proxy_max_response_bytes)?;
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use axum::{routing::get, Router};
+    use http::header::ACCEPT;
+    use tokio::{net::TcpListener, sync::Mutex};
+
+    use super::*;
+    use crate::state::rate_limits::RateLimiter;
+    use crate::test_util::{get_claims, new_state, response_bytes};
+
+    const URL: &str = "https://www.google.com/";
+
+    #[tokio::test]
+    async fn proxy_request() {
+        let state = Extension(new_state().await);
+        let mut request = Request::new(Body::empty());
+        request
+            .headers_mut()
+            .insert(PROXY_HEADER, HeaderValue::from_static(URL));
+        let data = proxy(state, get_claims(), request).await.unwrap();
+        let response = data.into_response();
+
+        assert_eq!(response.status(), 200);
+        assert_ne!(response_bytes(response).await.len(), 0);
+    }
+
+    fn proxy_request_to(url: &str) -> Request<Body> {
+        let mut request = Request::new(Body::empty());
+        request
+            .headers_mut()
+            .insert(PROXY_HEADER, HeaderValue::from_str(url).unwrap());
+        request
+    }
+
+    /// Serve a response of `len` bytes on localhost
+    async fn new_server(len: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let app = Router::new().route("/", get(move || async move { "a".repeat(len) }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        url
+    }
+
+    #[tokio::test]
+    async fn proxy_blocks_private_addresses() {
+        let state = Extension(new_state().await);
+
+        for url in [
+            "http://localhost:3003/health",
+            "http://169.254.169.254/latest/meta-data/",
+            "file:///etc/passwd",
+        ] {
+            let result = proxy(state.clone(), get_claims(), proxy_request_to(url)).await;
+            assert!(
+                matches!(result, Err(ConnectionError::Forbidden(_))),
+                "{url}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn proxy_response_size() {
+        let url = new_server(1_000).await;
+        let mut state = Extension(new_state().await);
+        state.proxy_rules = Arc::new(ProxyRules::new(&["127.0.0.1".into()], &[]).unwrap());
+        state.settings.proxy_max_response_bytes = 1_000;
+
+        let data = proxy(state.clone(), get_claims(), proxy_request_to(&url))
+            .await
+            .unwrap();
+        assert_eq!(response_bytes(data.into_response()).await.len(), 1_000);
+
+        state.settings.proxy_max_response_bytes = 999;
+        let result = proxy(state, get_claims(), proxy_request_to(&url)).await;
+        assert!(matches!(result, Err(ConnectionError::Proxy(_))));
+    }
+
+    #[tokio::test]
+    async fn proxy_rate_limit() {
+        let mut state = Extension(new_state().await);
+        let limiter = RateLimiter::new(0, Duration::from_secs(60));
+        state.proxy_rate_limits = Arc::new(Mutex::new(limiter));
+
+        let result = proxy(state, get_claims(), proxy_request_to(URL)).await;
+        assert!(matches!(result, Err(ConnectionError::RateLimited(_))));
+    }
+
+    #[tokio::test]
+    async fn proxy_axum_to_reqwest() {
+        let state = Extension(new_state().await);
+        let accept = "application/json";
+        let mut request = Request::new(Body::empty());
+        *request.method_mut() = http::Method::POST;
+        request
+            .headers_mut()
+            .insert(ACCEPT, HeaderValue::from_static(accept));
+        request
+            .headers_mut()
+            .insert(PROXY_HEADER, HeaderValue::from_static(URL));
+
+        let result = axum_to_reqwest(URL, request, state.client.clone())
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(result.method(), Method::POST);
+        assert_eq!(result.url().to_string(), URL);
+
+        // PROXY_HEADER doesn't get copied over
+        assert_eq!(result.headers().len(), 1);
+        assert_eq!(
+            result.headers().get(reqwest::header::ACCEPT).unwrap(),
+            accept
+        );
+    }
+}
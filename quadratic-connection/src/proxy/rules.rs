@@ -0,0 +1,388 @@
+//! Proxy Rules
+//!
+//! The proxy is called from code cells, so a request could try to reach the
+//! service's own network, eg, a cloud metadata endpoint or a database.
+//! Requests are checked against the configured allow and deny lists, and
+//! private, link-local and metadata addresses are blocked unless an allowed
+//! CIDR contains them.  Domains are checked with the addresses they resolve
+//! to, and again by `ProxyResolver` when the client connects, so a domain
+//! can't resolve to a blocked address after it has been checked.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use hyper_0_14::client::connect::dns::Name;
+use ipnet::IpNet;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::Url;
+use tokio::net::lookup_host;
+
+use crate::error::{ConnectionError, Result};
+
+/// Addresses that aren't on the public internet
+const BLOCKED_NETWORKS: &[&str] = &[
+    "0.0.0.0/8",       // this network
+    "10.0.0.0/8",      // private
+    "100.64.0.0/10",   // carrier-grade NAT
+    "127.0.0.0/8",     // loopback
+    "169.254.0.0/16",  // link-local, including cloud metadata endpoints
+    "172.16.0.0/12",   // private
+    "192.0.0.0/24",    // protocol assignments
+    "192.0.2.0/24",    // documentation
+    "192.168.0.0/16",  // private
+    "198.18.0.0/15",   // benchmarking
+    "198.51.100.0/24", // documentation
+    "203.0.113.0/24",  // documentation
+    "224.0.0.0/4",     // multicast
+    "240.0.0.0/4",     // reserved, including broadcast
+    "::/128",          // unspecified
+    "::1/128",         // loopback
+    "64:ff9b::/96",    // IPv4 translation, which can reach private IPv4 addresses
+    "100::/64",        // discard
+    "2001:db8::/32",   // documentation
+    "fc00::/7",        // unique local, including AWS's IPv6 metadata endpoint
+    "fe80::/10",       // link-local
+    "ff00::/8",        // multicast
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Rule {
+    /// A domain and its subdomains
+    Domain(String),
+    Network(IpNet),
+}
+
+impl Rule {
+    fn parse(rule: &str) -> Result<Rule> {
+        if let Ok(network) = rule.parse::<IpNet>() {
+            return Ok(Rule::Network(network.trunc()));
+        }
+
+        if let Ok(ip) = rule.parse::<IpAddr>() {
+            return Ok(Rule::Network(IpNet::from(ip)));
+        }
+
+        let domain = rule
+            .trim_start_matches("*.")
+            .trim_matches('.')
+            .to_lowercase();
+
+        if domain.is_empty() || domain.contains(&['/', ':', '*'][..]) {
+            return Err(ConnectionError::Config(format!(
+                "Invalid proxy rule, expected a domain or a CIDR: {rule}"
+            )));
+        }
+
+        Ok(Rule::Domain(domain))
+    }
+
+    fn matches_domain(&self, domain: &str) -> bool {
+        match self {
+            Rule::Domain(rule) => {
+                domain == rule
+                    || domain
+                        .strip_suffix(rule.as_str())
+                        .is_some_and(|subdomain| subdomain.ends_with('.'))
+            }
+            Rule::Network(_) => false,
+        }
+    }
+
+    fn matches_ip(&self, ip: &IpAddr) -> bool {
+        match self {
+            Rule::Domain(_) => false,
+            Rule::Network(network) => network.contains(ip),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ProxyRules {
+    /// When not empty, only these domains and networks can be requested
+    allow: Vec<Rule>,
+    deny: Vec<Rule>,
+    blocked: Vec<IpNet>,
+}
+
+impl ProxyRules {
+    /// Parse the `PROXY_ALLOW_LIST` and `PROXY_DENY_LIST` entries.  Empty
+    /// entries are skipped, as an empty variable is a list of one.
+    pub(crate) fn new(allow: &[String], deny: &[String]) -> Result<Self> {
+        let parse = |rules: &[String]| {
+            rules
+                .iter()
+                .map(|rule| rule.trim())
+                .filter(|rule| !rule.is_empty())
+                .map(Rule::parse)
+                .collect::<Result<Vec<_>>>()
+        };
+
+        let blocked = BLOCKED_NETWORKS
+            .iter()
+            .map(|network| network.parse::<IpNet>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| ConnectionError::Config(e.to_string()))?;
+
+        Ok(ProxyRules {
+            allow: parse(allow)?,
+            deny: parse(deny)?,
+            blocked,
+        })
+    }
+
+    /// Check a URL, resolving its domain
+    pub(crate) async fn check_url(&self, url: &Url) -> Result<()> {
+        if let Some(domain) = self.check_url_host(url)? {
+            let ips = lookup(domain).await?;
+            self.check(Some(domain), &ips)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check a URL that's redirected to.  Domains aren't resolved here, as
+    /// `ProxyResolver` checks them when the client connects.
+    pub(crate) fn check_redirect(&self, url: &Url) -> Result<()> {
+        self.check_url_host(url).map(|_| ())
+    }
+
+    /// Check the scheme and an IP address host.  Returns a domain host, which
+    /// needs its addresses to be checked.
+    fn check_url_host<'a>(&self, url: &'a Url) -> Result<Option<&'a str>> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(forbidden(format!("Scheme {} is not allowed", url.scheme())));
+        }
+
+        let host = url
+            .host_str()
+            .ok_or_else(|| forbidden(format!("No host in {url}")))?;
+
+        match host.trim_start_matches('[').trim_end_matches(']').parse() {
+            Ok(ip) => self.check(None, &[ip]).map(|_| None),
+            Err(_) => Ok(Some(host)),
+        }
+    }
+
+    /// Check a domain and the addresses it resolved to, or an IP address
+    fn check(&self, domain: Option<&str>, ips: &[IpAddr]) -> Result<()> {
+        let domain = domain.map(|domain| domain.trim_end_matches('.').to_lowercase());
+        let domain = domain.as_deref();
+
+        if let Some(domain) = domain {
+            if self.deny.iter().any(|rule| rule.matches_domain(domain)) {
+                return Err(forbidden(format!("{domain} is in the deny list")));
+            }
+        }
+
+        let allowed_domain =
+            domain.is_some_and(|domain| self.allow.iter().any(|rule| rule.matches_domain(domain)));
+
+        for ip in ips.iter().map(canonical_ip) {
+            if self.deny.iter().any(|rule| rule.matches_ip(&ip)) {
+                return Err(forbidden(format!("{ip} is in the deny list")));
+            }
+
+            let allowed_ip = self.allow.iter().any(|rule| rule.matches_ip(&ip));
+
+            if !self.allow.is_empty() && !allowed_domain && !allowed_ip {
+                return Err(forbidden(format!(
+                    "{} is not in the allow list",
+                    domain.map_or(ip.to_string(), str::to_owned)
+                )));
+            }
+
+            // an allowed domain could resolve to anything, so only an allowed
+            // network can contain a blocked address
+            if !allowed_ip && self.blocked.iter().any(|network| network.contains(&ip)) {
+                return Err(forbidden(format!("{ip} is a private address")));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn forbidden(message: String) -> ConnectionError {
+    ConnectionError::Forbidden(message)
+}
+
+/// IPv4 addresses can be written as IPv6 addresses, eg, `::ffff:127.0.0.1`
+pub(crate) fn canonical_ip(ip: &IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(*ip, IpAddr::V4),
+        IpAddr::V4(_) => *ip,
+    }
+}
+
+async fn lookup(domain: &str) -> Result<Vec<IpAddr>> {
+    // the port isn't used, but the lookup needs one
+    let ips = lookup_host((domain, 0))
+        .await
+        .map_err(|e| ConnectionError::Proxy(format!("Could not resolve {domain}: {e}")))?
+        .map(|address| address.ip())
+        .collect::<Vec<_>>();
+
+    match ips.is_empty() {
+        true => Err(ConnectionError::Proxy(format!(
+            "Could not resolve {domain}"
+        ))),
+        false => Ok(ips),
+    }
+}
+
+/// The proxy client's resolver, which only returns addresses that pass the
+/// rules.  This covers redirects and domains that resolve differently after
+/// they were checked.
+#[derive(Debug, Clone)]
+pub(crate) struct ProxyResolver(pub(crate) Arc<ProxyRules>);
+
+impl Resolve for ProxyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let rules = Arc::clone(&self.0);
+
+        Box::pin(async move {
+            let domain = name.as_str();
+            let ips = lookup(domain).await?;
+            rules.check(Some(domain), &ips)?;
+
+            // the client sets the port
+            let addresses: Addrs = Box::new(ips.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addresses)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(allow: &[&str], deny: &[&str]) -> ProxyRules {
+        let to_strings = |rules: &[&str]| rules.iter().map(|rule| rule.to_string()).collect();
+        let allow: Vec<String> = to_strings(allow);
+        let deny: Vec<String> = to_strings(deny);
+
+        ProxyRules::new(&allow, &deny).unwrap()
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    fn is_forbidden(result: Result<()>) -> bool {
+        matches!(result, Err(ConnectionError::Forbidden(_)))
+    }
+
+    #[test]
+    fn parses_rules() {
+        assert_eq!(
+            Rule::parse("*.Example.com").unwrap(),
+            Rule::Domain("example.com".into())
+        );
+        assert_eq!(
+            Rule::parse("10.1.2.3/8").unwrap(),
+            Rule::Network("10.0.0.0/8".parse().unwrap())
+        );
+        assert_eq!(
+            Rule::parse("::1").unwrap(),
+            Rule::Network("::1/128".parse().unwrap())
+        );
+        assert!(Rule::parse("10.0.0.0/99").is_err());
+        assert!(Rule::parse("*").is_err());
+
+        // an empty variable is a list with an empty entry
+        assert!(ProxyRules::new(&["".into()], &[]).unwrap().allow.is_empty());
+    }
+
+    #[test]
+    fn blocks_private_addresses() {
+        let rules = rules(&[], &[]);
+
+        for blocked in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::ffff:127.0.0.1",
+            "fd00:ec2::254",
+            "fe80::1",
+        ] {
+            assert!(is_forbidden(rules.check(None, &[ip(blocked)])), "{blocked}");
+        }
+
+        assert!(rules.check(None, &[ip("8.8.8.8")]).is_ok());
+        assert!(rules
+            .check(Some("example.com"), &[ip("2606:4700::1")])
+            .is_ok());
+
+        // every address a domain resolves to is checked
+        let ips = [ip("8.8.8.8"), ip("127.0.0.1")];
+        assert!(is_forbidden(rules.check(Some("example.com"), &ips)));
+    }
+
+    #[test]
+    fn applies_allow_and_deny_lists() {
+        let rules = rules(
+            &["example.com", "10.1.0.0/16"],
+            &["internal.example.com", "8.8.4.4"],
+        );
+
+        assert!(rules
+            .check(Some("api.example.com"), &[ip("8.8.8.8")])
+            .is_ok());
+        assert!(is_forbidden(
+            rules.check(Some("notexample.com"), &[ip("8.8.8.8")])
+        ));
+        assert!(is_forbidden(
+            rules.check(Some("quadratic.com"), &[ip("8.8.8.8")])
+        ));
+
+        // deny wins over allow
+        assert!(is_forbidden(
+            rules.check(Some("db.internal.example.com"), &[ip("8.8.8.8")])
+        ));
+        assert!(is_forbidden(
+            rules.check(Some("example.com"), &[ip("8.8.4.4")])
+        ));
+
+        // an allowed network can contain private addresses, an allowed domain
+        // can't resolve to them
+        assert!(rules.check(None, &[ip("10.1.2.3")]).is_ok());
+        assert!(is_forbidden(rules.check(None, &[ip("10.2.0.1")])));
+        assert!(is_forbidden(
+            rules.check(Some("example.com"), &[ip("127.0.0.1")])
+        ));
+    }
+
+    #[tokio::test]
+    async fn checks_urls() {
+        let rules = rules(&[], &[]);
+        let check = |url: &str| {
+            let url = Url::parse(url).unwrap();
+            let rules = rules.clone();
+            async move { rules.check_url(&url).await }
+        };
+
+        for url in [
+            "file:///etc/passwd",
+            "gopher://example.com",
+            "http://localhost:3003/health",
+            "http://127.0.0.1/",
+            "http://[::1]:8080/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://0x7f000001/",
+        ] {
+            assert!(is_forbidden(check(url).await), "{url}");
+        }
+
+        assert!(rules
+            .check_redirect(&Url::parse("https://example.com/").unwrap())
+            .is_ok());
+        assert!(is_forbidden(
+            rules.check_redirect(&Url::parse("http://10.0.0.1/").unwrap())
+        ));
+    }
+}
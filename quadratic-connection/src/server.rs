@@ -4,13 +4,20 @@
 //! to be shared across all requests and threads.  Adds tracing/logging.
 
 use axum::{
-    http::{header::AUTHORIZATION, Method},
+    extract::Path,
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        Method, StatusCode,
+    },
+    response::IntoResponse,
     routing::{any, get, post},
     Extension, Json, Router,
 };
 use jsonwebtoken::jwk::JwkSet;
 use quadratic_rust_shared::auth::jwt::get_jwks;
-use quadratic_rust_shared::sql::Connection;
+use quadratic_rust_shared::metrics::CONTENT_TYPE as METRICS_CONTENT_TYPE;
+use quadratic_rust_shared::sql::{Connection, SqlParameter};
+use quadratic_rust_shared::ssh::SshConfig;
 use serde::{Deserialize, Serialize};
 use std::{iter::once, time::Duration};
 use tokio::{sync::OnceCell, time};
@@ -23,15 +30,18 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use uuid::Uuid;
 
 use crate::{
-    auth::get_middleware,
+    auth::{get_middleware, Claims},
     config::config,
     error::{ConnectionError, Result},
     proxy::proxy,
     sql::{
+        duckdb::{query as query_duckdb, schema as schema_duckdb, test as test_duckdb},
+        mssql::{query as query_mssql, schema as schema_mssql, test as test_mssql},
         mysql::{query as query_mysql, schema as schema_mysql, test as test_mysql},
         postgres::{query as query_postgres, schema as schema_postgres, test as test_postgres},
+        sqlite::{query as query_sqlite, schema as schema_sqlite, test as test_sqlite},
     },
-    state::State,
+    state::{tunnels::SSH_TUNNEL_IDLE_TIMEOUT, State},
 };
 
 const HEALTHCHECK_INTERVAL_S: u64 = 5;
@@ -55,6 +65,33 @@ pub(crate) async fn get_const_jwks() -> &'static JwkSet {
 pub(crate) struct SqlQuery {
     pub(crate) query: String,
     pub(crate) connection_id: Uuid,
+    /// Values bound to the query's placeholders, eg, `$1` or `?`
+    #[serde(default)]
+    pub(crate) parameters: Vec<SqlParameter>,
+    /// The maximum number of rows to return
+    #[serde(default)]
+    pub(crate) limit: Option<u64>,
+    /// The number of rows to skip before returning rows
+    #[serde(default)]
+    pub(crate) offset: u64,
+    /// Identifies the query so it can be cancelled with `/cancel/:id`
+    #[serde(default)]
+    pub(crate) query_id: Option<Uuid>,
+    /// How long the query may run, capped at `QUERY_TIMEOUT_MS`
+    #[serde(default)]
+    pub(crate) timeout_ms: Option<u64>,
+    /// Run the query instead of returning cached results
+    #[serde(default)]
+    pub(crate) refresh: bool,
+}
+
+/// A connection to test, which may be through an SSH bastion
+#[derive(Deserialize)]
+pub(crate) struct TestConnection<T> {
+    #[serde(flatten)]
+    pub(crate) connection: T,
+    #[serde(default)]
+    pub(crate) ssh: Option<SshConfig>,
 }
 
 #[derive(Serialize, PartialEq, Debug)]
@@ -112,6 +149,21 @@ pub(crate) fn app(state: State) -> Result<Router> {
         .route("/mysql/test", post(test_mysql))
         .route("/mysql/query", post(query_mysql))
         .route("/mysql/schema/:id", get(schema_mysql))
+        // mssql
+        .route("/mssql/test", post(test_mssql))
+        .route("/mssql/query", post(query_mssql))
+        .route("/mssql/schema/:id", get(schema_mssql))
+        // sqlite
+        .route("/sqlite/test", post(test_sqlite))
+        .route("/sqlite/query", post(query_sqlite))
+        .route("/sqlite/schema/:id", get(schema_sqlite))
+        // duckdb
+        .route("/duckdb/test", post(test_duckdb))
+        .route("/duckdb/query", post(query_duckdb))
+        .route("/duckdb/schema/:id", get(schema_duckdb))
+        //
+        // cancel a running query
+        .route("/cancel/:id", post(cancel_query))
         //
         // proxy
         .route("/proxy", any(proxy))
@@ -122,6 +174,9 @@ pub(crate) fn app(state: State) -> Result<Router> {
         // auth middleware
         .route_layer(auth)
         //
+        // unprotected routes with state
+        .route("/metrics", get(metrics))
+        //
         // state, required
         .with_state(state.clone())
         //
@@ -178,6 +233,12 @@ pub(crate) async fn serve() -> Result<()> {
             loop {
                 interval.tick().await;
 
+                state
+                    .ssh_tunnels
+                    .lock()
+                    .await
+                    .evict_idle(SSH_TUNNEL_IDLE_TIMEOUT);
+
                 let stats = state.stats.lock().await;
 
                 // push stats to the logs if there are files to process
@@ -214,6 +275,13 @@ pub(crate) async fn healthcheck() -> Json<HealthResponse> {
     .into()
 }
 
+pub(crate) async fn metrics(Extension(state): Extension<State>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, METRICS_CONTENT_TYPE)],
+        state.stats.lock().await.to_prometheus(),
+    )
+}
+
 pub(crate) async fn static_ips() -> Result<Json<StaticIpsResponse>> {
     let static_ips = config()?.static_ips.to_vec();
     let response = StaticIpsResponse { static_ips };
@@ -221,6 +289,21 @@ pub(crate) async fn static_ips() -> Result<Json<StaticIpsResponse>> {
     Ok(response.into())
 }
 
+/// Cancel a running query.  Only the user that started a query can cancel it.
+pub(crate) async fn cancel_query(
+    Path(query_id): Path<Uuid>,
+    Extension(state): Extension<State>,
+    claims: Claims,
+) -> Result<StatusCode> {
+    state
+        .running_queries
+        .lock()
+        .await
+        .cancel(&query_id, &claims.sub)?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
 pub(crate) async fn test_connection(connection: impl Connection) -> Json<TestResponse> {
     let message = match connection.connect().await {
         Ok(_) => None,
@@ -237,6 +320,7 @@ pub(crate) mod tests {
         body::Body,
         http::{self, Request},
     };
+    use chrono::NaiveDate;
     use http::StatusCode;
     use tower::ServiceExt;
 
@@ -286,4 +370,58 @@ pub(crate) mod tests {
 
         assert_eq!(expected, body);
     }
+
+    #[test]
+    fn deserializes_sql_query_parameters() {
+        let connection_id = Uuid::new_v4();
+        let body = serde_json::json!({
+            "query": "select $1, $2, $3",
+            "connection_id": connection_id,
+            "parameters": [
+                { "type": "Integer", "value": 1 },
+                { "type": "Null" },
+                { "type": "Timestamp", "value": "2024-01-31T09:30:00" },
+            ],
+        });
+        let sql_query: SqlQuery = serde_json::from_value(body).unwrap();
+
+        assert_eq!(
+            sql_query.parameters,
+            vec![
+                SqlParameter::Integer(1),
+                SqlParameter::Null,
+                SqlParameter::Timestamp(
+                    NaiveDate::from_ymd_opt(2024, 1, 31)
+                        .unwrap()
+                        .and_hms_opt(9, 30, 0)
+                        .unwrap()
+                ),
+            ]
+        );
+
+        // parameters are optional
+        let body = serde_json::json!({ "query": "select 1", "connection_id": connection_id });
+        let sql_query: SqlQuery = serde_json::from_value(body).unwrap();
+
+        assert!(sql_query.parameters.is_empty());
+        assert_eq!(sql_query.limit, None);
+        assert_eq!(sql_query.offset, 0);
+        assert_eq!(sql_query.query_id, None);
+        assert_eq!(sql_query.timeout_ms, None);
+        assert!(!sql_query.refresh);
+    }
+
+    #[test]
+    fn deserializes_sql_query_limits() {
+        let body = serde_json::json!({
+            "query": "select * from users",
+            "connection_id": Uuid::new_v4(),
+            "limit": 100,
+            "offset": 200,
+        });
+        let sql_query: SqlQuery = serde_json::from_value(body).unwrap();
+
+        assert_eq!(sql_query.limit, Some(100));
+        assert_eq!(sql_query.offset, 200);
+    }
 }
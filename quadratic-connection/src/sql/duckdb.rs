@@ -0,0 +1,237 @@
+use axum::{
+    extract::{Path, Query},
+    response::IntoResponse,
+    Extension, Json,
+};
+use quadratic_rust_shared::{
+    quadratic_api::Connection as ApiConnection, sql::duckdb_connection::DuckDbConnection,
+};
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    connection::get_api_connection,
+    error::Result,
+    server::{test_connection, SqlQuery, TestResponse},
+    state::State,
+};
+
+use super::{local_database_path, query_generic, schema_generic, Schema, SchemaQuery};
+
+/// Test the connection to the database.
+pub(crate) async fn test(
+    state: Extension<State>,
+    Json(connection): Json<DuckDbConnection>,
+) -> Result<Json<TestResponse>> {
+    let database = local_database_path(&state, &connection.database)?;
+
+    Ok(test_connection(DuckDbConnection::new(database, connection.read_only)).await)
+}
+
+/// Get the connection details from the API and create a DuckDbConnection.
+async fn get_connection(
+    state: &State,
+    claims: &Claims,
+    connection_id: &Uuid,
+) -> Result<(DuckDbConnection, ApiConnection)> {
+    let connection = if cfg!(not(test)) {
+        get_api_connection(state, "", &claims.sub, connection_id).await?
+    } else {
+        ApiConnection {
+            uuid: *connection_id,
+            name: "".into(),
+            r#type: "".into(),
+            created_date: "".into(),
+            updated_date: "".into(),
+            type_details: quadratic_rust_shared::quadratic_api::TypeDetails {
+                host: "".into(),
+                port: None,
+                username: None,
+                password: None,
+                database: format!("{connection_id}.duckdb"),
+                ssh: None,
+                read_only: false,
+                trust_cert: false,
+            },
+        }
+    };
+
+    let type_details = &connection.type_details;
+    let database = local_database_path(state, &type_details.database)?;
+    let duckdb_connection = DuckDbConnection::new(database, type_details.read_only);
+
+    Ok((duckdb_connection, connection))
+}
+
+/// Query the database and return the results as a parquet file.
+pub(crate) async fn query(
+    state: Extension<State>,
+    claims: Claims,
+    sql_query: Json<SqlQuery>,
+) -> Result<impl IntoResponse> {
+    let (connection, api_connection) =
+        get_connection(&state, &claims, &sql_query.connection_id).await?;
+    query_generic::<DuckDbConnection>(connection, state, &claims, sql_query, &api_connection).await
+}
+
+/// Get the schema of the database
+pub(crate) async fn schema(
+    Path(id): Path<Uuid>,
+    Query(schema_query): Query<SchemaQuery>,
+    state: Extension<State>,
+    claims: Claims,
+) -> Result<Json<Schema>> {
+    let (connection, api_connection) = get_connection(&state, &claims, &id).await?;
+    schema_generic::<DuckDbConnection>(connection, api_connection, &state, schema_query).await
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{
+        num_vec,
+        test_util::{get_claims, new_state, response_bytes, str_vec, validate_parquet},
+    };
+    use arrow_schema::DataType;
+    use bytes::Bytes;
+    use http::StatusCode;
+    use quadratic_rust_shared::sql::{SchemaColumn, SchemaTable, SqlParameter, TableKind};
+    use tracing_test::traced_test;
+    use uuid::Uuid;
+
+    /// Create the database file for a connection in the local database directory.
+    fn new_database(state: &State, connection_id: &Uuid) {
+        let database = format!("{connection_id}.duckdb");
+        let pool =
+            ::duckdb::Connection::open(local_database_path(state, &database).unwrap()).unwrap();
+
+        pool.execute_batch(
+            "create table users (id integer primary key, name varchar);
+            insert into users values (1, 'Ada'), (2, 'Grace');",
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn duckdb_test_connection() {
+        let connection_id = Uuid::new_v4();
+        let state = Extension(new_state().await);
+        new_database(&state, &connection_id);
+
+        let connection = DuckDbConnection::new(format!("{connection_id}.duckdb"), false);
+        let response = test(state.clone(), Json(connection)).await.unwrap();
+        assert_eq!(response.0, TestResponse::new(true, None));
+
+        // only files in the local database directory can be opened
+        let connection = DuckDbConnection::new("/etc/passwd".into(), false);
+        assert!(test(state, Json(connection)).await.is_err());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn duckdb_schema() {
+        let connection_id = Uuid::new_v4();
+        let state = Extension(new_state().await);
+        new_database(&state, &connection_id);
+
+        let response = schema(
+            Path(connection_id),
+            Query(SchemaQuery::default()),
+            state,
+            get_claims(),
+        )
+        .await
+        .unwrap();
+
+        let expected = Schema {
+            id: response.0.id,
+            name: "".into(),
+            r#type: "".into(),
+            database: format!("{connection_id}.duckdb"),
+            tables: vec![SchemaTable {
+                name: "users".into(),
+                schema: "main".into(),
+                kind: TableKind::Table,
+                row_estimate: response.0.tables[0].row_estimate,
+                comment: None,
+                columns: vec![
+                    SchemaColumn {
+                        name: "id".into(),
+                        r#type: "INTEGER".into(),
+                        is_nullable: false,
+                        is_primary_key: true,
+                        comment: None,
+                    },
+                    SchemaColumn {
+                        name: "name".into(),
+                        r#type: "VARCHAR".into(),
+                        is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
+                    },
+                ],
+                foreign_keys: vec![],
+            }],
+        };
+
+        assert_eq!(response.0, expected);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn duckdb_query_with_parameters() {
+        let connection_id = Uuid::new_v4();
+        let state = Extension(new_state().await);
+        new_database(&state, &connection_id);
+
+        let sql_query = SqlQuery {
+            query: "select id, name from users where name = $1".into(),
+            connection_id,
+            parameters: vec![SqlParameter::Text("Grace".into())],
+            limit: None,
+            offset: 0,
+            query_id: None,
+            timeout_ms: None,
+            refresh: false,
+        };
+        let data = query(state, get_claims(), Json(sql_query)).await.unwrap();
+        let response = data.into_response();
+
+        let expected = vec![
+            (DataType::Int32, num_vec!(2_i32)),
+            (DataType::Utf8, str_vec("Grace")),
+        ];
+
+        assert_eq!(response.status(), StatusCode::OK);
+        validate_parquet(response, expected).await;
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn duckdb_query_max_response_bytes() {
+        let connection_id = Uuid::new_v4();
+        let mut state = Extension(new_state().await);
+        new_database(&state, &connection_id);
+        state.settings.max_response_bytes = 0;
+
+        let sql_query = SqlQuery {
+            query: "select * from users".into(),
+            connection_id,
+            parameters: vec![],
+            limit: None,
+            offset: 0,
+            query_id: None,
+            timeout_ms: None,
+            refresh: false,
+        };
+        let data = query(state, get_claims(), Json(sql_query)).await.unwrap();
+        let response = data.into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response_bytes(response).await;
+        assert_eq!(body, Bytes::new());
+    }
+}
@@ -1,18 +1,53 @@
-use axum::{http::HeaderMap, response::IntoResponse, Extension, Json};
-use quadratic_rust_shared::sql::{Connection, SchemaTable};
-use serde::Serialize;
-use tokio::time::Instant;
+use std::{
+    convert::Infallible,
+    future::ready,
+    path::{Component, Path},
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    http::{HeaderMap, HeaderValue},
+    response::IntoResponse,
+    Extension, Json,
+};
+use bytes::Bytes;
+use futures::{stream, Stream, StreamExt};
+use quadratic_rust_shared::{
+    quadratic_api::Connection as ApiConnection,
+    sql::{
+        read_only::{check_read_only, has_executable_comments, normalize_read_only},
+        Connection, ParquetChunks, ParquetQuery, QueryHandle, QueryLimits, SchemaTable,
+    },
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{
+        mpsc::{self, UnboundedReceiver},
+        Notify,
+    },
+    task::JoinHandle,
+    time::{sleep, Instant},
+};
 use uuid::Uuid;
 
 use crate::{
-    error::Result,
+    auth::Claims,
+    error::{ConnectionError, Result},
     header::{number_header, time_header},
     server::SqlQuery,
-    state::State,
+    state::{
+        queries::RunningQueryGuard,
+        query_cache::{get_cached, insert_cached, query_cache_key},
+        State,
+    },
 };
 
+pub(crate) mod duckdb;
+pub(crate) mod mssql;
 pub(crate) mod mysql;
 pub(crate) mod postgres;
+pub(crate) mod sqlite;
 
 #[derive(Debug, Serialize, PartialEq)]
 pub struct Schema {
@@ -23,43 +58,414 @@ pub struct Schema {
     pub tables: Vec<SchemaTable>,
 }
 
-/// Query the database and return the results as a parquet file.
-pub(crate) async fn query_generic<T: Connection>(
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct SchemaQuery {
+    /// Read the schema from the database instead of the cache
+    #[serde(default)]
+    refresh: bool,
+}
+
+/// Get the schema of the database.  Schemas are cached per connection, see
+/// `SchemaCache`, unless `refresh` is set.  The connection must be fetched
+/// from the API first, so the user is authorized for every request.
+pub(crate) async fn schema_generic<T: Connection + Sync>(
+    connection: T,
+    api_connection: ApiConnection,
+    state: &State,
+    schema_query: SchemaQuery,
+) -> Result<Json<Schema>> {
+    let id = api_connection.uuid;
+    let updated_date = &api_connection.updated_date;
+
+    let cached = match schema_query.refresh {
+        true => None,
+        false => state.schemas.lock().await.get(&id, updated_date),
+    };
+
+    let tables = match cached {
+        Some(tables) => tables,
+        None => {
+            let pool = connection.connect().await?;
+            let tables: Vec<SchemaTable> = connection
+                .schema(pool)
+                .await?
+                .tables
+                .into_values()
+                .collect();
+
+            state
+                .schemas
+                .lock()
+                .await
+                .insert(id, updated_date, tables.to_owned());
+
+            tables
+        }
+    };
+
+    let schema = Schema {
+        id,
+        name: api_connection.name,
+        r#type: api_connection.r#type,
+        database: api_connection.type_details.database,
+        tables,
+    };
+
+    Ok(Json(schema))
+}
+
+/// The number of rows in each Parquet row group, and the most rows held in
+/// memory at once while a query's results are converted.
+const QUERY_BATCH_SIZE: usize = 10_000;
+
+/// Query the database and return the results as a parquet file.  Rows are
+/// read from the database and converted to Parquet row groups a batch at a
+/// time, and each row group is streamed to the client as it's written.  The
+/// number of rows and whether `MAX_RESPONSE_BYTES` stopped the query early
+/// are only known once the last row is read, so they're written to the
+/// file's footer, see `RECORD_COUNT_KEY` and `OVER_THE_LIMIT_KEY`.
+///
+/// Errors before the first row group is written are returned as errors.  The
+/// response has started after that, so later errors end the response early,
+/// leaving the file without a footer.
+///
+/// The query is tracked by its `query_id` while it runs, so it can be
+/// cancelled by `/cancel/:id`.
+///
+/// On `read_only` connections, statements that could write are rejected
+/// before connecting, and the query runs in a read-only session.
+///
+/// When the query cache is enabled, the results of queries that only read are
+/// cached, see `QueryCache`, and returned until they expire unless `refresh`
+/// is set.  The `CACHE-STATUS` header is `HIT`, `MISS`, or `BYPASS` for
+/// queries that aren't cached.
+pub(crate) async fn query_generic<T: Connection + Send + Sync + 'static>(
     connection: T,
     state: Extension<State>,
+    claims: &Claims,
     sql_query: Json<SqlQuery>,
+    api_connection: &ApiConnection,
 ) -> Result<impl IntoResponse> {
     let mut headers = HeaderMap::new();
     let start = Instant::now();
-    let max_response_bytes = state.settings.max_response_bytes;
+    let query_id = sql_query.query_id.unwrap_or_else(Uuid::new_v4);
+    let cache_key = query_cache_key_for(&connection, &state, &sql_query, api_connection).await;
 
-    // let connection = get_connection(&*state, &claims, &sql_query.connection_id).await?;
-    // headers.insert("ELAPSED-API-CONNECTION-MS", time_header(start));
+    let cached = match (&cache_key, sql_query.refresh) {
+        (Some(cache_key), false) => get_cached(&state.query_cache, cache_key).await,
+        _ => None,
+    };
 
-    let start_connect = Instant::now();
-    let pool = connection.connect().await?;
+    if let Some((chunks, age)) = cached {
+        headers.insert("CACHE-STATUS", HeaderValue::from_static("HIT"));
+        headers.insert("CACHE-AGE-MS", number_header(age.as_millis()));
+        let chunks = stream::iter(chunks.into_iter().map(Ok::<_, Infallible>));
 
-    headers.insert("ELAPSED-DATABASE-CONNECTION-MS", time_header(start_connect));
+        return Ok(parquet_response(&state, Body::from_stream(chunks), headers, start).await);
+    }
 
+    let cancelled = state
+        .running_queries
+        .lock()
+        .await
+        .start(query_id, &claims.sub)?;
+    let running = RunningQueryGuard::new(&state.running_queries, query_id);
+
+    let timeout = query_timeout(&state, sql_query.timeout_ms);
+    let connected = connect_for_query(
+        &connection,
+        &sql_query,
+        api_connection.type_details.read_only,
+        timeout,
+        &mut headers,
+    )
+    .await?;
+
+    // rows are converted to Parquet as they're read, so the query and the
+    // conversion are timed together, until the first row group is written
     let start_query = Instant::now();
-    let (rows, over_the_limit) = connection
-        .query(pool, &sql_query.query, Some(max_response_bytes))
-        .await?;
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let limits = QueryLimits {
+        max_bytes: Some(state.settings.max_response_bytes),
+        limit: sql_query.limit,
+        offset: sql_query.offset,
+        batch_size: QUERY_BATCH_SIZE,
+    };
+    let Json(sql_query) = sql_query;
+
+    let task = tokio::spawn(async move {
+        let result = query_with_timeout(
+            &connection,
+            connected,
+            &sql_query,
+            limits,
+            timeout,
+            &cancelled,
+            sender,
+        )
+        .await;
+
+        drop(running);
+        result
+    });
+
+    let Some(first_chunk) = receiver.recv().await else {
+        // nothing was written, so the query failed
+        join_query(task).await?;
+
+        return Err(ConnectionError::Query("The query had no results".into()));
+    };
 
-    headers.insert("RECORD-COUNT", number_header(rows.len()));
     headers.insert("ELAPSED-DATABASE-QUERY-MS", time_header(start_query));
-    headers.insert("OVER-THE-LIMIT", number_header(over_the_limit));
 
-    let start_conversion = Instant::now();
-    let parquet = T::to_parquet(rows)?;
+    let cache_status = match cache_key {
+        Some(_) => "MISS",
+        None => "BYPASS",
+    };
+    headers.insert("CACHE-STATUS", HeaderValue::from_static(cache_status));
 
-    headers.insert(
-        "ELAPSED-PARQUET-CONVERSION-MS",
-        time_header(start_conversion),
-    );
+    let results = QueryResults {
+        chunks: receiver,
+        task,
+        state: state.0.clone(),
+        cache: cache_key.map(|key| (key, vec![first_chunk.to_owned()])),
+    };
+    let body = stream::once(ready(Ok(first_chunk))).chain(results.into_stream());
+
+    Ok(parquet_response(&state, Body::from_stream(body), headers, start).await)
+}
+
+/// The rest of a running query's Parquet file, after its first row group
+struct QueryResults {
+    chunks: UnboundedReceiver<Bytes>,
+    task: JoinHandle<Result<ParquetQuery>>,
+    state: State,
+    /// The file's chunks so far, which are cached once the query completes
+    cache: Option<(String, Vec<Bytes>)>,
+}
+
+impl QueryResults {
+    /// Stream the chunks as they're written, ending with an error if the
+    /// query fails.  If the client disconnects, the chunks are dropped and
+    /// the query stops when its next row group is written.
+    fn into_stream(self) -> impl Stream<Item = Result<Bytes>> {
+        stream::unfold(Some(self), |results| async move {
+            let mut results = results?;
+
+            match results.chunks.recv().await {
+                Some(chunk) => {
+                    results.cache_chunk(&chunk).await;
+                    Some((Ok(chunk), Some(results)))
+                }
+                None => match join_query(results.task).await {
+                    Ok(_) => {
+                        if let Some((key, chunks)) = results.cache {
+                            insert_cached(&results.state.query_cache, key, chunks).await;
+                        }
+
+                        None
+                    }
+                    Err(error) => Some((Err(error), None)),
+                },
+            }
+        })
+    }
+
+    /// Keep a chunk for the query cache, unless the results are too large to
+    /// be cached
+    async fn cache_chunk(&mut self, chunk: &Bytes) {
+        let Some((_, chunks)) = &mut self.cache else {
+            return;
+        };
+
+        chunks.push(chunk.to_owned());
+
+        let bytes = chunks.iter().map(|chunk| chunk.len() as u64).sum::<u64>();
+
+        if bytes > self.state.query_cache.lock().await.max_bytes() {
+            self.cache = None;
+        }
+    }
+}
 
+/// Wait for a query's task to finish
+async fn join_query(task: JoinHandle<Result<ParquetQuery>>) -> Result<ParquetQuery> {
+    task.await
+        .map_err(|e| ConnectionError::Query(format!("Error running query: {e}")))?
+}
+
+/// The query's key in the query cache, or None if the cache is disabled or
+/// the query might write, as replaying the results would skip the write.
+/// MySQL queries with executable comments aren't cached, as the comments are
+/// dropped when the SQL is normalized.
+async fn query_cache_key_for<T: Connection>(
+    connection: &T,
+    state: &State,
+    sql_query: &SqlQuery,
+    api_connection: &ApiConnection,
+) -> Option<String> {
+    if !state.query_cache.lock().await.enabled() {
+        return None;
+    }
+
+    let dialect = connection.dialect();
+
+    if has_executable_comments(dialect.as_ref(), &sql_query.query) {
+        return None;
+    }
+
+    let sql = normalize_read_only(dialect.as_ref(), &sql_query.query).ok()?;
+
+    Some(query_cache_key(
+        &api_connection.uuid,
+        &api_connection.updated_date,
+        &sql,
+        &sql_query.parameters,
+        sql_query.limit,
+        sql_query.offset,
+    ))
+}
+
+async fn parquet_response(
+    state: &State,
+    body: Body,
+    mut headers: HeaderMap,
+    start: Instant,
+) -> (HeaderMap, Body) {
     state.stats.lock().await.last_query_time = Some(Instant::now());
     headers.insert("ELAPSED-TOTAL-MS", time_header(start));
 
-    Ok((headers, parquet))
+    (headers, body)
+}
+
+/// Connect to the database for a query.  The timeout is also set as the
+/// database's statement timeout, where it has one.
+async fn connect_for_query<T: Connection>(
+    connection: &T,
+    sql_query: &SqlQuery,
+    read_only: bool,
+    timeout: Duration,
+    headers: &mut HeaderMap,
+) -> Result<(T::Conn, QueryHandle)> {
+    if read_only {
+        check_read_only(connection.dialect().as_ref(), &sql_query.query)?;
+    }
+
+    let start_connect = Instant::now();
+    let mut pool = connection.connect().await?;
+    connection.set_timeout(&mut pool, timeout).await?;
+
+    if read_only {
+        connection.set_read_only(&mut pool).await?;
+    }
+
+    let query_handle = connection.query_handle(&mut pool).await?;
+
+    headers.insert("ELAPSED-DATABASE-CONNECTION-MS", time_header(start_connect));
+
+    Ok((pool, query_handle))
+}
+
+/// Run the query on the connection from `connect_for_query` until it
+/// completes, times out or is cancelled.  A timed out or cancelled query is
+/// cancelled in the database, as dropping the connection doesn't stop a query
+/// that is still running.
+async fn query_with_timeout<T: Connection + Sync>(
+    connection: &T,
+    (pool, query_handle): (T::Conn, QueryHandle),
+    sql_query: &SqlQuery,
+    limits: QueryLimits,
+    timeout: Duration,
+    cancelled: &Notify,
+    chunks: ParquetChunks,
+) -> Result<ParquetQuery> {
+    let mut query = connection.query_parquet(
+        pool,
+        &sql_query.query,
+        &sql_query.parameters,
+        limits,
+        chunks,
+    );
+
+    let result = tokio::select! {
+        result = &mut query => result.map_err(ConnectionError::from),
+        _ = sleep(timeout) => Err(ConnectionError::Timeout(format!(
+            "Query did not complete within {}ms", timeout.as_millis()
+        ))),
+        _ = cancelled.notified() => Err(ConnectionError::Cancelled(
+            "Query was cancelled".into()
+        )),
+    };
+
+    // the query's future owns the connection, so it's only dropped once the
+    // query is cancelled, as embedded databases are interrupted through it
+    if let Err(ConnectionError::Timeout(_) | ConnectionError::Cancelled(_)) = result {
+        if let Err(error) = connection.cancel(query_handle).await {
+            tracing::warn!("Error cancelling query in the database: {error}");
+        }
+    }
+
+    drop(query);
+
+    result
+}
+
+/// The query's timeout, which can't be longer than `QUERY_TIMEOUT_MS`
+fn query_timeout(state: &State, timeout_ms: Option<u64>) -> Duration {
+    let max_timeout_ms = state.settings.query_timeout_ms;
+
+    Duration::from_millis(timeout_ms.map_or(max_timeout_ms, |ms| ms.min(max_timeout_ms)))
+}
+
+/// SQLite and DuckDB databases are files in the local database directory.
+/// Only a file name is accepted, so a connection can't open other files on
+/// the server.
+pub(crate) fn local_database_path(state: &State, database: &str) -> Result<String> {
+    let mut components = Path::new(database).components();
+
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(file_name)), None) => {
+            Ok(Path::new(&state.settings.local_database_dir)
+                .join(file_name)
+                .to_string_lossy()
+                .to_string())
+        }
+        _ => Err(ConnectionError::Connection(format!(
+            "Invalid database file name: {database}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::new_state;
+
+    #[tokio::test]
+    async fn local_database_paths_are_in_the_local_database_dir() {
+        let state = new_state().await;
+        let dir = Path::new(&state.settings.local_database_dir);
+
+        assert_eq!(
+            local_database_path(&state, "sales.db").unwrap(),
+            dir.join("sales.db").to_string_lossy()
+        );
+
+        for database in ["", "../sales.db", "/etc/sales.db", "data/sales.db", "."] {
+            assert!(local_database_path(&state, database).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn query_timeouts_are_capped() {
+        let mut state = new_state().await;
+        state.settings.query_timeout_ms = 1_000;
+
+        assert_eq!(query_timeout(&state, None), Duration::from_millis(1_000));
+        assert_eq!(query_timeout(&state, Some(10)), Duration::from_millis(10));
+        assert_eq!(
+            query_timeout(&state, Some(5_000)),
+            Duration::from_millis(1_000)
+        );
+    }
 }
@@ -0,0 +1,260 @@
+use axum::{
+    extract::{Path, Query},
+    response::IntoResponse,
+    Extension, Json,
+};
+use quadratic_rust_shared::{
+    quadratic_api::Connection as ApiConnection, sql::mssql_connection::MsSqlConnection,
+};
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    connection::{get_api_connection, test_tunnel_address, tunnel_address},
+    error::Result,
+    server::{test_connection, SqlQuery, TestConnection, TestResponse},
+    state::State,
+};
+
+use super::{query_generic, schema_generic, Schema, SchemaQuery};
+
+/// The port the database listens on when a connection doesn't have one
+const DEFAULT_PORT: u16 = 1433;
+
+/// Test the connection to the database.
+pub(crate) async fn test(
+    state: Extension<State>,
+    Json(test): Json<TestConnection<MsSqlConnection>>,
+) -> Json<TestResponse> {
+    let TestConnection {
+        mut connection,
+        ssh,
+    } = test;
+    let address = test_tunnel_address(
+        &state,
+        ssh.as_ref(),
+        &connection.host,
+        connection.port.as_deref(),
+        DEFAULT_PORT,
+    )
+    .await;
+
+    match address {
+        Ok((host, port, _tunnel)) => {
+            (connection.host, connection.port) = (host, port);
+            test_connection(connection).await
+        }
+        Err(error) => TestResponse::new(false, Some(error.to_string())).into(),
+    }
+}
+
+/// Get the connection details from the API and create a MsSqlConnection.
+async fn get_connection(
+    state: &State,
+    claims: &Claims,
+    connection_id: &Uuid,
+) -> Result<(MsSqlConnection, ApiConnection)> {
+    let connection = if cfg!(not(test)) {
+        get_api_connection(state, "", &claims.sub, connection_id).await?
+    } else {
+        ApiConnection {
+            uuid: *connection_id,
+            name: "".into(),
+            r#type: "".into(),
+            created_date: "".into(),
+            updated_date: "".into(),
+            type_details: quadratic_rust_shared::quadratic_api::TypeDetails {
+                host: "0.0.0.0".into(),
+                port: Some("1433".into()),
+                username: Some("sa".into()),
+                password: Some("yourStrong(!)Password".into()),
+                database: "mssql-connection".into(),
+                ssh: None,
+                read_only: false,
+                trust_cert: true,
+            },
+        }
+    };
+
+    let type_details = &connection.type_details;
+    let (host, port) = tunnel_address(
+        state,
+        type_details.ssh.as_ref(),
+        &type_details.host,
+        type_details.port.as_deref(),
+        DEFAULT_PORT,
+    )
+    .await?;
+
+    let mssql_connection = MsSqlConnection::new(
+        type_details.username.to_owned(),
+        type_details.password.to_owned(),
+        host,
+        port,
+        Some(type_details.database.to_owned()),
+        type_details.trust_cert,
+    );
+
+    Ok((mssql_connection, connection))
+}
+
+/// Query the database and return the results as a parquet file.
+pub(crate) async fn query(
+    state: Extension<State>,
+    claims: Claims,
+    sql_query: Json<SqlQuery>,
+) -> Result<impl IntoResponse> {
+    let (connection, api_connection) =
+        get_connection(&state, &claims, &sql_query.connection_id).await?;
+    query_generic::<MsSqlConnection>(connection, state, &claims, sql_query, &api_connection).await
+}
+
+/// Get the schema of the database
+pub(crate) async fn schema(
+    Path(id): Path<Uuid>,
+    Query(schema_query): Query<SchemaQuery>,
+    state: Extension<State>,
+    claims: Claims,
+) -> Result<Json<Schema>> {
+    let (connection, api_connection) = get_connection(&state, &claims, &id).await?;
+    schema_generic::<MsSqlConnection>(connection, api_connection, &state, schema_query).await
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{
+        num_vec, test_connection,
+        test_util::{get_claims, new_state, response_bytes, str_vec, validate_parquet},
+    };
+    use arrow::datatypes::Date32Type;
+    use arrow_schema::{DataType, TimeUnit};
+    use bytes::Bytes;
+    use chrono::{NaiveDate, NaiveTime, Timelike};
+    use http::StatusCode;
+    use quadratic_rust_shared::sql::{SchemaColumn, SqlParameter};
+    use tracing_test::traced_test;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    #[traced_test]
+    async fn mssql_test_connection() {
+        test_connection!(get_connection);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn mssql_schema() {
+        let connection_id = Uuid::new_v4();
+        let state = Extension(new_state().await);
+        let response = schema(
+            Path(connection_id),
+            Query(SchemaQuery::default()),
+            state,
+            get_claims(),
+        )
+        .await
+        .unwrap();
+
+        let table = &response.0.tables[0];
+
+        assert_eq!(response.0.database, "mssql-connection");
+        assert_eq!(table.name, "all_native_data_types");
+        assert_eq!(table.schema, "dbo");
+        assert_eq!(
+            table.columns[..3],
+            [
+                SchemaColumn {
+                    name: "id".into(),
+                    r#type: "int".into(),
+                    is_nullable: false,
+                    is_primary_key: true,
+                    comment: None,
+                },
+                SchemaColumn {
+                    name: "tinyint_col".into(),
+                    r#type: "tinyint".into(),
+                    is_nullable: true,
+                    is_primary_key: false,
+                    comment: None,
+                },
+                SchemaColumn {
+                    name: "smallint_col".into(),
+                    r#type: "smallint".into(),
+                    is_nullable: true,
+                    is_primary_key: false,
+                    comment: None,
+                },
+            ]
+        );
+        assert_eq!(table.columns.len(), 28);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn mssql_query_with_parameters() {
+        let connection_id = Uuid::new_v4();
+        let sql_query = SqlQuery {
+            query: "select id, money_col, date_col, time_col, varchar_col
+                from all_native_data_types where varchar_col = @P1"
+                .into(),
+            connection_id,
+            parameters: vec![SqlParameter::Text("varchar_data".into())],
+            limit: None,
+            offset: 0,
+            query_id: None,
+            timeout_ms: None,
+            refresh: false,
+        };
+        let state = Extension(new_state().await);
+        let data = query(state, get_claims(), Json(sql_query)).await.unwrap();
+        let response = data.into_response();
+
+        let expected = vec![
+            (DataType::Int32, num_vec!(1_i32)),
+            (DataType::Float64, num_vec!(12345.67_f64)),
+            (
+                DataType::Date32,
+                num_vec!(Date32Type::from_naive_date(
+                    NaiveDate::parse_from_str("2024-05-28", "%Y-%m-%d").unwrap(),
+                )),
+            ),
+            (
+                DataType::Time32(TimeUnit::Second),
+                num_vec!(NaiveTime::parse_from_str("12:34:56", "%H:%M:%S")
+                    .unwrap()
+                    .num_seconds_from_midnight()),
+            ),
+            (DataType::Utf8, str_vec("varchar_data")),
+        ];
+
+        assert_eq!(response.status(), StatusCode::OK);
+        validate_parquet(response, expected).await;
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn mssql_query_max_response_bytes() {
+        let connection_id = Uuid::new_v4();
+        let sql_query = SqlQuery {
+            query: "select top 1 * from all_native_data_types order by id".into(),
+            connection_id,
+            parameters: vec![],
+            limit: None,
+            offset: 0,
+            query_id: None,
+            timeout_ms: None,
+            refresh: false,
+        };
+        let mut state = Extension(new_state().await);
+        state.settings.max_response_bytes = 0;
+        let data = query(state, get_claims(), Json(sql_query)).await.unwrap();
+        let response = data.into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response_bytes(response).await;
+        assert_eq!(body, Bytes::new());
+    }
+}
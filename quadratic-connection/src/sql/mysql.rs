@@ -1,23 +1,51 @@
-use axum::{extract::Path, response::IntoResponse, Extension, Json};
+use axum::{
+    extract::{Path, Query},
+    response::IntoResponse,
+    Extension, Json,
+};
 use quadratic_rust_shared::{
-    quadratic_api::Connection as ApiConnection,
-    sql::{mysql_connection::MySqlConnection, Connection},
+    quadratic_api::Connection as ApiConnection, sql::mysql_connection::MySqlConnection,
 };
 use uuid::Uuid;
 
 use crate::{
     auth::Claims,
-    connection::get_api_connection,
+    connection::{get_api_connection, test_tunnel_address, tunnel_address},
     error::Result,
-    server::{test_connection, SqlQuery, TestResponse},
+    server::{test_connection, SqlQuery, TestConnection, TestResponse},
     state::State,
 };
 
-use super::{query_generic, Schema};
+use super::{query_generic, schema_generic, Schema, SchemaQuery};
+
+/// The port the database listens on when a connection doesn't have one
+const DEFAULT_PORT: u16 = 3306;
 
 /// Test the connection to the database.
-pub(crate) async fn test(Json(connection): Json<MySqlConnection>) -> Json<TestResponse> {
-    test_connection(connection).await
+pub(crate) async fn test(
+    state: Extension<State>,
+    Json(test): Json<TestConnection<MySqlConnection>>,
+) -> Json<TestResponse> {
+    let TestConnection {
+        mut connection,
+        ssh,
+    } = test;
+    let address = test_tunnel_address(
+        &state,
+        ssh.as_ref(),
+        &connection.host,
+        connection.port.as_deref(),
+        DEFAULT_PORT,
+    )
+    .await;
+
+    match address {
+        Ok((host, port, _tunnel)) => {
+            (connection.host, connection.port) = (host, port);
+            test_connection(connection).await
+        }
+        Err(error) => TestResponse::new(false, Some(error.to_string())).into(),
+    }
 }
 
 /// Get the connection details from the API and create a MySqlConnection.
@@ -30,7 +58,7 @@ async fn get_connection(
         get_api_connection(state, "", &claims.sub, connection_id).await?
     } else {
         ApiConnection {
-            uuid: Uuid::new_v4(),
+            uuid: *connection_id,
             name: "".into(),
             r#type: "".into(),
             created_date: "".into(),
@@ -41,16 +69,29 @@ async fn get_connection(
                 username: Some("user".into()),
                 password: Some("password".into()),
                 database: "mysql-connection".into(),
+                ssh: None,
+                read_only: false,
+                trust_cert: false,
             },
         }
     };
 
+    let type_details = &connection.type_details;
+    let (host, port) = tunnel_address(
+        state,
+        type_details.ssh.as_ref(),
+        &type_details.host,
+        type_details.port.as_deref(),
+        DEFAULT_PORT,
+    )
+    .await?;
+
     let pg_connection = MySqlConnection::new(
-        connection.type_details.username.to_owned(),
-        connection.type_details.password.to_owned(),
-        connection.type_details.host.to_owned(),
-        connection.type_details.port.to_owned(),
-        Some(connection.type_details.database.to_owned()),
+        type_details.username.to_owned(),
+        type_details.password.to_owned(),
+        host,
+        port,
+        Some(type_details.database.to_owned()),
     );
 
     Ok((pg_connection, connection))
@@ -62,30 +103,20 @@ pub(crate) async fn query(
     claims: Claims,
     sql_query: Json<SqlQuery>,
 ) -> Result<impl IntoResponse> {
-    let connection = get_connection(&state, &claims, &sql_query.connection_id)
-        .await?
-        .0;
-    query_generic::<MySqlConnection>(connection, state, sql_query).await
+    let (connection, api_connection) =
+        get_connection(&state, &claims, &sql_query.connection_id).await?;
+    query_generic::<MySqlConnection>(connection, state, &claims, sql_query, &api_connection).await
 }
 
 /// Get the schema of the database
 pub(crate) async fn schema(
     Path(id): Path<Uuid>,
+    Query(schema_query): Query<SchemaQuery>,
     state: Extension<State>,
     claims: Claims,
 ) -> Result<Json<Schema>> {
     let (connection, api_connection) = get_connection(&state, &claims, &id).await?;
-    let pool = connection.connect().await?;
-    let database_schema = connection.schema(pool).await?;
-    let schema = Schema {
-        id: api_connection.uuid,
-        name: api_connection.name,
-        r#type: api_connection.r#type,
-        database: api_connection.type_details.database,
-        tables: database_schema.tables.into_values().collect(),
-    };
-
-    Ok(Json(schema))
+    schema_generic::<MySqlConnection>(connection, api_connection, &state, schema_query).await
 }
 
 #[cfg(test)]
@@ -101,7 +132,7 @@ mod tests {
     use bytes::Bytes;
     use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
     use http::StatusCode;
-    use quadratic_rust_shared::sql::{SchemaColumn, SchemaTable};
+    use quadratic_rust_shared::sql::{SchemaColumn, SchemaTable, TableKind};
     use tracing_test::traced_test;
     use uuid::Uuid;
 
@@ -116,9 +147,14 @@ mod tests {
     async fn mysql_schema() {
         let connection_id = Uuid::new_v4();
         let state = Extension(new_state().await);
-        let response = schema(Path(connection_id), state, get_claims())
-            .await
-            .unwrap();
+        let response = schema(
+            Path(connection_id),
+            Query(SchemaQuery::default()),
+            state,
+            get_claims(),
+        )
+        .await
+        .unwrap();
 
         let expected = Schema {
             id: response.0.id,
@@ -128,158 +164,222 @@ mod tests {
             tables: vec![SchemaTable {
                 name: "all_native_data_types".into(),
                 schema: "mysql-connection".into(),
+                kind: TableKind::Table,
+                row_estimate: response.0.tables[0].row_estimate,
+                comment: None,
                 columns: vec![
                     SchemaColumn {
                         name: "id".into(),
                         r#type: "int".into(),
                         is_nullable: false,
+                        is_primary_key: true,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "tinyint_col".into(),
                         r#type: "tinyint".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "smallint_col".into(),
                         r#type: "smallint".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "mediumint_col".into(),
                         r#type: "mediumint".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "int_col".into(),
                         r#type: "int".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "bigint_col".into(),
                         r#type: "bigint".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "decimal_col".into(),
                         r#type: "decimal".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "float_col".into(),
                         r#type: "float".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "double_col".into(),
                         r#type: "double".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "bit_col".into(),
                         r#type: "bit".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "char_col".into(),
                         r#type: "char".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "varchar_col".into(),
                         r#type: "varchar".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "binary_col".into(),
                         r#type: "binary".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "varbinary_col".into(),
                         r#type: "varbinary".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "tinyblob_col".into(),
                         r#type: "tinyblob".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "blob_col".into(),
                         r#type: "blob".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "mediumblob_col".into(),
                         r#type: "mediumblob".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "longblob_col".into(),
                         r#type: "longblob".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "tinytext_col".into(),
                         r#type: "tinytext".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "text_col".into(),
                         r#type: "text".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "mediumtext_col".into(),
                         r#type: "mediumtext".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "longtext_col".into(),
                         r#type: "longtext".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "enum_col".into(),
                         r#type: "enum".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "set_col".into(),
                         r#type: "set".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "date_col".into(),
                         r#type: "date".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "datetime_col".into(),
                         r#type: "datetime".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "timestamp_col".into(),
                         r#type: "timestamp".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "time_col".into(),
                         r#type: "time".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "year_col".into(),
                         r#type: "year".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "json_col".into(),
                         r#type: "json".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                 ],
+                foreign_keys: vec![],
             }],
         };
 
@@ -293,6 +393,12 @@ mod tests {
         let sql_query = SqlQuery {
             query: "select * from all_native_data_types order by id limit 1".into(),
             connection_id,
+            parameters: vec![],
+            limit: None,
+            offset: 0,
+            query_id: None,
+            timeout_ms: None,
+            refresh: false,
         };
         let state = Extension(new_state().await);
         let data = query(state, get_claims(), Json(sql_query)).await.unwrap();
@@ -368,6 +474,12 @@ mod tests {
         let sql_query = SqlQuery {
             query: "select * from all_native_data_types order by id limit 1".into(),
             connection_id,
+            parameters: vec![],
+            limit: None,
+            offset: 0,
+            query_id: None,
+            timeout_ms: None,
+            refresh: false,
         };
         let mut state = Extension(new_state().await);
         state.settings.max_response_bytes = 0;
@@ -1,23 +1,51 @@
-use axum::{extract::Path, response::IntoResponse, Extension, Json};
+use axum::{
+    extract::{Path, Query},
+    response::IntoResponse,
+    Extension, Json,
+};
 use quadratic_rust_shared::{
-    quadratic_api::Connection as ApiConnection,
-    sql::{postgres_connection::PostgresConnection, Connection},
+    quadratic_api::Connection as ApiConnection, sql::postgres_connection::PostgresConnection,
 };
 use uuid::Uuid;
 
 use crate::{
     auth::Claims,
-    connection::get_api_connection,
+    connection::{get_api_connection, test_tunnel_address, tunnel_address},
     error::Result,
-    server::{test_connection, SqlQuery, TestResponse},
+    server::{test_connection, SqlQuery, TestConnection, TestResponse},
     state::State,
 };
 
-use super::{query_generic, Schema};
+use super::{query_generic, schema_generic, Schema, SchemaQuery};
+
+/// The port the database listens on when a connection doesn't have one
+const DEFAULT_PORT: u16 = 5432;
 
 /// Test the connection to the database.
-pub(crate) async fn test(Json(connection): Json<PostgresConnection>) -> Json<TestResponse> {
-    test_connection(connection).await
+pub(crate) async fn test(
+    state: Extension<State>,
+    Json(test): Json<TestConnection<PostgresConnection>>,
+) -> Json<TestResponse> {
+    let TestConnection {
+        mut connection,
+        ssh,
+    } = test;
+    let address = test_tunnel_address(
+        &state,
+        ssh.as_ref(),
+        &connection.host,
+        connection.port.as_deref(),
+        DEFAULT_PORT,
+    )
+    .await;
+
+    match address {
+        Ok((host, port, _tunnel)) => {
+            (connection.host, connection.port) = (host, port);
+            test_connection(connection).await
+        }
+        Err(error) => TestResponse::new(false, Some(error.to_string())).into(),
+    }
 }
 
 /// Get the connection details from the API and create a PostgresConnection.
@@ -30,7 +58,7 @@ async fn get_connection(
         get_api_connection(state, "", &claims.sub, connection_id).await?
     } else {
         ApiConnection {
-            uuid: Uuid::new_v4(),
+            uuid: *connection_id,
             name: "".into(),
             r#type: "".into(),
             created_date: "".into(),
@@ -41,16 +69,29 @@ async fn get_connection(
                 username: Some("user".into()),
                 password: Some("password".into()),
                 database: "postgres-connection".into(),
+                ssh: None,
+                read_only: false,
+                trust_cert: false,
             },
         }
     };
 
+    let type_details = &connection.type_details;
+    let (host, port) = tunnel_address(
+        state,
+        type_details.ssh.as_ref(),
+        &type_details.host,
+        type_details.port.as_deref(),
+        DEFAULT_PORT,
+    )
+    .await?;
+
     let pg_connection = PostgresConnection::new(
-        connection.type_details.username.to_owned(),
-        connection.type_details.password.to_owned(),
-        connection.type_details.host.to_owned(),
-        connection.type_details.port.to_owned(),
-        Some(connection.type_details.database.to_owned()),
+        type_details.username.to_owned(),
+        type_details.password.to_owned(),
+        host,
+        port,
+        Some(type_details.database.to_owned()),
     );
 
     Ok((pg_connection, connection))
@@ -62,45 +103,38 @@ pub(crate) async fn query(
     claims: Claims,
     sql_query: Json<SqlQuery>,
 ) -> Result<impl IntoResponse> {
-    let connection = get_connection(&state, &claims, &sql_query.connection_id)
-        .await?
-        .0;
-    query_generic::<PostgresConnection>(connection, state, sql_query).await
+    let (connection, api_connection) =
+        get_connection(&state, &claims, &sql_query.connection_id).await?;
+    query_generic::<PostgresConnection>(connection, state, &claims, sql_query, &api_connection)
+        .await
 }
 
 /// Get the schema of the database
 pub(crate) async fn schema(
     Path(id): Path<Uuid>,
+    Query(schema_query): Query<SchemaQuery>,
     state: Extension<State>,
     claims: Claims,
 ) -> Result<Json<Schema>> {
     let (connection, api_connection) = get_connection(&state, &claims, &id).await?;
-    let pool = connection.connect().await?;
-    let database_schema = connection.schema(pool).await?;
-    let schema = Schema {
-        id: api_connection.uuid,
-        name: api_connection.name,
-        r#type: api_connection.r#type,
-        database: api_connection.type_details.database,
-        tables: database_schema.tables.into_values().collect(),
-    };
-
-    Ok(Json(schema))
+    schema_generic::<PostgresConnection>(connection, api_connection, &state, schema_query).await
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
+        error::ConnectionError,
         num_vec, test_connection,
-        test_util::{get_claims, new_state, response_bytes, str_vec, validate_parquet},
+        test_util::{get_claims, new_state, response_bytes, ssh_config, str_vec, validate_parquet},
     };
     use arrow::datatypes::Date32Type;
     use arrow_schema::{DataType, TimeUnit};
     use bytes::Bytes;
     use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
     use http::StatusCode;
-    use quadratic_rust_shared::sql::{SchemaColumn, SchemaTable};
+    use quadratic_rust_shared::sql::{SchemaColumn, SchemaTable, TableKind};
+    use std::time::Duration;
     use tracing_test::traced_test;
     use uuid::Uuid;
 
@@ -110,14 +144,39 @@ mod tests {
         test_connection!(get_connection);
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn postgres_test_connection_through_ssh() {
+        let state = Extension(new_state().await);
+        let connection = PostgresConnection::new(
+            Some("user".into()),
+            Some("password".into()),
+            "postgres-connection".into(),
+            None,
+            Some("postgres-connection".into()),
+        );
+        let test_connection = TestConnection {
+            connection,
+            ssh: Some(ssh_config()),
+        };
+        let response = test(state, Json(test_connection)).await;
+
+        assert_eq!(response.0, TestResponse::new(true, None));
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn postgres_schema() {
         let connection_id = Uuid::new_v4();
         let state = Extension(new_state().await);
-        let response = schema(Path(connection_id), state, get_claims())
-            .await
-            .unwrap();
+        let response = schema(
+            Path(connection_id),
+            Query(SchemaQuery::default()),
+            state,
+            get_claims(),
+        )
+        .await
+        .unwrap();
 
         let expected = Schema {
             id: response.0.id,
@@ -127,198 +186,278 @@ mod tests {
             tables: vec![SchemaTable {
                 name: "all_native_data_types".into(),
                 schema: "public".into(),
+                kind: TableKind::Table,
+                row_estimate: response.0.tables[0].row_estimate,
+                comment: None,
                 columns: vec![
                     SchemaColumn {
                         name: "id".into(),
                         r#type: "int4".into(),
                         is_nullable: false,
+                        is_primary_key: true,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "smallint_col".into(),
                         r#type: "int2".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "integer_col".into(),
                         r#type: "int4".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "bigint_col".into(),
                         r#type: "int8".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "decimal_col".into(),
                         r#type: "numeric".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "numeric_col".into(),
                         r#type: "numeric".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "real_col".into(),
                         r#type: "float4".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "double_col".into(),
                         r#type: "float8".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "serial_col".into(),
                         r#type: "int4".into(),
                         is_nullable: false,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "bigserial_col".into(),
                         r#type: "int8".into(),
                         is_nullable: false,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "money_col".into(),
                         r#type: "money".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "char_col".into(),
                         r#type: "bpchar".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "varchar_col".into(),
                         r#type: "varchar".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "text_col".into(),
                         r#type: "text".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "bytea_col".into(),
                         r#type: "bytea".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "timestamp_col".into(),
                         r#type: "timestamp".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "timestamptz_col".into(),
                         r#type: "timestamptz".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "date_col".into(),
                         r#type: "date".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "time_col".into(),
                         r#type: "time".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "timetz_col".into(),
                         r#type: "timetz".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "interval_col".into(),
                         r#type: "interval".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "boolean_col".into(),
                         r#type: "bool".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "enum_col".into(),
                         r#type: "varchar".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "point_col".into(),
                         r#type: "point".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "line_col".into(),
                         r#type: "line".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "lseg_col".into(),
                         r#type: "lseg".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "box_col".into(),
                         r#type: "box".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "path_col".into(),
                         r#type: "path".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "polygon_col".into(),
                         r#type: "polygon".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "circle_col".into(),
                         r#type: "circle".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "cidr_col".into(),
                         r#type: "cidr".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "inet_col".into(),
                         r#type: "inet".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "macaddr_col".into(),
                         r#type: "macaddr".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "json_col".into(),
                         r#type: "json".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "jsonb_col".into(),
                         r#type: "jsonb".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "uuid_col".into(),
                         r#type: "uuid".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "xml_col".into(),
                         r#type: "xml".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                     SchemaColumn {
                         name: "array_col".into(),
                         r#type: "_int4".into(),
                         is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
                     },
                 ],
+                foreign_keys: vec![],
             }],
         };
         assert_eq!(response.0, expected)
@@ -331,6 +470,12 @@ mod tests {
         let sql_query = SqlQuery {
             query: "select * from all_native_data_types order by id limit 1".into(),
             connection_id,
+            parameters: vec![],
+            limit: None,
+            offset: 0,
+            query_id: None,
+            timeout_ms: None,
+            refresh: false,
         };
         let state = Extension(new_state().await);
         let data = query(state, get_claims(), Json(sql_query)).await.unwrap();
@@ -423,6 +568,12 @@ mod tests {
         let sql_query = SqlQuery {
             query: "select * from all_native_data_types order by id limit 1".into(),
             connection_id,
+            parameters: vec![],
+            limit: None,
+            offset: 0,
+            query_id: None,
+            timeout_ms: None,
+            refresh: false,
         };
         let mut state = Extension(new_state().await);
         state.settings.max_response_bytes = 0;
@@ -434,4 +585,67 @@ mod tests {
         let body = response_bytes(response).await;
         assert_eq!(body, Bytes::new());
     }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn postgres_query_timeout() {
+        let sql_query = SqlQuery {
+            query: "select pg_sleep(10)".into(),
+            connection_id: Uuid::new_v4(),
+            parameters: vec![],
+            limit: None,
+            offset: 0,
+            query_id: None,
+            timeout_ms: Some(100),
+            refresh: false,
+        };
+        let state = Extension(new_state().await);
+        let error = query(state, get_claims(), Json(sql_query)).await.err();
+
+        // either the database or the service times the query out first
+        assert!(matches!(
+            error,
+            Some(ConnectionError::Timeout(_) | ConnectionError::Query(_))
+        ));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn postgres_query_cancel() {
+        let query_id = Uuid::new_v4();
+        let sql_query = SqlQuery {
+            query: "select pg_sleep(10)".into(),
+            connection_id: Uuid::new_v4(),
+            parameters: vec![],
+            limit: None,
+            offset: 0,
+            query_id: Some(query_id),
+            timeout_ms: None,
+            refresh: false,
+        };
+        let state = Extension(new_state().await);
+        let running = tokio::spawn(query(state.clone(), get_claims(), Json(sql_query)));
+
+        // wait for the query to start
+        while state
+            .running_queries
+            .lock()
+            .await
+            .cancel(&query_id, &get_claims().sub)
+            .is_err()
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let error = running.await.unwrap().err();
+        assert!(matches!(error, Some(ConnectionError::Cancelled(_))));
+
+        // the query is no longer running
+        let cancelled = state
+            .running_queries
+            .lock()
+            .await
+            .cancel(&query_id, &get_claims().sub);
+        assert!(cancelled.is_err());
+    }
 }
@@ -0,0 +1,473 @@
+use axum::{
+    extract::{Path, Query},
+    response::IntoResponse,
+    Extension, Json,
+};
+use quadratic_rust_shared::{
+    quadratic_api::Connection as ApiConnection, sql::sqlite_connection::SqliteConnection,
+};
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    connection::get_api_connection,
+    error::Result,
+    server::{test_connection, SqlQuery, TestResponse},
+    state::State,
+};
+
+use super::{local_database_path, query_generic, schema_generic, Schema, SchemaQuery};
+
+/// Test the connection to the database.
+pub(crate) async fn test(
+    state: Extension<State>,
+    Json(connection): Json<SqliteConnection>,
+) -> Result<Json<TestResponse>> {
+    let database = local_database_path(&state, &connection.database)?;
+
+    Ok(test_connection(SqliteConnection::new(database)).await)
+}
+
+/// Get the connection details from the API and create a SqliteConnection.
+async fn get_connection(
+    state: &State,
+    claims: &Claims,
+    connection_id: &Uuid,
+) -> Result<(SqliteConnection, ApiConnection)> {
+    let connection = if cfg!(not(test)) {
+        get_api_connection(state, "", &claims.sub, connection_id).await?
+    } else {
+        ApiConnection {
+            uuid: *connection_id,
+            name: "".into(),
+            r#type: "".into(),
+            created_date: "".into(),
+            updated_date: "".into(),
+            type_details: quadratic_rust_shared::quadratic_api::TypeDetails {
+                host: "".into(),
+                port: None,
+                username: None,
+                password: None,
+                database: format!("{connection_id}.sqlite"),
+                ssh: None,
+                read_only: false,
+                trust_cert: false,
+            },
+        }
+    };
+
+    let database = local_database_path(state, &connection.type_details.database)?;
+    let sqlite_connection = SqliteConnection::new(database);
+
+    Ok((sqlite_connection, connection))
+}
+
+/// Query the database and return the results as a parquet file.
+pub(crate) async fn query(
+    state: Extension<State>,
+    claims: Claims,
+    sql_query: Json<SqlQuery>,
+) -> Result<impl IntoResponse> {
+    let (connection, api_connection) =
+        get_connection(&state, &claims, &sql_query.connection_id).await?;
+    query_generic::<SqliteConnection>(connection, state, &claims, sql_query, &api_connection).await
+}
+
+/// Get the schema of the database
+pub(crate) async fn schema(
+    Path(id): Path<Uuid>,
+    Query(schema_query): Query<SchemaQuery>,
+    state: Extension<State>,
+    claims: Claims,
+) -> Result<Json<Schema>> {
+    let (connection, api_connection) = get_connection(&state, &claims, &id).await?;
+    schema_generic::<SqliteConnection>(connection, api_connection, &state, schema_query).await
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::state::query_cache::QueryCache;
+    use crate::{
+        error::ConnectionError,
+        num_vec,
+        test_util::{
+            get_claims, new_state, parquet_metadata, response_bytes, str_vec, validate_parquet,
+        },
+    };
+    use arrow_schema::DataType;
+    use axum::body::Body;
+    use axum::response::Response;
+    use http::StatusCode;
+    use quadratic_rust_shared::sql::{
+        SchemaColumn, SchemaTable, SqlParameter, TableKind, OVER_THE_LIMIT_KEY, RECORD_COUNT_KEY,
+    };
+    use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Executor};
+    use std::{sync::Arc, time::Duration};
+    use tokio::sync::Mutex;
+    use tracing_test::traced_test;
+    use uuid::Uuid;
+
+    /// Create the database file for a connection in the local database directory.
+    async fn new_database(state: &State, connection_id: &Uuid) {
+        let database = format!("{connection_id}.sqlite");
+        let mut pool = SqliteConnectOptions::new()
+            .filename(local_database_path(state, &database).unwrap())
+            .create_if_missing(true)
+            .connect()
+            .await
+            .unwrap();
+
+        pool.execute(
+            "create table users (id integer primary key not null, name text);
+            insert into users values (1, 'Ada'), (2, 'Grace');",
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn sqlite_test_connection() {
+        let connection_id = Uuid::new_v4();
+        let state = Extension(new_state().await);
+        new_database(&state, &connection_id).await;
+
+        let connection = SqliteConnection::new(format!("{connection_id}.sqlite"));
+        let response = test(state.clone(), Json(connection)).await.unwrap();
+        assert_eq!(response.0, TestResponse::new(true, None));
+
+        // only files in the local database directory can be opened
+        let connection = SqliteConnection::new("/etc/passwd".into());
+        assert!(test(state, Json(connection)).await.is_err());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn sqlite_schema() {
+        let connection_id = Uuid::new_v4();
+        let state = Extension(new_state().await);
+        new_database(&state, &connection_id).await;
+
+        let response = schema(
+            Path(connection_id),
+            Query(SchemaQuery::default()),
+            state,
+            get_claims(),
+        )
+        .await
+        .unwrap();
+
+        let expected = Schema {
+            id: response.0.id,
+            name: "".into(),
+            r#type: "".into(),
+            database: format!("{connection_id}.sqlite"),
+            tables: vec![SchemaTable {
+                name: "users".into(),
+                schema: "main".into(),
+                kind: TableKind::Table,
+                row_estimate: None,
+                comment: None,
+                columns: vec![
+                    SchemaColumn {
+                        name: "id".into(),
+                        r#type: "INTEGER".into(),
+                        is_nullable: false,
+                        is_primary_key: true,
+                        comment: None,
+                    },
+                    SchemaColumn {
+                        name: "name".into(),
+                        r#type: "TEXT".into(),
+                        is_nullable: true,
+                        is_primary_key: false,
+                        comment: None,
+                    },
+                ],
+                foreign_keys: vec![],
+            }],
+        };
+
+        assert_eq!(response.0, expected);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn sqlite_schema_is_cached() {
+        let connection_id = Uuid::new_v4();
+        let state = Extension(new_state().await);
+        new_database(&state, &connection_id).await;
+        let schema = |refresh: bool| {
+            schema(
+                Path(connection_id),
+                Query(SchemaQuery { refresh }),
+                state.clone(),
+                get_claims(),
+            )
+        };
+
+        assert_eq!(schema(false).await.unwrap().0.tables.len(), 1);
+
+        let database = format!("{connection_id}.sqlite");
+        let mut pool = SqliteConnectOptions::new()
+            .filename(local_database_path(&state, &database).unwrap())
+            .connect()
+            .await
+            .unwrap();
+        pool.execute("create table orders (id integer primary key not null);")
+            .await
+            .unwrap();
+
+        // the new table is only read when the schema is refreshed
+        assert_eq!(schema(false).await.unwrap().0.tables.len(), 1);
+        assert_eq!(schema(true).await.unwrap().0.tables.len(), 2);
+        assert_eq!(schema(false).await.unwrap().0.tables.len(), 2);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn sqlite_query_with_parameters() {
+        let connection_id = Uuid::new_v4();
+        let state = Extension(new_state().await);
+        new_database(&state, &connection_id).await;
+
+        let sql_query = SqlQuery {
+            query: "select id, name from users where name = ?".into(),
+            connection_id,
+            parameters: vec![SqlParameter::Text("Grace".into())],
+            limit: None,
+            offset: 0,
+            query_id: None,
+            timeout_ms: None,
+            refresh: false,
+        };
+        let data = query(state, get_claims(), Json(sql_query)).await.unwrap();
+        let response = data.into_response();
+
+        let expected = vec![
+            (DataType::Int64, num_vec!(2_i64)),
+            (DataType::Utf8, str_vec("Grace")),
+        ];
+
+        assert_eq!(response.status(), StatusCode::OK);
+        validate_parquet(response, expected).await;
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn sqlite_query_timeout() {
+        let connection_id = Uuid::new_v4();
+        let query_id = Uuid::new_v4();
+        let state = Extension(new_state().await);
+        new_database(&state, &connection_id).await;
+
+        let sql_query = SqlQuery {
+            query: "with recursive numbers(n) as (select 1 union all
+                select n + 1 from numbers) select count(*) from numbers"
+                .into(),
+            connection_id,
+            parameters: vec![],
+            limit: None,
+            offset: 0,
+            query_id: Some(query_id),
+            timeout_ms: Some(50),
+            refresh: false,
+        };
+        let error = query(state.clone(), get_claims(), Json(sql_query))
+            .await
+            .err();
+
+        // either the database or the service times the query out first
+        assert!(matches!(
+            error,
+            Some(ConnectionError::Timeout(_) | ConnectionError::Query(_))
+        ));
+
+        // the query is no longer running
+        let cancelled = state
+            .running_queries
+            .lock()
+            .await
+            .cancel(&query_id, &get_claims().sub);
+        assert!(cancelled.is_err());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn sqlite_query_read_only() {
+        let connection_id = Uuid::new_v4();
+        let state = Extension(new_state().await);
+        new_database(&state, &connection_id).await;
+        let database = local_database_path(&state, &format!("{connection_id}.sqlite")).unwrap();
+        let (_, mut api_connection) = get_connection(&state, &get_claims(), &connection_id)
+            .await
+            .unwrap();
+        api_connection.type_details.read_only = true;
+        let sql_query = |query: &str| SqlQuery {
+            query: query.into(),
+            connection_id,
+            parameters: vec![],
+            limit: None,
+            offset: 0,
+            query_id: None,
+            timeout_ms: None,
+            refresh: false,
+        };
+
+        let result = query_generic(
+            SqliteConnection::new(database.to_owned()),
+            state.clone(),
+            &get_claims(),
+            Json(sql_query("delete from users")),
+            &api_connection,
+        )
+        .await;
+        assert!(matches!(result, Err(ConnectionError::ReadOnly(_))));
+
+        let response = query_generic(
+            SqliteConnection::new(database),
+            state,
+            &get_claims(),
+            Json(sql_query("select id from users order by id")),
+            &api_connection,
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response_bytes(response).await;
+        assert_eq!(parquet_metadata(&body, RECORD_COUNT_KEY), Some("2".into()));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn sqlite_query_cache() {
+        let connection_id = Uuid::new_v4();
+        let mut state = Extension(new_state().await);
+        new_database(&state, &connection_id).await;
+        let query_cache = QueryCache::new(Duration::from_secs(60), 1_000_000, None).unwrap();
+        state.query_cache = Arc::new(Mutex::new(query_cache));
+
+        let run = |sql: &str, refresh: bool| {
+            let sql_query = SqlQuery {
+                query: sql.into(),
+                connection_id,
+                parameters: vec![],
+                limit: None,
+                offset: 0,
+                query_id: None,
+                timeout_ms: None,
+                refresh,
+            };
+            let state = state.clone();
+
+            async move {
+                query(state, get_claims(), Json(sql_query))
+                    .await
+                    .unwrap()
+                    .into_response()
+            }
+        };
+        let cache_status = |response: &Response| response.headers()["cache-status"].to_owned();
+
+        let response = run("select count(*) as count from users", false).await;
+        assert_eq!(cache_status(&response), "MISS");
+        assert!(!response.headers().contains_key("cache-age-ms"));
+
+        // results are cached once they've been sent
+        let response = run("select count(*) as count from users", false).await;
+        assert_eq!(cache_status(&response), "MISS");
+        validate_parquet(response, vec![(DataType::Int64, num_vec!(2_i64))]).await;
+
+        // the same query with different formatting is read from the cache
+        let response = run("SELECT count(*) AS count\n  FROM users;", false).await;
+        assert_eq!(cache_status(&response), "HIT");
+        assert!(response.headers().contains_key("cache-age-ms"));
+        validate_parquet(response, vec![(DataType::Int64, num_vec!(2_i64))]).await;
+
+        // writes aren't cached
+        let response = run("insert into users values (3, 'Edsger')", false).await;
+        assert_eq!(cache_status(&response), "BYPASS");
+
+        // cached results are returned until they're refreshed
+        let response = run("select count(*) as count from users", false).await;
+        assert_eq!(cache_status(&response), "HIT");
+        validate_parquet(response, vec![(DataType::Int64, num_vec!(2_i64))]).await;
+
+        let response = run("select count(*) as count from users", true).await;
+        assert_eq!(cache_status(&response), "MISS");
+        validate_parquet(response, vec![(DataType::Int64, num_vec!(3_i64))]).await;
+
+        let response = run("select count(*) as count from users", false).await;
+        assert_eq!(cache_status(&response), "HIT");
+        validate_parquet(response, vec![(DataType::Int64, num_vec!(3_i64))]).await;
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn sqlite_query_max_response_bytes() {
+        let connection_id = Uuid::new_v4();
+        let mut state = Extension(new_state().await);
+        new_database(&state, &connection_id).await;
+        state.settings.max_response_bytes = 0;
+
+        let sql_query = SqlQuery {
+            query: "select * from users".into(),
+            connection_id,
+            parameters: vec![],
+            limit: None,
+            offset: 0,
+            query_id: None,
+            timeout_ms: None,
+            refresh: false,
+        };
+        let data = query(state, get_claims(), Json(sql_query)).await.unwrap();
+        let response = data.into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // a file without columns, with the rows written in its footer
+        let body = response_bytes(response).await;
+        assert_eq!(parquet_metadata(&body, RECORD_COUNT_KEY), Some("0".into()));
+        assert_eq!(
+            parquet_metadata(&body, OVER_THE_LIMIT_KEY),
+            Some("true".into())
+        );
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn sqlite_query_limit_and_offset() {
+        let connection_id = Uuid::new_v4();
+        let state = Extension(new_state().await);
+        new_database(&state, &connection_id).await;
+
+        let sql_query = SqlQuery {
+            query: "select id from users order by id".into(),
+            connection_id,
+            parameters: vec![],
+            limit: Some(1),
+            offset: 1,
+            query_id: None,
+            timeout_ms: None,
+            refresh: false,
+        };
+        let data = query(state, get_claims(), Json(sql_query)).await.unwrap();
+        let response = data.into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response_bytes(response).await;
+        assert_eq!(parquet_metadata(&body, RECORD_COUNT_KEY), Some("1".into()));
+        assert_eq!(
+            parquet_metadata(&body, OVER_THE_LIMIT_KEY),
+            Some("false".into())
+        );
+
+        let response = Response::new(Body::from(body));
+        validate_parquet(response, vec![(DataType::Int64, num_vec!(2_i64))]).await;
+    }
+}
@@ -3,10 +3,15 @@
 //! Store information about the state of the application in a send + sync
 //! struct.  All access and mutations to state should be performed here.
 
+pub mod queries;
+pub mod query_cache;
+pub mod rate_limits;
+pub mod schemas;
 pub mod settings;
 pub mod stats;
+pub mod tunnels;
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use jsonwebtoken::jwk::JwkSet;
 use reqwest::redirect::Policy;
@@ -15,7 +20,15 @@ use tokio::sync::Mutex;
 
 use crate::config::Config;
 use crate::error::{proxy_error, Result};
-use crate::state::settings::Settings;
+use crate::proxy::{new_proxy_client, rules::ProxyRules};
+use crate::state::{
+    queries::RunningQueries,
+    query_cache::QueryCache,
+    rate_limits::RateLimiter,
+    schemas::{SchemaCache, SCHEMA_CACHE_TTL},
+    settings::Settings,
+    tunnels::SshTunnels,
+};
 
 use self::stats::Stats;
 
@@ -24,10 +37,31 @@ pub(crate) struct State {
     pub(crate) settings: Settings,
     pub(crate) client: Client,
     pub(crate) stats: Arc<Mutex<Stats>>,
+    pub(crate) running_queries: Arc<Mutex<RunningQueries>>,
+    pub(crate) ssh_tunnels: Arc<Mutex<SshTunnels>>,
+    pub(crate) schemas: Arc<Mutex<SchemaCache>>,
+    pub(crate) query_cache: Arc<Mutex<QueryCache>>,
+    /// A client for the proxy, which only connects to addresses allowed by
+    /// `proxy_rules`
+    pub(crate) proxy_client: Client,
+    pub(crate) proxy_rules: Arc<ProxyRules>,
+    pub(crate) proxy_rate_limits: Arc<Mutex<RateLimiter>>,
 }
 
 impl State {
     pub(crate) fn new(config: &Config, jwks: Option<JwkSet>) -> Result<Self> {
+        let proxy_rules = Arc::new(ProxyRules::new(
+            &config.proxy_allow_list,
+            &config.proxy_deny_list,
+        )?);
+        let proxy_rate_limits =
+            RateLimiter::new(config.proxy_requests_per_minute, Duration::from_secs(60));
+        let query_cache = QueryCache::new(
+            Duration::from_secs(config.query_cache_ttl_seconds),
+            config.query_cache_max_bytes,
+            (!config.query_cache_dir.is_empty()).then(|| config.query_cache_dir.to_owned().into()),
+        )?;
+
         Ok(State {
             settings: Settings::new(config, jwks),
             client: Client::builder()
@@ -36,6 +70,13 @@ impl State {
                 .build()
                 .map_err(proxy_error)?,
             stats: Arc::new(Mutex::new(Stats::new())),
+            running_queries: Arc::new(Mutex::new(RunningQueries::new())),
+            ssh_tunnels: Arc::new(Mutex::new(SshTunnels::new())),
+            schemas: Arc::new(Mutex::new(SchemaCache::new(SCHEMA_CACHE_TTL))),
+            query_cache: Arc::new(Mutex::new(query_cache)),
+            proxy_client: new_proxy_client(&proxy_rules)?,
+            proxy_rules,
+            proxy_rate_limits: Arc::new(Mutex::new(proxy_rate_limits)),
         })
     }
 }
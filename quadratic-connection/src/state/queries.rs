@@ -0,0 +1,140 @@
+use std::collections::{hash_map::Entry, HashMap};
+use std::sync::Arc;
+
+use tokio::sync::{Mutex, Notify};
+use uuid::Uuid;
+
+use crate::error::{ConnectionError, Result};
+
+#[derive(Debug)]
+struct RunningQuery {
+    user: String,
+    cancel: Arc<Notify>,
+}
+
+/// Queries that are running, keyed by the query id the client sent, so they
+/// can be cancelled from another request.
+#[derive(Debug, Default)]
+pub(crate) struct RunningQueries {
+    queries: HashMap<Uuid, RunningQuery>,
+}
+
+impl RunningQueries {
+    pub(crate) fn new() -> Self {
+        RunningQueries::default()
+    }
+
+    /// Track a query.  The returned `Notify` is signalled if the query is
+    /// cancelled, including before it is first awaited.
+    pub(crate) fn start(&mut self, query_id: Uuid, user: &str) -> Result<Arc<Notify>> {
+        match self.queries.entry(query_id) {
+            Entry::Occupied(_) => Err(ConnectionError::Query(format!(
+                "Query {query_id} is already running"
+            ))),
+            Entry::Vacant(entry) => {
+                let cancel = Arc::new(Notify::new());
+                entry.insert(RunningQuery {
+                    user: user.to_owned(),
+                    cancel: Arc::clone(&cancel),
+                });
+
+                Ok(cancel)
+            }
+        }
+    }
+
+    /// Stop tracking a query once it has completed, failed or been cancelled.
+    pub(crate) fn finish(&mut self, query_id: &Uuid) {
+        self.queries.remove(query_id);
+    }
+
+    /// Signal a query to stop.  Only the user that started a query can cancel
+    /// it, other users are told it isn't running.
+    pub(crate) fn cancel(&mut self, query_id: &Uuid, user: &str) -> Result<()> {
+        match self.queries.get(query_id) {
+            Some(query) if query.user == user => {
+                query.cancel.notify_one();
+                Ok(())
+            }
+            _ => Err(ConnectionError::Connection(format!(
+                "Query {query_id} is not running"
+            ))),
+        }
+    }
+}
+
+/// Stops tracking a query when it's dropped, so queries are untracked even if
+/// their request is dropped, eg, when the client disconnects.
+pub(crate) struct RunningQueryGuard {
+    queries: Arc<Mutex<RunningQueries>>,
+    query_id: Uuid,
+}
+
+impl RunningQueryGuard {
+    pub(crate) fn new(queries: &Arc<Mutex<RunningQueries>>, query_id: Uuid) -> Self {
+        RunningQueryGuard {
+            queries: Arc::clone(queries),
+            query_id,
+        }
+    }
+}
+
+impl Drop for RunningQueryGuard {
+    fn drop(&mut self) {
+        if let Ok(mut queries) = self.queries.try_lock() {
+            queries.finish(&self.query_id);
+            return;
+        }
+
+        // the lock can't be awaited in drop
+        let queries = Arc::clone(&self.queries);
+        let query_id = self.query_id;
+
+        tokio::spawn(async move { queries.lock().await.finish(&query_id) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cancels_a_running_query() {
+        let mut queries = RunningQueries::new();
+        let query_id = Uuid::new_v4();
+        let cancel = queries.start(query_id, "user").unwrap();
+
+        // ids are unique while a query is running
+        assert!(queries.start(query_id, "user").is_err());
+
+        // only the user that started the query can cancel it
+        assert!(queries.cancel(&query_id, "other user").is_err());
+
+        // the signal is kept until the query awaits it
+        queries.cancel(&query_id, "user").unwrap();
+        cancel.notified().await;
+
+        queries.finish(&query_id);
+        assert!(queries.cancel(&query_id, "user").is_err());
+    }
+
+    #[tokio::test]
+    async fn guard_stops_tracking_a_query_when_dropped() {
+        let queries = Arc::new(Mutex::new(RunningQueries::new()));
+        let query_id = Uuid::new_v4();
+        queries.lock().await.start(query_id, "user").unwrap();
+
+        // dropped while the lock is held, eg, by a request cancelling a query
+        let locked = queries.lock().await;
+        drop(RunningQueryGuard::new(&queries, query_id));
+        drop(locked);
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        assert!(queries.lock().await.cancel(&query_id, "user").is_err());
+
+        let query_id = Uuid::new_v4();
+        queries.lock().await.start(query_id, "user").unwrap();
+        drop(RunningQueryGuard::new(&queries, query_id));
+        assert!(queries.lock().await.cancel(&query_id, "user").is_err());
+    }
+}
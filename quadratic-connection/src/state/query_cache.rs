@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bytes::Bytes;
+use quadratic_rust_shared::sql::SqlParameter;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use crate::error::{ConnectionError, Result};
+
+/// Cached results on disk are in this subdirectory of the cache directory, so
+/// clearing it on startup can't remove other files
+const CACHE_SUBDIRECTORY: &str = "query-results";
+
+#[derive(Debug)]
+struct CachedQuery {
+    created: Instant,
+    bytes: u64,
+    /// The Parquet chunks, which are empty when results are stored on disk
+    chunks: Vec<Bytes>,
+    /// The file the results are stored in
+    path: Option<PathBuf>,
+}
+
+/// Query results, keyed by `query_cache_key`.  Results are stored in memory,
+/// or in `dir` when it's set, until they expire or they're evicted, oldest
+/// first, to keep the cache under `max_bytes`.  A TTL of zero disables the
+/// cache.
+///
+/// The cache is shared behind a lock, so its files are read and written by
+/// `get_cached` and `insert_cached` without holding it.
+#[derive(Debug)]
+pub(crate) struct QueryCache {
+    ttl: Duration,
+    max_bytes: u64,
+    dir: Option<PathBuf>,
+    bytes: u64,
+    queries: HashMap<String, CachedQuery>,
+    /// Results are written to new files, so a file is never replaced while
+    /// it's read
+    next_file: u64,
+    /// Files of removed results, which are deleted once the lock is released
+    removed_files: Vec<PathBuf>,
+}
+
+impl QueryCache {
+    /// Results on disk aren't read back after a restart, so the directory's
+    /// cached results are removed.
+    pub(crate) fn new(ttl: Duration, max_bytes: u64, dir: Option<PathBuf>) -> Result<Self> {
+        let dir = dir.map(|dir| dir.join(CACHE_SUBDIRECTORY));
+
+        if let Some(dir) = &dir {
+            let config_error = |e: std::io::Error| {
+                ConnectionError::Config(format!("Invalid query cache directory: {e}"))
+            };
+
+            if dir.exists() {
+                std::fs::remove_dir_all(dir).map_err(config_error)?;
+            }
+
+            std::fs::create_dir_all(dir).map_err(config_error)?;
+        }
+
+        Ok(QueryCache {
+            ttl,
+            max_bytes,
+            dir,
+            bytes: 0,
+            queries: HashMap::new(),
+            next_file: 0,
+            removed_files: vec![],
+        })
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_bytes > 0
+    }
+
+    /// The largest results that can be cached
+    pub(crate) fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Get a query's results, their age and the file they're stored in if
+    /// they were cached within the TTL
+    fn get(&mut self, key: &str) -> Option<(Vec<Bytes>, Duration, Option<PathBuf>)> {
+        self.remove_expired();
+
+        let query = self.queries.get(key)?;
+
+        Some((
+            query.chunks.to_owned(),
+            query.created.elapsed(),
+            query.path.to_owned(),
+        ))
+    }
+
+    /// A new file to store results in, or None if they're stored in memory
+    fn next_path(&mut self, key: &str) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        self.next_file += 1;
+
+        Some(dir.join(format!("{key}-{}.parquet", self.next_file)))
+    }
+
+    fn insert(&mut self, key: String, chunks: Vec<Bytes>, bytes: u64, path: Option<PathBuf>) {
+        self.remove(&key);
+        self.remove_expired();
+
+        while self.bytes + bytes > self.max_bytes {
+            let oldest = self
+                .queries
+                .iter()
+                .min_by_key(|(_, query)| query.created)
+                .map(|(key, _)| key.to_owned());
+
+            match oldest {
+                Some(oldest) => self.remove(&oldest),
+                None => break,
+            }
+        }
+
+        let chunks = match path {
+            None => chunks,
+            Some(_) => vec![],
+        };
+
+        self.bytes += bytes;
+        self.queries.insert(
+            key,
+            CachedQuery {
+                created: Instant::now(),
+                bytes,
+                chunks,
+                path,
+            },
+        );
+    }
+
+    fn remove_expired(&mut self) {
+        let ttl = self.ttl;
+        let expired = self
+            .queries
+            .iter()
+            .filter(|(_, query)| query.created.elapsed() >= ttl)
+            .map(|(key, _)| key.to_owned())
+            .collect::<Vec<_>>();
+
+        for key in expired {
+            self.remove(&key);
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(query) = self.queries.remove(key) {
+            self.bytes -= query.bytes;
+            self.removed_files.extend(query.path);
+        }
+    }
+
+    /// Remove a query's results if they're still stored in `path`
+    fn remove_stored_in(&mut self, key: &str, path: &Path) {
+        if self
+            .queries
+            .get(key)
+            .is_some_and(|query| query.path.as_deref() == Some(path))
+        {
+            self.remove(key);
+        }
+    }
+}
+
+/// Get a query's Parquet file, in chunks, and its age if it was cached within
+/// the TTL
+pub(crate) async fn get_cached(
+    cache: &Mutex<QueryCache>,
+    key: &str,
+) -> Option<(Vec<Bytes>, Duration)> {
+    let (cached, removed_files) = {
+        let mut cache = cache.lock().await;
+        (cache.get(key), std::mem::take(&mut cache.removed_files))
+    };
+    remove_files(removed_files).await;
+
+    let (mut chunks, age, path) = cached?;
+
+    if let Some(path) = path {
+        match tokio::fs::read(&path).await {
+            Ok(data) => chunks = vec![Bytes::from(data)],
+            // the results were evicted while they were read
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(error) => {
+                tracing::warn!("Error reading cached query {key}: {error}");
+
+                let removed_files = {
+                    let mut cache = cache.lock().await;
+                    cache.remove_stored_in(key, &path);
+                    std::mem::take(&mut cache.removed_files)
+                };
+                remove_files(removed_files).await;
+
+                return None;
+            }
+        }
+    }
+
+    Some((chunks, age))
+}
+
+/// Cache a query's Parquet file.  Results larger than the cache aren't
+/// cached.
+pub(crate) async fn insert_cached(cache: &Mutex<QueryCache>, key: String, chunks: Vec<Bytes>) {
+    let bytes = chunks.iter().map(|chunk| chunk.len() as u64).sum();
+
+    let path = {
+        let mut cache = cache.lock().await;
+
+        if !cache.enabled() || bytes > cache.max_bytes {
+            return;
+        }
+
+        cache.next_path(&key)
+    };
+
+    if let Some(path) = &path {
+        if let Err(error) = tokio::fs::write(path, chunks.concat()).await {
+            tracing::warn!("Error writing cached query {key}: {error}");
+            remove_files(vec![path.to_owned()]).await;
+            return;
+        }
+    }
+
+    let removed_files = {
+        let mut cache = cache.lock().await;
+        cache.insert(key, chunks, bytes, path);
+        std::mem::take(&mut cache.removed_files)
+    };
+    remove_files(removed_files).await;
+}
+
+async fn remove_files(paths: Vec<PathBuf>) {
+    for path in paths {
+        if let Err(error) = tokio::fs::remove_file(&path).await {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!("Error removing cached query {}: {error}", path.display());
+            }
+        }
+    }
+}
+
+/// A hash of everything that changes a query's results: the connection and
+/// when it was last edited, the normalized SQL, the bound parameters and the
+/// row limits.
+pub(crate) fn query_cache_key(
+    connection_id: &Uuid,
+    updated_date: &str,
+    sql: &str,
+    parameters: &[SqlParameter],
+    limit: Option<u64>,
+    offset: u64,
+) -> String {
+    // strings are debug formatted, so they're quoted and escaped and the
+    // fields can't run into each other
+    let key =
+        format!("{connection_id}\n{updated_date:?}\n{sql:?}\n{parameters:?}\n{limit:?}\n{offset}");
+
+    format!("{:x}", Sha256::digest(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn parquet(chunks: &[&'static [u8]]) -> Vec<Bytes> {
+        chunks
+            .iter()
+            .map(|chunk| Bytes::from_static(chunk))
+            .collect()
+    }
+
+    async fn get_chunks(cache: &Mutex<QueryCache>, key: &str) -> Option<Bytes> {
+        get_cached(cache, key)
+            .await
+            .map(|(chunks, _)| chunks.concat().into())
+    }
+
+    #[test]
+    fn keys_change_with_the_query() {
+        let connection_id = Uuid::new_v4();
+        let key = |sql: &str, parameters: &[SqlParameter], offset: u64| {
+            query_cache_key(&connection_id, "2024-01-01", sql, parameters, None, offset)
+        };
+        let text = [SqlParameter::Text("1".into())];
+        let integer = [SqlParameter::Integer(1)];
+
+        assert_eq!(key("SELECT 1", &[], 0), key("SELECT 1", &[], 0));
+        assert_ne!(key("SELECT 1", &[], 0), key("SELECT 2", &[], 0));
+        assert_ne!(key("SELECT $1", &text, 0), key("SELECT $1", &integer, 0));
+        assert_ne!(key("SELECT 1", &[], 0), key("SELECT 1", &[], 1));
+        assert_ne!(
+            key("SELECT 1", &[], 0),
+            query_cache_key(&connection_id, "2024-01-02", "SELECT 1", &[], None, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn caches_results_in_memory() {
+        let cache = Mutex::new(QueryCache::new(TTL, 1_000, None).unwrap());
+        let results = parquet(&[b"row group", b"footer"]);
+        insert_cached(&cache, "key".into(), results.to_owned()).await;
+
+        let (cached, _) = get_cached(&cache, "key").await.unwrap();
+        assert_eq!(cached, results);
+        assert!(get_cached(&cache, "other key").await.is_none());
+
+        // results expire
+        let cache = Mutex::new(QueryCache::new(Duration::ZERO, 1_000, None).unwrap());
+        assert!(!cache.lock().await.enabled());
+        insert_cached(&cache, "key".into(), parquet(&[b"row group"])).await;
+        assert!(get_cached(&cache, "key").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn caches_results_on_disk() {
+        let dir = std::env::temp_dir().join(format!("query-cache-{}", Uuid::new_v4()));
+        let other_file = dir.join("other.parquet");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&other_file, b"other").unwrap();
+
+        let cache = QueryCache::new(TTL, 1_000, Some(dir.to_owned())).unwrap();
+        let cache = Mutex::new(cache);
+        let results = dir.join(CACHE_SUBDIRECTORY);
+        let files = || std::fs::read_dir(&results).unwrap().count();
+        insert_cached(&cache, "key".into(), parquet(&[b"row group", b"footer"])).await;
+
+        assert_eq!(files(), 1);
+        assert_eq!(
+            get_chunks(&cache, "key").await,
+            Some(Bytes::from_static(b"row groupfooter"))
+        );
+
+        // replaced results are removed
+        insert_cached(&cache, "key".into(), parquet(&[b"new row group"])).await;
+        assert_eq!(files(), 1);
+        assert_eq!(
+            get_chunks(&cache, "key").await,
+            Some(Bytes::from_static(b"new row group"))
+        );
+
+        // cached results are removed on startup, but not other files
+        QueryCache::new(TTL, 1_000, Some(dir.to_owned())).unwrap();
+        assert_eq!(files(), 0);
+        assert!(other_file.exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn evicts_the_oldest_results() {
+        let cache = Mutex::new(QueryCache::new(TTL, 10, None).unwrap());
+        for key in ["first", "second", "third"] {
+            insert_cached(&cache, key.into(), parquet(&[b"12345"])).await;
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        assert!(get_chunks(&cache, "first").await.is_none());
+        assert!(get_chunks(&cache, "second").await.is_some());
+        assert!(get_chunks(&cache, "third").await.is_some());
+
+        // results larger than the cache aren't cached
+        insert_cached(&cache, "large".into(), parquet(&[b"12345678901"])).await;
+        assert!(get_chunks(&cache, "large").await.is_none());
+        assert!(get_chunks(&cache, "third").await.is_some());
+    }
+}
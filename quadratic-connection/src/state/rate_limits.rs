@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::error::{ConnectionError, Result};
+
+/// The number of requests each user has made in the current window, keyed
+/// by the `sub` of their JWT.  Each user's window starts at their first
+/// request after the last one ended.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    users: HashMap<String, (Instant, u32)>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: u32, window: Duration) -> Self {
+        RateLimiter {
+            limit,
+            window,
+            users: HashMap::new(),
+        }
+    }
+
+    /// Count a request, or return an error if the user has reached the limit
+    pub(crate) fn check(&mut self, user: &str) -> Result<()> {
+        let now = Instant::now();
+        let window = self.window;
+        self.users
+            .retain(|_, (start, _)| now.duration_since(*start) < window);
+
+        let (_, count) = self.users.entry(user.to_owned()).or_insert((now, 0));
+
+        if *count >= self.limit {
+            return Err(ConnectionError::RateLimited(format!(
+                "More than {} requests in {} seconds",
+                self.limit,
+                window.as_secs()
+            )));
+        }
+
+        *count += 1;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn limits_requests_per_user() {
+        let mut limiter = RateLimiter::new(2, Duration::from_secs(60));
+
+        assert!(limiter.check("user").is_ok());
+        assert!(limiter.check("user").is_ok());
+        assert!(matches!(
+            limiter.check("user"),
+            Err(ConnectionError::RateLimited(_))
+        ));
+
+        // each user has their own limit
+        assert!(limiter.check("other user").is_ok());
+
+        // a new window starts once the last one ends
+        let mut limiter = RateLimiter::new(1, Duration::ZERO);
+        assert!(limiter.check("user").is_ok());
+        assert!(limiter.check("user").is_ok());
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use quadratic_rust_shared::sql::SchemaTable;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+/// How long a connection's schema is reused before it's read again
+pub(crate) const SCHEMA_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug)]
+struct CachedSchema {
+    updated_date: String,
+    fetched: Instant,
+    tables: Vec<SchemaTable>,
+}
+
+/// Database schemas, keyed by connection id.  Reading a schema queries the
+/// database's catalog, which is slow for large databases, so the result is
+/// reused until it expires or the connection is edited.
+#[derive(Debug)]
+pub(crate) struct SchemaCache {
+    ttl: Duration,
+    schemas: HashMap<Uuid, CachedSchema>,
+}
+
+impl SchemaCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        SchemaCache {
+            ttl,
+            schemas: HashMap::new(),
+        }
+    }
+
+    /// Get a connection's schema if it was read within the TTL and the
+    /// connection hasn't been edited since.
+    pub(crate) fn get(
+        &mut self,
+        connection_id: &Uuid,
+        updated_date: &str,
+    ) -> Option<Vec<SchemaTable>> {
+        let ttl = self.ttl;
+        self.schemas
+            .retain(|_, schema| schema.fetched.elapsed() < ttl);
+
+        self.schemas
+            .get(connection_id)
+            .filter(|schema| schema.updated_date == updated_date)
+            .map(|schema| schema.tables.to_owned())
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        connection_id: Uuid,
+        updated_date: &str,
+        tables: Vec<SchemaTable>,
+    ) {
+        self.schemas.insert(
+            connection_id,
+            CachedSchema {
+                updated_date: updated_date.to_owned(),
+                fetched: Instant::now(),
+                tables,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tables() -> Vec<SchemaTable> {
+        vec![SchemaTable {
+            name: "users".into(),
+            ..Default::default()
+        }]
+    }
+
+    #[tokio::test]
+    async fn schemas_expire() {
+        let mut cache = SchemaCache::new(SCHEMA_CACHE_TTL);
+        let connection_id = Uuid::new_v4();
+        cache.insert(connection_id, "2024-01-01", tables());
+
+        assert_eq!(cache.get(&connection_id, "2024-01-01"), Some(tables()));
+        assert_eq!(cache.get(&Uuid::new_v4(), "2024-01-01"), None);
+
+        let mut cache = SchemaCache::new(Duration::ZERO);
+        cache.insert(connection_id, "2024-01-01", tables());
+
+        assert_eq!(cache.get(&connection_id, "2024-01-01"), None);
+    }
+
+    #[tokio::test]
+    async fn edited_connections_are_read_again() {
+        let mut cache = SchemaCache::new(SCHEMA_CACHE_TTL);
+        let connection_id = Uuid::new_v4();
+        cache.insert(connection_id, "2024-01-01", tables());
+
+        assert_eq!(cache.get(&connection_id, "2024-01-02"), None);
+    }
+}
@@ -1,4 +1,5 @@
 use jsonwebtoken::jwk::JwkSet;
+use quadratic_rust_shared::environment::Environment;
 
 use crate::config::Config;
 
@@ -8,6 +9,11 @@ pub(crate) struct Settings {
     pub(crate) _m2m_auth_token: String,
     pub(crate) jwks: Option<JwkSet>,
     pub(crate) max_response_bytes: u64,
+    pub(crate) query_timeout_ms: u64,
+    pub(crate) local_database_dir: String,
+    pub(crate) proxy_max_response_bytes: u64,
+    /// Databases on the service's own host can be connected to directly
+    pub(crate) allow_loopback_databases: bool,
 }
 
 impl Settings {
@@ -17,6 +23,13 @@ impl Settings {
             _m2m_auth_token: config.m2m_auth_token.to_owned(),
             jwks,
             max_response_bytes: config.max_response_bytes,
+            query_timeout_ms: config.query_timeout_ms,
+            local_database_dir: config.local_database_dir.to_owned(),
+            proxy_max_response_bytes: config.proxy_max_response_bytes,
+            allow_loopback_databases: matches!(
+                config.environment,
+                Environment::Local | Environment::Docker | Environment::Test
+            ),
         }
     }
 }
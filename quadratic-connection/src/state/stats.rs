@@ -1,3 +1,4 @@
+use quadratic_rust_shared::metrics::Metrics;
 use serde::Serialize;
 use std::fmt::Display;
 use tokio::time::Instant;
@@ -35,4 +36,19 @@ impl Stats {
     pub(crate) fn new() -> Self {
         Stats::default()
     }
+
+    /// Render the stats in the Prometheus text format.
+    pub(crate) fn to_prometheus(&self) -> String {
+        let mut metrics = Metrics::new("quadratic_connection");
+
+        if let Some(time) = self.last_query_time {
+            metrics.gauge(
+                "last_query_seconds",
+                "Seconds since a query was last processed.",
+                time.elapsed().as_secs(),
+            );
+        }
+
+        metrics.to_string()
+    }
 }
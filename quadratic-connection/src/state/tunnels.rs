@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use quadratic_rust_shared::ssh::{SshConfig, SshTunnel};
+use tokio::sync::Mutex;
+
+use crate::error::Result;
+
+/// Tunnels that haven't been used for this long are closed
+pub(crate) const SSH_TUNNEL_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+type TunnelKey = (SshConfig, String, u16);
+
+struct CachedTunnel {
+    tunnel: SshTunnel,
+    last_used: Instant,
+}
+
+/// A key's tunnel, which is locked while it's opened
+type TunnelSlot = Arc<Mutex<Option<CachedTunnel>>>;
+
+/// Open SSH tunnels, keyed by the bastion and the remote host they forward
+/// to.  A tunnel is shared by every query to the same database through the
+/// same bastion and user, and is reopened once it closes.  Each key has its
+/// own lock, so opening a tunnel only holds up queries to the same database.
+#[derive(Default)]
+pub(crate) struct SshTunnels {
+    tunnels: HashMap<TunnelKey, TunnelSlot>,
+}
+
+impl SshTunnels {
+    pub(crate) fn new() -> Self {
+        SshTunnels::default()
+    }
+
+    fn slot(&mut self, key: TunnelKey) -> TunnelSlot {
+        Arc::clone(self.tunnels.entry(key).or_default())
+    }
+
+    /// Drop tunnels that have closed or haven't been used for `idle_timeout`.
+    /// Queries that are still using an evicted tunnel keep their connections.
+    pub(crate) fn evict_idle(&mut self, idle_timeout: Duration) {
+        self.tunnels.retain(|_, slot| match slot.try_lock() {
+            Ok(slot) => slot.as_ref().is_some_and(|cached| {
+                !cached.tunnel.is_closed() && cached.last_used.elapsed() < idle_timeout
+            }),
+            // the tunnel is being opened
+            Err(_) => true,
+        });
+    }
+}
+
+/// Get the local port of a tunnel to `remote_host:remote_port`, opening the
+/// tunnel if there isn't an open one.
+pub(crate) async fn local_port(
+    tunnels: &Mutex<SshTunnels>,
+    config: &SshConfig,
+    remote_host: &str,
+    remote_port: u16,
+) -> Result<u16> {
+    let key = (config.to_owned(), remote_host.to_owned(), remote_port);
+    let slot = tunnels.lock().await.slot(key);
+    let mut slot = slot.lock().await;
+
+    if let Some(cached) = slot.as_mut().filter(|cached| !cached.tunnel.is_closed()) {
+        cached.last_used = Instant::now();
+        return Ok(cached.tunnel.local_port());
+    }
+
+    let tunnel = SshTunnel::open(config, remote_host, remote_port).await?;
+    let local_port = tunnel.local_port();
+    *slot = Some(CachedTunnel {
+        tunnel,
+        last_used: Instant::now(),
+    });
+
+    Ok(local_port)
+}
+
+impl std::fmt::Debug for SshTunnels {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the keys contain private keys, so only the count is shown
+        f.debug_struct("SshTunnels")
+            .field("tunnels", &self.tunnels.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ssh_config;
+
+    #[tokio::test]
+    async fn keys_share_a_slot_until_it_is_evicted() {
+        let mut tunnels = SshTunnels::new();
+        let key = || (ssh_config(), "postgres-connection".to_string(), 5432);
+        let slot = tunnels.slot(key());
+
+        assert!(Arc::ptr_eq(&slot, &tunnels.slot(key())));
+
+        // slots are kept while a tunnel is being opened
+        let opening = slot.lock().await;
+        tunnels.evict_idle(SSH_TUNNEL_IDLE_TIMEOUT);
+        assert_eq!(tunnels.tunnels.len(), 1);
+
+        // and dropped if it couldn't be opened
+        drop(opening);
+        tunnels.evict_idle(SSH_TUNNEL_IDLE_TIMEOUT);
+        assert!(tunnels.tunnels.is_empty());
+    }
+}
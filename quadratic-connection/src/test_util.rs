@@ -11,10 +11,10 @@ use std::io::Read;
 use arrow_schema::DataType;
 use axum::response::Response;
 use bytes::Bytes;
-use futures::StreamExt;
 use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 use parquet::data_type::AsBytes;
 use quadratic_rust_shared::sql::postgres_connection::PostgresConnection;
+use quadratic_rust_shared::ssh::SshConfig;
 use serde::de::DeserializeOwned;
 
 use crate::auth::Claims;
@@ -31,7 +31,11 @@ macro_rules! test_connection {
         let (mysql_connection, _) = $get_connection(&state, &claims, &connection_id)
             .await
             .unwrap();
-        let response = test(axum::Json(mysql_connection)).await;
+        let test_connection = $crate::server::TestConnection {
+            connection: mysql_connection,
+            ssh: None,
+        };
+        let response = test(axum::Extension(state), axum::Json(test_connection)).await;
 
         assert_eq!(response.0, TestResponse::new(true, None));
     }};
@@ -73,14 +77,22 @@ pub(crate) fn get_claims() -> Claims {
     }
 }
 
+/// The bastion in docker-compose.yml, which can reach the databases by their
+/// service names
+pub(crate) fn ssh_config() -> SshConfig {
+    SshConfig {
+        host: "0.0.0.0".into(),
+        port: Some("2222".into()),
+        username: "user".into(),
+        private_key: include_str!("../../docker/ssh-connection/keys/id_ed25519").into(),
+        host_key_fingerprint: "SHA256:KoC1jnWuWCz5sCcjAY0LAD3Go5KdTIg42xAo3YVpUGA".into(),
+    }
+}
+
+/// Collect all of a response's body, which may be sent in chunks.
 pub(crate) async fn response_bytes(response: Response) -> Bytes {
-    response
-        .into_body()
-        .into_data_stream()
-        .into_future()
+    axum::body::to_bytes(response.into_body(), usize::MAX)
         .await
-        .0
-        .unwrap_or(Ok(Bytes::new()))
         .unwrap()
 }
 
@@ -89,6 +101,20 @@ pub(crate) async fn response_json<T: DeserializeOwned>(response: Response) -> T
     serde_json::from_slice::<T>(&body).unwrap()
 }
 
+/// A value from the key-value metadata in a Parquet file's footer
+pub(crate) fn parquet_metadata(bytes: &Bytes, key: &str) -> Option<String> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(bytes.to_owned()).unwrap();
+
+    builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()?
+        .iter()
+        .find(|key_value| key_value.key == key)?
+        .value
+        .to_owned()
+}
+
 /// Validate a parquet response against an expected array of (DataType, Value Byte Array)
 pub(crate) async fn validate_parquet(response: Response, expected: Vec<(DataType, Vec<u8>)>) {
     let bytes = response_bytes(response).await;
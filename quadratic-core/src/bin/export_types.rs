@@ -4,6 +4,8 @@ use controller::operations::clipboard::PasteSpecial;
 use grid::{
     formats::format::Format,
     js_types::{JsSheetFill, JsValidationWarning},
+    sheet::comments::{Comment, CommentThread},
+    sheet::protections::{EditUser, Protection},
     sheet::validations::{
         validation::{
             Validation, ValidationDisplay, ValidationDisplaySheet, ValidationError,
@@ -31,6 +33,7 @@ use quadratic_core::{
         },
         sheet::search::SearchOptions,
         BorderSelection, BorderStyle, CellBorderLine, CodeCellLanguage, ConnectionKind,
+        SqlParameter,
     },
     selection::Selection,
     sheet_offsets::{
@@ -63,6 +66,7 @@ fn main() {
     s += &generate_type_declarations!(
         CodeCellLanguage,
         ConnectionKind,
+        SqlParameter,
         JsHtmlOutput,
         JsCodeCell,
         JsRenderCodeCell,
@@ -133,7 +137,11 @@ fn main() {
         TextCase,
         TextMatch,
         ValidationText,
-        JsValidationWarning
+        JsValidationWarning,
+        Comment,
+        CommentThread,
+        EditUser,
+        Protection
     );
 
     if create_dir_all("../quadratic-client/src/app/quadratic-core-types").is_ok() {
@@ -15,6 +15,9 @@ pub enum CompressionFormat {
 
 pub enum SerializationFormat {
     Bincode,
+    /// Self-describing binary format, for data that bincode can't
+    /// deserialize (eg, internally tagged enums).
+    Cbor,
     Json,
 }
 
@@ -50,6 +53,14 @@ where
 {
     match serialization_format {
         SerializationFormat::Bincode => Ok(bincode::serialize::<T>(&data)?),
+        SerializationFormat::Cbor => {
+            // serialize through a Value, which isn't human readable, so that
+            // bytes and uuids aren't written as strings
+            let value = ciborium::value::Value::serialized(&data)?;
+            let mut serialized = Vec::new();
+            ciborium::into_writer(&value, &mut serialized)?;
+            Ok(serialized)
+        }
         SerializationFormat::Json => Ok(serde_json::to_string(&data)?.into_bytes()),
     }
 }
@@ -60,6 +71,7 @@ where
 {
     match serialization_format {
         SerializationFormat::Bincode => Ok(bincode::deserialize(data)?),
+        SerializationFormat::Cbor => Ok(ciborium::from_reader(data)?),
         SerializationFormat::Json => Ok(serde_json::from_slice(data)?),
     }
 }
@@ -94,6 +106,31 @@ pub fn decompress_zlib(data: &[u8]) -> Result<Vec<u8>> {
     Ok(decoder.finish()?)
 }
 
+/// Decompresses data, returning an error as soon as the decompressed data is
+/// larger than `max_bytes` (eg, a zip bomb).
+pub fn decompress_limited(
+    compression_format: &CompressionFormat,
+    data: &[u8],
+    max_bytes: usize,
+) -> Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    let reader: Box<dyn Read> = match compression_format {
+        CompressionFormat::None => Box::new(data),
+        CompressionFormat::Zlib => Box::new(flate2::read::ZlibDecoder::new(data)),
+    };
+    reader
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut decompressed)?;
+
+    if decompressed.len() > max_bytes {
+        return Err(anyhow!(
+            "Decompressed data exceeds the limit of {max_bytes} bytes"
+        ));
+    }
+
+    Ok(decompressed)
+}
+
 // HEADER
 
 pub fn add_header(header: Vec<u8>, data: Vec<u8>) -> Result<Vec<u8>> {
@@ -153,4 +190,23 @@ mod test {
 
         assert_roundtrip_compression(&serialization_format, &compression_format);
     }
+
+    #[test]
+    fn decompress_limited_rejects_large_data() {
+        let data = vec![0; 1000];
+        let compressed = compress(&CompressionFormat::Zlib, data.clone()).unwrap();
+
+        let decompressed = decompress_limited(&CompressionFormat::Zlib, &compressed, 1000);
+        assert_eq!(decompressed.unwrap(), data);
+        assert!(decompress_limited(&CompressionFormat::Zlib, &compressed, 999).is_err());
+        assert!(decompress_limited(&CompressionFormat::None, &data, 999).is_err());
+    }
+
+    #[test]
+    fn roundtrip_compression_cbor() {
+        let compression_format = CompressionFormat::None;
+        let serialization_format = SerializationFormat::Cbor;
+
+        assert_roundtrip_compression(&serialization_format, &compression_format);
+    }
 }
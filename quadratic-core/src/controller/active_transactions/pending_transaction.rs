@@ -63,7 +63,18 @@ pub struct PendingTransaction {
     // whether to resend the validations after the transaction completes
     pub send_validations: HashSet<SheetId>,
 
+    // whether to resend the comment threads after the transaction completes
+    pub send_comments: HashSet<SheetId>,
+
+    // whether to resend the protections after the transaction completes
+    pub send_protections: HashSet<SheetId>,
+
     pub resize_rows: HashMap<SheetId, HashSet<i64>>,
+
+    // the length of reverse_operations when volatile code cells were queued
+    // for recomputation; recomputing them isn't undone, so later reverse
+    // operations are dropped
+    pub volatile_reverse_len: Option<usize>,
 }
 
 impl Default for PendingTransaction {
@@ -86,7 +97,10 @@ impl Default for PendingTransaction {
             generate_thumbnail: false,
             cursor_undo_redo: None,
             send_validations: HashSet::new(),
+            send_comments: HashSet::new(),
+            send_protections: HashSet::new(),
             resize_rows: HashMap::new(),
+            volatile_reverse_len: None,
         }
     }
 }
@@ -175,6 +189,26 @@ impl PendingTransaction {
     pub fn is_multiplayer(&self) -> bool {
         matches!(self.transaction_type, TransactionType::Multiplayer)
     }
+
+    /// Returns the seed used by volatile functions (eg, `RAND()`) for a code
+    /// cell. The seed is derived from the transaction id, which is shared with
+    /// other players, so that every client computes the same values.
+    pub fn volatile_seed(&self, sheet_pos: SheetPos) -> u64 {
+        // FNV-1a, which (unlike `DefaultHasher`) is stable across platforms
+        // and compiler versions.
+        const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+
+        self.id
+            .as_bytes()
+            .iter()
+            .chain(sheet_pos.sheet_id.to_string().as_bytes())
+            .chain(&sheet_pos.x.to_le_bytes())
+            .chain(&sheet_pos.y.to_le_bytes())
+            .fold(FNV_OFFSET, |hash, &byte| {
+                (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+            })
+    }
 }
 
 #[cfg(test)]
@@ -184,6 +218,34 @@ mod tests {
     use super::*;
     use serial_test::parallel;
 
+    #[test]
+    #[parallel]
+    fn test_volatile_seed() {
+        let sheet_id = SheetId::new();
+        let transaction = PendingTransaction::default();
+        let pos = SheetPos {
+            x: 1,
+            y: 2,
+            sheet_id,
+        };
+        assert_eq!(
+            transaction.volatile_seed(pos),
+            transaction.volatile_seed(pos)
+        );
+        assert_ne!(
+            transaction.volatile_seed(pos),
+            transaction.volatile_seed(SheetPos {
+                x: 2,
+                y: 1,
+                sheet_id
+            })
+        );
+        assert_ne!(
+            transaction.volatile_seed(pos),
+            PendingTransaction::default().volatile_seed(pos)
+        );
+    }
+
     #[test]
     #[parallel]
     fn test_to_transaction() {
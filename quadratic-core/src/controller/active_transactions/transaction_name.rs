@@ -14,6 +14,7 @@ pub enum TransactionName {
     PasteClipboard,
     SetCode,
     RunCode,
+    Recalculate,
     Import,
     SetSheetMetadata,
     SheetAdd,
@@ -21,4 +22,6 @@ pub enum TransactionName {
     DuplicateSheet,
     MoveCells,
     Validation,
+    Comment,
+    Protection,
 }
@@ -27,6 +27,7 @@ impl GridController {
             Some(dependent_cells)
         }
     }
+
 }
 
 #[cfg(test)]
@@ -75,6 +76,7 @@ mod test {
                 std_err: None,
                 std_out: None,
                 spill_error: false,
+                volatile: false,
                 result: CodeRunResult::Ok(Value::Single(CellValue::Text("test".to_string()))),
                 return_type: Some("text".into()),
                 line_number: None,
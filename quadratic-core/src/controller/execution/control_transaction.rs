@@ -1,3 +1,4 @@
+use bytes::Bytes;
 use chrono::Utc;
 use uuid::Uuid;
 
@@ -10,8 +11,12 @@ use crate::controller::transaction_summary::{CELL_SHEET_HEIGHT, CELL_SHEET_WIDTH
 use crate::controller::transaction_types::JsCodeResult;
 use crate::error_core::Result;
 use crate::grid::{CodeRun, CodeRunResult};
-use crate::parquet::parquet_to_vec;
-use crate::{Pos, RunError, RunErrorMsg, Value};
+use crate::parquet::{parquet_over_the_limit, parquet_to_vec};
+use crate::{Pos, RunError, RunErrorMsg, SheetRect, Value};
+
+/// Shown in a connection's output when its results were cut short
+const OVER_THE_LIMIT_MESSAGE: &str =
+    "Exceeded maximum allowed bytes, not all available records returned.";
 
 impl GridController {
     // loop compute cycle until complete or an async call is made
@@ -26,6 +31,14 @@ impl GridController {
         }
         loop {
             if transaction.operations.is_empty() && transaction.resize_rows.is_empty() {
+                // rerun volatile code cells (and their dependents) before completing
+                if self.add_volatile_compute_operations(transaction) {
+                    continue;
+                }
+                // the recomputed values are shared, but not undone
+                if let Some(len) = transaction.volatile_reverse_len {
+                    transaction.reverse_operations.truncate(len);
+                }
                 transaction.complete = true;
                 break;
             }
@@ -64,9 +77,12 @@ impl GridController {
         if transaction.complete {
             match transaction.transaction_type {
                 TransactionType::User => {
-                    let undo = transaction.to_undo_transaction();
-                    self.undo_stack.push(undo);
-                    self.redo_stack.clear();
+                    // recalculating volatile cells doesn't change the user's data
+                    if transaction.transaction_name != TransactionName::Recalculate {
+                        let undo = transaction.to_undo_transaction();
+                        self.undo_stack.push(undo);
+                        self.redo_stack.clear();
+                    }
                     self.transactions
                         .unsaved_transactions
                         .insert_or_replace(&transaction, true);
@@ -109,6 +125,18 @@ impl GridController {
                     sheet.send_all_validations();
                 }
             });
+
+            transaction.send_comments.iter().for_each(|sheet_id| {
+                if let Some(sheet) = self.try_sheet(*sheet_id) {
+                    sheet.send_all_comments();
+                }
+            });
+
+            transaction.send_protections.iter().for_each(|sheet_id| {
+                if let Some(sheet) = self.try_sheet(*sheet_id) {
+                    sheet.send_all_protections();
+                }
+            });
         }
     }
 
@@ -118,6 +146,10 @@ impl GridController {
         cursor: Option<String>,
         transaction_name: TransactionName,
     ) {
+        if self.refuse_protected_operations(&operations) {
+            return;
+        }
+
         let mut transaction = PendingTransaction {
             transaction_type: TransactionType::User,
             operations: operations.into(),
@@ -166,7 +198,12 @@ impl GridController {
     ) -> Result<()> {
         let transaction_id = Uuid::parse_str(&transaction_id)?;
         let mut transaction = self.transactions.remove_awaiting_async(transaction_id)?;
-        let array = parquet_to_vec(data)?;
+        let data = Bytes::from(data);
+        let array = parquet_to_vec(data.clone())?;
+        let std_out = match parquet_over_the_limit(data) {
+            true => Some(OVER_THE_LIMIT_MESSAGE.to_string()),
+            false => std_out,
+        };
 
         if let Some(current_sheet_pos) = transaction.current_sheet_pos {
             let mut return_type = if array.is_empty() {
@@ -180,6 +217,22 @@ impl GridController {
                 return_type = format!("{return_type}\n{extra}");
             }
 
+            // the query's results are a data table that formulas can reference
+            let data_table = match (&std_err, array.first()) {
+                (None, Some(headers)) if !headers.is_empty() => {
+                    let sheet_rect = SheetRect::from_numbers(
+                        current_sheet_pos.x,
+                        current_sheet_pos.y,
+                        headers.len() as i64,
+                        array.len() as i64,
+                        current_sheet_pos.sheet_id,
+                    );
+                    let headers = headers.iter().map(|header| header.to_display()).collect();
+                    Some(self.set_data_table_operation(sheet_rect, headers))
+                }
+                _ => None,
+            };
+
             let result = if let Some(error_msg) = &std_err {
                 let msg = RunErrorMsg::PythonError(error_msg.clone().into());
                 CodeRunResult::Err(RunError { span: None, msg })
@@ -196,11 +249,15 @@ impl GridController {
                 std_out,
                 std_err,
                 spill_error: false,
+                volatile: false,
                 last_modified: Utc::now(),
                 cells_accessed: transaction.cells_accessed.clone(),
             };
 
             self.finalize_code_run(&mut transaction, current_sheet_pos, Some(code_run), None);
+            if let Some(data_table) = data_table {
+                transaction.operations.push_front(data_table);
+            }
             transaction.waiting_for_async = None;
             self.start_transaction(&mut transaction);
             self.finalize_transaction(transaction);
@@ -208,6 +265,24 @@ impl GridController {
 
         Ok(())
     }
+
+    /// Externally called when an async connection is cancelled.  The
+    /// connection's cell is marked with a cancellation error and the rest of
+    /// the transaction continues.
+    pub fn connection_cancelled(&mut self, transaction_id: String) -> Result<()> {
+        let transaction_id = Uuid::parse_str(&transaction_id)?;
+        let mut transaction = self.transactions.remove_awaiting_async(transaction_id)?;
+        let error = RunError {
+            span: None,
+            msg: RunErrorMsg::Cancelled,
+        };
+
+        self.code_cell_sheet_error(&mut transaction, &error)?;
+        self.start_transaction(&mut transaction);
+        self.finalize_transaction(transaction);
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -423,4 +498,44 @@ mod tests {
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    #[parallel]
+    fn test_connection_cancelled() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_code_cell(
+            SheetPos {
+                x: 0,
+                y: 0,
+                sheet_id,
+            },
+            CodeCellLanguage::Connection {
+                kind: ConnectionKind::Postgres,
+                id: Uuid::new_v4().to_string(),
+            },
+            "select * from table".into(),
+            None,
+        );
+
+        let transaction_id = gc.async_transactions()[0].id;
+        gc.connection_cancelled(transaction_id.to_string()).unwrap();
+
+        assert!(gc.async_transactions().is_empty());
+
+        let code_run = gc.sheet(sheet_id).code_run(Pos { x: 0, y: 0 }).unwrap();
+        assert_eq!(
+            code_run.result,
+            CodeRunResult::Err(RunError {
+                span: None,
+                msg: RunErrorMsg::Cancelled,
+            })
+        );
+        assert_eq!(code_run.std_err, Some("Cancelled".into()));
+
+        // the transaction can't be completed once it's cancelled
+        assert!(gc
+            .connection_complete(transaction_id.to_string(), vec![], None, None, None)
+            .is_err());
+    }
 }
@@ -38,6 +38,26 @@ impl GridController {
             });
     }
 
+    /// Adds compute operations for all volatile code cells. Their dependents
+    /// are added as each volatile cell finishes computing. Returns whether any
+    /// operations were added.
+    ///
+    /// This only runs once per user transaction, after all other operations.
+    pub(crate) fn add_volatile_compute_operations(
+        &mut self,
+        transaction: &mut PendingTransaction,
+    ) -> bool {
+        if !transaction.is_user() || transaction.volatile_reverse_len.is_some() {
+            return false;
+        }
+        transaction.volatile_reverse_len = Some(transaction.reverse_operations.len());
+
+        let operations = self.recalculate_operations();
+        let added = !operations.is_empty();
+        transaction.operations.extend(operations);
+        added
+    }
+
     // delete any code runs within the sheet_rect.
     pub(super) fn check_deleted_code_runs(
         &mut self,
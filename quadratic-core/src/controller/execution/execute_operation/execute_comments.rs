@@ -0,0 +1,361 @@
+use crate::{
+    controller::{
+        active_transactions::pending_transaction::PendingTransaction,
+        operations::operation::Operation, GridController,
+    },
+    Pos,
+};
+
+impl GridController {
+    pub(crate) fn execute_set_comment_thread(
+        &mut self,
+        transaction: &mut PendingTransaction,
+        op: Operation,
+    ) {
+        if let Operation::SetCommentThread {
+            sheet_id,
+            thread_id,
+            thread,
+        } = op
+        {
+            let Some(sheet) = self.grid.try_sheet_mut(sheet_id) else {
+                // sheet may have been deleted
+                return;
+            };
+            let old = match thread.clone() {
+                Some(thread) => sheet.comments.set(thread),
+                None => sheet.comments.remove(thread_id),
+            };
+            if old == thread {
+                return;
+            }
+
+            if transaction.is_user() || transaction.is_undo_redo() {
+                transaction
+                    .forward_operations
+                    .push(Operation::SetCommentThread {
+                        sheet_id,
+                        thread_id,
+                        thread,
+                    });
+                transaction
+                    .reverse_operations
+                    .push(Operation::SetCommentThread {
+                        sheet_id,
+                        thread_id,
+                        thread: old,
+                    });
+            }
+
+            transaction.send_comments.insert(sheet_id);
+        }
+    }
+
+    pub(crate) fn execute_set_comment(
+        &mut self,
+        transaction: &mut PendingTransaction,
+        op: Operation,
+    ) {
+        if let Operation::SetComment {
+            sheet_id,
+            thread_id,
+            comment_id,
+            comment,
+        } = op
+        {
+            // the thread may have been deleted by another user
+            let Some(thread) = self
+                .grid
+                .try_sheet_mut(sheet_id)
+                .and_then(|sheet| sheet.comments.get_mut(thread_id))
+            else {
+                return;
+            };
+            let old = thread.set_comment(comment_id, comment.clone());
+            if old == comment {
+                return;
+            }
+
+            if transaction.is_user() || transaction.is_undo_redo() {
+                transaction.forward_operations.push(Operation::SetComment {
+                    sheet_id,
+                    thread_id,
+                    comment_id,
+                    comment,
+                });
+                transaction.reverse_operations.push(Operation::SetComment {
+                    sheet_id,
+                    thread_id,
+                    comment_id,
+                    comment: old,
+                });
+            }
+
+            transaction.send_comments.insert(sheet_id);
+        }
+    }
+
+    pub(crate) fn execute_set_comment_thread_resolved(
+        &mut self,
+        transaction: &mut PendingTransaction,
+        op: Operation,
+    ) {
+        if let Operation::SetCommentThreadResolved {
+            sheet_id,
+            thread_id,
+            resolved,
+        } = op
+        {
+            let Some(thread) = self
+                .grid
+                .try_sheet_mut(sheet_id)
+                .and_then(|sheet| sheet.comments.get_mut(thread_id))
+            else {
+                return;
+            };
+            if thread.resolved == resolved {
+                return;
+            }
+            thread.resolved = resolved;
+
+            if transaction.is_user() || transaction.is_undo_redo() {
+                transaction
+                    .forward_operations
+                    .push(Operation::SetCommentThreadResolved {
+                        sheet_id,
+                        thread_id,
+                        resolved,
+                    });
+                transaction
+                    .reverse_operations
+                    .push(Operation::SetCommentThreadResolved {
+                        sheet_id,
+                        thread_id,
+                        resolved: !resolved,
+                    });
+            }
+
+            transaction.send_comments.insert(sheet_id);
+        }
+    }
+
+    pub(crate) fn execute_move_comment_thread(
+        &mut self,
+        transaction: &mut PendingTransaction,
+        op: Operation,
+    ) {
+        if let Operation::MoveCommentThread {
+            sheet_id,
+            thread_id,
+            dest,
+        } = op
+        {
+            if self.try_sheet(dest.sheet_id).is_none() {
+                return;
+            }
+            let Some(mut thread) = self
+                .grid
+                .try_sheet_mut(sheet_id)
+                .and_then(|sheet| sheet.comments.remove(thread_id))
+            else {
+                return;
+            };
+            let old = thread.pos.to_sheet_pos(sheet_id);
+            thread.pos = Pos::from(dest);
+            if let Some(sheet) = self.grid.try_sheet_mut(dest.sheet_id) {
+                sheet.comments.set(thread);
+            }
+            if old == dest {
+                return;
+            }
+
+            if transaction.is_user() || transaction.is_undo_redo() {
+                transaction
+                    .forward_operations
+                    .push(Operation::MoveCommentThread {
+                        sheet_id,
+                        thread_id,
+                        dest,
+                    });
+                transaction
+                    .reverse_operations
+                    .push(Operation::MoveCommentThread {
+                        sheet_id: dest.sheet_id,
+                        thread_id,
+                        dest: old,
+                    });
+            }
+
+            transaction.send_comments.insert(sheet_id);
+            transaction.send_comments.insert(dest.sheet_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::{parallel, serial};
+
+    use crate::{
+        controller::GridController,
+        grid::{
+            file::{export, import},
+            sheet::comments::CommentThread,
+        },
+        wasm_bindings::js::{clear_js_calls, expect_js_call},
+        Pos, SheetPos, SheetRect,
+    };
+
+    fn gc_with_thread() -> (GridController, SheetPos, CommentThread) {
+        let mut gc = GridController::test();
+        let sheet_pos = SheetPos::new(gc.sheet_ids()[0], 1, 2);
+        let thread_id = gc.add_comment_thread(
+            sheet_pos,
+            "user_1".into(),
+            "hello @user_2".into(),
+            vec!["user_2".into()],
+            None,
+        );
+        let thread = gc
+            .sheet(sheet_pos.sheet_id)
+            .comments
+            .get(thread_id)
+            .unwrap()
+            .clone();
+        (gc, sheet_pos, thread)
+    }
+
+    #[test]
+    #[parallel]
+    fn test_comment_thread_undo() {
+        let (mut gc, sheet_pos, thread) = gc_with_thread();
+        let sheet_id = sheet_pos.sheet_id;
+        assert_eq!(thread.pos, Pos::from(sheet_pos));
+        assert_eq!(thread.comments[0].mentions, vec!["user_2".to_string()]);
+
+        gc.reply_to_comment_thread(
+            sheet_id,
+            thread.id,
+            "user_2".into(),
+            "hi".into(),
+            vec![],
+            None,
+        );
+        gc.resolve_comment_thread(sheet_id, thread.id, true, None);
+        let updated = gc.sheet(sheet_id).comments.get(thread.id).unwrap();
+        assert!(updated.resolved);
+        assert_eq!(updated.comments.len(), 2);
+        assert_eq!(updated.comments[1].body, "hi");
+
+        gc.undo(None);
+        assert!(!gc.sheet(sheet_id).comments.get(thread.id).unwrap().resolved);
+        gc.undo(None);
+        assert_eq!(gc.sheet(sheet_id).comments.get(thread.id), Some(&thread));
+        gc.undo(None);
+        assert!(gc.sheet(sheet_id).comments.is_empty());
+        gc.redo(None);
+        assert_eq!(gc.sheet(sheet_id).comments.get(thread.id), Some(&thread));
+    }
+
+    #[test]
+    #[parallel]
+    fn test_edit_and_delete_comments() {
+        let (mut gc, sheet_pos, thread) = gc_with_thread();
+        let sheet_id = sheet_pos.sheet_id;
+        let comment_id = thread.comments[0].id;
+
+        gc.edit_comment(
+            sheet_id,
+            thread.id,
+            comment_id,
+            "edited".into(),
+            vec![],
+            None,
+        );
+        let comment = gc.sheet(sheet_id).comments.get(thread.id).unwrap().comments[0].clone();
+        assert_eq!(comment.body, "edited");
+        assert!(comment.mentions.is_empty());
+        assert!(comment.modified.is_some());
+
+        // deleting the first comment deletes the thread
+        gc.delete_comment(sheet_id, thread.id, comment_id, None);
+        assert!(gc.sheet(sheet_id).comments.is_empty());
+        gc.undo(None);
+        assert_eq!(
+            gc.sheet(sheet_id).comments.get(thread.id).unwrap().comments[0],
+            comment
+        );
+    }
+
+    #[test]
+    #[parallel]
+    fn test_comment_threads_move_with_cells() {
+        let (mut gc, sheet_pos, thread) = gc_with_thread();
+        let sheet_id = sheet_pos.sheet_id;
+
+        gc.move_cells(
+            SheetRect::new_pos_span(Pos { x: 0, y: 0 }, Pos { x: 2, y: 2 }, sheet_id),
+            SheetPos::new(sheet_id, 10, 20),
+            None,
+        );
+        let moved = gc.sheet(sheet_id).comments.get(thread.id).unwrap();
+        assert_eq!(moved.pos, Pos { x: 11, y: 22 });
+
+        gc.undo(None);
+        let restored = gc.sheet(sheet_id).comments.get(thread.id).unwrap();
+        assert_eq!(restored.pos, Pos::from(sheet_pos));
+
+        // threads outside the moved cells stay where they are
+        gc.move_cells(
+            SheetRect::new_pos_span(Pos { x: 5, y: 5 }, Pos { x: 6, y: 6 }, sheet_id),
+            SheetPos::new(sheet_id, 10, 20),
+            None,
+        );
+        let unmoved = gc.sheet(sheet_id).comments.get(thread.id).unwrap();
+        assert_eq!(unmoved.pos, Pos::from(sheet_pos));
+    }
+
+    #[test]
+    #[parallel]
+    fn test_comment_threads_move_between_sheets() {
+        let (mut gc, sheet_pos, thread) = gc_with_thread();
+        let sheet_id = sheet_pos.sheet_id;
+        gc.add_sheet(None);
+        let other_sheet_id = gc.sheet_ids()[1];
+
+        gc.move_cells(
+            SheetRect::single_sheet_pos(sheet_pos),
+            SheetPos::new(other_sheet_id, 3, 4),
+            None,
+        );
+        assert!(gc.sheet(sheet_id).comments.is_empty());
+        let moved = gc.sheet(other_sheet_id).comments.get(thread.id).unwrap();
+        assert_eq!(moved.pos, Pos { x: 3, y: 4 });
+
+        gc.undo(None);
+        assert!(gc.sheet(other_sheet_id).comments.is_empty());
+        assert_eq!(gc.sheet(sheet_id).comments.get(thread.id), Some(&thread));
+    }
+
+    #[test]
+    #[parallel]
+    fn test_comment_threads_are_saved() {
+        let (gc, sheet_pos, thread) = gc_with_thread();
+        let grid = import(export(gc.grid().clone()).unwrap()).unwrap();
+        let sheet = grid.try_sheet(sheet_pos.sheet_id).unwrap();
+        assert_eq!(sheet.comments.get(thread.id), Some(&thread));
+    }
+
+    #[test]
+    #[serial]
+    fn test_comment_threads_are_sent_to_client() {
+        clear_js_calls();
+        let (_, sheet_pos, thread) = gc_with_thread();
+        let comments = serde_json::to_string(&vec![thread]).unwrap();
+        expect_js_call(
+            "jsSheetComments",
+            format!("{},{}", sheet_pos.sheet_id, comments),
+            true,
+        );
+    }
+}
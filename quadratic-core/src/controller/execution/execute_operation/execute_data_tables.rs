@@ -0,0 +1,247 @@
+use crate::{
+    controller::{
+        active_transactions::pending_transaction::PendingTransaction,
+        operations::operation::Operation, GridController,
+    },
+    grid::sheet::data_tables::DataTable,
+    Pos, Rect, SheetRect,
+};
+
+impl GridController {
+    pub(crate) fn execute_set_data_table(
+        &mut self,
+        transaction: &mut PendingTransaction,
+        op: Operation,
+    ) {
+        if let Operation::SetDataTable {
+            sheet_id,
+            name,
+            table,
+        } = op
+        {
+            let Some(sheet) = self.grid.try_sheet_mut(sheet_id) else {
+                // sheet may have been deleted
+                return;
+            };
+            let old = match table.clone() {
+                Some(table) => {
+                    // the table may have been renamed
+                    let old = sheet.data_tables.get(&name).cloned();
+                    if !name.eq_ignore_ascii_case(&table.name) {
+                        sheet.data_tables.remove(&name);
+                    }
+                    sheet.data_tables.set(table);
+                    old
+                }
+                None => sheet.data_tables.remove(&name),
+            };
+            if old == table {
+                return;
+            }
+
+            if transaction.is_user() || transaction.is_undo_redo() {
+                let reverse_name = table
+                    .as_ref()
+                    .map_or_else(|| name.clone(), |table| table.name.clone());
+                transaction
+                    .forward_operations
+                    .push(Operation::SetDataTable {
+                        sheet_id,
+                        name,
+                        table: table.clone(),
+                    });
+                transaction
+                    .reverse_operations
+                    .push(Operation::SetDataTable {
+                        sheet_id,
+                        name: reverse_name,
+                        table: old.clone(),
+                    });
+            }
+
+            // formulas that reference the table need to be recomputed using
+            // the table's new bounds
+            if transaction.is_user() {
+                let affected = old
+                    .iter()
+                    .chain(table.iter())
+                    .map(|table| table.rect)
+                    .reduce(|a, b| a.union(&b));
+                if let Some(rect) = affected {
+                    self.add_compute_operations(transaction, &rect.to_sheet_rect(sheet_id), None);
+                }
+            }
+        }
+    }
+
+    /// Checks whether values written to `sheet_rect` change any data table.
+    /// Tables expand to include non-blank rows written directly below them,
+    /// and their headers follow edits to the header row. Adds the necessary
+    /// operations to the front of the transaction.
+    pub(crate) fn check_data_tables(
+        &self,
+        transaction: &mut PendingTransaction,
+        sheet_rect: &SheetRect,
+    ) {
+        let Some(sheet) = self.try_sheet(sheet_rect.sheet_id) else {
+            return;
+        };
+        let rect: Rect = (*sheet_rect).into();
+        for table in sheet.data_tables.iter() {
+            let mut updated = table.clone();
+
+            // expand the table downward while the row below it has data
+            let columns = table.rect.x_range();
+            while rect.y_range().contains(&(updated.rect.max.y + 1))
+                && rect.x_range().any(|x| columns.contains(&x))
+                && columns.clone().any(|x| {
+                    sheet
+                        .display_value(Pos {
+                            x,
+                            y: updated.rect.max.y + 1,
+                        })
+                        .is_some_and(|value| !value.is_blank_or_empty_string())
+                })
+            {
+                updated.rect.max.y += 1;
+            }
+
+            if rect.intersects(table.header_rect()) {
+                updated.headers = table_headers(sheet, &updated);
+            }
+
+            if updated != *table {
+                transaction.operations.push_front(Operation::SetDataTable {
+                    sheet_id: sheet_rect.sheet_id,
+                    name: table.name.clone(),
+                    table: Some(updated),
+                });
+            }
+        }
+    }
+
+    /// Returns an operation that creates or updates the data table whose
+    /// header row starts at `sheet_rect`'s top-left corner.
+    pub fn set_data_table_operation(
+        &self,
+        sheet_rect: SheetRect,
+        headers: Vec<String>,
+    ) -> Operation {
+        let rect: Rect = sheet_rect.into();
+        let name = self
+            .try_sheet(sheet_rect.sheet_id)
+            .and_then(|sheet| sheet.data_tables.table_anchored_at(rect.min))
+            .map_or_else(
+                || self.grid.next_data_table_name(),
+                |table| table.name.clone(),
+            );
+        Operation::SetDataTable {
+            sheet_id: sheet_rect.sheet_id,
+            name: name.clone(),
+            table: Some(DataTable::new(name, rect, headers)),
+        }
+    }
+}
+
+/// Reads a table's header names from the sheet.
+fn table_headers(sheet: &crate::grid::Sheet, table: &DataTable) -> Vec<String> {
+    table
+        .rect
+        .x_range()
+        .map(|x| {
+            sheet
+                .display_value(Pos {
+                    x,
+                    y: table.rect.min.y,
+                })
+                .map(|value| value.to_display())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::parallel;
+
+    use crate::{
+        controller::{active_transactions::transaction_name::TransactionName, GridController},
+        grid::sheet::data_tables::DataTable,
+        Rect, SheetPos,
+    };
+
+    fn gc_with_table() -> GridController {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        for (y, (name, amount)) in [("Name", "Amount"), ("a", "1"), ("b", "2")]
+            .into_iter()
+            .enumerate()
+        {
+            gc.set_cell_value(SheetPos::new(sheet_id, 0, y as i64), name.into(), None);
+            gc.set_cell_value(SheetPos::new(sheet_id, 1, y as i64), amount.into(), None);
+        }
+        let op = gc.set_data_table_operation(
+            Rect::new(0, 0, 1, 2).to_sheet_rect(sheet_id),
+            vec!["Name".into(), "Amount".into()],
+        );
+        gc.start_user_transaction(vec![op], None, TransactionName::Unknown);
+        gc
+    }
+
+    #[test]
+    #[parallel]
+    fn test_set_data_table_undo() {
+        let mut gc = gc_with_table();
+        let sheet_id = gc.sheet_ids()[0];
+        assert_eq!(
+            gc.sheet(sheet_id).data_tables.get("Table1"),
+            Some(&DataTable::new(
+                "Table1".into(),
+                Rect::new(0, 0, 1, 2),
+                vec!["Name".into(), "Amount".into()]
+            ))
+        );
+        gc.undo(None);
+        assert!(gc.sheet(sheet_id).data_tables.is_empty());
+        gc.redo(None);
+        assert!(gc.sheet(sheet_id).data_tables.get("Table1").is_some());
+    }
+
+    #[test]
+    #[parallel]
+    fn test_data_table_expands() {
+        let mut gc = gc_with_table();
+        let sheet_id = gc.sheet_ids()[0];
+
+        // a value outside the table's columns does not expand it
+        gc.set_cell_value(SheetPos::new(sheet_id, 2, 3), "x".into(), None);
+        assert_eq!(
+            gc.sheet(sheet_id).data_tables.get("Table1").unwrap().rect,
+            Rect::new(0, 0, 1, 2)
+        );
+
+        gc.set_cell_value(SheetPos::new(sheet_id, 1, 3), "3".into(), None);
+        assert_eq!(
+            gc.sheet(sheet_id).data_tables.get("Table1").unwrap().rect,
+            Rect::new(0, 0, 1, 3)
+        );
+
+        // renaming a header updates the table
+        gc.set_cell_value(SheetPos::new(sheet_id, 1, 0), "Total".into(), None);
+        assert_eq!(
+            gc.sheet(sheet_id)
+                .data_tables
+                .get("Table1")
+                .unwrap()
+                .headers,
+            vec!["Name".to_string(), "Total".to_string()]
+        );
+
+        gc.undo(None);
+        gc.undo(None);
+        assert_eq!(
+            gc.sheet(sheet_id).data_tables.get("Table1").unwrap().rect,
+            Rect::new(0, 0, 1, 2)
+        );
+    }
+}
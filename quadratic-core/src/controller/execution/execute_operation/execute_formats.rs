@@ -187,6 +187,7 @@ mod test {
             Some(CodeRun {
                 formatted_code_string: None,
                 spill_error: false,
+                volatile: false,
                 output_type: None,
                 std_err: None,
                 std_out: None,
@@ -7,6 +7,7 @@ use crate::{
         GridController,
     },
     selection::Selection,
+    Rect, SheetPos,
 };
 
 impl GridController {
@@ -29,6 +30,22 @@ impl GridController {
                 ) {
                     operations.extend(paste_ops);
                 }
+
+                // comment threads move with their cells
+                if let Some(sheet) = self.try_sheet(source.sheet_id) {
+                    let rect: Rect = source.into();
+                    operations.extend(sheet.comments.in_rect(rect).map(|thread| {
+                        Operation::MoveCommentThread {
+                            sheet_id: source.sheet_id,
+                            thread_id: thread.id,
+                            dest: SheetPos::new(
+                                dest.sheet_id,
+                                dest.x + thread.pos.x - rect.min.x,
+                                dest.y + thread.pos.y - rect.min.y,
+                            ),
+                        }
+                    }));
+                }
                 operations.extend(transaction.operations.drain(..));
                 transaction.operations = operations;
             }
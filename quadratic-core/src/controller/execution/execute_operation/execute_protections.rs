@@ -0,0 +1,128 @@
+use crate::controller::{
+    active_transactions::pending_transaction::PendingTransaction, operations::operation::Operation,
+    GridController,
+};
+
+impl GridController {
+    pub(crate) fn execute_set_protection(
+        &mut self,
+        transaction: &mut PendingTransaction,
+        op: Operation,
+    ) {
+        if let Operation::SetProtection {
+            sheet_id,
+            protection_id,
+            protection,
+        } = op
+        {
+            let Some(sheet) = self.grid.try_sheet_mut(sheet_id) else {
+                // sheet may have been deleted
+                return;
+            };
+            let old = match protection.clone() {
+                Some(protection) => sheet.protections.set(protection),
+                None => sheet.protections.remove(protection_id),
+            };
+            if old == protection {
+                return;
+            }
+
+            if transaction.is_user() || transaction.is_undo_redo() {
+                transaction
+                    .forward_operations
+                    .push(Operation::SetProtection {
+                        sheet_id,
+                        protection_id,
+                        protection,
+                    });
+                transaction
+                    .reverse_operations
+                    .push(Operation::SetProtection {
+                        sheet_id,
+                        protection_id,
+                        protection: old,
+                    });
+            }
+
+            transaction.send_protections.insert(sheet_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::{parallel, serial};
+
+    use crate::{
+        controller::GridController,
+        grid::{
+            file::{export, import},
+            sheet::protections::EditUser,
+        },
+        wasm_bindings::js::{clear_js_calls, expect_js_call},
+        Rect,
+    };
+
+    #[test]
+    #[parallel]
+    fn test_protection_undo() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_edit_user(EditUser {
+            user_id: "owner".into(),
+            roles: vec![],
+        });
+        let protection_id = gc.add_protection(
+            sheet_id,
+            Some(Rect::new(1, 1, 2, 2)),
+            vec!["owner".into()],
+            vec![],
+            None,
+        );
+        let protection = gc
+            .sheet(sheet_id)
+            .protections
+            .get(protection_id)
+            .unwrap()
+            .clone();
+        assert_eq!(protection.rect, Some(Rect::new(1, 1, 2, 2)));
+
+        gc.undo(None);
+        assert!(gc.sheet(sheet_id).protections.is_empty());
+        gc.redo(None);
+        assert_eq!(
+            gc.sheet(sheet_id).protections.get(protection_id),
+            Some(&protection)
+        );
+    }
+
+    #[test]
+    #[parallel]
+    fn test_protections_are_saved() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        let protection_id =
+            gc.add_protection(sheet_id, None, vec![], vec!["FILE_DELETE".into()], None);
+        let protection = gc.sheet(sheet_id).protections.get(protection_id).cloned();
+
+        let grid = import(export(gc.grid().clone()).unwrap()).unwrap();
+        let sheet = grid.try_sheet(sheet_id).unwrap();
+        assert_eq!(sheet.protections.get(protection_id).cloned(), protection);
+    }
+
+    #[test]
+    #[serial]
+    fn test_protections_are_sent_to_client() {
+        clear_js_calls();
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.add_protection(sheet_id, None, vec!["owner".into()], vec![], None);
+        let protections =
+            serde_json::to_string(&gc.sheet(sheet_id).protections.protections).unwrap();
+        expect_js_call(
+            "jsSheetProtections",
+            format!("{},{}", sheet_id, protections),
+            true,
+        );
+    }
+}
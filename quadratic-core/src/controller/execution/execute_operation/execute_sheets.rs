@@ -94,8 +94,15 @@ impl GridController {
                     schema: export_sheet(deleted_sheet),
                 });
 
-            // create a sheet if we deleted the last one (only for user actions)
-            if transaction.is_user() && self.sheet_ids().is_empty() {
+            // create a sheet if we deleted the last one (only for user actions
+            // that don't add a sheet later, eg, restoring a version)
+            let adds_sheet = transaction.operations.iter().any(|op| {
+                matches!(
+                    op,
+                    Operation::AddSheet { .. } | Operation::AddSheetSchema { .. }
+                )
+            });
+            if transaction.is_user() && self.sheet_ids().is_empty() && !adds_sheet {
                 let new_first_sheet_id = SheetId::new();
                 let name = String::from("Sheet 1");
                 let order = self.grid.end_order();
@@ -4,10 +4,13 @@ use crate::controller::GridController;
 
 pub mod execute_borders;
 pub mod execute_code;
+pub mod execute_comments;
 pub mod execute_cursor;
+pub mod execute_data_tables;
 pub mod execute_formats;
 pub mod execute_move_cells;
 pub mod execute_offsets;
+pub mod execute_protections;
 pub mod execute_sheets;
 pub mod execute_validation;
 pub mod execute_values;
@@ -56,6 +59,21 @@ impl GridController {
                 Operation::SetValidationWarning { .. } => {
                     self.execute_set_validation_warning(transaction, op);
                 }
+
+                Operation::SetDataTable { .. } => self.execute_set_data_table(transaction, op),
+
+                Operation::SetCommentThread { .. } => {
+                    self.execute_set_comment_thread(transaction, op);
+                }
+                Operation::SetComment { .. } => self.execute_set_comment(transaction, op),
+                Operation::SetCommentThreadResolved { .. } => {
+                    self.execute_set_comment_thread_resolved(transaction, op);
+                }
+                Operation::MoveCommentThread { .. } => {
+                    self.execute_move_comment_thread(transaction, op);
+                }
+
+                Operation::SetProtection { .. } => self.execute_set_protection(transaction, op),
             }
 
             if cfg!(target_family = "wasm") || cfg!(test) {
@@ -1,6 +1,7 @@
 pub mod auto_resize_row_heights;
 pub mod control_transaction;
 pub mod execute_operation;
+pub mod protections;
 pub mod receive_multiplayer;
 pub mod run_code;
 pub mod spills;
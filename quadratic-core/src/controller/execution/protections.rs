@@ -0,0 +1,324 @@
+//! Checks operations against protected ranges and sheets before they are
+//! applied.
+
+use crate::controller::operations::operation::Operation;
+use crate::controller::operations::targets::OperationTarget;
+use crate::controller::GridController;
+use crate::grid::sheet::protections::{EditUser, Protection};
+use crate::grid::SheetId;
+use crate::{CellValue, SheetRect};
+
+impl GridController {
+    /// Sets the user making local edits.
+    pub fn set_edit_user(&mut self, edit_user: EditUser) {
+        self.edit_user = edit_user;
+    }
+
+    pub fn edit_user(&self) -> &EditUser {
+        &self.edit_user
+    }
+
+    /// Gets the first protection that prevents the user from applying the
+    /// operations.
+    ///
+    /// Recomputed code runs aren't checked, see `is_recompute`, so editing an
+    /// input of a protected formula is allowed, and a transaction is checked
+    /// the same way by the client, before its dependents are recomputed, and
+    /// by the server, which receives the recomputed code runs.
+    pub fn blocking_protection(
+        &self,
+        operations: &[Operation],
+        user: &EditUser,
+    ) -> Option<(SheetId, &Protection)> {
+        operations
+            .iter()
+            .filter(|op| !self.is_recompute(op, operations))
+            .flat_map(|op| self.operation_targets(op))
+            .find_map(|target| {
+                let sheet_id = match target {
+                    OperationTarget::Cells(sheet_rect) => sheet_rect.sheet_id,
+                    OperationTarget::Sheet(sheet_id) => sheet_id,
+                    OperationTarget::Protection { sheet_id, .. } => sheet_id,
+                };
+                let protections = &self.try_sheet(sheet_id)?.protections;
+                let protection = match target {
+                    OperationTarget::Cells(sheet_rect) => {
+                        protections.blocking(sheet_rect.into(), user)
+                    }
+                    OperationTarget::Sheet(_) => protections.blocking_sheet(user),
+
+                    // only users allowed by a protection may change it
+                    OperationTarget::Protection { protection_id, .. } => protections
+                        .get(protection_id)
+                        .filter(|protection| !protection.allows(user))
+                        .or_else(|| protections.blocking_sheet(user)),
+                };
+                protection.map(|protection| (sheet_id, protection))
+            })
+    }
+
+    /// The operation's targets, including the current output of a code cell
+    /// that a code operation replaces.
+    fn operation_targets(&self, op: &Operation) -> Vec<OperationTarget> {
+        let mut targets = op.targets();
+        if let Operation::SetCodeRun { sheet_pos, .. } | Operation::ComputeCode { sheet_pos } = op {
+            let code_run = self
+                .try_sheet(sheet_pos.sheet_id)
+                .and_then(|sheet| sheet.code_run((*sheet_pos).into()));
+            if let Some(code_run) = code_run {
+                targets.push(OperationTarget::Cells(
+                    code_run.output_sheet_rect(*sheet_pos, false),
+                ));
+            }
+        }
+        targets
+    }
+
+    /// Whether the operation sets the code run of a code cell that's already
+    /// in the grid and that no other operation changes, ie, it recomputes the
+    /// cell's existing code, such as a formula whose inputs changed or a
+    /// volatile formula.
+    fn is_recompute(&self, op: &Operation, operations: &[Operation]) -> bool {
+        let Operation::SetCodeRun { sheet_pos, .. } = op else {
+            return false;
+        };
+        let is_code_cell = self
+            .try_sheet(sheet_pos.sheet_id)
+            .and_then(|sheet| sheet.cell_value((*sheet_pos).into()))
+            .is_some_and(|value| matches!(value, CellValue::Code(_)));
+
+        is_code_cell
+            && !operations
+                .iter()
+                .filter(|other| !matches!(other, Operation::SetCodeRun { .. }))
+                .flat_map(|other| self.operation_targets(other))
+                .any(|target| match target {
+                    OperationTarget::Cells(sheet_rect) => {
+                        sheet_rect.intersects(SheetRect::single_sheet_pos(*sheet_pos))
+                    }
+                    OperationTarget::Sheet(sheet_id) => sheet_id == sheet_pos.sheet_id,
+                    OperationTarget::Protection { .. } => false,
+                })
+    }
+
+    /// Returns true (and notifies the client) if the local user is not
+    /// allowed to apply the operations.
+    pub(crate) fn refuse_protected_operations(&self, operations: &[Operation]) -> bool {
+        let Some((sheet_id, protection)) = self.blocking_protection(operations, &self.edit_user)
+        else {
+            return false;
+        };
+
+        if cfg!(target_family = "wasm") || cfg!(test) {
+            if let Ok(protection) = serde_json::to_string(protection) {
+                crate::wasm_bindings::js::jsProtectedEdit(sheet_id.to_string(), protection);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::{parallel, serial};
+
+    use super::*;
+    use crate::{
+        grid::CodeCellLanguage,
+        wasm_bindings::js::{clear_js_calls, expect_js_call},
+        Pos, Rect, SheetPos,
+    };
+
+    fn user(user_id: &str) -> EditUser {
+        EditUser {
+            user_id: user_id.to_string(),
+            roles: vec!["FILE_EDIT".to_string()],
+        }
+    }
+
+    fn gc_with_protection(rect: Option<Rect>) -> (GridController, SheetId, Protection) {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_edit_user(user("owner"));
+        let protection_id = gc.add_protection(sheet_id, rect, vec![], vec![], None);
+        let protection = gc
+            .sheet(sheet_id)
+            .protections
+            .get(protection_id)
+            .unwrap()
+            .clone();
+        (gc, sheet_id, protection)
+    }
+
+    #[test]
+    #[parallel]
+    fn protected_cells_refuse_local_edits() {
+        let (mut gc, sheet_id, protection) = gc_with_protection(Some(Rect::new(0, 0, 1, 1)));
+        assert_eq!(protection.users, vec!["owner".to_string()]);
+
+        // the owner can edit protected cells
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 0), "owner".into(), None);
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Text("owner".into()))
+        );
+
+        // other users can only edit unprotected cells
+        gc.set_edit_user(user("editor"));
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 0), "editor".into(), None);
+        gc.set_cell_value(SheetPos::new(sheet_id, 5, 5), "editor".into(), None);
+        let sheet = gc.sheet(sheet_id);
+        assert_eq!(
+            sheet.display_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Text("owner".into()))
+        );
+        assert_eq!(
+            sheet.display_value(Pos { x: 5, y: 5 }),
+            Some(CellValue::Text("editor".into()))
+        );
+
+        // nor remove the protection
+        gc.remove_protection(sheet_id, protection.id, None);
+        assert_eq!(
+            gc.sheet(sheet_id).protections.get(protection.id),
+            Some(&protection)
+        );
+    }
+
+    #[test]
+    #[parallel]
+    fn code_outputs_are_protected() {
+        let (mut gc, sheet_id, protection) = gc_with_protection(Some(Rect::new(0, 0, 1, 1)));
+
+        // the owner's formula spills into the protected cells
+        let sheet_pos = SheetPos::new(sheet_id, 0, -2);
+        gc.set_code_cell(
+            sheet_pos,
+            CodeCellLanguage::Formula,
+            "{1; 2; 3}".into(),
+            None,
+        );
+
+        // rerunning the formula replaces its output in the protected cells
+        let operations = [Operation::ComputeCode { sheet_pos }];
+        assert_eq!(
+            gc.blocking_protection(&operations, &user("editor")),
+            Some((sheet_id, &protection))
+        );
+        assert_eq!(gc.blocking_protection(&operations, &user("owner")), None);
+    }
+
+    #[test]
+    #[parallel]
+    fn recomputed_outputs_are_not_checked() {
+        let (mut gc, sheet_id, protection) = gc_with_protection(Some(Rect::new(1, 0, 2, 0)));
+        let input = SheetPos::new(sheet_id, 0, 0);
+        let formula = SheetPos::new(sheet_id, 1, 0);
+        gc.set_cell_value(input, "1".into(), None);
+        gc.set_code_cell(formula, CodeCellLanguage::Formula, "A0 + 1".into(), None);
+
+        // editing an unprotected input recomputes the protected formula
+        gc.set_edit_user(user("editor"));
+        gc.set_cell_value(input, "2".into(), None);
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(formula.into()),
+            Some(CellValue::Number(3.into()))
+        );
+
+        // and the server, which receives the recomputed code run, agrees
+        let forward = gc.last_transaction().unwrap().operations.clone();
+        assert!(forward.iter().any(|op| matches!(
+            op,
+            Operation::SetCodeRun { sheet_pos, .. } if *sheet_pos == formula
+        )));
+        assert_eq!(gc.blocking_protection(&forward, &user("editor")), None);
+
+        // a formula that spills into the protected cells
+        let spill = SheetPos::new(sheet_id, 2, -2);
+        gc.set_edit_user(user("owner"));
+        gc.set_code_cell(spill, CodeCellLanguage::Formula, "{1; 2; 3}".into(), None);
+
+        // can't be edited by other users, as its code run isn't a recompute
+        gc.set_code_cell(spill, CodeCellLanguage::Formula, "{4; 5; 6}".into(), None);
+        let forward = gc.last_transaction().unwrap().operations.clone();
+        assert_eq!(
+            gc.blocking_protection(&forward, &user("editor")),
+            Some((sheet_id, &protection))
+        );
+
+        gc.set_edit_user(user("editor"));
+        gc.set_code_cell(spill, CodeCellLanguage::Formula, "{7; 8; 9}".into(), None);
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(Pos { x: 2, y: 0 }),
+            Some(CellValue::Number(6.into()))
+        );
+    }
+
+    #[test]
+    #[parallel]
+    fn protected_sheets_refuse_local_edits() {
+        let (mut gc, sheet_id, protection) = gc_with_protection(None);
+        gc.set_edit_user(user("editor"));
+
+        gc.set_cell_value(SheetPos::new(sheet_id, 100, 100), "editor".into(), None);
+        gc.set_sheet_name(sheet_id, "renamed".into(), None);
+        let sheet = gc.sheet(sheet_id);
+        assert_eq!(sheet.display_value(Pos { x: 100, y: 100 }), None);
+        assert_ne!(sheet.name, "renamed");
+
+        // roles may be allowed as well as users
+        let mut protection = protection;
+        protection.roles = vec!["FILE_EDIT".to_string()];
+        gc.set_edit_user(user("owner"));
+        gc.update_protection(sheet_id, protection, None);
+        gc.set_edit_user(user("editor"));
+        gc.set_sheet_name(sheet_id, "renamed".into(), None);
+        assert_eq!(gc.sheet(sheet_id).name, "renamed");
+    }
+
+    #[test]
+    #[parallel]
+    fn undo_is_refused_after_cells_are_protected() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_edit_user(user("editor"));
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 0), "editor".into(), None);
+
+        // another user protects the edited cell
+        let protection = Protection::new(
+            Some(Rect::new(0, 0, 0, 0)),
+            vec!["owner".to_string()],
+            vec![],
+        );
+        gc.try_sheet_mut(sheet_id)
+            .unwrap()
+            .protections
+            .set(protection);
+
+        gc.undo(None);
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Text("editor".into()))
+        );
+        assert!(gc.has_undo());
+    }
+
+    #[test]
+    #[serial]
+    fn protected_edits_are_sent_to_client() {
+        let (mut gc, sheet_id, protection) = gc_with_protection(Some(Rect::new(0, 0, 1, 1)));
+        gc.set_edit_user(user("editor"));
+        clear_js_calls();
+
+        gc.set_cell_value(SheetPos::new(sheet_id, 1, 1), "editor".into(), None);
+        expect_js_call(
+            "jsProtectedEdit",
+            format!(
+                "{},{}",
+                sheet_id,
+                serde_json::to_string(&protection).unwrap()
+            ),
+            true,
+        );
+    }
+}
@@ -10,6 +10,7 @@ use crate::controller::{
     transaction::{Transaction, TransactionServer},
     GridController,
 };
+use crate::grid::Grid;
 use chrono::{Duration, TimeDelta, Utc};
 use uuid::Uuid;
 
@@ -208,21 +209,29 @@ impl GridController {
             // We could apply these transactions as they come in, but only if multiplayer also sent all undo
             // operations w/each Transaction. I don't think this would be worth the cost.
             // We ignore any transactions that we already applied (ie, sequence_num <= self.last_sequence_num).
-            let default_sequence_number = self.transactions.out_of_order_transactions.len();
-            let index = self
-                .transactions
-                .out_of_order_transactions
-                .iter()
-                .position(|t| {
-                    t.sequence_num.unwrap_or(default_sequence_number as u64) < sequence_num
-                })
-                .unwrap_or(default_sequence_number);
-            self.transactions
-                .out_of_order_transactions
-                .insert(index, transaction.to_transaction(Some(sequence_num)));
+            self.insert_out_of_order_transaction(transaction, sequence_num);
         }
     }
 
+    /// Holds on to a transaction that arrived before its turn in the sorted
+    /// out_of_order_transactions.
+    fn insert_out_of_order_transaction(
+        &mut self,
+        transaction: &PendingTransaction,
+        sequence_num: u64,
+    ) {
+        let default_sequence_number = self.transactions.out_of_order_transactions.len();
+        let index = self
+            .transactions
+            .out_of_order_transactions
+            .iter()
+            .position(|t| t.sequence_num.unwrap_or(default_sequence_number as u64) < sequence_num)
+            .unwrap_or(default_sequence_number);
+        self.transactions
+            .out_of_order_transactions
+            .insert(index, transaction.to_transaction(Some(sequence_num)));
+    }
+
     /// Received transactions from the server
     pub fn received_transactions(&mut self, transactions: &[TransactionServer]) {
         // used to track client changes when combining transactions
@@ -257,6 +266,99 @@ impl GridController {
         self.finalize_transaction(results);
     }
 
+    /// Received the latest checkpoint and the transactions after it from the
+    /// server. This happens when the server no longer has every transaction
+    /// since our last_sequence_num (eg, after a laptop wakes from sleep).
+    ///
+    /// The grid is replaced by the checkpoint, the transactions are applied in
+    /// order, and the unsaved transactions are reapplied so offline work is
+    /// not lost. The undo and redo stacks are cleared since they no longer
+    /// apply to the grid.
+    pub fn received_catch_up(
+        &mut self,
+        checkpoint: Grid,
+        checkpoint_sequence_num: u64,
+        transactions: &[TransactionServer],
+    ) {
+        // used to track client changes when combining transactions
+        let results = PendingTransaction {
+            transaction_type: TransactionType::Multiplayer,
+            ..Default::default()
+        };
+
+        // replacing the grid also removes the unsaved transactions' changes
+        let mut restore = PendingTransaction {
+            transaction_type: TransactionType::Multiplayer,
+            operations: self.restore_grid_operations(checkpoint).into(),
+            ..Default::default()
+        };
+        self.start_transaction(&mut restore);
+        restore.send_transaction();
+
+        self.transactions.last_sequence_num = checkpoint_sequence_num;
+        self.transactions
+            .out_of_order_transactions
+            .retain(|t| t.sequence_num > Some(checkpoint_sequence_num));
+
+        transactions.iter().for_each(|t| {
+            if t.sequence_num <= self.transactions.last_sequence_num {
+                return;
+            }
+
+            let Ok(operations) =
+                Transaction::decompress_and_deserialize::<Vec<Operation>>(&t.operations)
+            else {
+                dbgjs!("Unable to decompress and deserialize operations in received_catch_up()");
+                return;
+            };
+
+            // our unsaved transactions that reached the server are applied with the others
+            if let Some(index) = self.transactions.unsaved_transactions.find_index(t.id) {
+                self.transactions.unsaved_transactions.remove(index);
+            }
+
+            let mut transaction = PendingTransaction {
+                id: t.id,
+                transaction_type: TransactionType::Multiplayer,
+                operations: operations.into(),
+                ..Default::default()
+            };
+
+            if t.sequence_num == self.transactions.last_sequence_num + 1 {
+                self.start_transaction(&mut transaction);
+                transaction.send_transaction();
+                self.transactions.last_sequence_num = t.sequence_num;
+            } else {
+                self.insert_out_of_order_transaction(&transaction, t.sequence_num);
+            }
+        });
+        self.apply_out_of_order_transactions(self.transactions.last_sequence_num);
+
+        // reapply the remaining unsaved transactions one at a time so their
+        // reverse operations match the new grid
+        for index in 0..self.transactions.unsaved_transactions.len() {
+            let forward = self.transactions.unsaved_transactions[index]
+                .forward
+                .clone();
+            let mut reapply = PendingTransaction {
+                id: forward.id,
+                transaction_type: TransactionType::Multiplayer,
+                operations: forward.operations.into(),
+                ..Default::default()
+            };
+            self.start_transaction(&mut reapply);
+            reapply.send_transaction();
+            self.transactions.unsaved_transactions[index]
+                .reverse
+                .operations = reapply.to_undo_transaction().operations;
+        }
+
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+
+        self.finalize_transaction(results);
+    }
+
     /// Called by TS for each offline transaction it has in its offline queue.
     pub fn apply_offline_unsaved_transaction(
         &mut self,
@@ -1204,4 +1306,95 @@ mod tests {
             Some(CellValue::Number(BigDecimal::from(3)))
         );
     }
+
+    #[test]
+    #[parallel]
+    fn received_catch_up_keeps_unsaved_transactions() {
+        let mut server = GridController::test();
+        let sheet_id = server.sheet_ids()[0];
+        let mut client = GridController::from_grid(server.grid().clone(), 0);
+        let pos = |x| SheetPos { x, y: 0, sheet_id };
+        let file_id = Uuid::new_v4();
+        let to_server = |gc: &GridController, sequence_num| {
+            let transaction = gc.last_transaction().unwrap();
+            TransactionServer {
+                id: transaction.id,
+                file_id,
+                operations: Transaction::serialize_and_compress(&transaction.operations).unwrap(),
+                sequence_num,
+            }
+        };
+
+        // the server checkpoints after the first transaction
+        server.set_cell_value(pos(0), "checkpoint".to_string(), None);
+        let checkpoint = server.grid().clone();
+
+        // while the client is asleep, another user makes a change
+        let mut other = GridController::from_grid(checkpoint.clone(), 1);
+        other.set_cell_value(pos(1), "other".to_string(), None);
+        let other_transaction = to_server(&other, 2);
+
+        // the client made two offline changes, and only the first reached the server
+        client.set_cell_value(pos(2), "sent".to_string(), None);
+        let sent_transaction = to_server(&client, 3);
+        client.set_cell_value(pos(3), "offline".to_string(), None);
+        assert_eq!(client.transactions.unsaved_transactions.len(), 2);
+
+        client.received_catch_up(checkpoint, 1, &[other_transaction, sent_transaction]);
+
+        let sheet = client.sheet(sheet_id);
+        let text = |x: &str| Some(CellValue::Text(x.to_string()));
+        assert_eq!(sheet.display_value(Pos { x: 0, y: 0 }), text("checkpoint"));
+        assert_eq!(sheet.display_value(Pos { x: 1, y: 0 }), text("other"));
+        assert_eq!(sheet.display_value(Pos { x: 2, y: 0 }), text("sent"));
+        assert_eq!(sheet.display_value(Pos { x: 3, y: 0 }), text("offline"));
+        assert_eq!(client.transactions.last_sequence_num, 3);
+        assert_eq!(client.transactions.unsaved_transactions.len(), 1);
+        assert!(!client.has_undo());
+
+        // the offline change survives later transactions from the server
+        other.set_cell_value(pos(4), "later".to_string(), None);
+        let later = other.last_transaction().unwrap().clone();
+        client.received_transaction(later.id, 4, later.operations);
+
+        let sheet = client.sheet(sheet_id);
+        assert_eq!(sheet.display_value(Pos { x: 3, y: 0 }), text("offline"));
+        assert_eq!(sheet.display_value(Pos { x: 4, y: 0 }), text("later"));
+        assert_eq!(client.transactions.unsaved_transactions.len(), 1);
+    }
+
+    #[test]
+    #[parallel]
+    fn received_catch_up_holds_out_of_order_transactions() {
+        let mut server = GridController::test();
+        let sheet_id = server.sheet_ids()[0];
+        let checkpoint = server.grid().clone();
+        let mut client = GridController::from_grid(checkpoint.clone(), 0);
+
+        server.set_cell_value(
+            SheetPos {
+                x: 0,
+                y: 0,
+                sheet_id,
+            },
+            "later".to_string(),
+            None,
+        );
+        let transaction = server.last_transaction().unwrap().clone();
+        let later = TransactionServer {
+            id: transaction.id,
+            file_id: Uuid::new_v4(),
+            operations: Transaction::serialize_and_compress(&transaction.operations).unwrap(),
+            sequence_num: 7,
+        };
+
+        client.received_catch_up(checkpoint, 5, &[later]);
+
+        assert_eq!(client.transactions.last_sequence_num, 5);
+        assert_eq!(client.transactions.out_of_order_transactions.len(), 1);
+        assert_eq!(
+            client.sheet(sheet_id).display_value(Pos { x: 0, y: 0 }),
+            None
+        );
+    }
 }
@@ -276,6 +276,7 @@ impl GridController {
                     std_out: None,
                     std_err: Some(error.msg.to_string()),
                     spill_error: false,
+                    volatile: false,
                     last_modified: Utc::now(),
 
                     // keep the old cells_accessed to better rerun after an error
@@ -293,6 +294,7 @@ impl GridController {
                 std_out: None,
                 std_err: Some(error.msg.to_string()),
                 spill_error: false,
+                volatile: false,
                 last_modified: Utc::now(),
                 cells_accessed: transaction.cells_accessed.clone(),
             },
@@ -327,6 +329,7 @@ impl GridController {
                 std_out: None,
                 std_err: None,
                 spill_error: false,
+                volatile: false,
                 last_modified: Utc::now(),
                 cells_accessed: transaction.cells_accessed.clone(),
             };
@@ -382,6 +385,7 @@ impl GridController {
             std_out: js_code_result.std_out,
             std_err: js_code_result.std_err,
             spill_error: false,
+            volatile: false,
             last_modified: Utc::now(),
             cells_accessed: transaction.cells_accessed.clone(),
         };
@@ -437,6 +441,7 @@ mod test {
             last_modified: Utc::now(),
             cells_accessed: HashSet::new(),
             spill_error: false,
+            volatile: false,
         };
         gc.finalize_code_run(transaction, sheet_pos, Some(new_code_run.clone()), None);
         assert_eq!(transaction.forward_operations.len(), 1);
@@ -466,6 +471,7 @@ mod test {
             last_modified: Utc::now(),
             cells_accessed: HashSet::new(),
             spill_error: false,
+            volatile: false,
         };
         gc.finalize_code_run(transaction, sheet_pos, Some(new_code_run.clone()), None);
         assert_eq!(transaction.forward_operations.len(), 1);
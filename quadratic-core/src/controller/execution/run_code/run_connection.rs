@@ -1,9 +1,33 @@
+use std::collections::HashSet;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
 use crate::{
     controller::{active_transactions::pending_transaction::PendingTransaction, GridController},
-    grid::{CodeCellLanguage, ConnectionKind},
-    SheetPos,
+    formulas::{parse_sheet_name, CellRef},
+    grid::{CodeCellLanguage, ConnectionKind, SqlParameter},
+    CellValue, Pos, Rect, RunError, RunErrorMsg, SheetPos, SheetRect, Span,
 };
 
+lazy_static! {
+    /// Matches a cell reference in a connection's query, eg, `{{A1}}` or
+    /// `{{'Inputs'!B2:B10}}`.
+    static ref CELL_REFERENCE_REGEX: Regex = Regex::new(r"\{\{(.*?)\}\}").unwrap();
+}
+
+/// The most cells a query can reference, which is the most parameters that
+/// Postgres and MySQL can bind to a statement.
+pub(crate) const MAX_CONNECTION_PARAMETERS: usize = 65535;
+
+/// A connection's query with its cell references replaced by placeholders.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct BoundQuery {
+    pub(crate) query: String,
+    pub(crate) parameters: Vec<SqlParameter>,
+    pub(crate) cells_accessed: HashSet<SheetRect>,
+}
+
 impl GridController {
     pub(crate) fn run_connection(
         &mut self,
@@ -13,22 +37,362 @@ impl GridController {
         kind: ConnectionKind,
         id: String,
     ) {
+        transaction.current_sheet_pos = Some(sheet_pos);
+
+        let bound = match self.bind_connection_parameters(&code, sheet_pos, kind) {
+            Ok(bound) => bound,
+            Err(error) => {
+                let _ = self.code_cell_sheet_error(transaction, &error);
+                return;
+            }
+        };
+
+        // the referenced cells are dependencies, so the query reruns when they change
+        transaction.cells_accessed = bound.cells_accessed;
+
         // send the request to get the sql data via the connector to the host
         if (cfg!(target_family = "wasm") || cfg!(test)) && !transaction.is_server() {
-            crate::wasm_bindings::js::jsConnection(
-                transaction.id.to_string(),
-                sheet_pos.x as i32,
-                sheet_pos.y as i32,
-                sheet_pos.sheet_id.to_string(),
-                code,
-                kind,
-                id.to_owned(),
-            );
+            if let Ok(parameters) = serde_json::to_string(&bound.parameters) {
+                crate::wasm_bindings::js::jsConnection(
+                    transaction.id.to_string(),
+                    sheet_pos.x as i32,
+                    sheet_pos.y as i32,
+                    sheet_pos.sheet_id.to_string(),
+                    bound.query,
+                    parameters,
+                    kind,
+                    id.to_owned(),
+                );
+            }
         }
 
         // stop the computation cycle until async returns
-        transaction.current_sheet_pos = Some(sheet_pos);
         transaction.waiting_for_async = Some(CodeCellLanguage::Connection { kind, id });
         self.transactions.add_async_transaction(transaction);
     }
+
+    /// Replaces the cell references in a connection's query with placeholders
+    /// for its driver, and collects the referenced values as parameters.  A
+    /// range is replaced by a comma separated list of placeholders, one for
+    /// each cell in row-major order, eg, for use in `IN ({{A1:A3}})`.
+    pub(crate) fn bind_connection_parameters(
+        &self,
+        code: &str,
+        sheet_pos: SheetPos,
+        kind: ConnectionKind,
+    ) -> Result<BoundQuery, RunError> {
+        let mut bound = BoundQuery::default();
+        let mut last = 0;
+
+        for captures in CELL_REFERENCE_REGEX.captures_iter(code) {
+            let Some(reference) = captures.get(0) else {
+                continue;
+            };
+            let span = Span {
+                start: reference.start() as u32,
+                end: reference.end() as u32,
+            };
+            let bad_ref = || RunErrorMsg::BadCellReference.with_span(span);
+            let sheet_rect = self
+                .parse_connection_reference(&captures[1], sheet_pos)
+                .ok_or_else(bad_ref)?;
+            let sheet = self.try_sheet(sheet_rect.sheet_id).ok_or_else(bad_ref)?;
+
+            if bound.parameters.len() + sheet_rect.len() > MAX_CONNECTION_PARAMETERS {
+                return Err(RunErrorMsg::TooManyParameters {
+                    max: MAX_CONNECTION_PARAMETERS,
+                }
+                .with_span(span));
+            }
+
+            let mut placeholders = vec![];
+            for y in sheet_rect.y_range() {
+                for x in sheet_rect.x_range() {
+                    let value = sheet
+                        .display_value(Pos { x, y })
+                        .unwrap_or(CellValue::Blank);
+                    bound.parameters.push(value.into());
+                    placeholders.push(match kind {
+                        ConnectionKind::Postgres | ConnectionKind::Duckdb => {
+                            format!("${}", bound.parameters.len())
+                        }
+                        ConnectionKind::Mysql | ConnectionKind::Sqlite => "?".to_string(),
+                        ConnectionKind::Mssql => format!("@P{}", bound.parameters.len()),
+                    });
+                }
+            }
+
+            bound.query.push_str(&code[last..reference.start()]);
+            bound.query.push_str(&placeholders.join(", "));
+            bound.cells_accessed.insert(sheet_rect);
+            last = reference.end();
+        }
+
+        bound.query.push_str(&code[last..]);
+
+        Ok(bound)
+    }
+
+    /// Parses the inside of a `{{...}}` reference, eg, `A1` or
+    /// `'Inputs'!B2:B10`.  References without a sheet name are to the
+    /// connection's sheet.
+    fn parse_connection_reference(
+        &self,
+        reference: &str,
+        sheet_pos: SheetPos,
+    ) -> Option<SheetRect> {
+        let (sheet_name, cells) = parse_sheet_name(reference.trim());
+        let sheet = match sheet_name {
+            Some(sheet_name) => self.grid().try_sheet_from_name(sheet_name)?,
+            None => self.try_sheet(sheet_pos.sheet_id)?,
+        };
+
+        let origin = Pos { x: 0, y: 0 };
+        let parse_pos =
+            |s: &str| CellRef::parse_a1(s, origin).map(|cell_ref| cell_ref.resolve_from(origin));
+        let rect = match cells.split_once(':') {
+            Some((start, end)) => Rect::new_span(parse_pos(start)?, parse_pos(end)?),
+            None => Rect::single_pos(parse_pos(&cells)?),
+        };
+
+        Some(rect.to_sheet_rect(sheet.id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use serial_test::{parallel, serial};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{wasm_bindings::js::expect_js_call, Instant};
+
+    #[test]
+    #[parallel]
+    fn binds_cell_references_as_parameters() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_cell_values(
+            SheetPos::from((0, 1, sheet_id)),
+            vec![vec!["42"], vec!["1.5"], vec!["true"], vec![""], vec!["hi"]],
+            None,
+        );
+
+        let bound = gc
+            .bind_connection_parameters(
+                "select * from t where a = {{A1}} and b in ({{ $A$2:A5 }})",
+                SheetPos::from((5, 5, sheet_id)),
+                ConnectionKind::Postgres,
+            )
+            .unwrap();
+
+        assert_eq!(
+            bound.query,
+            "select * from t where a = $1 and b in ($2, $3, $4, $5)"
+        );
+        assert_eq!(
+            bound.parameters,
+            vec![
+                SqlParameter::Integer(42),
+                SqlParameter::Number(1.5),
+                SqlParameter::Boolean(true),
+                SqlParameter::Null,
+                SqlParameter::Text("hi".into()),
+            ]
+        );
+        assert_eq!(
+            bound.cells_accessed,
+            HashSet::from([
+                SheetRect::single_pos(Pos { x: 0, y: 1 }, sheet_id),
+                SheetRect::new_pos_span(Pos { x: 0, y: 2 }, Pos { x: 0, y: 5 }, sheet_id),
+            ])
+        );
+    }
+
+    #[test]
+    #[parallel]
+    fn binds_references_to_other_sheets() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.add_sheet_with_name("Inputs".into(), None);
+        let inputs_id = gc.sheet_ids()[1];
+        gc.set_cell_values(
+            SheetPos::from((1, 2, inputs_id)),
+            vec![vec!["a"], vec!["b"]],
+            None,
+        );
+
+        let bound = gc
+            .bind_connection_parameters(
+                "select * from t where a in ({{'Inputs'!B2:B3}})",
+                SheetPos::from((0, 0, sheet_id)),
+                ConnectionKind::Mysql,
+            )
+            .unwrap();
+
+        assert_eq!(bound.query, "select * from t where a in (?, ?)");
+        assert_eq!(
+            bound.parameters,
+            vec![
+                SqlParameter::Text("a".into()),
+                SqlParameter::Text("b".into())
+            ]
+        );
+        assert_eq!(
+            bound.cells_accessed,
+            HashSet::from([SheetRect::new_pos_span(
+                Pos { x: 1, y: 2 },
+                Pos { x: 1, y: 3 },
+                inputs_id
+            )])
+        );
+    }
+
+    #[test]
+    #[parallel]
+    fn placeholders_for_each_kind() {
+        let gc = GridController::test();
+        let sheet_pos = SheetPos::from((0, 0, gc.sheet_ids()[0]));
+        let query = |kind| {
+            gc.bind_connection_parameters("select {{A1}}, {{B1}}", sheet_pos, kind)
+                .unwrap()
+                .query
+        };
+
+        assert_eq!(query(ConnectionKind::Postgres), "select $1, $2");
+        assert_eq!(query(ConnectionKind::Mysql), "select ?, ?");
+        assert_eq!(query(ConnectionKind::Sqlite), "select ?, ?");
+        assert_eq!(query(ConnectionKind::Duckdb), "select $1, $2");
+        assert_eq!(query(ConnectionKind::Mssql), "select @P1, @P2");
+    }
+
+    #[test]
+    #[parallel]
+    fn bad_cell_reference() {
+        let gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        let sheet_pos = SheetPos::from((0, 0, sheet_id));
+
+        for code in ["select {{'Missing'!A1}}", "select {{not a cell}}"] {
+            let error = gc
+                .bind_connection_parameters(code, sheet_pos, ConnectionKind::Postgres)
+                .unwrap_err();
+            assert_eq!(error.msg, RunErrorMsg::BadCellReference);
+        }
+    }
+
+    #[test]
+    #[parallel]
+    fn too_many_parameters() {
+        let gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        let sheet_pos = SheetPos::from((5, 5, sheet_id));
+        let too_many = RunErrorMsg::TooManyParameters {
+            max: MAX_CONNECTION_PARAMETERS,
+        };
+
+        let bound = gc
+            .bind_connection_parameters("select {{A1:A65535}}", sheet_pos, ConnectionKind::Mysql)
+            .unwrap();
+        assert_eq!(bound.parameters.len(), MAX_CONNECTION_PARAMETERS);
+
+        let error = gc
+            .bind_connection_parameters("select {{A1:A65536}}", sheet_pos, ConnectionKind::Mysql)
+            .unwrap_err();
+        assert_eq!(error.msg, too_many);
+
+        // the limit is for the whole query
+        let code = "select {{A1:A40000}}, {{B1:B40000}}";
+        let error = gc
+            .bind_connection_parameters(code, sheet_pos, ConnectionKind::Postgres)
+            .unwrap_err();
+        assert_eq!(error.msg, too_many);
+        assert_eq!(error.span, Some(Span { start: 22, end: 35 }));
+    }
+
+    #[test]
+    #[parallel]
+    fn binds_instants_as_timestamps() {
+        let parameter = SqlParameter::from(CellValue::Instant(Instant::new(1_700_000_000.5)));
+        let timestamp = NaiveDate::from_ymd_opt(2023, 11, 14)
+            .unwrap()
+            .and_hms_milli_opt(22, 13, 20, 500)
+            .unwrap();
+        assert_eq!(parameter, SqlParameter::Timestamp(timestamp));
+        assert_eq!(
+            serde_json::to_string(&parameter).unwrap(),
+            r#"{"type":"Timestamp","value":"2023-11-14T22:13:20.500"}"#
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn run_connection_sends_parameters_and_reruns_on_change() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_cell_value(SheetPos::from((0, 0, sheet_id)), "7".into(), None);
+
+        let connection_id = Uuid::new_v4().to_string();
+        gc.set_code_cell(
+            SheetPos::from((1, 0, sheet_id)),
+            CodeCellLanguage::Connection {
+                kind: ConnectionKind::Postgres,
+                id: connection_id.clone(),
+            },
+            "select {{A0}}".into(),
+            None,
+        );
+        let transaction_id = gc.last_transaction().unwrap().id;
+        expect_js_call(
+            "jsConnection",
+            format!(
+                "{},1,0,{},select $1,{},Postgres,{}",
+                transaction_id, sheet_id, r#"[{"type":"Integer","value":7}]"#, connection_id
+            ),
+            true,
+        );
+        gc.connection_complete(transaction_id.to_string(), vec![], None, None, None)
+            .unwrap();
+
+        let sheet = gc.sheet(sheet_id);
+        assert_eq!(
+            sheet.code_run(Pos { x: 1, y: 0 }).unwrap().cells_accessed,
+            HashSet::from([SheetRect::single_pos(Pos { x: 0, y: 0 }, sheet_id)])
+        );
+
+        // changing the referenced cell reruns the query
+        gc.set_cell_value(SheetPos::from((0, 0, sheet_id)), "8".into(), None);
+        let transaction_id = gc.last_transaction().unwrap().id;
+        expect_js_call(
+            "jsConnection",
+            format!(
+                "{},1,0,{},select $1,{},Postgres,{}",
+                transaction_id, sheet_id, r#"[{"type":"Integer","value":8}]"#, connection_id
+            ),
+            true,
+        );
+    }
+
+    #[test]
+    #[parallel]
+    fn run_connection_bad_reference_is_an_error() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_code_cell(
+            SheetPos::from((0, 0, sheet_id)),
+            CodeCellLanguage::Connection {
+                kind: ConnectionKind::Postgres,
+                id: Uuid::new_v4().to_string(),
+            },
+            "select {{'Missing'!A1}}".into(),
+            None,
+        );
+
+        let code_run = gc.sheet(sheet_id).code_run(Pos { x: 0, y: 0 }).unwrap();
+        assert_eq!(
+            code_run.std_err,
+            Some(RunErrorMsg::BadCellReference.to_string())
+        );
+    }
 }
@@ -15,6 +15,7 @@ impl GridController {
         code: String,
     ) {
         let mut ctx = Ctx::new(self.grid(), sheet_pos);
+        ctx.seed_rng(transaction.volatile_seed(sheet_pos));
         transaction.current_sheet_pos = Some(sheet_pos);
         match parse_formula(&code, sheet_pos.into()) {
             Ok(parsed) => {
@@ -25,6 +26,7 @@ impl GridController {
                     std_err: None,
                     formatted_code_string: None,
                     spill_error: false,
+                    volatile: ctx.volatile,
                     last_modified: Utc::now(),
                     cells_accessed: transaction.cells_accessed.clone(),
                     result: CodeRunResult::Ok(output.inner),
@@ -169,6 +171,77 @@ mod test {
         );
     }
 
+    #[test]
+    #[parallel]
+    fn test_volatile_formula_reruns() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+
+        gc.set_code_cell(
+            SheetPos {
+                x: 0,
+                y: 0,
+                sheet_id,
+            },
+            CodeCellLanguage::Formula,
+            "RAND()".into(),
+            None,
+        );
+        gc.set_code_cell(
+            SheetPos {
+                x: 0,
+                y: 1,
+                sheet_id,
+            },
+            CodeCellLanguage::Formula,
+            "A0 * 1".into(),
+            None,
+        );
+
+        let sheet = gc.try_sheet(sheet_id).unwrap();
+        assert!(sheet.code_run(Pos { x: 0, y: 0 }).unwrap().volatile);
+        assert!(!sheet.code_run(Pos { x: 0, y: 1 }).unwrap().volatile);
+        let first = sheet.display_value(Pos { x: 0, y: 0 });
+
+        // an unrelated edit reruns the volatile cell and its dependents
+        gc.set_cell_value(
+            SheetPos {
+                x: 5,
+                y: 5,
+                sheet_id,
+            },
+            "1".into(),
+            None,
+        );
+        let sheet = gc.try_sheet(sheet_id).unwrap();
+        let second = sheet.display_value(Pos { x: 0, y: 0 });
+        assert_ne!(first, second);
+        assert_eq!(second, sheet.display_value(Pos { x: 0, y: 1 }));
+
+        // the recomputed values are shared with other players
+        let forward = gc.last_transaction().unwrap();
+        assert!(forward.operations.iter().any(|op| matches!(
+            op,
+            Operation::SetCodeRun { sheet_pos, .. } if sheet_pos.x == 0 && sheet_pos.y == 0
+        )));
+
+        // but undoing the edit only reverts the edit
+        let undo = gc.undo_stack.last().unwrap();
+        assert!(!undo
+            .operations
+            .iter()
+            .any(|op| matches!(op, Operation::SetCodeRun { .. })));
+
+        // recalculating reruns the volatile cell without an undo step
+        let undo_count = gc.undo_stack.len();
+        gc.recalculate(None);
+        let sheet = gc.try_sheet(sheet_id).unwrap();
+        let third = sheet.display_value(Pos { x: 0, y: 0 });
+        assert_ne!(second, third);
+        assert_eq!(third, sheet.display_value(Pos { x: 0, y: 1 }));
+        assert_eq!(gc.undo_stack.len(), undo_count);
+    }
+
     #[test]
     #[parallel]
     fn test_deleting_to_trigger_compute() {
@@ -256,6 +329,7 @@ mod test {
                 output_type: None,
                 cells_accessed: HashSet::new(),
                 spill_error: false,
+                volatile: false,
             },
         );
     }
@@ -320,6 +394,7 @@ mod test {
                 output_type: None,
                 cells_accessed: HashSet::new(),
                 spill_error: false,
+                volatile: false,
                 last_modified: result.last_modified,
             }
         );
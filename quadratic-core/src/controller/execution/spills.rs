@@ -434,6 +434,7 @@ mod tests {
             line_number: None,
             output_type: None,
             spill_error: false,
+            volatile: false,
             last_modified: Utc::now(),
             cells_accessed: HashSet::new(),
             formatted_code_string: None,
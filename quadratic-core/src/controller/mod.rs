@@ -1,5 +1,5 @@
 use self::{active_transactions::ActiveTransactions, transaction::Transaction};
-use crate::grid::Grid;
+use crate::grid::{sheet::protections::EditUser, Grid};
 use wasm_bindgen::prelude::*;
 pub mod active_transactions;
 pub mod dependencies;
@@ -25,6 +25,9 @@ pub struct GridController {
 
     // holds information about transactions in progress
     transactions: ActiveTransactions,
+
+    // the user making local edits, used to check protections
+    edit_user: EditUser,
 }
 
 impl GridController {
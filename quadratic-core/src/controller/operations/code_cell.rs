@@ -128,6 +128,33 @@ impl GridController {
             .collect()
     }
 
+    /// Recomputes all code cells whose last run called a volatile function
+    /// (eg, `RAND()`). Their dependents are added as each cell finishes
+    /// computing.
+    pub fn recalculate_operations(&self) -> Vec<Operation> {
+        let mut code_cell_positions = self
+            .grid()
+            .sheets()
+            .iter()
+            .flat_map(|sheet| {
+                sheet
+                    .code_runs
+                    .iter()
+                    .filter(|(_, code_run)| code_run.volatile)
+                    .map(|(pos, code_run)| (pos.to_sheet_pos(sheet.id), code_run))
+            })
+            .collect::<Vec<_>>();
+
+        self.order_code_cells(&mut code_cell_positions);
+
+        code_cell_positions
+            .iter()
+            .map(|(sheet_pos, _)| Operation::ComputeCode {
+                sheet_pos: *sheet_pos,
+            })
+            .collect()
+    }
+
     /// Reruns a code cell
     pub fn rerun_code_cell_operations(&self, sheet_pos: SheetPos) -> Vec<Operation> {
         vec![Operation::ComputeCode { sheet_pos }]
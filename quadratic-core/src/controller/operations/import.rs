@@ -11,7 +11,7 @@ use crate::cell_values::CellValues;
 use crate::controller::GridController;
 use crate::grid::file::sheet_schema::export_sheet;
 use crate::grid::{CodeCellLanguage, Sheet, SheetId};
-use crate::{CellValue, CodeCellValue, Pos, SheetPos};
+use crate::{CellValue, CodeCellValue, Pos, SheetPos, SheetRect};
 
 const IMPORT_LINES_PER_OPERATION: u32 = 10000;
 
@@ -296,7 +296,11 @@ impl GridController {
         let metadata = builder.metadata();
         let total_size = metadata.file_metadata().num_rows() as u32;
         let fields = metadata.file_metadata().schema().get_fields();
-        let headers: Vec<CellValue> = fields.iter().map(|f| f.name().into()).collect();
+        let header_names: Vec<String> = fields.iter().map(|f| f.name().to_string()).collect();
+        let headers: Vec<CellValue> = header_names
+            .iter()
+            .map(|name| name.as_str().into())
+            .collect();
         let mut width = headers.len() as u32;
 
         ops.push(Operation::SetCellValues {
@@ -347,6 +351,18 @@ impl GridController {
             }
         }
 
+        // the imported data is a data table that formulas can reference
+        if !header_names.is_empty() {
+            let sheet_rect = SheetRect::from_numbers(
+                insert_at.x,
+                insert_at.y,
+                width as i64,
+                current_size as i64 + 1,
+                sheet_id,
+            );
+            ops.push(self.set_data_table_operation(sheet_rect, header_names));
+        }
+
         Ok(ops)
     }
 }
@@ -380,8 +396,10 @@ fn read_utf16(bytes: &[u8]) -> Option<String> {
 mod test {
     use serial_test::parallel;
 
+    use calamine::CellErrorType;
+
     use super::{read_utf16, *};
-    use crate::CellValue;
+    use crate::{CellValue, RunErrorMsg};
 
     const INVALID_ENCODING_FILE: &[u8] =
         include_bytes!("../../../../quadratic-rust-shared/data/csv/encoding_issue.csv");
@@ -501,6 +519,53 @@ mod test {
         assert_eq!(sheet.cell_value((3, 1).into()), None);
     }
 
+    /// Compares formula results against the values Excel cached when the
+    /// workbook was saved.
+    #[test]
+    #[parallel]
+    fn import_excel_formula_results_match_excel() {
+        let file = include_bytes!("../../../test-files/excel_broadcasting.xlsx");
+        let mut gc = GridController::new_blank();
+        gc.import_excel(file.to_vec(), "excel_broadcasting.xlsx", None)
+            .unwrap();
+        let sheet = gc.sheet(gc.grid.sheets()[0].id);
+
+        let mut workbook: Xlsx<_> = ExcelReader::new(Cursor::new(file)).unwrap();
+        let formulas = workbook.worksheet_formula("Sheet1").unwrap();
+        let cached_values = workbook.worksheet_range("Sheet1").unwrap();
+        let mut formula_count = 0;
+        for (row, col, formula) in formulas.used_cells() {
+            if formula.is_empty() {
+                continue;
+            }
+            let (row, col) = (
+                formulas.start().unwrap().0 + row as u32,
+                formulas.start().unwrap().1 + col as u32,
+            );
+            let pos = Pos {
+                x: col as i64,
+                y: row as i64 + 1,
+            };
+            let value = sheet.display_value(pos).unwrap();
+            match cached_values.get_value((row, col)).unwrap() {
+                ExcelData::Float(f) => {
+                    assert_eq!(value.to_string(), f.to_string(), "{formula}");
+                }
+                ExcelData::String(s) => assert_eq!(value.to_string(), *s, "{formula}"),
+                ExcelData::Bool(b) => assert_eq!(value, CellValue::Logical(*b), "{formula}"),
+                ExcelData::Error(CellErrorType::NA) => match value {
+                    CellValue::Error(e) => {
+                        assert_eq!(e.msg, RunErrorMsg::NotAvailable, "{formula}");
+                    }
+                    _ => panic!("expected #N/A from {formula}, got {value}"),
+                },
+                other => panic!("unsupported cached value {other:?} for {formula}"),
+            }
+            formula_count += 1;
+        }
+        assert_eq!(formula_count, 8);
+    }
+
     #[test]
     #[parallel]
     fn import_excel_invalid() {
@@ -12,3 +12,4 @@ pub mod formatting;
 pub mod import;
 pub mod operation;
 pub mod sheets;
+pub mod targets;
@@ -5,9 +5,17 @@ use uuid::Uuid;
 use crate::{
     cell_values::CellValues,
     grid::{
-        file::sheet_schema::SheetSchema, formats::Formats, formatting::CellFmtArray,
-        js_types::JsRowHeight, sheet::validations::validation::Validation, CodeRun, Sheet,
-        SheetBorders, SheetId,
+        file::sheet_schema::SheetSchema,
+        formats::Formats,
+        formatting::CellFmtArray,
+        js_types::JsRowHeight,
+        sheet::{
+            comments::{Comment, CommentThread},
+            data_tables::DataTable,
+            protections::Protection,
+            validations::validation::Validation,
+        },
+        CodeRun, Sheet, SheetBorders, SheetId,
     },
     selection::Selection,
     SheetPos, SheetRect,
@@ -135,6 +143,47 @@ pub enum Operation {
         sheet_pos: SheetPos,
         validation_id: Option<Uuid>,
     },
+
+    /// Adds, replaces, or (if `table` is None) removes the named data table.
+    SetDataTable {
+        sheet_id: SheetId,
+        name: String,
+        table: Option<DataTable>,
+    },
+
+    /// Adds, replaces, or (if `thread` is None) removes a comment thread.
+    SetCommentThread {
+        sheet_id: SheetId,
+        thread_id: Uuid,
+        thread: Option<CommentThread>,
+    },
+    /// Adds, replaces, or (if `comment` is None) removes a comment in a
+    /// thread.
+    SetComment {
+        sheet_id: SheetId,
+        thread_id: Uuid,
+        comment_id: Uuid,
+        comment: Option<Comment>,
+    },
+    SetCommentThreadResolved {
+        sheet_id: SheetId,
+        thread_id: Uuid,
+        resolved: bool,
+    },
+    /// Moves a comment thread to another cell, which may be on another sheet.
+    MoveCommentThread {
+        sheet_id: SheetId,
+        thread_id: Uuid,
+        dest: SheetPos,
+    },
+
+    /// Adds, replaces, or (if `protection` is None) removes a protected range
+    /// or sheet.
+    SetProtection {
+        sheet_id: SheetId,
+        protection_id: Uuid,
+        protection: Option<Protection>,
+    },
 }
 
 impl fmt::Display for Operation {
@@ -258,6 +307,73 @@ impl fmt::Display for Operation {
                     sheet_pos, validation_id
                 )
             }
+            Operation::SetDataTable {
+                sheet_id,
+                name,
+                table,
+            } => {
+                write!(
+                    fmt,
+                    "SetDataTable {{ sheet_id: {}, name: {}, table: {:?} }}",
+                    sheet_id, name, table
+                )
+            }
+            Operation::SetCommentThread {
+                sheet_id,
+                thread_id,
+                thread,
+            } => {
+                write!(
+                    fmt,
+                    "SetCommentThread {{ sheet_id: {}, thread_id: {}, thread: {:?} }}",
+                    sheet_id, thread_id, thread
+                )
+            }
+            Operation::SetComment {
+                sheet_id,
+                thread_id,
+                comment_id,
+                comment,
+            } => {
+                write!(
+                    fmt,
+                    "SetComment {{ sheet_id: {}, thread_id: {}, comment_id: {}, comment: {:?} }}",
+                    sheet_id, thread_id, comment_id, comment
+                )
+            }
+            Operation::SetCommentThreadResolved {
+                sheet_id,
+                thread_id,
+                resolved,
+            } => {
+                write!(
+                    fmt,
+                    "SetCommentThreadResolved {{ sheet_id: {}, thread_id: {}, resolved: {} }}",
+                    sheet_id, thread_id, resolved
+                )
+            }
+            Operation::MoveCommentThread {
+                sheet_id,
+                thread_id,
+                dest,
+            } => {
+                write!(
+                    fmt,
+                    "MoveCommentThread {{ sheet_id: {}, thread_id: {}, dest: {} }}",
+                    sheet_id, thread_id, dest
+                )
+            }
+            Operation::SetProtection {
+                sheet_id,
+                protection_id,
+                protection,
+            } => {
+                write!(
+                    fmt,
+                    "SetProtection {{ sheet_id: {}, protection_id: {}, protection: {:?} }}",
+                    sheet_id, protection_id, protection
+                )
+            }
         }
     }
 }
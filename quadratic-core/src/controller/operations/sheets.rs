@@ -3,7 +3,7 @@ use lexicon_fractional_index::key_between;
 
 use crate::{
     controller::GridController,
-    grid::{file::sheet_schema::export_sheet, Sheet, SheetId},
+    grid::{file::sheet_schema::export_sheet, Grid, Sheet, SheetId},
     util,
 };
 
@@ -112,11 +112,77 @@ impl GridController {
         ops.extend(code_run_ops);
         ops
     }
+
+    /// Returns the operations that change the sheets to match the sheets
+    /// from `grid`, as a single transaction so the restore is applied and
+    /// undone as a whole. Unchanged sheets are skipped, and sheets that differ
+    /// only in their name, color or order are updated in place. Used to
+    /// restore a file to an earlier version.
+    ///
+    /// Added and replaced sheets come before deleted sheets, so the file
+    /// always has a sheet while the operations are applied.
+    pub fn restore_grid_operations(&self, grid: Grid) -> Vec<Operation> {
+        let mut ops = vec![];
+
+        for sheet in grid.sheets() {
+            let Some(live) = self.try_sheet(sheet.id) else {
+                ops.push(Operation::AddSheetSchema {
+                    schema: export_sheet(sheet.clone()),
+                });
+                continue;
+            };
+
+            let mut normalized = sheet.clone();
+            normalized.name.clone_from(&live.name);
+            normalized.color.clone_from(&live.color);
+            normalized.order.clone_from(&live.order);
+
+            if normalized != *live {
+                ops.push(Operation::DeleteSheet { sheet_id: sheet.id });
+                ops.push(Operation::AddSheetSchema {
+                    schema: export_sheet(sheet.clone()),
+                });
+                continue;
+            }
+
+            if sheet.name != live.name {
+                ops.push(Operation::SetSheetName {
+                    sheet_id: sheet.id,
+                    name: sheet.name.clone(),
+                });
+            }
+            if sheet.color != live.color {
+                ops.push(Operation::SetSheetColor {
+                    sheet_id: sheet.id,
+                    color: sheet.color.clone(),
+                });
+            }
+            if sheet.order != live.order {
+                ops.push(Operation::ReorderSheet {
+                    target: sheet.id,
+                    order: sheet.order.clone(),
+                });
+            }
+        }
+
+        ops.extend(
+            self.sheet_ids()
+                .into_iter()
+                .filter(|sheet_id| grid.try_sheet(*sheet_id).is_none())
+                .map(|sheet_id| Operation::DeleteSheet { sheet_id }),
+        );
+
+        ops
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{
+        controller::active_transactions::transaction_name::TransactionName, CellValue, Pos,
+        SheetPos,
+    };
     use serial_test::parallel;
 
     #[test]
@@ -194,4 +260,60 @@ mod test {
         gc.add_sheet(None);
         assert_eq!(gc.sheet_names(), vec!["Sheet 1", "Sheet 2", "Sheet 3"]);
     }
+
+    #[test]
+    #[parallel]
+    fn restore_grid_operations() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 0), "old".into(), None);
+        gc.add_sheet(None);
+        let renamed_id = gc.sheet_ids()[1];
+        let version = gc.grid().clone();
+
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 0), "new".into(), None);
+        gc.set_sheet_name(renamed_id, "Renamed".into(), None);
+        gc.add_sheet(None);
+        let added_id = gc.sheet_ids()[2];
+
+        let ops = gc.restore_grid_operations(version);
+        assert_eq!(ops.len(), 4);
+        assert!(matches!(
+            ops[..2],
+            [
+                Operation::DeleteSheet { .. },
+                Operation::AddSheetSchema { .. }
+            ]
+        ));
+        assert_eq!(
+            ops[2..],
+            [
+                Operation::SetSheetName {
+                    sheet_id: renamed_id,
+                    name: "Sheet 2".into(),
+                },
+                Operation::DeleteSheet { sheet_id: added_id }
+            ]
+        );
+
+        gc.start_user_transaction(ops, None, TransactionName::Unknown);
+        assert_eq!(gc.sheet_ids(), vec![sheet_id, renamed_id]);
+        assert_eq!(gc.sheet_names(), vec!["Sheet 1", "Sheet 2"]);
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Text("old".into()))
+        );
+
+        // nothing changed
+        assert!(gc.restore_grid_operations(gc.grid().clone()).is_empty());
+
+        // the restore is undone as a whole
+        gc.undo(None);
+        assert_eq!(gc.sheet_ids(), vec![sheet_id, renamed_id, added_id]);
+        assert_eq!(gc.sheet(renamed_id).name, "Renamed");
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Text("new".into()))
+        );
+    }
 }
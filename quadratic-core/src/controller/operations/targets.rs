@@ -0,0 +1,301 @@
+//! The parts of the grid that an operation changes. Targets are used to check
+//! operations against protected ranges and sheets.
+
+use uuid::Uuid;
+
+use super::operation::Operation;
+use crate::{grid::SheetId, selection::Selection, Pos, SheetRect};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OperationTarget {
+    /// Cells within the rect. Whole rows, columns, and sheets are unbounded
+    /// rects.
+    Cells(SheetRect),
+
+    /// Properties of the sheet itself, such as its name or column sizes.
+    Sheet(SheetId),
+
+    /// A protected range or sheet.
+    Protection {
+        sheet_id: SheetId,
+        protection_id: Uuid,
+    },
+}
+
+/// Returns a rect that covers the cells in the given columns and rows. None
+/// leaves that dimension unbounded.
+fn unbounded_rect(
+    sheet_id: SheetId,
+    columns: Option<(i64, i64)>,
+    rows: Option<(i64, i64)>,
+) -> SheetRect {
+    let (min_x, max_x) = columns.unwrap_or((i64::MIN, i64::MAX));
+    let (min_y, max_y) = rows.unwrap_or((i64::MIN, i64::MAX));
+    SheetRect {
+        sheet_id,
+        min: Pos { x: min_x, y: min_y },
+        max: Pos { x: max_x, y: max_y },
+    }
+}
+
+fn selection_targets(selection: &Selection) -> Vec<OperationTarget> {
+    let sheet_id = selection.sheet_id;
+    if selection.all {
+        return vec![OperationTarget::Cells(unbounded_rect(sheet_id, None, None))];
+    }
+
+    let columns = selection
+        .columns
+        .iter()
+        .flatten()
+        .map(|&x| OperationTarget::Cells(unbounded_rect(sheet_id, Some((x, x)), None)));
+    let rows = selection
+        .rows
+        .iter()
+        .flatten()
+        .map(|&y| OperationTarget::Cells(unbounded_rect(sheet_id, None, Some((y, y)))));
+    let rects = selection
+        .rects
+        .iter()
+        .flatten()
+        .map(|rect| OperationTarget::Cells(rect.to_sheet_rect(sheet_id)));
+
+    columns.chain(rows).chain(rects).collect()
+}
+
+impl Operation {
+    /// Returns the parts of the grid that the operation changes.
+    ///
+    /// Validation warnings and cursors are recalculated or belong to a single
+    /// user, so they are not treated as edits. Comments may be added to any
+    /// cell. Code operations target the code cell and its new output; the
+    /// output that they replace is only known to the grid.
+    pub fn targets(&self) -> Vec<OperationTarget> {
+        match self {
+            Operation::SetCodeRun {
+                sheet_pos,
+                code_run,
+                ..
+            } => vec![OperationTarget::Cells(match code_run {
+                Some(code_run) => code_run.output_sheet_rect(*sheet_pos, false),
+                None => SheetRect::single_sheet_pos(*sheet_pos),
+            })],
+            Operation::ComputeCode { sheet_pos } => {
+                vec![OperationTarget::Cells(SheetRect::single_sheet_pos(
+                    *sheet_pos,
+                ))]
+            }
+
+            Operation::SetCellValues { sheet_pos, values } => {
+                vec![OperationTarget::Cells(SheetRect::from_numbers(
+                    sheet_pos.x,
+                    sheet_pos.y,
+                    values.w.max(1) as i64,
+                    values.h.max(1) as i64,
+                    sheet_pos.sheet_id,
+                ))]
+            }
+            Operation::SetCellFormats { sheet_rect, .. }
+            | Operation::SetBorders { sheet_rect, .. } => {
+                vec![OperationTarget::Cells(*sheet_rect)]
+            }
+            Operation::SetCellFormatsSelection { selection, .. } => selection_targets(selection),
+            Operation::MoveCells { source, dest } => {
+                let dest = SheetRect::from_numbers(
+                    dest.x,
+                    dest.y,
+                    source.width() as i64,
+                    source.height() as i64,
+                    dest.sheet_id,
+                );
+                vec![
+                    OperationTarget::Cells(*source),
+                    OperationTarget::Cells(dest),
+                ]
+            }
+
+            Operation::DeleteSheet { sheet_id } => {
+                vec![OperationTarget::Cells(unbounded_rect(
+                    *sheet_id, None, None,
+                ))]
+            }
+
+            // a sheet that's added with an existing id replaces it
+            Operation::AddSheetSchema { schema } => schema
+                .sheet_id()
+                .map(|sheet_id| OperationTarget::Cells(unbounded_rect(sheet_id, None, None)))
+                .into_iter()
+                .collect(),
+            Operation::SetSheetName { sheet_id, .. }
+            | Operation::SetSheetColor { sheet_id, .. }
+            | Operation::ReorderSheet {
+                target: sheet_id, ..
+            }
+            | Operation::ResizeColumn { sheet_id, .. }
+            | Operation::ResizeRow { sheet_id, .. }
+            | Operation::ResizeRows { sheet_id, .. }
+            | Operation::RemoveValidation { sheet_id, .. }
+            | Operation::DuplicateSheet { sheet_id, .. } => {
+                vec![OperationTarget::Sheet(*sheet_id)]
+            }
+
+            Operation::SetValidation { validation } => selection_targets(&validation.selection),
+            Operation::SetDataTable {
+                sheet_id, table, ..
+            } => match table {
+                Some(table) => vec![OperationTarget::Cells(table.rect.to_sheet_rect(*sheet_id))],
+                None => vec![OperationTarget::Sheet(*sheet_id)],
+            },
+
+            Operation::SetProtection {
+                sheet_id,
+                protection_id,
+                ..
+            } => vec![OperationTarget::Protection {
+                sheet_id: *sheet_id,
+                protection_id: *protection_id,
+            }],
+
+            Operation::SetValidationWarning { .. }
+            | Operation::AddSheet { .. }
+            | Operation::SetCursor { .. }
+            | Operation::SetCursorSelection { .. }
+            | Operation::SetCommentThread { .. }
+            | Operation::SetComment { .. }
+            | Operation::SetCommentThreadResolved { .. }
+            | Operation::MoveCommentThread { .. } => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use chrono::Utc;
+    use serial_test::parallel;
+
+    use super::*;
+    use crate::grid::file::sheet_schema::export_sheet;
+    use crate::grid::{CodeRun, CodeRunResult, Sheet};
+    use crate::{cell_values::CellValues, Array, ArraySize, Rect, SheetPos, Value};
+
+    #[test]
+    #[parallel]
+    fn cell_targets() {
+        let sheet_id = SheetId::new();
+        let op = Operation::SetCellValues {
+            sheet_pos: SheetPos::new(sheet_id, 1, 2),
+            values: CellValues::new(2, 3),
+        };
+        assert_eq!(
+            op.targets(),
+            vec![OperationTarget::Cells(SheetRect::from_numbers(
+                1, 2, 2, 3, sheet_id
+            ))]
+        );
+
+        let op = Operation::MoveCells {
+            source: SheetRect::from_numbers(0, 0, 2, 2, sheet_id),
+            dest: SheetPos::new(sheet_id, 10, 10),
+        };
+        assert_eq!(
+            op.targets(),
+            vec![
+                OperationTarget::Cells(SheetRect::from_numbers(0, 0, 2, 2, sheet_id)),
+                OperationTarget::Cells(SheetRect::from_numbers(10, 10, 2, 2, sheet_id)),
+            ]
+        );
+    }
+
+    #[test]
+    #[parallel]
+    fn selection_targets_are_unbounded() {
+        let sheet_id = SheetId::new();
+        let selection = Selection {
+            sheet_id,
+            columns: Some(vec![3]),
+            rects: Some(vec![Rect::new(1, 1, 2, 2)]),
+            ..Default::default()
+        };
+        let targets = selection_targets(&selection);
+        assert_eq!(targets.len(), 2);
+
+        let OperationTarget::Cells(column) = targets[0] else {
+            panic!("expected a cell target");
+        };
+        assert!(column.intersects(SheetRect::single_pos(Pos { x: 3, y: 1_000_000 }, sheet_id)));
+        assert!(!column.intersects(SheetRect::single_pos(Pos { x: 4, y: 1 }, sheet_id)));
+        assert_eq!(
+            targets[1],
+            OperationTarget::Cells(Rect::new(1, 1, 2, 2).to_sheet_rect(sheet_id))
+        );
+    }
+
+    #[test]
+    #[parallel]
+    fn sheet_targets() {
+        let sheet_id = SheetId::new();
+        let op = Operation::SetSheetName {
+            sheet_id,
+            name: "name".to_string(),
+        };
+        assert_eq!(op.targets(), vec![OperationTarget::Sheet(sheet_id)]);
+
+        let op = Operation::DuplicateSheet {
+            sheet_id,
+            new_sheet_id: SheetId::new(),
+        };
+        assert_eq!(op.targets(), vec![OperationTarget::Sheet(sheet_id)]);
+
+        let sheet = Sheet::new(sheet_id, "Sheet 1".into(), "a0".into());
+        let op = Operation::AddSheetSchema {
+            schema: export_sheet(sheet),
+        };
+        assert_eq!(
+            op.targets(),
+            vec![OperationTarget::Cells(unbounded_rect(sheet_id, None, None))]
+        );
+    }
+
+    #[test]
+    #[parallel]
+    fn code_targets() {
+        let sheet_id = SheetId::new();
+        let sheet_pos = SheetPos::new(sheet_id, 1, 1);
+        let op = Operation::ComputeCode { sheet_pos };
+        assert_eq!(
+            op.targets(),
+            vec![OperationTarget::Cells(SheetRect::single_sheet_pos(
+                sheet_pos
+            ))]
+        );
+
+        let code_run = CodeRun {
+            formatted_code_string: None,
+            std_out: None,
+            std_err: None,
+            cells_accessed: HashSet::new(),
+            result: CodeRunResult::Ok(Value::Array(Array::new_empty(
+                ArraySize::new(2, 3).unwrap(),
+            ))),
+            return_type: None,
+            spill_error: false,
+            line_number: None,
+            output_type: None,
+            last_modified: Utc::now(),
+            volatile: false,
+        };
+        let op = Operation::SetCodeRun {
+            sheet_pos,
+            code_run: Some(code_run),
+            index: 0,
+        };
+        assert_eq!(
+            op.targets(),
+            vec![OperationTarget::Cells(SheetRect::from_numbers(
+                1, 1, 2, 3, sheet_id
+            ))]
+        );
+    }
+}
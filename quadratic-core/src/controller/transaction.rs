@@ -8,8 +8,8 @@ use super::execution::TransactionType;
 use super::operations::operation::Operation;
 use super::GridController;
 use crate::compression::{
-    add_header, decompress_and_deserialize, deserialize, remove_header, serialize,
-    serialize_and_compress, CompressionFormat, SerializationFormat,
+    add_header, decompress_and_deserialize, decompress_limited, deserialize, remove_header,
+    serialize, serialize_and_compress, CompressionFormat, SerializationFormat,
 };
 
 pub static SERIALIZATION_FORMAT: SerializationFormat = SerializationFormat::Json;
@@ -74,6 +74,19 @@ impl Transaction {
 
         decompress_and_deserialize::<T>(&SERIALIZATION_FORMAT, &COMPRESSION_FORMAT, data)
     }
+
+    /// Decompress and deserialize the transaction's operations, returning an
+    /// error if they are larger than `max_bytes` once decompressed.
+    pub fn decompress_and_deserialize_limited<T: DeserializeOwned>(
+        operations: &[u8],
+        max_bytes: usize,
+    ) -> Result<T> {
+        let (header, data) = remove_header(operations)?;
+        let _version = deserialize::<TransactionVersion>(&HEADER_SERIALIZATION_FORMAT, header)?;
+        let decompressed = decompress_limited(&COMPRESSION_FORMAT, data, max_bytes)?;
+
+        deserialize::<T>(&SERIALIZATION_FORMAT, &decompressed)
+    }
 }
 
 // Transaction received from Server
@@ -34,6 +34,14 @@ impl GridController {
         let ops = self.rerun_code_cell_operations(sheet_pos);
         self.start_user_transaction(ops, cursor, TransactionName::RunCode);
     }
+
+    /// Recomputes volatile code cells (eg, `RAND()`) and their dependents
+    /// without changing anything else. Every user transaction ends by
+    /// recomputing them, so this is an empty transaction. The recalculation
+    /// isn't added to the undo stack.
+    pub fn recalculate(&mut self, cursor: Option<String>) {
+        self.start_user_transaction(vec![], cursor, TransactionName::Recalculate);
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,154 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    controller::{
+        active_transactions::transaction_name::TransactionName, operations::operation::Operation,
+        GridController,
+    },
+    grid::{
+        sheet::comments::{Comment, CommentThread},
+        SheetId,
+    },
+    Pos, SheetPos,
+};
+
+impl GridController {
+    /// Gets the comment threads for a sheet.
+    pub fn comment_threads(&self, sheet_id: SheetId) -> Option<&Vec<CommentThread>> {
+        self.try_sheet(sheet_id)
+            .map(|sheet| &sheet.comments.threads)
+    }
+
+    /// Starts a new comment thread on a cell. Returns the thread's id.
+    pub fn add_comment_thread(
+        &mut self,
+        sheet_pos: SheetPos,
+        author: String,
+        body: String,
+        mentions: Vec<String>,
+        cursor: Option<String>,
+    ) -> Uuid {
+        let comment = Comment::new(author, body, mentions);
+        let thread = CommentThread::new(Pos::from(sheet_pos), comment);
+        let thread_id = thread.id;
+        let ops = vec![Operation::SetCommentThread {
+            sheet_id: sheet_pos.sheet_id,
+            thread_id,
+            thread: Some(thread),
+        }];
+        self.start_user_transaction(ops, cursor, TransactionName::Comment);
+        thread_id
+    }
+
+    /// Adds a reply to a comment thread.
+    pub fn reply_to_comment_thread(
+        &mut self,
+        sheet_id: SheetId,
+        thread_id: Uuid,
+        author: String,
+        body: String,
+        mentions: Vec<String>,
+        cursor: Option<String>,
+    ) {
+        let comment = Comment::new(author, body, mentions);
+        let ops = vec![Operation::SetComment {
+            sheet_id,
+            thread_id,
+            comment_id: comment.id,
+            comment: Some(comment),
+        }];
+        self.start_user_transaction(ops, cursor, TransactionName::Comment);
+    }
+
+    /// Changes the body of a comment.
+    pub fn edit_comment(
+        &mut self,
+        sheet_id: SheetId,
+        thread_id: Uuid,
+        comment_id: Uuid,
+        body: String,
+        mentions: Vec<String>,
+        cursor: Option<String>,
+    ) {
+        let Some(comment) = self
+            .try_sheet(sheet_id)
+            .and_then(|sheet| sheet.comments.get(thread_id))
+            .and_then(|thread| thread.comment(comment_id))
+        else {
+            return;
+        };
+        let comment = Comment {
+            body,
+            mentions,
+            modified: Some(Utc::now()),
+            ..comment.clone()
+        };
+        let ops = vec![Operation::SetComment {
+            sheet_id,
+            thread_id,
+            comment_id,
+            comment: Some(comment),
+        }];
+        self.start_user_transaction(ops, cursor, TransactionName::Comment);
+    }
+
+    /// Deletes a comment. Deleting the comment that started a thread deletes
+    /// the whole thread.
+    pub fn delete_comment(
+        &mut self,
+        sheet_id: SheetId,
+        thread_id: Uuid,
+        comment_id: Uuid,
+        cursor: Option<String>,
+    ) {
+        let Some(thread) = self
+            .try_sheet(sheet_id)
+            .and_then(|sheet| sheet.comments.get(thread_id))
+        else {
+            return;
+        };
+        if thread.comments.first().is_some_and(|c| c.id == comment_id) {
+            self.delete_comment_thread(sheet_id, thread_id, cursor);
+            return;
+        }
+        let ops = vec![Operation::SetComment {
+            sheet_id,
+            thread_id,
+            comment_id,
+            comment: None,
+        }];
+        self.start_user_transaction(ops, cursor, TransactionName::Comment);
+    }
+
+    /// Resolves or reopens a comment thread.
+    pub fn resolve_comment_thread(
+        &mut self,
+        sheet_id: SheetId,
+        thread_id: Uuid,
+        resolved: bool,
+        cursor: Option<String>,
+    ) {
+        let ops = vec![Operation::SetCommentThreadResolved {
+            sheet_id,
+            thread_id,
+            resolved,
+        }];
+        self.start_user_transaction(ops, cursor, TransactionName::Comment);
+    }
+
+    /// Deletes a comment thread and all of its replies.
+    pub fn delete_comment_thread(
+        &mut self,
+        sheet_id: SheetId,
+        thread_id: Uuid,
+        cursor: Option<String>,
+    ) {
+        let ops = vec![Operation::SetCommentThread {
+            sheet_id,
+            thread_id,
+            thread: None,
+        }];
+        self.start_user_transaction(ops, cursor, TransactionName::Comment);
+    }
+}
@@ -422,6 +422,16 @@ mod tests {
                 "",                                     // xml
             ],
         );
+
+        // the imported data is a data table
+        let table = grid_controller
+            .sheet(sheet_id)
+            .data_tables
+            .get("Table1")
+            .unwrap();
+        assert_eq!(table.rect.min, pos);
+        assert_eq!(table.headers.len(), 23);
+        assert_eq!(table.headers[0], "id");
     }
 
     // The following tests run too slowly to be included in the test suite:
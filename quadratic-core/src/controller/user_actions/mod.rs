@@ -5,9 +5,11 @@ pub mod borders;
 pub mod cells;
 pub mod clipboard;
 pub mod code;
+pub mod comments;
 pub mod formats;
 pub mod formatting;
 pub mod import;
+pub mod protections;
 pub mod sheets;
 pub mod undo;
 pub mod validations;
@@ -0,0 +1,75 @@
+use uuid::Uuid;
+
+use crate::{
+    controller::{
+        active_transactions::transaction_name::TransactionName, operations::operation::Operation,
+        GridController,
+    },
+    grid::{sheet::protections::Protection, SheetId},
+    Rect,
+};
+
+impl GridController {
+    /// Gets the protections for a sheet.
+    pub fn protections(&self, sheet_id: SheetId) -> Option<&Vec<Protection>> {
+        self.try_sheet(sheet_id)
+            .map(|sheet| &sheet.protections.protections)
+    }
+
+    /// Protects a range of cells (or the whole sheet if `rect` is None) so
+    /// that only the listed users and roles may edit it. The user adding the
+    /// protection is always allowed. Returns the protection's id.
+    pub fn add_protection(
+        &mut self,
+        sheet_id: SheetId,
+        rect: Option<Rect>,
+        mut users: Vec<String>,
+        roles: Vec<String>,
+        cursor: Option<String>,
+    ) -> Uuid {
+        let user_id = &self.edit_user.user_id;
+        if !user_id.is_empty() && !users.contains(user_id) {
+            users.push(user_id.clone());
+        }
+        let protection = Protection::new(rect, users, roles);
+        let protection_id = protection.id;
+        let ops = vec![Operation::SetProtection {
+            sheet_id,
+            protection_id,
+            protection: Some(protection),
+        }];
+        self.start_user_transaction(ops, cursor, TransactionName::Protection);
+        protection_id
+    }
+
+    /// Changes the protected cells or the users and roles allowed to edit
+    /// them.
+    pub fn update_protection(
+        &mut self,
+        sheet_id: SheetId,
+        protection: Protection,
+        cursor: Option<String>,
+    ) {
+        let ops = vec![Operation::SetProtection {
+            sheet_id,
+            protection_id: protection.id,
+            protection: Some(protection),
+        }];
+        self.start_user_transaction(ops, cursor, TransactionName::Protection);
+    }
+
+    /// Removes a protection.
+    pub fn remove_protection(
+        &mut self,
+        sheet_id: SheetId,
+        protection_id: Uuid,
+        cursor: Option<String>,
+    ) {
+        let ops = vec![Operation::SetProtection {
+            sheet_id,
+            protection_id,
+            protection: None,
+        }];
+        self.start_user_transaction(ops, cursor, TransactionName::Protection);
+    }
+}
@@ -10,6 +10,12 @@ impl GridController {
         !self.redo_stack.is_empty()
     }
     pub fn undo(&mut self, cursor: Option<String>) {
+        // leave the transaction on the stack if it changes protected cells
+        if let Some(transaction) = self.undo_stack.last() {
+            if self.refuse_protected_operations(&transaction.operations) {
+                return;
+            }
+        }
         if let Some(mut transaction) = self.undo_stack.pop() {
             // we need to assign the transaction a new id to avoid conflicts with the original transaction.
             transaction.id = Uuid::new_v4();
@@ -17,6 +23,11 @@ impl GridController {
         }
     }
     pub fn redo(&mut self, cursor: Option<String>) {
+        if let Some(transaction) = self.redo_stack.last() {
+            if self.refuse_protected_operations(&transaction.operations) {
+                return;
+            }
+        }
         if let Some(mut transaction) = self.redo_stack.pop() {
             // we need to assign the transaction a new id to avoid conflicts with the original transaction.
             transaction.id = Uuid::new_v4();
@@ -50,6 +50,12 @@ pub enum RunErrorMsg {
     Unimplemented(Cow<'static, str>),
     UnknownError,
     InternalError(Cow<'static, str>),
+    /// The code was stopped before it completed, eg, a cancelled query
+    Cancelled,
+    /// A connection's query references more cells than can be bound
+    TooManyParameters {
+        max: usize,
+    },
 
     // Compile errors
     Unterminated(Cow<'static, str>),
@@ -101,6 +107,8 @@ pub enum RunErrorMsg {
     Infinity,
     IndexOutOfBounds,
     NoMatch,
+    /// Excel's `#N/A`, such as when arrays of different sizes are combined.
+    NotAvailable,
     InvalidArgument,
 }
 
@@ -122,6 +130,15 @@ impl fmt::Display for RunErrorMsg {
             Self::InternalError(s) => {
                 write!(f, "Internal error: {s}\nThis is a bug in Quadratic, not your formula. Please report this to us!")
             }
+            Self::Cancelled => {
+                write!(f, "Cancelled")
+            }
+            Self::TooManyParameters { max } => {
+                write!(
+                    f,
+                    "Too many cell references: a query can use at most {max} cells"
+                )
+            }
 
             Self::Unterminated(s) => {
                 write!(f, "This {s} never ends")
@@ -239,6 +256,9 @@ impl fmt::Display for RunErrorMsg {
             Self::NoMatch => {
                 write!(f, "No match found")
             }
+            Self::NotAvailable => {
+                write!(f, "Value not available")
+            }
             Self::InvalidArgument => {
                 write!(f, "Invalid argument")
             }
@@ -4,8 +4,8 @@ use smallvec::smallvec;
 
 use super::*;
 use crate::{
-    Array, ArraySize, CellValue, CodeResult, CodeResultExt, CoerceInto, RunErrorMsg, SheetRect,
-    Span, Spanned, Value,
+    Array, ArraySize, CellValue, CodeResult, CodeResultExt, CoerceInto, RunErrorMsg, SheetPos,
+    SheetRect, Span, Spanned, Value,
 };
 
 /// Abstract syntax tree of a formula expression.
@@ -26,6 +26,7 @@ pub enum AstNodeContents {
     Paren(Vec<AstNode>),
     Array(Vec<Vec<AstNode>>),
     CellRef(CellRef),
+    TableRef(TableRef),
     String(String),
     Number(f64),
     Bool(bool),
@@ -46,6 +47,7 @@ impl AstNodeContents {
             },
             AstNodeContents::Array(_) => "array literal",
             AstNodeContents::CellRef(_) => "cell reference",
+            AstNodeContents::TableRef(_) => "table reference",
             AstNodeContents::String(_) => "string literal",
             AstNodeContents::Number(_) => "numeric literal",
             AstNodeContents::Bool(_) => "boolean literal",
@@ -74,11 +76,43 @@ impl AstNode {
                 Value::Array(ctx.get_cell_array(rect.inner, self.span)?.inner)
             }
 
+            AstNodeContents::FunctionCall { func, args }
+                if is_implicit_intersection(&func.inner) =>
+            {
+                let arg = match args.as_slice() {
+                    [arg] => arg,
+                    [] => {
+                        return Err(RunErrorMsg::MissingRequiredArgument {
+                            func_name: func.inner.clone().into(),
+                            arg_name: "value".into(),
+                        }
+                        .with_span(self.span))
+                    }
+                    _ => {
+                        return Err(RunErrorMsg::TooManyArguments {
+                            func_name: func.inner.clone().into(),
+                            max_arg_count: 1,
+                        }
+                        .with_span(self.span))
+                    }
+                };
+                Value::Single(eval_implicit_intersection(ctx, arg, self.span)?)
+            }
+
+            AstNodeContents::FunctionCall { func, args } if is_offset(&func.inner) => {
+                ctx.volatile = true;
+                let rect = eval_offset(ctx, args, self.span)?;
+                Value::Array(ctx.get_cell_array(rect.inner, self.span)?.inner)
+            }
+
             // Other operator/function
             AstNodeContents::FunctionCall { func, args } => {
                 let func_name = &func.inner;
                 match functions::lookup_function(func_name) {
                     Some(f) => {
+                        if f.volatile {
+                            ctx.volatile = true;
+                        }
                         let arg_values: Vec<Spanned<Value>> =
                             args.iter().map(|arg| arg.eval(&mut *ctx)).try_collect()?;
                         let args = FormulaFnArgs::new(arg_values, self.span, f.name);
@@ -139,6 +173,11 @@ impl AstNode {
                 Array::from(ctx.get_cell(pos, self.span).inner).into()
             }
 
+            AstNodeContents::TableRef(table_ref) => {
+                let rect = ctx.resolve_table_ref(table_ref, self.span)?;
+                Value::Array(ctx.get_cell_array(rect.inner, self.span)?.inner)
+            }
+
             AstNodeContents::String(s) => Value::from(s.to_string()),
             AstNodeContents::Number(n) => Value::from(*n),
             AstNodeContents::Bool(b) => Value::from(*b),
@@ -297,3 +336,121 @@ fn eval_cell_range_op(
     })
     .with_span(span)
 }
+
+/// Returns whether `func` is the implicit intersection operator `@`, or its
+/// Excel function equivalent `SINGLE()`.
+fn is_implicit_intersection(func: &str) -> bool {
+    func == "@"
+        || functions::excel::remove_excel_function_prefix(func).eq_ignore_ascii_case("SINGLE")
+}
+
+/// Returns whether `func` is `OFFSET()`, which is evaluated as a range
+/// reference rather than from the values of its arguments.
+fn is_offset(func: &str) -> bool {
+    functions::excel::remove_excel_function_prefix(func).eq_ignore_ascii_case("OFFSET")
+}
+
+/// Evaluates `OFFSET()` to the range `reference` moved by `rows` and
+/// `columns`, and resized to `height` and `width` if they're given.
+fn eval_offset(ctx: &mut Ctx<'_>, args: &[AstNode], span: Span) -> CodeResult<Spanned<SheetRect>> {
+    let reference = args.first().ok_or(
+        RunErrorMsg::MissingRequiredArgument {
+            func_name: "OFFSET".into(),
+            arg_name: "reference".into(),
+        }
+        .with_span(span),
+    )?;
+    let rect = match &reference.inner {
+        AstNodeContents::TableRef(table_ref) => ctx.resolve_table_ref(table_ref, reference.span)?,
+        _ => {
+            let range = reference.to_range_ref(ctx)?;
+            ctx.resolve_range_ref(&range.inner, reference.span)?
+        }
+    }
+    .inner;
+
+    let arg_values: Vec<Spanned<Value>> = args
+        .iter()
+        .skip(1)
+        .map(|arg| arg.eval(&mut *ctx))
+        .try_collect()?;
+    let mut args = FormulaFnArgs::new(arg_values, span, "OFFSET");
+    let rows: i64 = args.take_next_required("rows")?.try_coerce()?.inner;
+    let columns: i64 = args.take_next_required("columns")?.try_coerce()?.inner;
+    let height: Option<Spanned<i64>> = args
+        .take_next_optional()
+        .map(CoerceInto::try_coerce)
+        .transpose()?;
+    let width: Option<Spanned<i64>> = args
+        .take_next_optional()
+        .map(CoerceInto::try_coerce)
+        .transpose()?;
+    args.error_if_more_args()?;
+
+    let size = |arg: Option<Spanned<i64>>, default: usize| match arg {
+        None => Ok(default as i64),
+        Some(arg) if arg.inner < 1 => Err(RunErrorMsg::InvalidArgument.with_span(arg.span)),
+        Some(arg) if arg.inner > crate::limits::CELL_RANGE_LIMIT as i64 => {
+            Err(RunErrorMsg::ArrayTooBig.with_span(arg.span))
+        }
+        Some(arg) => Ok(arg.inner),
+    };
+    let height = size(height, rect.height())?;
+    let width = size(width, rect.width())?;
+
+    let x = rect.min.x.checked_add(columns);
+    let y = rect.min.y.checked_add(rows);
+    match (x, y) {
+        (Some(x), Some(y)) if x.checked_add(width).is_some() && y.checked_add(height).is_some() => {
+            Ok(SheetRect::from_numbers(x, y, width, height, rect.sheet_id)).with_span(span)
+        }
+        _ => Err(RunErrorMsg::Overflow.with_span(span)),
+    }
+}
+
+/// Evaluates implicit intersection (`@`). A range returns the cell in the
+/// formula's row or column, and an array returns its top-left value.
+fn eval_implicit_intersection(
+    ctx: &mut Ctx<'_>,
+    arg: &AstNode,
+    span: Span,
+) -> CodeResult<CellValue> {
+    let rect = match &arg.inner {
+        AstNodeContents::TableRef(table_ref) => Some(ctx.resolve_table_ref(table_ref, arg.span)?),
+        _ => match arg.to_range_ref(ctx) {
+            Ok(range) => Some(ctx.resolve_range_ref(&range.inner, arg.span)?),
+            Err(_) => None,
+        },
+    };
+
+    let Some(rect) = rect else {
+        let value = arg.eval(ctx)?;
+        return value
+            .inner
+            .get(0, 0)
+            .cloned()
+            .map_err(|e| e.with_span(span));
+    };
+    if ctx.skip_computation {
+        return Ok(CellValue::Blank);
+    }
+
+    // A single row or column intersects the formula's column or row. Anything
+    // else must contain the formula's row or column.
+    let intersect = |min: i64, max: i64, formula_coord: i64| {
+        if min == max {
+            Ok(min)
+        } else if (min..=max).contains(&formula_coord) {
+            Ok(formula_coord)
+        } else {
+            Err(RunErrorMsg::InvalidArgument.with_span(span))
+        }
+    };
+    let rect = rect.inner;
+    let pos = SheetPos {
+        x: intersect(rect.min.x, rect.max.x, ctx.sheet_pos.x)?,
+        y: intersect(rect.min.y, rect.max.y, ctx.sheet_pos.y)?,
+        sheet_id: rect.sheet_id,
+    };
+    Ok(ctx.get_cell(pos, span).inner)
+}
@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 
 use itertools::Itertools;
+use rand::{rngs::StdRng, SeedableRng};
 use smallvec::{smallvec, SmallVec};
 
 use super::*;
@@ -20,6 +21,12 @@ pub struct Ctx<'ctx> {
 
     /// Whether to only parse, skipping expensive computations.
     pub skip_computation: bool,
+
+    /// Whether a volatile function (such as `RAND()`) has been evaluated.
+    pub volatile: bool,
+    /// Random number generator used by functions such as `RAND()`. Code
+    /// cells seed this from their transaction using [`Ctx::seed_rng()`].
+    pub rng: StdRng,
 }
 impl<'ctx> Ctx<'ctx> {
     /// Constructs a context for evaluating a formula at `pos` in `grid`.
@@ -29,9 +36,17 @@ impl<'ctx> Ctx<'ctx> {
             sheet_pos,
             cells_accessed: HashSet::new(),
             skip_computation: false,
+            volatile: false,
+            rng: StdRng::seed_from_u64(0),
         }
     }
 
+    /// Reseeds the random number generator so that volatile functions return
+    /// the same results for the same seed.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
     /// Constructs a context for checking the syntax and some basic types of a
     /// formula in `grid`. Expensive computations are skipped, so the value
     /// returned by "evaluating" the formula will be nonsense (probably blank).
@@ -41,6 +56,8 @@ impl<'ctx> Ctx<'ctx> {
             sheet_pos: Pos::ORIGIN.to_sheet_pos(grid.sheets()[0].id),
             cells_accessed: HashSet::new(),
             skip_computation: true,
+            volatile: false,
+            rng: StdRng::seed_from_u64(0),
         }
     }
 
@@ -89,6 +106,45 @@ impl<'ctx> Ctx<'ctx> {
         }
     }
 
+    /// Resolves a structured table reference. References without a table name
+    /// (eg, `[@Amount]`) use the table on the formula's sheet whose data rows
+    /// include the formula's row.
+    pub fn resolve_table_ref(
+        &self,
+        table_ref: &TableRef,
+        span: Span,
+    ) -> CodeResult<Spanned<SheetRect>> {
+        if self.skip_computation {
+            return Ok(SheetRect::single_sheet_pos(self.sheet_pos)).with_span(span);
+        }
+
+        let bad_ref = || RunErrorMsg::BadCellReference.with_span(span);
+        let (sheet, table) = match &table_ref.table {
+            Some(name) => self.grid.data_table(name).ok_or_else(bad_ref)?,
+            None => {
+                let sheet = self
+                    .grid
+                    .try_sheet(self.sheet_pos.sheet_id)
+                    .ok_or_else(bad_ref)?;
+                let table = sheet
+                    .data_tables
+                    .iter()
+                    .find(|table| table.contains_data_row(self.sheet_pos.y))
+                    .ok_or_else(bad_ref)?;
+                (sheet, table)
+            }
+        };
+        if matches!(table_ref.spec, TableSpecifier::ThisRow(_))
+            && sheet.id != self.sheet_pos.sheet_id
+        {
+            return Err(bad_ref());
+        }
+        let rect = table
+            .resolve_specifier(&table_ref.spec, self.sheet_pos.y)
+            .ok_or_else(bad_ref)?;
+        Ok(rect.to_sheet_rect(sheet.id)).with_span(span)
+    }
+
     /// Fetches the contents of the cell at `pos` evaluated at `self.sheet_pos`,
     /// or returns an error in the case of a circular reference.
     pub fn get_cell(&mut self, pos: SheetPos, span: Span) -> Spanned<CellValue> {
@@ -150,8 +206,9 @@ impl<'ctx> Ctx<'ctx> {
     /// `{11,22,33}`. If any argument is not an array, it is expanded into an
     /// array with the same size as other arguments. This also works
     /// 2-dimensionally: if one argument is a 1x3 array and the other argument
-    /// is a 3x1 array, then both arguments are first expanded to 3x3 arrays. If
-    /// arrays cannot be expanded like this, then an error is returned.
+    /// is a 3x1 array, then both arguments are first expanded to 3x3 arrays.
+    /// Like Excel, arrays that are too small to expand are padded with `#N/A`,
+    /// so cells past the end of a smaller array are `#N/A`.
     pub fn zip_map<'a, I: Copy + IntoIterator<Item = &'a Spanned<Value>>>(
         &mut self,
         arrays: I,
@@ -164,7 +221,7 @@ impl<'ctx> Ctx<'ctx> {
             return Ok(CellValue::Blank.into());
         }
 
-        let size = Value::common_array_size(arrays)?;
+        let size = Value::broadcast_array_size(arrays);
 
         let mut args_buffer = Vec::with_capacity(arrays.into_iter().len());
 
@@ -178,14 +235,33 @@ impl<'ctx> Ctx<'ctx> {
             return Ok(Value::Single(f(self, &args_buffer)?));
         }
 
+        // Like Excel, pad arrays that are too small with `#N/A`.
+        let not_available = CellValue::Error(Box::new(RunErrorMsg::NotAvailable.without_span()));
+
         let mut values = SmallVec::with_capacity(size.len());
         for (x, y) in size.iter() {
             args_buffer.clear();
+            let mut is_padded = false;
             for array in arrays {
-                args_buffer.push(array.get(x, y)?);
+                match array.get(x, y) {
+                    Ok(value) => args_buffer.push(value),
+                    Err(e) if e.msg == RunErrorMsg::IndexOutOfBounds => {
+                        is_padded = true;
+                        args_buffer.push(Spanned {
+                            span: array.span,
+                            inner: &not_available,
+                        });
+                    }
+                    Err(e) => return Err(e),
+                }
             }
 
-            values.push(f(self, &args_buffer)?);
+            values.push(match f(self, &args_buffer) {
+                Ok(value) => value,
+                // Errors caused by padding only affect their own cell.
+                Err(_) if is_padded => not_available.clone(),
+                Err(e) => return Err(e),
+            });
         }
 
         let result = Array::new_row_major(size, values)?;
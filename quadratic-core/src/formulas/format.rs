@@ -0,0 +1,306 @@
+//! Canonical text rendering of a formula AST.
+//!
+//! Formatting normalizes function name casing, spacing around operators and
+//! commas, and cell reference notation, so that two formulas that parse the
+//! same way are formatted the same way.
+
+use super::ast::{AstNode, AstNodeContents};
+use super::lexer::Token;
+use super::*;
+use crate::{CodeResult, Pos, RunErrorMsg};
+
+/// Number of spaces per indentation level in multi-line layout.
+const INDENT: &str = "    ";
+
+/// Options for formatting a formula.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Whether to put the arguments of nested `IF` calls on separate lines.
+    pub multiline: bool,
+}
+
+/// Parses a formula and returns its canonical rendering.
+///
+/// Comments aren't part of the AST, so formulas that contain them are not
+/// formatted and an error is returned instead.
+pub fn format_formula(source: &str, pos: Pos, options: FormatOptions) -> CodeResult<String> {
+    let formula = parse_formula(source, pos)?;
+    if let Some(comment) = lexer::tokenize(source).find(|token| token.inner == Token::Comment) {
+        return Err(RunErrorMsg::Unexpected("comment".into()).with_span(comment.span));
+    }
+    Ok(formula.to_formatted_string(pos, options))
+}
+
+impl Formula {
+    /// Returns the canonical rendering of the formula. `pos` must be the same
+    /// position that was used to parse the formula so that relative cell
+    /// references are preserved.
+    pub fn to_formatted_string(&self, pos: Pos, options: FormatOptions) -> String {
+        let mut out = String::new();
+        Formatter { pos, options }.write_node(&mut out, &self.ast, 0);
+        out
+    }
+}
+
+struct Formatter {
+    pos: Pos,
+    options: FormatOptions,
+}
+impl Formatter {
+    fn write_node(&self, out: &mut String, node: &AstNode, depth: usize) {
+        match &node.inner {
+            AstNodeContents::Empty => (),
+
+            AstNodeContents::FunctionCall { func, args } => {
+                match (canonical_operator(&func.inner), args.as_slice()) {
+                    (Some(op @ (":" | "..")), [lhs, rhs]) => {
+                        self.write_node(out, lhs, depth);
+                        out.push_str(op);
+                        self.write_node(out, rhs, depth);
+                    }
+                    (Some(op), [lhs, rhs]) => {
+                        self.write_node(out, lhs, depth);
+                        out.push_str(&format!(" {op} "));
+                        self.write_node(out, rhs, depth);
+                    }
+                    (Some("%"), [arg]) => {
+                        self.write_node(out, arg, depth);
+                        out.push('%');
+                    }
+                    (Some(op), [arg]) => {
+                        out.push_str(op);
+                        self.write_node(out, arg, depth);
+                    }
+                    _ => {
+                        let name = match functions::lookup_function(&func.inner) {
+                            Some(f) => f.name.to_string(),
+                            None => func.inner.to_ascii_uppercase(),
+                        };
+                        out.push_str(&name);
+                        out.push('(');
+                        if self.options.multiline
+                            && is_if(&func.inner)
+                            && args.iter().any(contains_if)
+                        {
+                            for (i, arg) in args.iter().enumerate() {
+                                out.push('\n');
+                                out.push_str(&INDENT.repeat(depth + 1));
+                                self.write_node(out, arg, depth + 1);
+                                if i + 1 < args.len() {
+                                    out.push(',');
+                                }
+                            }
+                            out.push('\n');
+                            out.push_str(&INDENT.repeat(depth));
+                        } else {
+                            self.write_list(out, args, depth);
+                        }
+                        out.push(')');
+                    }
+                }
+            }
+
+            AstNodeContents::Paren(contents) => {
+                out.push('(');
+                self.write_list(out, contents, depth);
+                out.push(')');
+            }
+
+            AstNodeContents::Array(rows) => {
+                out.push('{');
+                for (i, row) in rows.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str("; ");
+                    }
+                    self.write_list(out, row, depth);
+                }
+                out.push('}');
+            }
+
+            AstNodeContents::CellRef(cell_ref) => {
+                if let Some(sheet) = &cell_ref.sheet {
+                    out.push_str(&sheet_prefix(sheet));
+                }
+                let cell_ref = CellRef {
+                    sheet: None,
+                    ..cell_ref.clone()
+                };
+                out.push_str(&cell_ref.a1_string(self.pos));
+            }
+
+            AstNodeContents::TableRef(table_ref) => out.push_str(&table_ref.to_string()),
+
+            AstNodeContents::String(s) => out.push_str(&quote_string(s)),
+
+            AstNodeContents::Number(n) => out.push_str(&n.to_string()),
+
+            AstNodeContents::Bool(b) => out.push_str(if *b { "TRUE" } else { "FALSE" }),
+        }
+    }
+
+    /// Writes a comma-separated list of expressions on a single line.
+    fn write_list(&self, out: &mut String, nodes: &[AstNode], depth: usize) {
+        for (i, node) in nodes.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            self.write_node(out, node, depth);
+        }
+    }
+}
+
+/// Returns the canonical spelling of an operator, or `None` if `func` is not
+/// an operator.
+fn canonical_operator(func: &str) -> Option<&'static str> {
+    Some(match func {
+        "=" | "==" => "=",
+        "<>" | "!=" => "<>",
+        "<" => "<",
+        ">" => ">",
+        "<=" => "<=",
+        ">=" => ">=",
+        "&" => "&",
+        "+" => "+",
+        "-" => "-",
+        "*" => "*",
+        "/" => "/",
+        "^" => "^",
+        "%" => "%",
+        ".." => "..",
+        ":" => ":",
+        "@" => "@",
+        _ => return None,
+    })
+}
+
+fn is_if(func: &str) -> bool {
+    func.eq_ignore_ascii_case("IF")
+}
+
+/// Returns whether an expression contains a call to `IF`.
+fn contains_if(node: &AstNode) -> bool {
+    match &node.inner {
+        AstNodeContents::FunctionCall { func, args } => {
+            is_if(&func.inner) || args.iter().any(contains_if)
+        }
+        AstNodeContents::Paren(contents) => contents.iter().any(contains_if),
+        AstNodeContents::Array(rows) => rows.iter().flatten().any(contains_if),
+        _ => false,
+    }
+}
+
+/// Returns a string literal that parses back to `s`.
+fn quote_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Returns the prefix used to refer to a sheet, quoting the sheet name only if
+/// necessary.
+fn sheet_prefix(sheet: &str) -> String {
+    let unquoted = format!("{sheet}!");
+    let is_valid_unquoted = lexer::UNQUOTED_SHEET_REFERENCE
+        .find(&unquoted)
+        .is_some_and(|m| m.len() == unquoted.len());
+    if is_valid_unquoted {
+        unquoted
+    } else {
+        format!("{}!", quote_string(sheet))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::parallel;
+
+    fn format(s: &str) -> String {
+        format_formula(s, Pos::ORIGIN, FormatOptions::default()).unwrap()
+    }
+
+    fn format_multiline(s: &str) -> String {
+        format_formula(s, Pos::ORIGIN, FormatOptions { multiline: true }).unwrap()
+    }
+
+    #[test]
+    #[parallel]
+    fn test_format_formula() {
+        let cases = [
+            ("sum(A1:B2,3)", "SUM(A1:B2, 3)"),
+            ("1+2*3", "1 + 2 * 3"),
+            ("(1+2)*3", "(1 + 2) * 3"),
+            ("A1==B1", "A1 = B1"),
+            ("(A1)!=B1", "(A1) <> B1"),
+            ("-A1 %", "-A1%"),
+            ("{1,2;3,4}", "{1, 2; 3, 4}"),
+            ("if(true,'yes',\"no\")", "IF(TRUE, \"yes\", \"no\")"),
+            ("'Sheet 2'!A1 + Sheet2!$B$3", "\"Sheet 2\"!A1 + Sheet2!$B$3"),
+            ("Table1[amount]", "Table1[amount]"),
+            ("1..5", "1..5"),
+            ("@ A1:A10", "@A1:A10"),
+            ("1.50e2", "150"),
+            ("notafunction(1)", "NOTAFUNCTION(1)"),
+            ("\"say \\\"hi\\\"\"", "\"say \\\"hi\\\"\""),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(format(input), expected, "formatting {input:?}");
+        }
+    }
+
+    #[test]
+    #[parallel]
+    fn test_format_formula_round_trip() {
+        let pos = Pos { x: 3, y: 5 };
+        for input in ["SUM(A1, nB2)", "IF(A1 > 0, \"x\", 'y')", "R[1]C[-1] & \"\""] {
+            let formatted = format_formula(input, pos, FormatOptions::default()).unwrap();
+            let reformatted = format_formula(&formatted, pos, FormatOptions::default()).unwrap();
+            assert_eq!(formatted, reformatted);
+        }
+    }
+
+    #[test]
+    #[parallel]
+    fn test_format_formula_with_comments() {
+        for input in ["SUM(A1, 2) // total", "SUM(A1, /* tax */ 2)"] {
+            assert_eq!(
+                format_formula(input, Pos::ORIGIN, FormatOptions::default())
+                    .unwrap_err()
+                    .msg,
+                RunErrorMsg::Unexpected("comment".into()),
+            );
+        }
+    }
+
+    #[test]
+    #[parallel]
+    fn test_format_formula_multiline() {
+        assert_eq!(
+            format_multiline("if(A1>1,\"big\",if(A1>0,\"small\",\"none\"))"),
+            [
+                "IF(",
+                "    A1 > 1,",
+                "    \"big\",",
+                "    IF(A1 > 0, \"small\", \"none\")",
+                ")",
+            ]
+            .join("\n"),
+        );
+        assert_eq!(
+            format_multiline("IF(A1, IF(B1, IF(C1, 1, 2), 3), 4)"),
+            [
+                "IF(",
+                "    A1,",
+                "    IF(",
+                "        B1,",
+                "        IF(C1, 1, 2),",
+                "        3",
+                "    ),",
+                "    4",
+                ")",
+            ]
+            .join("\n"),
+        );
+
+        // a single IF stays on one line
+        assert_eq!(format_multiline("if(A1,1,2)"), "IF(A1, 1, 2)");
+    }
+}
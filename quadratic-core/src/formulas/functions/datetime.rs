@@ -0,0 +1,76 @@
+use chrono::{NaiveTime, Utc};
+
+use super::*;
+
+pub const CATEGORY: FormulaFunctionCategory = FormulaFunctionCategory {
+    include_in_docs: true,
+    include_in_completions: true,
+    name: "Date & time functions",
+    docs: "Dates and times are returned as serial numbers, where `1` is \
+           January 1, 1900 and the fractional part is the time of day. This \
+           is the same representation used by other spreadsheet software.\
+           \n\n",
+    get_functions,
+};
+
+/// Number of days between the spreadsheet epoch (December 30, 1899) and the
+/// Unix epoch (January 1, 1970).
+const UNIX_EPOCH_SERIAL: f64 = 25569.0;
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+fn get_functions() -> Vec<FormulaFunction> {
+    vec![
+        formula_fn!(
+            /// Returns the current date and time as a serial number.
+            ///
+            /// The value is updated every time the sheet changes.
+            #[volatile(true)]
+            #[examples("NOW()", "NOW() - A1")]
+            fn NOW() {
+                let now = Utc::now();
+                datetime_to_serial(now.timestamp_millis() as f64 / 1000.0)
+            }
+        ),
+        formula_fn!(
+            /// Returns the current date as a serial number, without the time
+            /// of day.
+            ///
+            /// The value is updated every time the sheet changes.
+            #[volatile(true)]
+            #[examples("TODAY()", "TODAY() + 7")]
+            fn TODAY() {
+                let today = Utc::now().date_naive().and_time(NaiveTime::MIN);
+                datetime_to_serial(today.and_utc().timestamp() as f64)
+            }
+        ),
+    ]
+}
+
+/// Converts a Unix timestamp (in seconds) to a spreadsheet serial number.
+fn datetime_to_serial(unix_seconds: f64) -> f64 {
+    unix_seconds / SECONDS_PER_DAY + UNIX_EPOCH_SERIAL
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formulas::tests::*;
+    use serial_test::parallel;
+
+    #[test]
+    #[parallel]
+    fn test_datetime_to_serial() {
+        assert_eq!(25569.0, super::datetime_to_serial(0.0));
+        assert_eq!(45292.5, super::datetime_to_serial(1704110400.0));
+    }
+
+    #[test]
+    #[parallel]
+    fn test_formula_today() {
+        let g = Grid::new();
+        let today = eval_to_string(&g, "TODAY()").parse::<f64>().unwrap();
+        assert_eq!(today.fract(), 0.0);
+        let now = eval_to_string(&g, "NOW()").parse::<f64>().unwrap();
+        assert!(now >= today && now < today + 1.0);
+    }
+}
@@ -90,12 +90,6 @@ fn get_functions() -> Vec<FormulaFunction> {
             )]
             #[zip_map]
             fn IFERROR([value]: CellValue, [fallback]: CellValue) {
-                // This is slightly inconsistent with Excel; Excel does a weird
-                // sort of zip-map here that doesn't require `value` and
-                // `fallback` to have the same size, and also has special
-                // handling if `value` is size=1 along an axis. This is
-                // something we could try to fix later, but it's probably not
-                // worth it.
                 value
                     .clone()
                     .into_non_error_value()
@@ -165,5 +159,11 @@ mod tests {
             RunErrorMsg::DivideByZero,
             eval_to_err(&g, "IFERROR(A6, 0/0)").msg,
         );
+
+        // arrays of different sizes are padded with `#N/A`
+        assert_eq!(
+            "{1, 2, 0}",
+            eval_to_string(&g, "IFERROR({1, 2}, {0, 0, 0})"),
+        );
     }
 }
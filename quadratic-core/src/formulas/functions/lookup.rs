@@ -18,6 +18,7 @@ fn get_functions() -> Vec<FormulaFunction> {
     vec![
         formula_fn!(
             /// Returns the value of the cell at a given location.
+            #[volatile(true)]
             #[examples("INDIRECT(\"Cn7\")", "INDIRECT(\"F\" & B0)")]
             #[zip_map]
             fn INDIRECT(ctx: Ctx, [cellref_string]: (Spanned<String>)) {
@@ -29,6 +30,26 @@ fn get_functions() -> Vec<FormulaFunction> {
                 ctx.get_cell(pos, span).inner
             }
         ),
+        formula_fn!(
+            /// Returns the range that is `rows` rows down and `columns`
+            /// columns right of `reference`. Negative offsets move up and
+            /// left. The range is `height` rows tall and `width` columns wide,
+            /// which default to the size of `reference`.
+            #[volatile(true)]
+            #[examples("OFFSET(A1, 2, 3)", "SUM(OFFSET(A1:A10, 0, B1))")]
+            fn OFFSET(
+                reference: Array,
+                rows: i64,
+                columns: i64,
+                height: (Option<i64>),
+                width: (Option<i64>),
+            ) {
+                // `OFFSET()` returns a range reference rather than a value, so
+                // it's evaluated by the AST instead.
+                let _ = (reference, rows, columns, height, width);
+                internal_error!("OFFSET must be evaluated as a range reference")
+            }
+        ),
         formula_fn!(
             /// Searches for a value in the first vertical column of a range and
             /// return the corresponding cell in another vertical column, or an
@@ -685,6 +706,39 @@ mod tests {
         assert_eq!("35".to_string(), eval_to_string(&g, "INDIRECT(\"D5\")"));
     }
 
+    #[test]
+    #[parallel]
+    fn test_formula_offset() {
+        let g = Grid::from_array(pos![A1], &array![1, 2, 3; 4, 5, 6; 7, 8, 9]);
+
+        assert_eq!("{5}", eval_to_string(&g, "OFFSET(A1, 1, 1)"));
+        assert_eq!("{2}", eval_to_string(&g, "OFFSET(C3, -2, -1)"));
+        assert_eq!("28", eval_to_string(&g, "SUM(OFFSET(A1, 1, 1, 2, 2))"));
+        assert_eq!("24", eval_to_string(&g, "SUM(OFFSET(A1:B2, 1, 0))"));
+        assert_eq!("15", eval_to_string(&g, "SUM(OFFSET(A1:C1, 1, 0, , 3))"));
+
+        assert_eq!(
+            RunErrorMsg::InvalidArgument,
+            eval_to_err(&g, "OFFSET(A1, 0, 0, 0)").msg,
+        );
+        assert!(matches!(
+            eval_to_err(&g, "OFFSET(1, 0, 0)").msg,
+            RunErrorMsg::Expected { .. },
+        ));
+        assert!(matches!(
+            eval_to_err(&g, "OFFSET(A1, 0)").msg,
+            RunErrorMsg::MissingRequiredArgument { .. },
+        ));
+
+        // OFFSET is volatile
+        let sheet_id = g.sheets()[0].id;
+        let mut ctx = Ctx::new(&g, pos![E5].to_sheet_pos(sheet_id));
+        parse_formula("OFFSET(A1, 1, 1)", pos![E5])
+            .unwrap()
+            .eval(&mut ctx);
+        assert!(ctx.volatile);
+    }
+
     /// Test VLOOKUP error conditions.
     #[test]
     #[parallel]
@@ -84,6 +84,7 @@ macro_rules! see_docs_for_more_about_wildcards {
 /// - `#[doc = "..."]` (or doc comments using `///`) - user-facing documentation
 /// - `#[operator]` - removes the function from documentation
 /// - `#[name = "..."]` - overrides the function name
+/// - `#[volatile(true)]` - recomputes the function at the end of every user transaction
 /// - `#[examples("EXAMPLE()", "EXAMPLE(A, B)")]` - example usages
 /// - `#[zip_map]` - if certain arguments are arrays, **zip** them together
 ///                  and **map** a **pure** function over them.
@@ -134,6 +135,7 @@ macro_rules! formula_fn {
             usage: "",
             examples: &[],
             doc: "",
+            volatile: false,
             eval: formula_fn_eval!(
                 { $($body)* };
                 $(#[$($attr)*])*
@@ -147,6 +149,7 @@ macro_rules! formula_fn {
         $(#[doc = $additional_doc:expr])*
         $(#[include_args_in_completion($include_args_in_completion:expr)])?
         $(#[name = $name_str:literal])?
+        $(#[volatile($volatile:expr)])?
         #[examples($($example_str:expr),+ $(,)?)]
         $(#[$($attr:tt)*])*
         fn $fn_name:ident( $($params:tt)* ) { $($body:tt)* }
@@ -155,6 +158,8 @@ macro_rules! formula_fn {
 
         // Default to `true`
         let include_args_in_completion = [$($include_args_in_completion, )? true][0];
+        // Default to `false`
+        let volatile = [$($volatile, )? false][0];
 
         $crate::formulas::functions::FormulaFunction {
             name: $(if true { $name_str } else)? { stringify!($fn_name) },
@@ -164,6 +169,7 @@ macro_rules! formula_fn {
             usage: $crate::formulas::params::usage_string(&params_list),
             examples: &[$($example_str),+],
             doc: concat!($doc $(, "\n", $additional_doc)*),
+            volatile,
             eval: formula_fn_eval!(
                 { $($body)* };
                 $(#[$($attr)*])*
@@ -505,6 +511,7 @@ macro_rules! params_list {
     // Entry points (at the bottom so that the other rules take priority)
     () => { vec![] };
     ($($arg_name:tt: $arg_type:tt),+ $(,)?) => {{
+        #[allow(unused_mut)]
         let mut result = vec![];
 
         $(
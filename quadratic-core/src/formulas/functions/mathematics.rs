@@ -1,4 +1,7 @@
+use rand::Rng;
+
 use super::*;
+use crate::ArraySize;
 
 pub const CATEGORY: FormulaFunctionCategory = FormulaFunctionCategory {
     include_in_docs: true,
@@ -303,6 +306,77 @@ fn get_functions() -> Vec<FormulaFunction> {
                 number.ln()
             }
         ),
+        // Random numbers
+        formula_fn!(
+            /// Returns a random number between 0 (inclusive) and 1 (exclusive).
+            ///
+            /// A new number is generated every time the sheet changes.
+            #[volatile(true)]
+            #[examples("RAND()", "RAND() * 100")]
+            fn RAND(ctx: Ctx) {
+                ctx.rng.gen::<f64>()
+            }
+        ),
+        formula_fn!(
+            /// Returns a random integer between `bottom` and `top`, inclusive.
+            /// Returns an error if `bottom` is greater than `top`.
+            ///
+            /// A new number is generated every time the sheet changes.
+            #[volatile(true)]
+            #[examples("RANDBETWEEN(1, 6)", "RANDBETWEEN(-100, 100)")]
+            fn RANDBETWEEN(ctx: Ctx, span: Span, bottom: f64, top: f64) {
+                let (bottom, top) = (bottom.ceil() as i64, top.floor() as i64);
+                if bottom > top {
+                    return Err(RunErrorMsg::InvalidArgument.with_span(span));
+                }
+                ctx.rng.gen_range(bottom..=top)
+            }
+        ),
+        formula_fn!(
+            /// Returns an array of random numbers with `rows` rows and
+            /// `columns` columns. Both default to `1`.
+            ///
+            /// Numbers are between `min` (inclusive, default `0`) and `max`
+            /// (exclusive, default `1`). If `whole_number` is true, then only
+            /// integers between `min` and `max` (both inclusive) are returned.
+            ///
+            /// New numbers are generated every time the sheet changes.
+            #[volatile(true)]
+            #[examples("RANDARRAY(5)", "RANDARRAY(3, 4, 1, 100, TRUE)")]
+            fn RANDARRAY(
+                ctx: Ctx,
+                span: Span,
+                rows: (Option<u32>),
+                columns: (Option<u32>),
+                min: (Option<f64>),
+                max: (Option<f64>),
+                whole_number: (Option<bool>),
+            ) {
+                let size = ArraySize::new_or_err(columns.unwrap_or(1), rows.unwrap_or(1))
+                    .map_err(|e| e.with_span(span))?;
+                if std::cmp::max(size.w, size.h).get() > crate::limits::CELL_RANGE_LIMIT {
+                    return Err(RunErrorMsg::ArrayTooBig.with_span(span));
+                }
+                let (min, max) = (min.unwrap_or(0.0), max.unwrap_or(1.0));
+                if min > max {
+                    return Err(RunErrorMsg::InvalidArgument.with_span(span));
+                }
+                let values = if whole_number.unwrap_or(false) {
+                    let (min, max) = (min.ceil() as i64, max.floor() as i64);
+                    if min > max {
+                        return Err(RunErrorMsg::InvalidArgument.with_span(span));
+                    }
+                    (0..size.len())
+                        .map(|_| CellValue::from(ctx.rng.gen_range(min..=max)))
+                        .collect()
+                } else {
+                    (0..size.len())
+                        .map(|_| CellValue::from(min + ctx.rng.gen::<f64>() * (max - min)))
+                        .collect()
+                };
+                Array::new_row_major(size, values)?
+            }
+        ),
         // Constants
         formula_fn!(
             /// Returns π, the circle constant.
@@ -780,4 +854,68 @@ mod tests {
                 .msg,
         );
     }
+
+    #[test]
+    #[parallel]
+    fn test_rand() {
+        let g = Grid::new();
+        let sheet_pos = Pos::ORIGIN.to_sheet_pos(g.sheets()[0].id);
+        let formula = parse_formula("RAND()", Pos::ORIGIN).unwrap();
+
+        let mut ctx = Ctx::new(&g, sheet_pos);
+        let n = formula
+            .eval(&mut ctx)
+            .inner
+            .to_string()
+            .parse::<f64>()
+            .unwrap();
+        assert!((0.0..1.0).contains(&n));
+        assert!(ctx.volatile);
+
+        // The same seed produces the same result.
+        let eval_seeded = |seed| {
+            let mut ctx = Ctx::new(&g, sheet_pos);
+            ctx.seed_rng(seed);
+            formula.eval(&mut ctx).inner.to_string()
+        };
+        assert_eq!(eval_seeded(42), eval_seeded(42));
+        assert_ne!(eval_seeded(42), eval_seeded(43));
+
+        // Non-volatile formulas are not marked as volatile.
+        let mut ctx = Ctx::new(&g, sheet_pos);
+        parse_formula("SUM(1, 2)", Pos::ORIGIN)
+            .unwrap()
+            .eval(&mut ctx);
+        assert!(!ctx.volatile);
+    }
+
+    #[test]
+    #[parallel]
+    fn test_randbetween() {
+        let g = Grid::new();
+        for _ in 0..20 {
+            let n = eval_to_string(&g, "RANDBETWEEN(1.5, 3)")
+                .parse::<i64>()
+                .unwrap();
+            assert!((2..=3).contains(&n));
+        }
+        assert_eq!("4", eval_to_string(&g, "RANDBETWEEN(4, 4)"));
+        expect_err(&RunErrorMsg::InvalidArgument, &g, "RANDBETWEEN(5, 1)");
+    }
+
+    #[test]
+    #[parallel]
+    fn test_randarray() {
+        let g = Grid::new();
+        let Value::Array(a) = eval(&g, "RANDARRAY(3, 2, 10, 20, TRUE)") else {
+            panic!("expected array");
+        };
+        assert_eq!((2, 3), (a.width(), a.height()));
+        for v in a.cell_values_slice() {
+            let n = v.to_string().parse::<i64>().unwrap();
+            assert!((10..=20).contains(&n));
+        }
+        expect_err(&RunErrorMsg::EmptyArray, &g, "RANDARRAY(0)");
+        expect_err(&RunErrorMsg::InvalidArgument, &g, "RANDARRAY(1, 1, 5, 1)");
+    }
 }
@@ -6,6 +6,7 @@ use lazy_static::lazy_static;
 
 #[macro_use]
 mod macros;
+mod datetime;
 pub mod excel;
 mod logic;
 mod lookup;
@@ -41,6 +42,7 @@ pub const CATEGORIES: &[FormulaFunctionCategory] = &[
     logic::CATEGORY,
     string::CATEGORY,
     lookup::CATEGORY,
+    datetime::CATEGORY,
     #[cfg(test)]
     tests::CATEGORY,
 ];
@@ -157,6 +159,10 @@ pub struct FormulaFunction {
     pub usage: &'static str,
     pub examples: &'static [&'static str],
     pub doc: &'static str,
+    /// Whether the function's result can change even when its inputs do not
+    /// (e.g., `RAND()` or `NOW()`). Formulas that call a volatile function
+    /// are recomputed at the end of every user transaction.
+    pub volatile: bool,
     pub eval: FormulaFn,
 }
 impl FormulaFunction {
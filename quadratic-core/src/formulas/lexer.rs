@@ -39,6 +39,15 @@ const FUNCTION_CALL_PATTERN: &str = r"[A-Za-z_](\.?[A-Za-z_\d])*\(";
 const A1_CELL_REFERENCE_PATTERN: &str = r"\$?n?[A-Z]+\$?n?\d+";
 const INTERNAL_CELL_REFERENCE_PATTERN: &str = r"R([\[|\{]-?\d+[\]|\}])C([\[|\{]-?\d+[\]|\}])";
 
+/// Structured reference to a data table, such as `Table1[Amount]`,
+/// `Table1[#Headers]`, or `[@Amount]`.
+///
+/// ([A-Za-z_][A-Za-z0-9_\.]*)?\[[^\[\]]*\]
+/// (                        )?                 optional table name
+///                            \[        \]      brackets
+///                              [^\[\]]*        column or specifier
+const TABLE_REFERENCE_PATTERN: &str = r"([A-Za-z_][A-Za-z0-9_\.]*)?\[[^\[\]]*\]";
+
 /// Floating-point or integer number, without leading sign.
 ///
 /// (\d+(\.\d*)?|\.\d+)([eE][+-]?\d+)?
@@ -87,10 +96,13 @@ const TOKEN_PATTERNS: &[&str] = &[
     FUNCTION_CALL_PATTERN,
     // Boolean literal (case-insensitive).
     r#"false|true"#,
-    // Reference to a cell.
-    A1_CELL_REFERENCE_PATTERN,
     // Internal cell reference.
     INTERNAL_CELL_REFERENCE_PATTERN,
+    // Reference to a data table (must come before cell references so that
+    // `Table1` is not lexed as a cell).
+    TABLE_REFERENCE_PATTERN,
+    // Reference to a cell.
+    A1_CELL_REFERENCE_PATTERN,
     // Whitespace.
     r"\s+",
     // Any other single Unicode character.
@@ -119,6 +131,10 @@ lazy_static! {
     pub static ref INTERNAL_CELL_REFERENCE_REGEX: Regex =
         new_fullmatch_regex(INTERNAL_CELL_REFERENCE_PATTERN);
 
+    /// Regex that matches a structured table reference.
+    pub static ref TABLE_REFERENCE_REGEX: Regex =
+        new_fullmatch_regex(TABLE_REFERENCE_PATTERN);
+
     /// Regex that matches all valid numeric literals and some invalid ones.
     pub static ref NUMERIC_LITERAL_REGEX: Regex =
         new_fullmatch_regex(NUMERIC_LITERAL_PATTERN);
@@ -196,6 +212,8 @@ pub enum Token {
     SheetRefOp, // !
     #[strum(to_string = "ellipsis")]
     Ellipsis, // ...
+    #[strum(to_string = "implicit intersection operator")]
+    ImplicitIntersection, // @
 
     // Booleans
     #[strum(to_string = "FALSE")]
@@ -224,6 +242,8 @@ pub enum Token {
     CellRef,
     #[strum(to_string = "internal cell reference")]
     InternalCellRef,
+    #[strum(to_string = "table reference")]
+    TableRef,
     #[strum(to_string = "whitespace")]
     Whitespace,
     #[strum(to_string = "unknown symbol")]
@@ -264,6 +284,7 @@ impl Token {
             ":" => Self::CellRangeOp,
             "!" => Self::SheetRefOp,
             "..." => Self::Ellipsis,
+            "@" => Self::ImplicitIntersection,
             s if s.eq_ignore_ascii_case("false") => Self::False,
             s if s.eq_ignore_ascii_case("true") => Self::True,
 
@@ -304,8 +325,9 @@ impl Token {
             s if s.eq_ignore_ascii_case("false") => Self::False,
             s if s.eq_ignore_ascii_case("true") => Self::True,
             s if NUMERIC_LITERAL_REGEX.is_match(s) => Self::NumericLiteral,
-            s if A1_CELL_REFERENCE_REGEX.is_match(s) => Self::CellRef,
             s if INTERNAL_CELL_REFERENCE_REGEX.is_match(s) => Self::InternalCellRef,
+            s if TABLE_REFERENCE_REGEX.is_match(s) => Self::TableRef,
+            s if A1_CELL_REFERENCE_REGEX.is_match(s) => Self::CellRef,
             s if s.trim().is_empty() => Self::Whitespace,
 
             // Give up.
@@ -355,6 +377,24 @@ mod tests {
         test_block_comment(false, "/* /*");
         test_block_comment(false, "/*/");
     }
+
+    #[test]
+    #[parallel]
+    fn test_lex_table_reference() {
+        for s in [
+            "Table1[Amount]",
+            "Table1[#Headers]",
+            "[@Amount]",
+            "Sales.Q1[Unit Price]",
+        ] {
+            let tokens = tokenize(s).collect_vec();
+            assert_eq!(1, tokens.len(), "Too many tokens: {:?}", tokens);
+            assert_eq!(Token::TableRef, tokens[0].inner);
+        }
+        let tokens = tokenize("R[1]C[2]").collect_vec();
+        assert_eq!(Token::InternalCellRef, tokens[0].inner);
+    }
+
     fn test_block_comment(expected_to_end: bool, s: &str) {
         let tokens = tokenize(s).collect_vec();
         if expected_to_end {
@@ -1,18 +1,141 @@
 //! Language server implementation for Monaco editor
 
+use std::borrow::Cow;
+
+use itertools::Itertools;
 use lazy_static::lazy_static;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 pub mod types;
 
 pub use types::*;
 
-use super::functions;
+use super::lexer::{self, Token};
+use super::{escape_string, functions, parse_formula, CellRef, Ctx, RangeRef, TableRef};
+use crate::grid::sheet::data_tables::DataTable;
+use crate::grid::{Grid, Sheet, SheetId};
+use crate::{CoerceInto, Pos, Rect, Span, Spanned};
 
 #[derive(Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct CompletionList<'a> {
-    suggestions: &'a [CompletionItem],
+    suggestions: Cow<'a, [CompletionItem]>,
+}
+
+/// Information about the grid used by the language server. The language server
+/// does not have access to the grid, so the client gets this from core (see
+/// [`LspContext::new()`]) and sends it whenever it changes.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LspContext {
+    #[serde(default)]
+    pub sheets: Vec<String>,
+    #[serde(default)]
+    pub tables: Vec<LspTable>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LspTable {
+    pub name: String,
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub sheet: String,
+    /// Bounds of the table, including the header row.
+    #[serde(default)]
+    pub rect: Rect,
+}
+
+impl LspTable {
+    /// Resolves a structured reference to this table from a formula at `pos`.
+    fn resolve(&self, table_ref: &TableRef, pos: Pos) -> Option<RangeRef> {
+        let table = DataTable::new(self.name.clone(), self.rect, self.columns.clone());
+        let rect = table.resolve_specifier(&table_ref.spec, pos.y)?;
+        let sheet = Some(self.sheet.clone());
+        Some(RangeRef::CellRange {
+            start: CellRef::absolute(sheet.clone(), rect.min),
+            end: CellRef::absolute(sheet, rect.max),
+        })
+    }
+}
+
+impl LspContext {
+    pub fn new(grid: &Grid) -> Self {
+        LspContext {
+            sheets: grid
+                .sheets()
+                .iter()
+                .map(|sheet| sheet.name.clone())
+                .collect(),
+            tables: grid
+                .sheets()
+                .iter()
+                .flat_map(|sheet| sheet.data_tables.iter().map(move |table| (sheet, table)))
+                .map(|(sheet, table)| LspTable {
+                    name: table.name.clone(),
+                    columns: table.headers.clone(),
+                    sheet: sheet.name.clone(),
+                    rect: table.rect,
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns an empty grid with the same sheet names as the real grid, for
+    /// checking formulas without access to the real grid.
+    fn syntax_check_grid(&self) -> Grid {
+        if self.sheets.is_empty() {
+            return Grid::new();
+        }
+        let mut grid = Grid::new_blank();
+        for name in &self.sheets {
+            let order = grid.end_order();
+            grid.add_sheet(Some(Sheet::new(SheetId::new(), name.clone(), order)));
+        }
+        grid
+    }
+
+    /// Returns completion items for sheet names and structured table
+    /// references.
+    fn completion_items(&self) -> impl '_ + Iterator<Item = CompletionItem> {
+        let sheet_items = self.sheets.iter().map(|name| {
+            let is_unquoted = lexer::UNQUOTED_SHEET_REFERENCE
+                .find(&format!("{name}!"))
+                .is_some_and(|m| m.len() == name.len() + 1);
+            let reference = if is_unquoted {
+                name.clone()
+            } else {
+                escape_string(name)
+            };
+            CompletionItem {
+                detail: Some("Sheet".to_string()),
+                insert_text: Some(format!("{reference}!")),
+                kind: CompletionItemKind::Module,
+                label: name.clone(),
+                ..Default::default()
+            }
+        });
+        let table_items = self.tables.iter().flat_map(|table| {
+            let table_item = CompletionItem {
+                detail: Some("Table".to_string()),
+                kind: CompletionItemKind::Struct,
+                label: table.name.clone(),
+                ..Default::default()
+            };
+            let column_items = table
+                .columns
+                .iter()
+                .filter(|column| !column.trim().is_empty())
+                .map(|column| CompletionItem {
+                    detail: Some(format!("Column in {}", table.name)),
+                    kind: CompletionItemKind::Field,
+                    label: format!("{}[{}]", table.name, column),
+                    ..Default::default()
+                });
+            std::iter::once(table_item).chain(column_items)
+        });
+        sheet_items.chain(table_items)
+    }
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -39,10 +162,17 @@ lazy_static! {
         .collect();
 }
 
-pub fn provide_completion_items() -> CompletionList<'static> {
-    CompletionList {
-        suggestions: &FUNCTION_COMPLETION_ITEMS,
-    }
+pub fn provide_completion_items(context: &LspContext) -> CompletionList<'static> {
+    let suggestions = if context.sheets.is_empty() && context.tables.is_empty() {
+        Cow::Borrowed(FUNCTION_COMPLETION_ITEMS.as_slice())
+    } else {
+        FUNCTION_COMPLETION_ITEMS
+            .iter()
+            .cloned()
+            .chain(context.completion_items())
+            .collect()
+    };
+    CompletionList { suggestions }
 }
 
 pub fn provide_hover(partial_function_name: &str) -> Option<Hover> {
@@ -53,3 +183,331 @@ pub fn provide_hover(partial_function_name: &str) -> Option<Hover> {
         }],
     })
 }
+
+/// Returns help for the innermost function call around byte offset `offset`,
+/// with the argument at `offset` highlighted.
+pub fn provide_signature_help(formula: &str, offset: usize) -> Option<SignatureHelp> {
+    // Stack of open function calls (with the index of the current argument)
+    // and other brackets.
+    let mut stack: Vec<Option<(&str, u32)>> = vec![];
+    for token in lexer::tokenize(formula) {
+        if token.span.end as usize > offset {
+            break;
+        }
+        match token.inner {
+            Token::FunctionCall => {
+                let name = token.span.of_str(formula).trim_end_matches('(').trim();
+                stack.push(Some((name, 0)));
+            }
+            Token::LParen | Token::LBrace => stack.push(None),
+            Token::RParen | Token::RBrace => {
+                stack.pop();
+            }
+            Token::ArgSep => {
+                if let Some(Some((_, arg_index))) = stack.last_mut() {
+                    *arg_index += 1;
+                }
+            }
+            _ => (),
+        }
+    }
+    let (name, arg_index) = stack.into_iter().rev().flatten().next()?;
+    let function = functions::lookup_function(name)?;
+
+    let label = function.usages_string();
+    let mut start = function.name.encode_utf16().count() as u32 + 1;
+    let parameters: Vec<ParameterInformation> = function
+        .usage
+        .split(", ")
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let end = start + param.encode_utf16().count() as u32;
+            let info = ParameterInformation {
+                label: [start, end],
+            };
+            start = end + 2;
+            info
+        })
+        .collect();
+
+    // Repeating parameters (eg, `[numbers...]`) stay highlighted for the rest
+    // of the arguments.
+    let active_parameter = if function.usage.ends_with("...]") {
+        arg_index.min(parameters.len().saturating_sub(1) as u32)
+    } else {
+        arg_index
+    };
+
+    Some(SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label,
+            documentation: Some(Documentation::Markdown(MarkdownString {
+                value: function.docs_string(),
+            })),
+            parameters,
+        }],
+        active_signature: 0,
+        active_parameter,
+    })
+}
+
+/// Returns diagnostics for a formula at `pos`: syntax errors, unknown
+/// functions, wrong argument counts, and references to missing sheets.
+pub fn provide_diagnostics(formula: &str, pos: Pos, context: &LspContext) -> Vec<MarkerData> {
+    let error = match parse_formula(formula, pos) {
+        Err(error) => error,
+        Ok(parsed) => {
+            let grid = context.syntax_check_grid();
+            let mut ctx = Ctx::new_for_syntax_check(&grid);
+            match parsed.eval(&mut ctx).into_non_error_value() {
+                Ok(_) => return vec![],
+                Err(error) => error,
+            }
+        }
+    };
+    let span = error.span.unwrap_or(Span {
+        start: 0,
+        end: formula.len() as u32,
+    });
+    let (start_line_number, start_column) = line_and_column(formula, span.start as usize);
+    let (end_line_number, end_column) = line_and_column(formula, span.end as usize);
+    vec![MarkerData {
+        severity: MarkerSeverity::Error,
+        message: error.msg.to_string(),
+        start_line_number,
+        start_column,
+        end_line_number,
+        end_column,
+    }]
+}
+
+/// Returns the cell and table references in a formula at `pos`, in the order
+/// they appear. Table references are resolved to the cells they refer to.
+pub fn find_references(formula: &str, pos: Pos, context: &LspContext) -> Vec<Spanned<RangeRef>> {
+    let mut references = super::find_cell_references(formula, pos);
+    for token in lexer::tokenize(formula) {
+        if token.inner != Token::TableRef {
+            continue;
+        }
+        let Some(table_ref) = TableRef::parse(token.span.of_str(formula)) else {
+            continue;
+        };
+        let table = match &table_ref.table {
+            Some(name) => context
+                .tables
+                .iter()
+                .find(|table| table.name.eq_ignore_ascii_case(name)),
+            // Without the formula's sheet, `[@Column]` is only resolved when
+            // a single table includes the formula's row.
+            None => context
+                .tables
+                .iter()
+                .filter(|table| table.rect.y_range().contains(&pos.y))
+                .exactly_one()
+                .ok(),
+        };
+        if let Some(range) = table.and_then(|table| table.resolve(&table_ref, pos)) {
+            references.push(Spanned {
+                span: token.span,
+                inner: range,
+            });
+        }
+    }
+    references.sort_by_key(|reference| reference.span.start);
+    references
+}
+
+/// Converts a byte offset in `s` to a UTF-16 offset, as used by JavaScript.
+pub fn utf16_offset(s: &str, byte_offset: usize) -> usize {
+    s[..byte_offset.min(s.len())].encode_utf16().count()
+}
+
+/// Converts a UTF-16 offset, as used by JavaScript, to a byte offset in `s`.
+pub fn byte_offset(s: &str, utf16_offset: usize) -> usize {
+    let mut utf16_count = 0;
+    for (i, c) in s.char_indices() {
+        if utf16_count >= utf16_offset {
+            return i;
+        }
+        utf16_count += c.len_utf16();
+    }
+    s.len()
+}
+
+/// Returns the 1-indexed line number and (UTF-16) column of a byte offset in
+/// `s`, as used by Monaco.
+fn line_and_column(s: &str, byte_offset: usize) -> (u32, u32) {
+    let before = &s[..byte_offset.min(s.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line_number = before.matches('\n').count() + 1;
+    let column = utf16_offset(&before[line_start..], usize::MAX) + 1;
+    (line_number as u32, column as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::parallel;
+
+    #[test]
+    #[parallel]
+    fn test_table_completion_items() {
+        let no_tables = provide_completion_items(&LspContext::default());
+        assert!(matches!(no_tables.suggestions, Cow::Borrowed(_)));
+
+        let context = LspContext {
+            sheets: vec![],
+            tables: vec![LspTable {
+                name: "Table1".into(),
+                columns: vec!["Amount".into(), "".into()],
+                ..Default::default()
+            }],
+        };
+        let list = provide_completion_items(&context);
+        let labels = list
+            .suggestions
+            .iter()
+            .filter(|item| item.kind != CompletionItemKind::Function)
+            .map(|item| item.label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec!["Table1", "Table1[Amount]"]);
+        assert_eq!(
+            list.suggestions.len(),
+            no_tables.suggestions.len() + labels.len()
+        );
+    }
+
+    #[test]
+    #[parallel]
+    fn test_grid_table_completes() {
+        let mut grid = Grid::new();
+        let sheet = &mut grid.sheets_mut()[0];
+        sheet.data_tables.set(DataTable::new(
+            "Sales".into(),
+            Rect::new(0, 0, 1, 3),
+            vec!["Name".into(), "Amount".into()],
+        ));
+        let sheet_name = sheet.name.clone();
+
+        // the context is serialized by core and deserialized by the client
+        let json = serde_json::to_string(&LspContext::new(&grid)).unwrap();
+        let context = serde_json::from_str::<LspContext>(&json).unwrap();
+        assert_eq!(context.sheets, vec![sheet_name]);
+
+        let list = provide_completion_items(&context);
+        let labels = list
+            .suggestions
+            .iter()
+            .filter(|item| {
+                matches!(
+                    item.kind,
+                    CompletionItemKind::Struct | CompletionItemKind::Field
+                )
+            })
+            .map(|item| item.label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec!["Sales", "Sales[Name]", "Sales[Amount]"]);
+    }
+
+    #[test]
+    #[parallel]
+    fn test_sheet_completion_items() {
+        let context = LspContext {
+            sheets: vec!["Sheet1".into(), "My Sheet".into()],
+            tables: vec![],
+        };
+        let list = provide_completion_items(&context);
+        let inserts = list
+            .suggestions
+            .iter()
+            .filter(|item| item.kind == CompletionItemKind::Module)
+            .map(|item| item.insert_text.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(inserts, vec!["Sheet1!", "\"My Sheet\"!"]);
+    }
+
+    #[test]
+    #[parallel]
+    fn test_signature_help() {
+        let formula = "IF(A1 > 0, SUM(1, 2, ";
+        let help = provide_signature_help(formula, formula.len()).unwrap();
+        let signature = &help.signatures[0];
+        assert_eq!(signature.label, "SUM([numbers...])");
+        assert_eq!(signature.parameters.len(), 1);
+        assert_eq!(signature.parameters[0].label, [4, 16]);
+        // repeating parameters stay highlighted
+        assert_eq!(help.active_parameter, 0);
+
+        let help = provide_signature_help(formula, "IF(A1 > 0, ".len()).unwrap();
+        let signature = &help.signatures[0];
+        assert_eq!(signature.label, "IF(condition, t, f)");
+        assert_eq!(help.active_parameter, 1);
+        let [start, end] = signature.parameters[1].label;
+        assert_eq!(&signature.label[start as usize..end as usize], "t");
+
+        // nested parentheses don't count as a function's arguments
+        let formula = "IF((1, 2), ";
+        let help = provide_signature_help(formula, formula.len()).unwrap();
+        assert_eq!(help.active_parameter, 1);
+
+        assert!(provide_signature_help("1 + 2", 5).is_none());
+        assert!(provide_signature_help("NOTAFUNCTION(", 13).is_none());
+    }
+
+    #[test]
+    #[parallel]
+    fn test_diagnostics() {
+        let context = LspContext {
+            sheets: vec!["Sheet1".into()],
+            tables: vec![],
+        };
+        let diagnostics = |formula: &str| provide_diagnostics(formula, Pos::ORIGIN, &context);
+
+        assert_eq!(diagnostics("SUM(A1, Sheet1!B2)"), vec![]);
+
+        let unknown_function = diagnostics("1 +\n  FOO(1)");
+        assert_eq!(unknown_function.len(), 1);
+        assert_eq!(unknown_function[0].start_line_number, 2);
+        assert_eq!(unknown_function[0].start_column, 3);
+
+        assert_eq!(diagnostics("NOT(1, 2)").len(), 1);
+        assert_eq!(diagnostics("SUM(Missing!A1)").len(), 1);
+        assert_eq!(diagnostics("SUM(1,").len(), 1);
+    }
+
+    #[test]
+    #[parallel]
+    fn test_find_references() {
+        let context = LspContext {
+            sheets: vec!["Sheet1".into()],
+            tables: vec![LspTable {
+                name: "Sales".into(),
+                columns: vec!["Name".into(), "Amount".into()],
+                sheet: "Sheet1".into(),
+                rect: Rect::new(0, 0, 1, 3),
+            }],
+        };
+        let formula = "SUM(Sales[Amount]) + A1";
+        let references = find_references(formula, Pos::ORIGIN, &context);
+        assert_eq!(references.len(), 2);
+        assert_eq!(references[0].span.of_str(formula), "Sales[Amount]");
+        assert_eq!(
+            references[0].inner,
+            RangeRef::CellRange {
+                start: CellRef::absolute(Some("Sheet1".into()), Pos { x: 1, y: 1 }),
+                end: CellRef::absolute(Some("Sheet1".into()), Pos { x: 1, y: 3 }),
+            }
+        );
+        assert_eq!(references[1].span.of_str(formula), "A1");
+    }
+
+    #[test]
+    #[parallel]
+    fn test_offsets() {
+        let s = "\"é\" & 😀";
+        assert_eq!(utf16_offset(s, s.len()), 8);
+        assert_eq!(byte_offset(s, 8), s.len());
+        assert_eq!(byte_offset(s, 2), 3);
+        assert_eq!(line_and_column("a\nbc", 4), (2, 3));
+    }
+}
@@ -82,3 +82,48 @@ impl CompletionItemInsertTextRule {
     pub const KEEP_WHITESPACE: Self = Self(1);
     pub const INSERT_AS_SNIPPET: Self = Self(4);
 }
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureHelp {
+    pub signatures: Vec<SignatureInformation>,
+    pub active_signature: u32,
+    pub active_parameter: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureInformation {
+    pub label: String,
+    pub documentation: Option<Documentation>,
+    pub parameters: Vec<ParameterInformation>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ParameterInformation {
+    /// Start and end (UTF-16) offsets of the parameter in the signature label.
+    pub label: [u32; 2],
+}
+
+/// Diagnostic shown in the editor. Monaco calls this `IMarkerData`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkerData {
+    pub severity: MarkerSeverity,
+    pub message: String,
+    pub start_line_number: u32,
+    pub start_column: u32,
+    pub end_line_number: u32,
+    pub end_column: u32,
+}
+
+#[derive(Serialize_repr, Deserialize_repr, Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum MarkerSeverity {
+    Hint = 1,
+    Info = 2,
+    Warning = 4,
+    #[default]
+    Error = 8,
+}
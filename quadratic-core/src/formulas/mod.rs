@@ -2,12 +2,14 @@ pub mod ast;
 mod cell_ref;
 mod criteria;
 mod ctx;
+mod format;
 #[allow(clippy::vec_init_then_push)]
 pub mod functions;
 mod lexer;
 pub mod lsp;
 mod params;
 mod parser;
+mod table_ref;
 mod wildcards;
 
 #[cfg(test)]
@@ -18,12 +20,14 @@ pub use ast::Formula;
 pub use cell_ref::*;
 pub use criteria::Criterion;
 pub use ctx::Ctx;
+pub use format::{format_formula, FormatOptions};
 use functions::FormulaFnArgs;
 use params::{Param, ParamKind};
 pub use parser::{
     find_cell_references, parse_and_check_formula, parse_formula, replace_a1_notation,
     replace_internal_cell_references,
 };
+pub use table_ref::*;
 use wildcards::wildcard_pattern_to_regex;
 
 /// Escapes a formula string.
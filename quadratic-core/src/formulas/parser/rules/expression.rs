@@ -62,7 +62,7 @@ impl OpPrecedence {
     pub fn prefix_ops(self) -> &'static [Token] {
         use Token::*;
         match self {
-            Self::Prefix => &[Plus, Minus],
+            Self::Prefix => &[Plus, Minus, ImplicitIntersection],
             _ => &[],
         }
     }
@@ -175,7 +175,7 @@ impl SyntaxRule for ExpressionWithPrecedence {
 
                 Token::Eql | Token::Neq | Token::Lt | Token::Gt | Token::Lte | Token::Gte => false,
 
-                Token::Plus | Token::Minus => true,
+                Token::Plus | Token::Minus | Token::ImplicitIntersection => true,
 
                 Token::Mult
                 | Token::Div
@@ -197,7 +197,8 @@ impl SyntaxRule for ExpressionWithPrecedence {
                 | Token::UnterminatedStringLiteral
                 | Token::NumericLiteral
                 | Token::CellRef
-                | Token::InternalCellRef => true,
+                | Token::InternalCellRef
+                | Token::TableRef => true,
 
                 Token::Whitespace => false,
                 Token::Unknown => false,
@@ -215,6 +216,7 @@ impl SyntaxRule for ExpressionWithPrecedence {
                 [
                     FunctionCall.map(Some),
                     CellReferenceExpression.map(Some),
+                    TableReferenceExpression.map(Some),
                     StringLiteralExpression.map(Some),
                     NumericLiteral.map(Some),
                     ArrayLiteral.map(Some),
@@ -313,9 +315,16 @@ fn parse_prefix_ops(p: &mut Parser<'_>, precedence: OpPrecedence) -> CodeResult<
             break;
         }
     }
-    let mut ret = p.parse(ExpressionWithPrecedence(precedence.next()))?;
+    // `@` applies to a whole cell range, such as `@A1:A10`, so its operand
+    // may include the cell range operator.
+    let operand_precedence = if ops.iter().any(|op| op.inner == "@") {
+        OpPrecedence::CellRange
+    } else {
+        precedence.next()
+    };
+    let mut ret = p.parse(ExpressionWithPrecedence(operand_precedence))?;
     // Now pop the operators off the list from right to left.
-    for op in ops {
+    for op in ops.into_iter().rev() {
         ret = AstNode {
             span: Span::merge(op.span, ret.span),
             inner: ast::AstNodeContents::FunctionCall {
@@ -410,6 +419,29 @@ impl SyntaxRule for CellReferenceExpression {
     }
 }
 
+/// Matches a structured reference to a data table.
+#[derive(Debug, Copy, Clone)]
+pub struct TableReferenceExpression;
+impl_display!(for TableReferenceExpression, "table reference such as 'Table1[Amount]' or '[@Amount]'");
+impl SyntaxRule for TableReferenceExpression {
+    type Output = AstNode;
+
+    fn prefix_matches(&self, mut p: Parser<'_>) -> bool {
+        p.next() == Some(Token::TableRef)
+    }
+    fn consume_match(&self, p: &mut Parser<'_>) -> CodeResult<Self::Output> {
+        if p.next() != Some(Token::TableRef) {
+            return p.expected(self);
+        }
+        let table_ref = TableRef::parse(p.token_str())
+            .ok_or_else(|| RunErrorMsg::BadCellReference.with_span(p.span()))?;
+        Ok(AstNode {
+            span: p.span(),
+            inner: ast::AstNodeContents::TableRef(table_ref),
+        })
+    }
+}
+
 /// Matches a pair of parentheses containing an expression.
 #[derive(Debug, Copy, Clone)]
 pub struct ParenExpression;
@@ -0,0 +1,123 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Structured reference to part of a named data table, such as
+/// `Table1[Amount]`, `Table1[#Headers]`, or `[@Amount]`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TableRef {
+    /// Name of the table. If this is `None`, the reference is to the table
+    /// containing the formula's row (eg, `[@Amount]`).
+    pub table: Option<String>,
+    pub spec: TableSpecifier,
+}
+
+/// Which part of a table a structured reference refers to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TableSpecifier {
+    /// Header row and data rows (`[#All]`).
+    All,
+    /// Data rows only (`[]` or `[#Data]`).
+    Data,
+    /// Header row only (`[#Headers]`).
+    Headers,
+    /// Data rows of a single column (`[Amount]`).
+    Column(String),
+    /// The cell in a column in the same row as the formula (`[@Amount]`).
+    ThisRow(String),
+}
+
+impl fmt::Display for TableRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(table) = &self.table {
+            write!(f, "{table}")?;
+        }
+        match &self.spec {
+            TableSpecifier::All => write!(f, "[#All]"),
+            TableSpecifier::Data => write!(f, "[#Data]"),
+            TableSpecifier::Headers => write!(f, "[#Headers]"),
+            TableSpecifier::Column(column) => write!(f, "[{column}]"),
+            TableSpecifier::ThisRow(column) => write!(f, "[@{column}]"),
+        }
+    }
+}
+
+impl TableRef {
+    /// Parses a structured table reference such as `Table1[Amount]`. Returns
+    /// `None` if the string is not a valid table reference.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (table, rest) = s.split_once('[')?;
+        let inner = rest.strip_suffix(']')?.trim();
+        let table = table.trim();
+        let table = (!table.is_empty()).then(|| table.to_string());
+
+        let spec = match inner {
+            "" => TableSpecifier::Data,
+            s if s.eq_ignore_ascii_case("#All") => TableSpecifier::All,
+            s if s.eq_ignore_ascii_case("#Data") => TableSpecifier::Data,
+            s if s.eq_ignore_ascii_case("#Headers") => TableSpecifier::Headers,
+            s if s.starts_with('#') => return None,
+            s => match s.strip_prefix('@') {
+                Some(column) => TableSpecifier::ThisRow(column.trim().to_string()),
+                None => TableSpecifier::Column(s.to_string()),
+            },
+        };
+
+        // `[@Amount]` may omit the table name, but nothing else may.
+        if table.is_none() && !matches!(spec, TableSpecifier::ThisRow(_)) {
+            return None;
+        }
+
+        Some(TableRef { table, spec })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::parallel;
+
+    #[test]
+    #[parallel]
+    fn test_parse_table_ref() {
+        let table = Some("Table1".to_string());
+        let cases = [
+            ("Table1[]", TableSpecifier::Data),
+            ("Table1[#Data]", TableSpecifier::Data),
+            ("Table1[#all]", TableSpecifier::All),
+            ("Table1[#Headers]", TableSpecifier::Headers),
+            ("Table1[Amount]", TableSpecifier::Column("Amount".into())),
+            (
+                "Table1[Unit Price]",
+                TableSpecifier::Column("Unit Price".into()),
+            ),
+            ("Table1[@Amount]", TableSpecifier::ThisRow("Amount".into())),
+        ];
+        for (s, spec) in cases {
+            let expected = TableRef {
+                table: table.clone(),
+                spec,
+            };
+            assert_eq!(TableRef::parse(s), Some(expected), "parsing {s}");
+        }
+
+        assert_eq!(
+            TableRef::parse("[@Amount]"),
+            Some(TableRef {
+                table: None,
+                spec: TableSpecifier::ThisRow("Amount".into()),
+            })
+        );
+        assert_eq!(TableRef::parse("[Amount]"), None);
+        assert_eq!(TableRef::parse("Table1[#Totals]"), None);
+        assert_eq!(TableRef::parse("Table1"), None);
+    }
+
+    #[test]
+    #[parallel]
+    fn test_table_ref_display() {
+        for s in ["Table1[#All]", "Table1[Amount]", "[@Amount]", "T[#Headers]"] {
+            assert_eq!(TableRef::parse(s).unwrap().to_string(), s);
+        }
+    }
+}
@@ -177,11 +177,94 @@ fn test_formula_array_op() {
         eval(&g, "B1:C4 * D1:E4"),
     );
     assert_eq!(
-        "Array height mismatch: expected value with 1 row or 4 rows, got 5 rows",
-        eval_to_err(&g, "B1:C4 * D1:E5").msg.to_string(),
+        "{341, 861; 384, 924; 429, 989; 476, 1056; \
+         Value not available, Value not available}",
+        eval_to_string(&g, "B1:C4 * D1:E5"),
     );
 }
 
+#[test]
+#[parallel]
+fn test_formula_array_broadcasting() {
+    let g = Grid::new();
+
+    // a row and a column expand to fill a rectangle
+    assert_eq!(
+        "{11, 12, 13; 21, 22, 23}",
+        eval_to_string(&g, "{1, 2, 3} + {10; 20}"),
+    );
+    assert_eq!("{1, 2; 2, 4}", eval_to_string(&g, "{1; 2} * {1, 2}"));
+
+    // missing values are `#N/A`
+    assert_eq!(
+        "{11, 22, Value not available}",
+        eval_to_string(&g, "{1, 2} + {10, 20, 30}"),
+    );
+    assert_eq!(
+        "{11, 22, Value not available; 13, 24, Value not available}",
+        eval_to_string(&g, "{1, 2; 3, 4} + {10, 20, 30}"),
+    );
+    expect_err(&RunErrorMsg::NotAvailable, &g, "SUM({1, 2} + {10, 20, 30})");
+    assert_eq!(
+        "na",
+        eval_to_string(&g, "IFERROR(INDEX({1, 2} + {10, 20, 30}, 1, 3), \"na\")"),
+    );
+
+    // a division by zero is an error in its own cell, next to the padded
+    // `#N/A` cell
+    assert_eq!(
+        "{Divide by zero, 2, Value not available}",
+        eval_to_string(&g, "{1, 2} / {0, 1, 2}"),
+    );
+}
+
+#[test]
+#[parallel]
+fn test_formula_implicit_intersection() {
+    let mut g = Grid::new();
+    let sheet_id = g.sheets()[0].id;
+    for y in 1..=5 {
+        g.sheets_mut()[0].set_cell_value(Pos { x: 0, y }, y * 10);
+    }
+    for x in 2..=4 {
+        g.sheets_mut()[0].set_cell_value(Pos { x, y: 0 }, x * 100);
+    }
+
+    // same row as the formula
+    let pos = Pos { x: 5, y: 3 }.to_sheet_pos(sheet_id);
+    assert_eq!("30", eval_to_string_at(&g, pos, "@$A$1:$A$5"));
+    assert_eq!("30", eval_to_string_at(&g, pos, "SINGLE($A$1:$A$5)"));
+    assert_eq!("30", eval_to_string_at(&g, pos, "_xlfn.SINGLE($A$1:$A$5)"));
+    assert_eq!("60", eval_to_string_at(&g, pos, "@$A$1:$A$5 * 2"));
+    assert_eq!("-30", eval_to_string_at(&g, pos, "-@$A$1:$A$5"));
+
+    // same column as the formula
+    let pos = Pos { x: 3, y: 7 }.to_sheet_pos(sheet_id);
+    assert_eq!("300", eval_to_string_at(&g, pos, "@$C$0:$E$0"));
+
+    // single cell
+    assert_eq!("20", eval_to_string_at(&g, pos, "@$A$2"));
+
+    // no intersection
+    assert_eq!("Invalid argument", eval_to_string_at(&g, pos, "@$A$1:$A$5"),);
+
+    // arrays use the top-left value
+    assert_eq!("1", eval_to_string(&g, "@{1, 2, 3}"));
+    assert_eq!("1", eval_to_string(&g, "SINGLE({1, 2; 3, 4})"));
+
+    expect_err(
+        &RunErrorMsg::MissingRequiredArgument {
+            func_name: "SINGLE".into(),
+            arg_name: "value".into(),
+        },
+        &g,
+        "SINGLE()",
+    );
+
+    assert_check_syntax_succeeds(&g, "@A1:A10");
+    assert_check_syntax_succeeds(&g, "SUM(@A1:A10, 1)");
+}
+
 #[test]
 #[parallel]
 fn test_array_parsing() {
@@ -390,6 +473,57 @@ fn test_sheet_references() {
     );
 }
 
+#[test]
+#[parallel]
+fn test_table_references() {
+    use crate::grid::sheet::data_tables::DataTable;
+    use crate::Rect;
+
+    let mut g = Grid::new();
+    let sheet = &mut g.sheets_mut()[0];
+    let _ = sheet.set_cell_value(pos![A1], "Name");
+    let _ = sheet.set_cell_value(pos![B1], "Amount");
+    for (i, (name, amount)) in [("a", 1), ("b", 2), ("c", 3)].into_iter().enumerate() {
+        let _ = sheet.set_cell_value(
+            Pos {
+                x: 0,
+                y: 2 + i as i64,
+            },
+            name,
+        );
+        let _ = sheet.set_cell_value(
+            Pos {
+                x: 1,
+                y: 2 + i as i64,
+            },
+            amount,
+        );
+    }
+    sheet.data_tables.set(DataTable::new(
+        "Sales".into(),
+        Rect::new(0, 1, 1, 4),
+        vec!["Name".into(), "Amount".into()],
+    ));
+    let sheet_id = sheet.id;
+
+    assert_eq!("6", eval_to_string(&g, "SUM(Sales[Amount])"));
+    assert_eq!("6", eval_to_string(&g, "SUM(sales[amount])"));
+    assert_eq!("{Name, Amount}", eval_to_string(&g, "Sales[#Headers]"));
+    assert_eq!("6", eval_to_string(&g, "COUNTA(Sales[])"));
+    assert_eq!("8", eval_to_string(&g, "COUNTA(Sales[#All])"));
+
+    // `[@Column]` refers to the formula's row
+    let pos = pos![C3].to_sheet_pos(sheet_id);
+    assert_eq!("20", eval_to_string_at(&g, pos, "SUM([@Amount]) * 10"));
+    assert_eq!("{2}", eval_to_string_at(&g, pos, "Sales[@Amount]"));
+
+    expect_err(&RunErrorMsg::BadCellReference, &g, "Sales[Missing]");
+    expect_err(&RunErrorMsg::BadCellReference, &g, "Missing[Amount]");
+    expect_err(&RunErrorMsg::BadCellReference, &g, "[@Amount]");
+
+    assert_check_syntax_succeeds(&Grid::new(), "SUM(Sales[Amount])");
+}
+
 #[test]
 fn test_cell_range_op_errors() {
     let g = Grid::new();
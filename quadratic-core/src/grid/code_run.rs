@@ -5,7 +5,8 @@
 //! performed yet).
 
 use crate::{ArraySize, CellValue, Pos, Rect, RunError, RunErrorMsg, SheetPos, SheetRect, Value};
-use chrono::{DateTime, Utc};
+use bigdecimal::ToPrimitive;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use strum_macros::Display;
@@ -25,6 +26,11 @@ pub struct CodeRun {
     pub line_number: Option<u32>,
     pub output_type: Option<String>,
     pub last_modified: DateTime<Utc>,
+
+    /// Whether the code calls a volatile function (eg, `RAND()`) and must be
+    /// rerun at the end of every user transaction.
+    #[serde(default)]
+    pub volatile: bool,
 }
 
 impl CodeRun {
@@ -140,6 +146,9 @@ pub enum CodeCellLanguage {
 pub enum ConnectionKind {
     Postgres,
     Mysql,
+    Sqlite,
+    Duckdb,
+    Mssql,
 }
 
 impl wasm_bindgen::describe::WasmDescribe for ConnectionKind {
@@ -158,6 +167,43 @@ impl wasm_bindgen::convert::IntoWasmAbi for ConnectionKind {
     }
 }
 
+/// A value bound to a placeholder in a connection's query.  Cell references
+/// in the query, eg, `{{A1}}`, are replaced by placeholders and sent as
+/// parameters so cell values are never interpolated into the SQL.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "js", derive(ts_rs::TS))]
+#[serde(tag = "type", content = "value")]
+pub enum SqlParameter {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    Text(String),
+    /// A date and time, without a timezone
+    Timestamp(#[cfg_attr(feature = "js", ts(type = "string"))] NaiveDateTime),
+}
+
+impl From<CellValue> for SqlParameter {
+    fn from(value: CellValue) -> Self {
+        match value {
+            CellValue::Blank => SqlParameter::Null,
+            CellValue::Logical(b) => SqlParameter::Boolean(b),
+            CellValue::Number(n) => match n.to_i64() {
+                Some(i) if n.is_integer() => SqlParameter::Integer(i),
+                _ => n.to_f64().map_or(SqlParameter::Null, SqlParameter::Number),
+            },
+            CellValue::Text(s) => SqlParameter::Text(s),
+            CellValue::Instant(instant) => {
+                DateTime::from_timestamp_micros((instant.seconds * 1_000_000.0) as i64)
+                    .map_or(SqlParameter::Null, |datetime| {
+                        SqlParameter::Timestamp(datetime.naive_utc())
+                    })
+            }
+            other => SqlParameter::Text(other.to_display()),
+        }
+    }
+}
+
 /// Custom version of [`std::result::Result`] that serializes the way we want.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(untagged)]
@@ -202,6 +248,7 @@ mod test {
             line_number: None,
             output_type: None,
             spill_error: false,
+            volatile: false,
             last_modified: Utc::now(),
         };
         assert_eq!(code_run.output_size(), ArraySize::_1X1);
@@ -229,6 +276,7 @@ mod test {
             line_number: None,
             output_type: None,
             spill_error: false,
+            volatile: false,
             last_modified: Utc::now(),
         };
         assert_eq!(code_run.output_size().w.get(), 10);
@@ -262,6 +310,7 @@ mod test {
             line_number: None,
             output_type: None,
             spill_error: true,
+            volatile: false,
             last_modified: Utc::now(),
         };
         assert_eq!(code_run.output_size().w.get(), 10);
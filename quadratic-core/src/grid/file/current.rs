@@ -14,6 +14,9 @@ use crate::grid::file::v1_6::schema::{self as current};
 use crate::grid::formats::format::Format;
 use crate::grid::formatting::RenderSize;
 use crate::grid::resize::{Resize, ResizeMap};
+use crate::grid::sheet::comments::{Comment, CommentThread, CommentThreads};
+use crate::grid::sheet::data_tables::{DataTable, DataTables};
+use crate::grid::sheet::protections::{Protection, Protections};
 use crate::grid::{
     generate_borders, set_rect_borders, BorderSelection, BorderStyle, CellAlign, CellBorderLine,
     CellVerticalAlign, CellWrap, CodeRun, CodeRunResult, Column, ColumnData, Grid, GridBounds,
@@ -262,6 +265,7 @@ fn import_code_cell_builder(sheet: &current::Sheet) -> Result<IndexMap<Pos, Code
                 std_out: code_run.std_out.to_owned(),
                 std_err: code_run.std_err.to_owned(),
                 spill_error: code_run.spill_error,
+                volatile: code_run.volatile,
                 cells_accessed,
                 result,
                 return_type: code_run.return_type.to_owned(),
@@ -340,6 +344,63 @@ pub fn import_rows_size(row_sizes: &[(i64, current::Resize)]) -> Result<ResizeMa
         })
 }
 
+fn import_data_tables(data_tables: &[current::DataTable]) -> DataTables {
+    DataTables {
+        tables: data_tables
+            .iter()
+            .map(|table| {
+                DataTable::new(
+                    table.name.clone(),
+                    Rect::from(&table.rect),
+                    table.headers.clone(),
+                )
+            })
+            .collect(),
+    }
+}
+
+fn import_comments(comments: &[current::CommentThread]) -> CommentThreads {
+    CommentThreads {
+        threads: comments
+            .iter()
+            .map(|thread| CommentThread {
+                id: thread.id,
+                pos: Pos {
+                    x: thread.pos.x,
+                    y: thread.pos.y,
+                },
+                resolved: thread.resolved,
+                comments: thread
+                    .comments
+                    .iter()
+                    .map(|comment| Comment {
+                        id: comment.id,
+                        author: comment.author.clone(),
+                        body: comment.body.clone(),
+                        mentions: comment.mentions.clone(),
+                        created: comment.created,
+                        modified: comment.modified,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+fn import_protections(protections: &[current::Protection]) -> Protections {
+    Protections {
+        protections: protections
+            .iter()
+            .map(|protection| Protection {
+                id: protection.id,
+                rect: protection.rect.as_ref().map(Rect::from),
+                users: protection.users.clone(),
+                roles: protection.roles.clone(),
+            })
+            .collect(),
+    }
+}
+
 pub fn import_sheet(sheet: current::Sheet) -> Result<Sheet> {
     let mut new_sheet = Sheet {
         id: SheetId::from_str(&sheet.id.id)?,
@@ -362,6 +423,9 @@ pub fn import_sheet(sheet: current::Sheet) -> Result<Sheet> {
         formats_rows: import_formats(&sheet.formats_rows),
 
         validations: import_validations(&sheet.validations),
+        data_tables: import_data_tables(&sheet.data_tables),
+        comments: import_comments(&sheet.comments),
+        protections: import_protections(&sheet.protections),
         rows_resize: import_rows_size(&sheet.rows_resize)?,
     };
     new_sheet.recalculate_bounds();
@@ -725,6 +789,7 @@ pub fn export_rows_code_runs(sheet: &Sheet) -> Vec<(current::Pos, current::CodeR
                     std_out: code_run.std_out.clone(),
                     std_err: code_run.std_err.clone(),
                     spill_error: code_run.spill_error,
+                    volatile: code_run.volatile,
                     cells_accessed: code_run
                         .cells_accessed
                         .iter()
@@ -740,6 +805,52 @@ pub fn export_rows_code_runs(sheet: &Sheet) -> Vec<(current::Pos, current::CodeR
         .collect()
 }
 
+fn export_data_tables(data_tables: &DataTables) -> Vec<current::DataTable> {
+    data_tables
+        .iter()
+        .map(|table| current::DataTable {
+            name: table.name.clone(),
+            rect: current::Rect::from(&table.rect),
+            headers: table.headers.clone(),
+        })
+        .collect()
+}
+
+fn export_comments(comments: &CommentThreads) -> Vec<current::CommentThread> {
+    comments
+        .iter()
+        .map(|thread| current::CommentThread {
+            id: thread.id,
+            pos: current::Pos::from(thread.pos),
+            resolved: thread.resolved,
+            comments: thread
+                .comments
+                .iter()
+                .map(|comment| current::Comment {
+                    id: comment.id,
+                    author: comment.author.clone(),
+                    body: comment.body.clone(),
+                    mentions: comment.mentions.clone(),
+                    created: comment.created,
+                    modified: comment.modified,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+fn export_protections(protections: &Protections) -> Vec<current::Protection> {
+    protections
+        .iter()
+        .map(|protection| current::Protection {
+            id: protection.id,
+            rect: protection.rect.as_ref().map(current::Rect::from),
+            users: protection.users.clone(),
+            roles: protection.roles.clone(),
+        })
+        .collect()
+}
+
 pub(crate) fn export_sheet(sheet: Sheet) -> current::Sheet {
     current::Sheet {
         id: current::Id {
@@ -754,6 +865,9 @@ pub(crate) fn export_sheet(sheet: Sheet) -> current::Sheet {
         formats_columns: export_formats(&sheet.formats_columns),
         formats_rows: export_formats(&sheet.formats_rows),
         validations: export_validations(&sheet.validations),
+        data_tables: export_data_tables(&sheet.data_tables),
+        comments: export_comments(&sheet.comments),
+        protections: export_protections(&sheet.protections),
         rows_resize: export_rows_size(&sheet),
         code_runs: export_rows_code_runs(&sheet),
         columns: export_column_builder(sheet),
@@ -1,8 +1,9 @@
 use super::current;
 use super::v1_6;
-use crate::grid::Sheet;
+use crate::grid::{Sheet, SheetId};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 /// Used to serialize a Sheet for use in Operation::AddSheetSchema.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -17,6 +18,13 @@ impl SheetSchema {
             SheetSchema::V1_6(sheet) => current::import_sheet(sheet),
         }
     }
+
+    /// The id of the sheet, or None if it isn't valid.
+    pub fn sheet_id(&self) -> Option<SheetId> {
+        match self {
+            SheetSchema::V1_6(sheet) => SheetId::from_str(&sheet.id.id).ok(),
+        }
+    }
 }
 
 /// Exports a Sheet to the latest schema version.
@@ -262,6 +262,7 @@ fn upgrade_code_runs(sheet: &v1_5::Sheet) -> Vec<(v1_6::Pos, v1_6::CodeRun)> {
                     std_err: old_code_run.std_err.clone(),
                     std_out: old_code_run.std_out.clone(),
                     spill_error: old_code_run.spill_error,
+                    volatile: false,
                     cells_accessed: old_code_run.cells_accessed.clone(),
                     return_type: old_code_run.return_type.clone(),
                     line_number: old_code_run.line_number,
@@ -382,6 +383,9 @@ fn upgrade_sheet(sheet: &v1_5::Sheet) -> v1_6::Sheet {
         formats_rows: vec![],
         rows_resize: vec![],
         validations: Validations::default(),
+        data_tables: vec![],
+        comments: vec![],
+        protections: vec![],
     }
 }
 
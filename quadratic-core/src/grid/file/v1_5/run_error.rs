@@ -26,6 +26,10 @@ pub enum RunErrorMsg {
     Unimplemented(Cow<'static, str>),
     UnknownError,
     InternalError(Cow<'static, str>),
+    Cancelled,
+    TooManyParameters {
+        max: usize,
+    },
 
     // Compile errors
     Unterminated(Cow<'static, str>),
@@ -76,6 +80,7 @@ pub enum RunErrorMsg {
     Infinity,
     IndexOutOfBounds,
     NoMatch,
+    NotAvailable,
     InvalidArgument,
 }
 
@@ -94,6 +99,10 @@ impl RunError {
                 crate::RunErrorMsg::Unimplemented(str) => RunErrorMsg::Unimplemented(str),
                 crate::RunErrorMsg::UnknownError => RunErrorMsg::UnknownError,
                 crate::RunErrorMsg::InternalError(str) => RunErrorMsg::InternalError(str),
+                crate::RunErrorMsg::Cancelled => RunErrorMsg::Cancelled,
+                crate::RunErrorMsg::TooManyParameters { max } => {
+                    RunErrorMsg::TooManyParameters { max }
+                }
 
                 // Compile errors
                 crate::RunErrorMsg::Unterminated(str) => RunErrorMsg::Unterminated(str),
@@ -170,6 +179,7 @@ impl RunError {
                 crate::RunErrorMsg::Infinity => RunErrorMsg::Infinity,
                 crate::RunErrorMsg::IndexOutOfBounds => RunErrorMsg::IndexOutOfBounds,
                 crate::RunErrorMsg::NoMatch => RunErrorMsg::NoMatch,
+                crate::RunErrorMsg::NotAvailable => RunErrorMsg::NotAvailable,
                 crate::RunErrorMsg::InvalidArgument => RunErrorMsg::InvalidArgument,
             },
         }
@@ -189,6 +199,10 @@ impl From<RunError> for crate::RunError {
                 RunErrorMsg::Unimplemented(str) => crate::RunErrorMsg::Unimplemented(str),
                 RunErrorMsg::UnknownError => crate::RunErrorMsg::UnknownError,
                 RunErrorMsg::InternalError(str) => crate::RunErrorMsg::InternalError(str),
+                RunErrorMsg::Cancelled => crate::RunErrorMsg::Cancelled,
+                RunErrorMsg::TooManyParameters { max } => {
+                    crate::RunErrorMsg::TooManyParameters { max }
+                }
 
                 // Compile errors
                 RunErrorMsg::Unterminated(str) => crate::RunErrorMsg::Unterminated(str),
@@ -268,6 +282,7 @@ impl From<RunError> for crate::RunError {
                 RunErrorMsg::Infinity => crate::RunErrorMsg::Infinity,
                 RunErrorMsg::IndexOutOfBounds => crate::RunErrorMsg::IndexOutOfBounds,
                 RunErrorMsg::NoMatch => crate::RunErrorMsg::NoMatch,
+                RunErrorMsg::NotAvailable => crate::RunErrorMsg::NotAvailable,
                 RunErrorMsg::InvalidArgument => crate::RunErrorMsg::InvalidArgument,
             },
         }
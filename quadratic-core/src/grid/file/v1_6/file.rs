@@ -23,6 +23,9 @@ pub fn export_cell_value(cell_value: CellValue) -> current::CellValue {
                         kind: match kind {
                             ConnectionKind::Postgres => current::ConnectionKind::Postgres,
                             ConnectionKind::Mysql => current::ConnectionKind::Mysql,
+                            ConnectionKind::Sqlite => current::ConnectionKind::Sqlite,
+                            ConnectionKind::Duckdb => current::ConnectionKind::Duckdb,
+                            ConnectionKind::Mssql => current::ConnectionKind::Mssql,
                         },
                         id,
                     }
@@ -68,6 +71,9 @@ pub fn import_cell_value(value: &current::CellValue) -> CellValue {
                         kind: match kind {
                             current::ConnectionKind::Postgres => ConnectionKind::Postgres,
                             current::ConnectionKind::Mysql => ConnectionKind::Mysql,
+                            current::ConnectionKind::Sqlite => ConnectionKind::Sqlite,
+                            current::ConnectionKind::Duckdb => ConnectionKind::Duckdb,
+                            current::ConnectionKind::Mssql => ConnectionKind::Mssql,
                         },
                         id: id.clone(),
                     }
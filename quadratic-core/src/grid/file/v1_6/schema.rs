@@ -125,6 +125,55 @@ pub struct Sheet {
 
     #[serde(default)]
     pub validations: Validations,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub data_tables: Vec<DataTable>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub comments: Vec<CommentThread>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub protections: Vec<Protection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataTable {
+    pub name: String,
+    pub rect: Rect,
+    pub headers: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: Uuid,
+    pub author: String,
+    pub body: String,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub mentions: Vec<String>,
+
+    pub created: DateTime<Utc>,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommentThread {
+    pub id: Uuid,
+    pub pos: Pos,
+    pub resolved: bool,
+    pub comments: Vec<Comment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Protection {
+    pub id: Uuid,
+    pub rect: Option<Rect>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub users: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub roles: Vec<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -146,6 +195,9 @@ pub struct CodeRun {
     pub output_type: Option<String>,
     pub spill_error: bool,
     pub last_modified: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub volatile: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -250,6 +302,9 @@ pub enum CodeCellLanguage {
 pub enum ConnectionKind {
     Postgres,
     Mysql,
+    Sqlite,
+    Duckdb,
+    Mssql,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
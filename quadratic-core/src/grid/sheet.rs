@@ -2,7 +2,10 @@ use std::collections::{btree_map, BTreeMap, HashSet};
 use std::str::FromStr;
 
 use bigdecimal::{BigDecimal, RoundingMode};
+use comments::CommentThreads;
+use data_tables::DataTables;
 use indexmap::IndexMap;
+use protections::Protections;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use validations::Validations;
@@ -25,8 +28,11 @@ pub mod cell_array;
 pub mod cell_values;
 pub mod clipboard;
 pub mod code;
+pub mod comments;
+pub mod data_tables;
 pub mod formats;
 pub mod formatting;
+pub mod protections;
 pub mod rendering;
 pub mod row_resize;
 pub mod search;
@@ -79,6 +85,15 @@ pub struct Sheet {
     #[serde(default)]
     pub validations: Validations,
 
+    #[serde(default)]
+    pub data_tables: DataTables,
+
+    #[serde(default)]
+    pub comments: CommentThreads,
+
+    #[serde(default)]
+    pub protections: Protections,
+
     // bounds for the grid with only data
     pub(super) data_bounds: GridBounds,
 
@@ -112,6 +127,9 @@ impl Sheet {
             format_bounds: GridBounds::Empty,
 
             validations: Validations::default(),
+            data_tables: DataTables::default(),
+            comments: CommentThreads::default(),
+            protections: Protections::default(),
             rows_resize: ResizeMap::default(),
         }
     }
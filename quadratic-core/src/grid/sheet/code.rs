@@ -258,6 +258,7 @@ mod test {
             line_number: None,
             output_type: None,
             spill_error: false,
+            volatile: false,
         };
         let old = sheet.set_code_run(Pos { x: 0, y: 0 }, Some(code_run.clone()));
         assert_eq!(old, None);
@@ -282,6 +283,7 @@ mod test {
             line_number: None,
             output_type: None,
             spill_error: false,
+            volatile: false,
             last_modified: Utc::now(),
         };
         sheet.set_code_run(Pos { x: 0, y: 0 }, Some(code_run.clone()));
@@ -316,6 +318,7 @@ mod test {
             line_number: None,
             output_type: None,
             spill_error: false,
+            volatile: false,
             last_modified: Utc::now(),
         };
         sheet.set_code_run(Pos { x: 0, y: 0 }, Some(code_run.clone()));
@@ -407,6 +410,7 @@ mod test {
             line_number: None,
             output_type: None,
             spill_error: false,
+            volatile: false,
             last_modified: Utc::now(),
         };
         sheet.set_code_run(Pos { x: 0, y: 0 }, Some(code_run.clone()));
@@ -438,6 +442,7 @@ mod test {
             line_number: None,
             output_type: None,
             spill_error: false,
+            volatile: false,
             last_modified: Utc::now(),
         };
         sheet.set_code_run(Pos { x: 0, y: 0 }, Some(code_run.clone()));
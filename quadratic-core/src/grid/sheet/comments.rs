@@ -0,0 +1,220 @@
+//! Comment threads for a Sheet.
+//!
+//! A comment thread is anchored to a cell. It starts with a single comment and
+//! collects replies until it is resolved.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{Pos, Rect};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct Comment {
+    pub id: Uuid,
+
+    /// User id of the comment's author.
+    pub author: String,
+
+    pub body: String,
+
+    /// User ids of the users mentioned in the comment.
+    #[serde(default)]
+    pub mentions: Vec<String>,
+
+    #[ts(type = "string")]
+    pub created: DateTime<Utc>,
+
+    /// Time of the last edit, or None if the comment was never edited.
+    #[ts(type = "string | null")]
+    pub modified: Option<DateTime<Utc>>,
+}
+
+impl Comment {
+    pub fn new(author: String, body: String, mentions: Vec<String>) -> Self {
+        Comment {
+            id: Uuid::new_v4(),
+            author,
+            body,
+            mentions,
+            created: Utc::now(),
+            modified: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct CommentThread {
+    pub id: Uuid,
+    pub pos: Pos,
+    pub resolved: bool,
+
+    /// The comment that started the thread, followed by its replies in the
+    /// order they were created.
+    pub comments: Vec<Comment>,
+}
+
+impl CommentThread {
+    pub fn new(pos: Pos, comment: Comment) -> Self {
+        CommentThread {
+            id: Uuid::new_v4(),
+            pos,
+            resolved: false,
+            comments: vec![comment],
+        }
+    }
+
+    /// Gets a comment by its id.
+    pub fn comment(&self, comment_id: Uuid) -> Option<&Comment> {
+        self.comments
+            .iter()
+            .find(|comment| comment.id == comment_id)
+    }
+
+    /// Adds, replaces, or (if `comment` is None) removes a comment. New
+    /// comments are kept in creation order so that undoing a deletion
+    /// restores the comment to its original place. Returns the previous
+    /// comment.
+    pub fn set_comment(&mut self, comment_id: Uuid, comment: Option<Comment>) -> Option<Comment> {
+        let index = self.comments.iter().position(|c| c.id == comment_id);
+        match (index, comment) {
+            (Some(index), Some(comment)) => {
+                Some(std::mem::replace(&mut self.comments[index], comment))
+            }
+            (Some(index), None) => Some(self.comments.remove(index)),
+            (None, Some(comment)) => {
+                let index = self
+                    .comments
+                    .partition_point(|c| c.created <= comment.created);
+                self.comments.insert(index, comment);
+                None
+            }
+            (None, None) => None,
+        }
+    }
+
+    /// Returns the user ids mentioned anywhere in the thread.
+    pub fn mentions(&self) -> impl Iterator<Item = &String> {
+        self.comments
+            .iter()
+            .flat_map(|comment| comment.mentions.iter())
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CommentThreads {
+    #[serde(default)]
+    pub threads: Vec<CommentThread>,
+}
+
+impl CommentThreads {
+    /// Gets a thread by its id.
+    pub fn get(&self, thread_id: Uuid) -> Option<&CommentThread> {
+        self.threads.iter().find(|thread| thread.id == thread_id)
+    }
+
+    /// Gets a mutable thread by its id.
+    pub fn get_mut(&mut self, thread_id: Uuid) -> Option<&mut CommentThread> {
+        self.threads
+            .iter_mut()
+            .find(|thread| thread.id == thread_id)
+    }
+
+    /// Adds or replaces a thread. Returns the previous thread with that id.
+    pub fn set(&mut self, thread: CommentThread) -> Option<CommentThread> {
+        match self.get_mut(thread.id) {
+            Some(existing) => Some(std::mem::replace(existing, thread)),
+            None => {
+                self.threads.push(thread);
+                None
+            }
+        }
+    }
+
+    /// Removes a thread by its id. Returns the removed thread.
+    pub fn remove(&mut self, thread_id: Uuid) -> Option<CommentThread> {
+        let index = self
+            .threads
+            .iter()
+            .position(|thread| thread.id == thread_id)?;
+        Some(self.threads.remove(index))
+    }
+
+    /// Gets the threads anchored to a cell.
+    pub fn at(&self, pos: Pos) -> impl Iterator<Item = &CommentThread> {
+        self.threads.iter().filter(move |thread| thread.pos == pos)
+    }
+
+    /// Gets the threads anchored to cells within `rect`.
+    pub fn in_rect(&self, rect: Rect) -> impl Iterator<Item = &CommentThread> {
+        self.threads
+            .iter()
+            .filter(move |thread| rect.contains(thread.pos))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.threads.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CommentThread> {
+        self.threads.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use serial_test::parallel;
+
+    fn comment(body: &str) -> Comment {
+        Comment::new("user".to_string(), body.to_string(), vec![])
+    }
+
+    #[test]
+    #[parallel]
+    fn set_comment_keeps_creation_order() {
+        let first = comment("first");
+        let mut reply = comment("reply");
+        reply.created = first.created + Duration::seconds(1);
+        let mut thread = CommentThread::new(Pos { x: 1, y: 2 }, first.clone());
+
+        assert_eq!(thread.set_comment(reply.id, Some(reply.clone())), None);
+        assert_eq!(thread.comments, vec![first.clone(), reply.clone()]);
+
+        // deleting and restoring the first comment keeps it first
+        assert_eq!(thread.set_comment(first.id, None), Some(first.clone()));
+        assert_eq!(thread.set_comment(first.id, Some(first.clone())), None);
+        assert_eq!(thread.comments, vec![first.clone(), reply.clone()]);
+
+        let mut edited = reply.clone();
+        edited.body = "edited".to_string();
+        assert_eq!(
+            thread.set_comment(reply.id, Some(edited.clone())),
+            Some(reply)
+        );
+        assert_eq!(thread.comment(edited.id), Some(&edited));
+    }
+
+    #[test]
+    #[parallel]
+    fn comment_threads() {
+        let mut threads = CommentThreads::default();
+        let thread = CommentThread::new(Pos { x: 1, y: 2 }, comment("hello"));
+        let id = thread.id;
+
+        assert_eq!(threads.set(thread.clone()), None);
+        assert_eq!(threads.at(Pos { x: 1, y: 2 }).count(), 1);
+        assert_eq!(threads.in_rect(Rect::new(0, 0, 1, 1)).count(), 0);
+        assert_eq!(threads.in_rect(Rect::new(0, 0, 1, 2)).count(), 1);
+
+        let mut resolved = thread.clone();
+        resolved.resolved = true;
+        assert_eq!(threads.set(resolved.clone()), Some(thread));
+        assert_eq!(threads.get(id), Some(&resolved));
+
+        assert_eq!(threads.remove(id), Some(resolved));
+        assert!(threads.is_empty());
+    }
+}
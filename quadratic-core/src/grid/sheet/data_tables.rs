@@ -0,0 +1,204 @@
+//! Named data tables for a Sheet.
+//!
+//! A data table is a rectangular region whose first row contains column
+//! headers. Formulas can refer to tables by name using structured references
+//! (eg, `Table1[Amount]`).
+
+use serde::{Deserialize, Serialize};
+
+use crate::{formulas::TableSpecifier, Pos, Rect};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DataTable {
+    pub name: String,
+
+    /// Bounds of the table, including the header row.
+    pub rect: Rect,
+
+    pub headers: Vec<String>,
+}
+
+impl DataTable {
+    pub fn new(name: String, rect: Rect, headers: Vec<String>) -> Self {
+        DataTable {
+            name,
+            rect,
+            headers,
+        }
+    }
+
+    /// Returns the header row of the table.
+    pub fn header_rect(&self) -> Rect {
+        Rect::new(
+            self.rect.min.x,
+            self.rect.min.y,
+            self.rect.max.x,
+            self.rect.min.y,
+        )
+    }
+
+    /// Returns the data rows of the table, or None if the table only has a
+    /// header row.
+    pub fn data_rect(&self) -> Option<Rect> {
+        (self.rect.max.y > self.rect.min.y).then(|| {
+            Rect::new(
+                self.rect.min.x,
+                self.rect.min.y + 1,
+                self.rect.max.x,
+                self.rect.max.y,
+            )
+        })
+    }
+
+    /// Returns the index of a column by its (case-insensitive) header name.
+    pub fn column_index(&self, column: &str) -> Option<usize> {
+        let column = column.trim();
+        self.headers
+            .iter()
+            .position(|header| header.trim().eq_ignore_ascii_case(column))
+    }
+
+    /// Returns the data rows of a column, or None if the column does not
+    /// exist or the table has no data rows.
+    pub fn column_rect(&self, column: &str) -> Option<Rect> {
+        let x = self.rect.min.x + self.column_index(column)? as i64;
+        let data = self.data_rect()?;
+        Some(Rect::new(x, data.min.y, x, data.max.y))
+    }
+
+    /// Returns whether `pos` is in a data row of the table.
+    pub fn contains_data_pos(&self, pos: Pos) -> bool {
+        self.data_rect().is_some_and(|rect| rect.contains(pos))
+    }
+
+    /// Returns whether row `y` is one of the table's data rows.
+    pub fn contains_data_row(&self, y: i64) -> bool {
+        self.data_rect()
+            .is_some_and(|rect| rect.y_range().contains(&y))
+    }
+
+    /// Returns the cells referred to by a structured reference to this table
+    /// from a formula in row `row`, or None if the reference is invalid.
+    pub fn resolve_specifier(&self, spec: &TableSpecifier, row: i64) -> Option<Rect> {
+        match spec {
+            TableSpecifier::All => Some(self.rect),
+            TableSpecifier::Data => self.data_rect(),
+            TableSpecifier::Headers => Some(self.header_rect()),
+            TableSpecifier::Column(column) => self.column_rect(column),
+            TableSpecifier::ThisRow(column) => {
+                if !self.contains_data_row(row) {
+                    return None;
+                }
+                let x = self.rect.min.x + self.column_index(column)? as i64;
+                Some(Rect::single_pos(Pos { x, y: row }))
+            }
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DataTables {
+    #[serde(default)]
+    pub tables: Vec<DataTable>,
+}
+
+impl DataTables {
+    /// Gets a table by its (case-insensitive) name.
+    pub fn get(&self, name: &str) -> Option<&DataTable> {
+        self.tables
+            .iter()
+            .find(|table| table.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Adds or replaces a table. Returns the previous table with that name.
+    pub fn set(&mut self, table: DataTable) -> Option<DataTable> {
+        match self
+            .tables
+            .iter_mut()
+            .find(|t| t.name.eq_ignore_ascii_case(&table.name))
+        {
+            Some(existing) => Some(std::mem::replace(existing, table)),
+            None => {
+                self.tables.push(table);
+                None
+            }
+        }
+    }
+
+    /// Removes a table by name. Returns the removed table.
+    pub fn remove(&mut self, name: &str) -> Option<DataTable> {
+        let index = self
+            .tables
+            .iter()
+            .position(|table| table.name.eq_ignore_ascii_case(name))?;
+        Some(self.tables.remove(index))
+    }
+
+    /// Gets the table whose top-left corner is `pos`.
+    pub fn table_anchored_at(&self, pos: Pos) -> Option<&DataTable> {
+        self.tables.iter().find(|table| table.rect.min == pos)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DataTable> {
+        self.tables.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::parallel;
+
+    fn table() -> DataTable {
+        DataTable::new(
+            "Table1".to_string(),
+            Rect::new(1, 1, 2, 4),
+            vec!["Name".to_string(), "Amount".to_string()],
+        )
+    }
+
+    #[test]
+    #[parallel]
+    fn data_table_rects() {
+        let table = table();
+        assert_eq!(table.header_rect(), Rect::new(1, 1, 2, 1));
+        assert_eq!(table.data_rect(), Some(Rect::new(1, 2, 2, 4)));
+        assert_eq!(table.column_index("amount"), Some(1));
+        assert_eq!(table.column_rect("Amount"), Some(Rect::new(2, 2, 2, 4)));
+        assert_eq!(table.column_rect("Missing"), None);
+        assert!(table.contains_data_pos(Pos { x: 1, y: 2 }));
+        assert!(!table.contains_data_pos(Pos { x: 1, y: 1 }));
+
+        let this_row = TableSpecifier::ThisRow("Amount".into());
+        assert_eq!(
+            table.resolve_specifier(&this_row, 3),
+            Some(Rect::single_pos(Pos { x: 2, y: 3 }))
+        );
+        assert_eq!(table.resolve_specifier(&this_row, 1), None);
+        assert_eq!(
+            table.resolve_specifier(&TableSpecifier::Headers, 0),
+            Some(Rect::new(1, 1, 2, 1))
+        );
+    }
+
+    #[test]
+    #[parallel]
+    fn data_tables_set_get_remove() {
+        let mut tables = DataTables::default();
+        assert_eq!(tables.set(table()), None);
+        assert_eq!(tables.get("TABLE1"), Some(&table()));
+        assert_eq!(tables.table_anchored_at(Pos { x: 1, y: 1 }), Some(&table()));
+
+        let mut updated = table();
+        updated.rect = Rect::new(1, 1, 2, 5);
+        assert_eq!(tables.set(updated.clone()), Some(table()));
+        assert_eq!(tables.get("Table1"), Some(&updated));
+
+        assert_eq!(tables.remove("table1"), Some(updated));
+        assert!(tables.is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+//! Protected ranges and sheets.
+//!
+//! A protection locks a range of cells (or the whole sheet) so that only the
+//! listed users and roles may edit it. Protections are checked before a
+//! transaction is applied locally. The multiplayer server only enforces them
+//! when it validates transactions with a dry run; otherwise they're advisory.
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::Rect;
+
+/// The user making an edit. Roles are the user's file permissions (eg,
+/// "FILE_EDIT" or "FILE_DELETE").
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, TS)]
+pub struct EditUser {
+    pub user_id: String,
+    pub roles: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct Protection {
+    pub id: Uuid,
+
+    /// The protected cells, or None if the whole sheet is protected.
+    pub rect: Option<Rect>,
+
+    /// User ids of the users allowed to edit the protected cells.
+    #[serde(default)]
+    pub users: Vec<String>,
+
+    /// Roles allowed to edit the protected cells.
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl Protection {
+    pub fn new(rect: Option<Rect>, users: Vec<String>, roles: Vec<String>) -> Self {
+        Protection {
+            id: Uuid::new_v4(),
+            rect,
+            users,
+            roles,
+        }
+    }
+
+    /// Whether the user may edit the protected cells.
+    pub fn allows(&self, user: &EditUser) -> bool {
+        (!user.user_id.is_empty() && self.users.contains(&user.user_id))
+            || user.roles.iter().any(|role| self.roles.contains(role))
+    }
+
+    /// Whether the protection covers any of the cells in `rect`.
+    pub fn protects(&self, rect: Rect) -> bool {
+        match self.rect {
+            Some(protected) => protected.intersects(rect),
+            None => true,
+        }
+    }
+
+    /// Whether the protection covers the whole sheet.
+    pub fn is_sheet(&self) -> bool {
+        self.rect.is_none()
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Protections {
+    #[serde(default)]
+    pub protections: Vec<Protection>,
+}
+
+impl Protections {
+    /// Gets a protection by its id.
+    pub fn get(&self, protection_id: Uuid) -> Option<&Protection> {
+        self.protections
+            .iter()
+            .find(|protection| protection.id == protection_id)
+    }
+
+    /// Adds or replaces a protection. Returns the previous protection with
+    /// that id.
+    pub fn set(&mut self, protection: Protection) -> Option<Protection> {
+        match self
+            .protections
+            .iter_mut()
+            .find(|existing| existing.id == protection.id)
+        {
+            Some(existing) => Some(std::mem::replace(existing, protection)),
+            None => {
+                self.protections.push(protection);
+                None
+            }
+        }
+    }
+
+    /// Removes a protection by its id. Returns the removed protection.
+    pub fn remove(&mut self, protection_id: Uuid) -> Option<Protection> {
+        let index = self
+            .protections
+            .iter()
+            .position(|protection| protection.id == protection_id)?;
+        Some(self.protections.remove(index))
+    }
+
+    /// Gets the first protection over `rect` that doesn't allow the user to
+    /// edit it.
+    pub fn blocking(&self, rect: Rect, user: &EditUser) -> Option<&Protection> {
+        self.protections
+            .iter()
+            .find(|protection| protection.protects(rect) && !protection.allows(user))
+    }
+
+    /// Gets the first sheet protection that doesn't allow the user to edit
+    /// the sheet.
+    pub fn blocking_sheet(&self, user: &EditUser) -> Option<&Protection> {
+        self.protections
+            .iter()
+            .find(|protection| protection.is_sheet() && !protection.allows(user))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.protections.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Protection> {
+        self.protections.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::parallel;
+
+    fn user(user_id: &str, roles: &[&str]) -> EditUser {
+        EditUser {
+            user_id: user_id.to_string(),
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    #[parallel]
+    fn protection_allows_users_and_roles() {
+        let protection = Protection::new(
+            Some(Rect::new(1, 1, 2, 2)),
+            vec!["owner".to_string()],
+            vec!["FILE_DELETE".to_string()],
+        );
+
+        assert!(protection.allows(&user("owner", &["FILE_EDIT"])));
+        assert!(protection.allows(&user("admin", &["FILE_EDIT", "FILE_DELETE"])));
+        assert!(!protection.allows(&user("editor", &["FILE_EDIT"])));
+        assert!(!protection.allows(&EditUser::default()));
+
+        assert!(protection.protects(Rect::new(2, 2, 3, 3)));
+        assert!(!protection.protects(Rect::new(3, 3, 4, 4)));
+    }
+
+    #[test]
+    #[parallel]
+    fn protections_blocking() {
+        let mut protections = Protections::default();
+        let range = Protection::new(Some(Rect::new(1, 1, 2, 2)), vec!["a".to_string()], vec![]);
+        let sheet = Protection::new(None, vec!["b".to_string()], vec![]);
+        assert_eq!(protections.set(range.clone()), None);
+
+        let editor = user("c", &[]);
+        assert_eq!(
+            protections.blocking(Rect::new(0, 0, 1, 1), &editor),
+            Some(&range)
+        );
+        assert_eq!(protections.blocking(Rect::new(5, 5, 6, 6), &editor), None);
+        assert_eq!(protections.blocking_sheet(&editor), None);
+
+        protections.set(sheet.clone());
+        assert_eq!(
+            protections.blocking(Rect::new(5, 5, 6, 6), &editor),
+            Some(&sheet)
+        );
+        assert_eq!(protections.blocking_sheet(&user("a", &[])), Some(&sheet));
+        assert_eq!(protections.blocking_sheet(&user("b", &[])), None);
+
+        assert_eq!(protections.remove(sheet.id), Some(sheet));
+        assert_eq!(protections.get(range.id), Some(&range));
+    }
+}
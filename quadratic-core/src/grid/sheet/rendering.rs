@@ -499,6 +499,20 @@ impl Sheet {
         }
     }
 
+    /// Sends all comment threads for this sheet to the client.
+    pub fn send_all_comments(&self) {
+        if let Ok(comments) = serde_json::to_string(&self.comments.threads) {
+            crate::wasm_bindings::js::jsSheetComments(self.id.to_string(), comments);
+        }
+    }
+
+    /// Sends all protections for this sheet to the client.
+    pub fn send_all_protections(&self) {
+        if let Ok(protections) = serde_json::to_string(&self.protections.protections) {
+            crate::wasm_bindings::js::jsSheetProtections(self.id.to_string(), protections);
+        }
+    }
+
     /// Sends validation warnings for a hashed region to the client.
     pub fn send_validation_warnings(&self, hash_x: i64, hash_y: i64, rect: Rect) {
         let warnings = self
@@ -600,6 +614,7 @@ mod tests {
                 std_err: None,
                 std_out: None,
                 spill_error: false,
+                volatile: false,
                 cells_accessed: HashSet::new(),
                 result: CodeRunResult::Ok(Value::Single(CellValue::Text("hello".to_string()))),
                 return_type: Some("text".into()),
@@ -819,6 +834,7 @@ mod tests {
             )),
             return_type: Some("text".into()),
             spill_error: false,
+            volatile: false,
             line_number: None,
             output_type: None,
         };
@@ -868,6 +884,7 @@ mod tests {
             result: CodeRunResult::Ok(Value::Single(CellValue::Number(1.into()))),
             return_type: Some("number".into()),
             spill_error: false,
+            volatile: false,
             line_number: None,
             output_type: None,
         };
@@ -967,6 +984,7 @@ mod tests {
             result: CodeRunResult::Ok(Value::Single(CellValue::Number(2.into()))),
             return_type: Some("number".into()),
             spill_error: false,
+            volatile: false,
             line_number: None,
             output_type: None,
         };
@@ -1012,6 +1030,7 @@ mod tests {
             result: CodeRunResult::Ok(Value::Single(CellValue::Image(image.clone()))),
             return_type: Some("image".into()),
             spill_error: false,
+            volatile: false,
             line_number: None,
             output_type: None,
         };
@@ -468,6 +468,7 @@ mod test {
             std_err: None,
             cells_accessed: HashSet::new(),
             spill_error: false,
+            volatile: false,
             return_type: None,
             line_number: None,
             output_type: None,
@@ -510,6 +511,7 @@ mod test {
             std_err: None,
             cells_accessed: HashSet::new(),
             spill_error: false,
+            volatile: false,
             return_type: None,
             line_number: None,
             output_type: None,
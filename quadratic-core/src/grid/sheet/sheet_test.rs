@@ -71,6 +71,7 @@ impl Sheet {
                 line_number: None,
                 output_type: None,
                 spill_error: false,
+                volatile: false,
                 last_modified: chrono::Utc::now(),
             }),
         );
@@ -138,6 +139,7 @@ impl Sheet {
                 line_number: None,
                 output_type: None,
                 spill_error: false,
+                volatile: false,
                 last_modified: Utc::now(),
             }),
         );
@@ -186,6 +188,7 @@ impl Sheet {
                 line_number: None,
                 output_type: None,
                 spill_error: false,
+                volatile: false,
                 last_modified: Utc::now(),
             }),
         );
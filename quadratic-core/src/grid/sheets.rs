@@ -1,4 +1,4 @@
-use super::{Grid, Sheet, SheetId};
+use super::{sheet::data_tables::DataTable, Grid, Sheet, SheetId};
 use lexicon_fractional_index::key_between;
 use std::str::FromStr;
 
@@ -38,6 +38,21 @@ impl Grid {
         self.sheets.iter_mut().find(|sheet| sheet.name == name)
     }
 
+    /// Finds a data table by its (case-insensitive) name across all sheets.
+    pub fn data_table(&self, name: &str) -> Option<(&Sheet, &DataTable)> {
+        self.sheets
+            .iter()
+            .find_map(|sheet| sheet.data_tables.get(name).map(|table| (sheet, table)))
+    }
+
+    /// Returns an unused name for a new data table (eg, "Table1").
+    pub fn next_data_table_name(&self) -> String {
+        (1..)
+            .map(|i| format!("Table{i}"))
+            .find(|name| self.data_table(name).is_none())
+            .expect("there is always an unused table name")
+    }
+
     pub fn try_sheet_from_string_id(&self, id: String) -> Option<&Sheet> {
         SheetId::from_str(&id).map_or(None, |sheet_id| self.try_sheet(sheet_id))
     }
@@ -256,8 +256,9 @@ impl Array {
             None => "array",
         }
     }
-    /// Returns the unique length that fits all `values` along `axis`. See
-    /// `common_array_size()` for more.
+    /// Returns the unique length that fits all `values` along `axis`. Lengths
+    /// of `1` are ignored, and any other mismatch is an error. See
+    /// `Value::broadcast_array_size()` for the more lenient Excel rules.
     pub fn common_len<'a>(
         axis: Axis,
         arrays: impl IntoIterator<Item = Spanned<&'a Array>>,
@@ -204,6 +204,7 @@ macro_rules! impl_try_from_cell_value_for {
 }
 impl_try_from_cell_value_for!(f64);
 impl_try_from_cell_value_for!(i64);
+impl_try_from_cell_value_for!(u32);
 impl_try_from_cell_value_for!(bool);
 
 impl<'a> TryFrom<&'a Value> for &'a CellValue {
@@ -242,6 +243,7 @@ macro_rules! impl_try_from_value_for {
 impl_try_from_value_for!(String);
 impl_try_from_value_for!(f64);
 impl_try_from_value_for!(i64);
+impl_try_from_value_for!(u32);
 impl_try_from_value_for!(bool);
 
 /// Coercion from `Value` or `CellValue` into a particular Rust type.
@@ -151,20 +151,25 @@ impl Value {
         }
     }
 
-    /// Returns the unique width and height that fits all of `values`.
+    /// Returns the width and height that fits all of `values`, following
+    /// Excel's broadcasting rules.
     ///
     /// - If `values` does not contain any arrays, returns `(1, 1)`.
-    /// - Sizes of `1` are ignored.
-    /// - If there are multiple unequal sizes greater than one, returns an
-    ///   error.
+    /// - Otherwise, returns the largest width and the largest height of any
+    ///   array. Arrays with a width or height of `1` are repeated along that
+    ///   axis, and other arrays that are too small are padded with `#N/A`.
     /// - Both numbers returned are always nonzero.
-    pub fn common_array_size<'a>(
-        values: impl Copy + IntoIterator<Item = &'a Spanned<Value>>,
-    ) -> CodeResult<ArraySize> {
-        Ok(ArraySize {
-            w: Array::common_len(Axis::X, values.into_iter().filter_map(|v| v.as_array()))?,
-            h: Array::common_len(Axis::Y, values.into_iter().filter_map(|v| v.as_array()))?,
-        })
+    pub fn broadcast_array_size<'a>(
+        values: impl IntoIterator<Item = &'a Spanned<Value>>,
+    ) -> ArraySize {
+        values
+            .into_iter()
+            .filter_map(|v| v.as_array())
+            .map(|array| array.inner.size())
+            .fold(ArraySize::_1X1, |a, b| ArraySize {
+                w: a.w.max(b.w),
+                h: a.h.max(b.h),
+            })
     }
 
     /// Returns the contained error, or panics the value is not just a single
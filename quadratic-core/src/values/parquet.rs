@@ -4,13 +4,18 @@ use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 
 use crate::{arrow::arrow_col_to_cell_value_vec, CellValue};
 
-pub fn parquet_to_vec(file: Vec<u8>) -> Result<Vec<Vec<CellValue>>> {
-    if file.is_empty() {
+/// The key in the connection service's Parquet footer of whether the query
+/// stopped early because its results were too large
+const OVER_THE_LIMIT_KEY: &str = "over-the-limit";
+
+pub fn parquet_to_vec(file: impl Into<Bytes>) -> Result<Vec<Vec<CellValue>>> {
+    // this is not expensive
+    let bytes = file.into();
+
+    if bytes.is_empty() {
         return Ok(vec![]);
     }
 
-    // this is not expensive
-    let bytes = Bytes::from(file);
     let builder = ParquetRecordBatchReaderBuilder::try_new(bytes)?;
 
     // headers
@@ -18,6 +23,11 @@ pub fn parquet_to_vec(file: Vec<u8>) -> Result<Vec<Vec<CellValue>>> {
     let total_size = metadata.file_metadata().num_rows() as usize;
     let fields = metadata.file_metadata().schema().get_fields();
 
+    // queries without rows are a file without columns
+    if fields.is_empty() {
+        return Ok(vec![]);
+    }
+
     let headers: Vec<CellValue> = fields.iter().map(|f| f.name().into()).collect();
     let width = headers.len();
 
@@ -43,10 +53,31 @@ pub fn parquet_to_vec(file: Vec<u8>) -> Result<Vec<Vec<CellValue>>> {
 
     Ok(output)
 }
+
+/// Whether the connection service stopped a query early because its results
+/// were too large.  It's written to the file's footer, as the file is sent
+/// before it's known.
+pub fn parquet_over_the_limit(file: Bytes) -> bool {
+    let Ok(builder) = ParquetRecordBatchReaderBuilder::try_new(file) else {
+        return false;
+    };
+
+    builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|metadata| metadata.iter().find(|kv| kv.key == OVER_THE_LIMIT_KEY))
+        .is_some_and(|kv| kv.value.as_deref() == Some("true"))
+}
 #[cfg(test)]
 mod test {
     use std::fs::File;
     use std::io::Read;
+    use std::sync::Arc;
+
+    use arrow_schema::Schema;
+    use parquet::arrow::ArrowWriter;
+    use parquet::file::metadata::KeyValue;
 
     use super::*;
 
@@ -62,4 +93,29 @@ mod test {
         let _results = parquet_to_vec(buffer);
         // println!("{:?}", results);
     }
+
+    #[test]
+    fn test_parquet_over_the_limit() {
+        let write = |over_the_limit: Option<&str>| {
+            let mut buffer = vec![];
+            let schema = Arc::new(Schema::empty());
+            let mut writer = ArrowWriter::try_new(&mut buffer, schema, None).unwrap();
+
+            if let Some(value) = over_the_limit {
+                let key_value = KeyValue::new(OVER_THE_LIMIT_KEY.into(), value.to_string());
+                writer.append_key_value_metadata(key_value);
+            }
+
+            writer.close().unwrap();
+            Bytes::from(buffer)
+        };
+
+        assert!(parquet_over_the_limit(write(Some("true"))));
+        assert!(!parquet_over_the_limit(write(Some("false"))));
+        assert!(!parquet_over_the_limit(write(None)));
+        assert!(!parquet_over_the_limit(Bytes::new()));
+
+        // a file without columns has no values
+        assert!(parquet_to_vec(write(Some("true"))).unwrap().is_empty());
+    }
 }
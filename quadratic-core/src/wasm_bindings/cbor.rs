@@ -0,0 +1,157 @@
+//! CBOR codec for binary messages with the quadratic-multiplayer server,
+//! using the same `compression` functions as the server.
+
+use ciborium::value::Value;
+use serde::Serialize;
+use uuid::Uuid;
+use wasm_bindgen::prelude::*;
+
+use crate::compression::{deserialize, serialize, SerializationFormat};
+
+// fields that carry raw bytes instead of uuids
+const BYTE_FIELDS: [&str; 1] = ["operations"];
+
+/// Encodes a message for the multiplayer server.  Operations are sent as a
+/// Uint8Array.
+#[wasm_bindgen(js_name = "encodeCbor")]
+pub fn js_encode_cbor(message: JsValue) -> Result<Vec<u8>, JsValue> {
+    let value: Value = serde_wasm_bindgen::from_value(message)?;
+    serialize(&SerializationFormat::Cbor, skip_nulls(value))
+        .map_err(|e| JsValue::from_str(&format!("Unable to encode CBOR: {}", e)))
+}
+
+/// Decodes a message from the multiplayer server into the same shape as its
+/// JSON messages, except that operations are a Uint8Array.
+#[wasm_bindgen(js_name = "decodeCbor")]
+pub fn js_decode_cbor(data: &[u8]) -> Result<JsValue, JsValue> {
+    let value = deserialize::<Value>(&SerializationFormat::Cbor, data)
+        .map_err(|e| JsValue::from_str(&format!("Unable to decode CBOR: {}", e)))?;
+    let serializer = serde_wasm_bindgen::Serializer::new()
+        .serialize_missing_as_null(true)
+        .serialize_maps_as_objects(true);
+
+    Ok(bytes_to_uuids(value, None).serialize(&serializer)?)
+}
+
+/// JS can't tell null from undefined here, so null fields are skipped (as
+/// JSON.stringify skips undefined fields), which serde reads as None.
+fn skip_nulls(value: Value) -> Value {
+    match value {
+        Value::Array(items) => Value::Array(items.into_iter().map(skip_nulls).collect()),
+        Value::Map(entries) => Value::Map(
+            entries
+                .into_iter()
+                .filter(|(_, item)| !item.is_null())
+                .map(|(key, item)| (key, skip_nulls(item)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// The server writes uuids as 16 byte strings, so every byte string outside
+/// of `BYTE_FIELDS` is converted to a uuid.  Tags are dropped.
+fn bytes_to_uuids(value: Value, field: Option<&str>) -> Value {
+    match value {
+        Value::Bytes(bytes) if !field.is_some_and(|field| BYTE_FIELDS.contains(&field)) => {
+            match Uuid::from_slice(&bytes) {
+                Ok(uuid) => Value::Text(uuid.to_string()),
+                Err(_) => Value::Bytes(bytes),
+            }
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| bytes_to_uuids(item, field))
+                .collect(),
+        ),
+        Value::Map(entries) => Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, item)| {
+                    let item = bytes_to_uuids(item, key.as_text());
+                    (key, item)
+                })
+                .collect(),
+        ),
+        Value::Tag(_, value) => bytes_to_uuids(*value, field),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> Value {
+        Value::Text(text.to_string())
+    }
+
+    #[test]
+    fn converts_uuids_except_operations() {
+        let id = Uuid::parse_str("00112233-4455-6677-8899-aabbccddeeff").unwrap();
+        let operations = Value::Bytes(id.as_bytes().to_vec());
+        let value = Value::Map(vec![
+            (text("id"), Value::Bytes(id.as_bytes().to_vec())),
+            (
+                text("users"),
+                Value::Array(vec![Value::Tag(
+                    37,
+                    Box::new(Value::Bytes(id.as_bytes().to_vec())),
+                )]),
+            ),
+            (text("operations"), operations.clone()),
+            (text("short"), Value::Bytes(vec![0, 1, 2])),
+        ]);
+
+        assert_eq!(
+            bytes_to_uuids(value, None),
+            Value::Map(vec![
+                (text("id"), text(&id.to_string())),
+                (text("users"), Value::Array(vec![text(&id.to_string())])),
+                (text("operations"), operations),
+                (text("short"), Value::Bytes(vec![0, 1, 2])),
+            ])
+        );
+    }
+
+    #[test]
+    fn skips_null_fields() {
+        let value = Value::Map(vec![
+            (text("follow"), Value::Null),
+            (
+                text("update"),
+                Value::Map(vec![(text("x"), Value::Null), (text("y"), 1.into())]),
+            ),
+            (text("flags"), Value::Array(vec![Value::Null, true.into()])),
+        ]);
+
+        assert_eq!(
+            skip_nulls(value),
+            Value::Map(vec![
+                (text("update"), Value::Map(vec![(text("y"), 1.into())])),
+                (text("flags"), Value::Array(vec![Value::Null, true.into()])),
+            ])
+        );
+    }
+
+    #[test]
+    fn round_trips_through_the_server_codec() {
+        let id = Uuid::new_v4();
+        let value = Value::Map(vec![
+            (text("type"), text("Transaction")),
+            (text("id"), text(&id.to_string())),
+            (text("sequence_num"), (1u64 << 32).into()),
+            (text("operations"), Value::Bytes(vec![0, 1, 2, 255])),
+        ]);
+        let data = serialize(&SerializationFormat::Cbor, skip_nulls(value.clone())).unwrap();
+
+        assert_eq!(
+            bytes_to_uuids(
+                deserialize(&SerializationFormat::Cbor, &data).unwrap(),
+                None
+            ),
+            value
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use js_sys::Uint8Array;
 
+use crate::formulas::lsp::LspContext;
+
 use super::*;
 
 #[wasm_bindgen]
@@ -63,6 +65,13 @@ impl GridController {
         }
     }
 
+    /// Returns the grid information used by the formula language server
+    /// (see `setLspContext` in quadratic-rust-client).
+    #[wasm_bindgen(js_name = "getLspContext")]
+    pub fn js_lsp_context(&self) -> String {
+        serde_json::to_string(&LspContext::new(self.grid())).unwrap_or_default()
+    }
+
     /// Sets the code on a cell
     #[wasm_bindgen(js_name = "setCellCode")]
     pub fn js_set_cell_code(
@@ -87,6 +96,12 @@ impl GridController {
         self.rerun_all_code_cells(cursor);
     }
 
+    /// Recomputes volatile code cells (eg, `RAND()`) and their dependents.
+    #[wasm_bindgen(js_name = "recalculate")]
+    pub fn js_recalculate(&mut self, cursor: Option<String>) {
+        self.recalculate(cursor);
+    }
+
     /// Reruns all code cells in a sheet.
     #[wasm_bindgen(js_name = "rerunSheetCodeCells")]
     pub fn js_rerun_sheet_code_cells(&mut self, sheet_id: String, cursor: Option<String>) {
@@ -120,4 +135,12 @@ impl GridController {
 
         Ok(())
     }
+
+    #[wasm_bindgen(js_name = "connectionCancelled")]
+    pub fn js_connection_cancelled(&mut self, transaction_id: String) -> Result<(), JsValue> {
+        self.connection_cancelled(transaction_id)
+            .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
 }
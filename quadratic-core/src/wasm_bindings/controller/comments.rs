@@ -0,0 +1,130 @@
+//! WASM functions for comment threads
+
+use uuid::Uuid;
+
+use super::*;
+
+#[wasm_bindgen]
+impl GridController {
+    /// Returns a stringified version of Vec<CommentThread> for a sheet
+    #[wasm_bindgen(js_name = "getCommentThreads")]
+    pub fn js_comment_threads(&self, sheet_id: String) -> String {
+        if let Ok(sheet_id) = SheetId::from_str(&sheet_id) {
+            serde_json::to_string(&self.comment_threads(sheet_id)).unwrap_or_default()
+        } else {
+            String::new()
+        }
+    }
+
+    /// Starts a new comment thread on a cell. Returns the thread's id.
+    #[wasm_bindgen(js_name = "addCommentThread")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn js_add_comment_thread(
+        &mut self,
+        sheet_id: String,
+        x: i64,
+        y: i64,
+        author: String,
+        body: String,
+        mentions: String, // Vec<String>
+        cursor: Option<String>,
+    ) -> Option<String> {
+        let Ok(sheet_id) = SheetId::from_str(&sheet_id) else {
+            dbgjs!("Error parsing sheet_id in addCommentThread");
+            return None;
+        };
+        let mentions = serde_json::from_str::<Vec<String>>(&mentions).unwrap_or_default();
+        let sheet_pos = SheetPos::new(sheet_id, x, y);
+        let thread_id = self.add_comment_thread(sheet_pos, author, body, mentions, cursor);
+        Some(thread_id.to_string())
+    }
+
+    /// Adds a reply to a comment thread
+    #[wasm_bindgen(js_name = "replyToCommentThread")]
+    pub fn js_reply_to_comment_thread(
+        &mut self,
+        sheet_id: String,
+        thread_id: String,
+        author: String,
+        body: String,
+        mentions: String, // Vec<String>
+        cursor: Option<String>,
+    ) {
+        if let (Ok(sheet_id), Ok(thread_id)) =
+            (SheetId::from_str(&sheet_id), Uuid::from_str(&thread_id))
+        {
+            let mentions = serde_json::from_str::<Vec<String>>(&mentions).unwrap_or_default();
+            self.reply_to_comment_thread(sheet_id, thread_id, author, body, mentions, cursor);
+        }
+    }
+
+    /// Changes the body of a comment
+    #[wasm_bindgen(js_name = "editComment")]
+    pub fn js_edit_comment(
+        &mut self,
+        sheet_id: String,
+        thread_id: String,
+        comment_id: String,
+        body: String,
+        mentions: String, // Vec<String>
+        cursor: Option<String>,
+    ) {
+        if let (Ok(sheet_id), Ok(thread_id), Ok(comment_id)) = (
+            SheetId::from_str(&sheet_id),
+            Uuid::from_str(&thread_id),
+            Uuid::from_str(&comment_id),
+        ) {
+            let mentions = serde_json::from_str::<Vec<String>>(&mentions).unwrap_or_default();
+            self.edit_comment(sheet_id, thread_id, comment_id, body, mentions, cursor);
+        }
+    }
+
+    /// Deletes a comment (or its thread if it started the thread)
+    #[wasm_bindgen(js_name = "deleteComment")]
+    pub fn js_delete_comment(
+        &mut self,
+        sheet_id: String,
+        thread_id: String,
+        comment_id: String,
+        cursor: Option<String>,
+    ) {
+        if let (Ok(sheet_id), Ok(thread_id), Ok(comment_id)) = (
+            SheetId::from_str(&sheet_id),
+            Uuid::from_str(&thread_id),
+            Uuid::from_str(&comment_id),
+        ) {
+            self.delete_comment(sheet_id, thread_id, comment_id, cursor);
+        }
+    }
+
+    /// Resolves or reopens a comment thread
+    #[wasm_bindgen(js_name = "resolveCommentThread")]
+    pub fn js_resolve_comment_thread(
+        &mut self,
+        sheet_id: String,
+        thread_id: String,
+        resolved: bool,
+        cursor: Option<String>,
+    ) {
+        if let (Ok(sheet_id), Ok(thread_id)) =
+            (SheetId::from_str(&sheet_id), Uuid::from_str(&thread_id))
+        {
+            self.resolve_comment_thread(sheet_id, thread_id, resolved, cursor);
+        }
+    }
+
+    /// Deletes a comment thread
+    #[wasm_bindgen(js_name = "deleteCommentThread")]
+    pub fn js_delete_comment_thread(
+        &mut self,
+        sheet_id: String,
+        thread_id: String,
+        cursor: Option<String>,
+    ) {
+        if let (Ok(sheet_id), Ok(thread_id)) =
+            (SheetId::from_str(&sheet_id), Uuid::from_str(&thread_id))
+        {
+            self.delete_comment_thread(sheet_id, thread_id, cursor);
+        }
+    }
+}
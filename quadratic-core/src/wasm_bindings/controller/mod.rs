@@ -10,9 +10,11 @@ pub mod bounds;
 pub mod cells;
 pub mod clipboard;
 pub mod code;
+pub mod comments;
 pub mod export;
 pub mod formatting;
 pub mod import;
+pub mod protections;
 pub mod render;
 pub mod search;
 pub mod sheet_info;
@@ -88,6 +90,16 @@ impl GridController {
 
                             // sends all validations to the client
                             sheet.send_all_validations();
+
+                            // sends all comment threads to the client
+                            if !sheet.comments.is_empty() {
+                                sheet.send_all_comments();
+                            }
+
+                            // sends all protections to the client
+                            if !sheet.protections.is_empty() {
+                                sheet.send_all_protections();
+                            }
                         }
                     });
                 }
@@ -0,0 +1,91 @@
+//! WASM functions for protected ranges and sheets
+
+use sheet::protections::{EditUser, Protection};
+use uuid::Uuid;
+
+use super::*;
+
+#[wasm_bindgen]
+impl GridController {
+    /// Sets the user making local edits. Edits to cells protected from the
+    /// user are refused.
+    #[wasm_bindgen(js_name = "setEditUser")]
+    pub fn js_set_edit_user(&mut self, edit_user: String /* EditUser */) {
+        match serde_json::from_str::<EditUser>(&edit_user) {
+            Ok(edit_user) => self.set_edit_user(edit_user),
+            Err(e) => dbgjs!(format!("Error parsing edit user: {}", e.to_string())),
+        }
+    }
+
+    /// Returns a stringified version of Vec<Protection> for a sheet
+    #[wasm_bindgen(js_name = "getProtections")]
+    pub fn js_protections(&self, sheet_id: String) -> String {
+        if let Ok(sheet_id) = SheetId::from_str(&sheet_id) {
+            serde_json::to_string(&self.protections(sheet_id)).unwrap_or_default()
+        } else {
+            String::new()
+        }
+    }
+
+    /// Protects a range of cells (or the whole sheet if rect is undefined).
+    /// Returns the protection's id.
+    #[wasm_bindgen(js_name = "addProtection")]
+    pub fn js_add_protection(
+        &mut self,
+        sheet_id: String,
+        rect: Option<String>, // Rect
+        users: String,        // Vec<String>
+        roles: String,        // Vec<String>
+        cursor: Option<String>,
+    ) -> Option<String> {
+        let Ok(sheet_id) = SheetId::from_str(&sheet_id) else {
+            dbgjs!("Error parsing sheet_id in addProtection");
+            return None;
+        };
+        let rect = match rect.map(|rect| serde_json::from_str::<Rect>(&rect)) {
+            Some(Ok(rect)) => Some(rect),
+            Some(Err(e)) => {
+                dbgjs!(format!("Error parsing rect: {}", e.to_string()));
+                return None;
+            }
+            None => None,
+        };
+        let users = serde_json::from_str::<Vec<String>>(&users).unwrap_or_default();
+        let roles = serde_json::from_str::<Vec<String>>(&roles).unwrap_or_default();
+        let protection_id = self.add_protection(sheet_id, rect, users, roles, cursor);
+        Some(protection_id.to_string())
+    }
+
+    /// Changes a protection
+    #[wasm_bindgen(js_name = "updateProtection")]
+    pub fn js_update_protection(
+        &mut self,
+        sheet_id: String,
+        protection: String, // Protection
+        cursor: Option<String>,
+    ) {
+        let Ok(sheet_id) = SheetId::from_str(&sheet_id) else {
+            dbgjs!("Error parsing sheet_id in updateProtection");
+            return;
+        };
+        match serde_json::from_str::<Protection>(&protection) {
+            Ok(protection) => self.update_protection(sheet_id, protection, cursor),
+            Err(e) => dbgjs!(format!("Error parsing protection: {}", e.to_string())),
+        }
+    }
+
+    /// Removes a protection
+    #[wasm_bindgen(js_name = "removeProtection")]
+    pub fn js_remove_protection(
+        &mut self,
+        sheet_id: String,
+        protection_id: String,
+        cursor: Option<String>,
+    ) {
+        if let (Ok(sheet_id), Ok(protection_id)) =
+            (SheetId::from_str(&sheet_id), Uuid::from_str(&protection_id))
+        {
+            self.remove_protection(sheet_id, protection_id, cursor);
+        }
+    }
+}
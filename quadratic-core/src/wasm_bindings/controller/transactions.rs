@@ -1,8 +1,10 @@
 use super::*;
 use crate::controller::{
     active_transactions::unsaved_transactions::UnsavedTransaction,
-    operations::operation::Operation, transaction::Transaction,
+    operations::operation::Operation,
+    transaction::{Transaction, TransactionServer},
 };
+use crate::grid::file::import;
 use uuid::Uuid;
 
 #[wasm_bindgen]
@@ -62,6 +64,27 @@ impl GridController {
     //     }
     // }
 
+    /// Catch up from the server's latest checkpoint (the file's bytes) and
+    /// the transactions after it, keeping any unsaved transactions.
+    #[wasm_bindgen(js_name = "receiveCatchUp")]
+    pub fn js_receive_catch_up(
+        &mut self,
+        checkpoint: Vec<u8>,
+        checkpoint_sequence_num: u32,
+        transactions: &str,
+    ) -> Result<JsValue, JsValue> {
+        let checkpoint = import(checkpoint)
+            .map_err(|e| JsValue::from_str(&format!("Invalid checkpoint: {}", e)))?;
+        let transactions = serde_json::from_str::<Vec<TransactionServer>>(transactions)
+            .map_err(|e| JsValue::from_str(&format!("Invalid transactions: {}", e)))?;
+
+        Ok(serde_wasm_bindgen::to_value(&self.received_catch_up(
+            checkpoint,
+            checkpoint_sequence_num as u64,
+            &transactions,
+        ))?)
+    }
+
     #[wasm_bindgen(js_name = "applyOfflineUnsavedTransaction")]
     pub fn js_apply_offline_unsaved_transaction(
         &mut self,
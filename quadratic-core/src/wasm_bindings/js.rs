@@ -98,6 +98,7 @@ extern "C" {
         y: i32,
         sheet_id: String,
         query: String,
+        parameters: String, /* Vec<SqlParameter> */
         connector_type: ConnectionKind,
         connection_id: String,
     );
@@ -117,6 +118,9 @@ extern "C" {
     pub fn jsResizeRowHeights(sheet_id: String, row_heights: String /*Vec<JsRowHeight>*/);
 
     pub fn jsSheetValidations(sheet_id: String, validations: String /* Vec<Validation> */);
+    pub fn jsSheetComments(sheet_id: String, comments: String /* Vec<CommentThread> */);
+    pub fn jsSheetProtections(sheet_id: String, protections: String /* Vec<Protection> */);
+    pub fn jsProtectedEdit(sheet_id: String, protection: String /* Protection */);
     pub fn jsValidationWarning(
         sheet_id: String,
         validations: String, /* Vec<(x, y, validation_id, failed) */
@@ -512,14 +516,15 @@ pub fn jsConnection(
     y: i32,
     sheet_id: String,
     query: String,
+    parameters: String,
     connector_type: ConnectionKind,
     connection_id: String,
 ) -> JsValue {
     TEST_ARRAY.lock().unwrap().push(TestFunction::new(
         "jsConnection",
         format!(
-            "{},{},{},{},{},{},{}",
-            transactionId, x, y, sheet_id, query, connector_type, connection_id
+            "{},{},{},{},{},{},{},{}",
+            transactionId, x, y, sheet_id, query, parameters, connector_type, connection_id
         ),
     ));
     JsValue::NULL
@@ -558,6 +563,33 @@ pub fn jsSheetValidations(sheet_id: String, validations: String /* JsValidation
     ));
 }
 
+#[cfg(test)]
+#[allow(non_snake_case)]
+pub fn jsSheetComments(sheet_id: String, comments: String /* Vec<CommentThread> */) {
+    TEST_ARRAY.lock().unwrap().push(TestFunction::new(
+        "jsSheetComments",
+        format!("{},{}", sheet_id, comments),
+    ));
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+pub fn jsSheetProtections(sheet_id: String, protections: String /* Vec<Protection> */) {
+    TEST_ARRAY.lock().unwrap().push(TestFunction::new(
+        "jsSheetProtections",
+        format!("{},{}", sheet_id, protections),
+    ));
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+pub fn jsProtectedEdit(sheet_id: String, protection: String /* Protection */) {
+    TEST_ARRAY.lock().unwrap().push(TestFunction::new(
+        "jsProtectedEdit",
+        format!("{},{}", sheet_id, protection),
+    ));
+}
+
 #[cfg(test)]
 #[allow(non_snake_case)]
 pub fn jsRequestRowHeights(
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+pub mod cbor;
 pub mod controller;
 pub mod js;
 
@@ -0,0 +1,58 @@
+//! Authorization
+//!
+//! The files service doesn't decode user tokens.  Instead, it forwards the
+//! user's token to the Quadratic API to get the user's permissions for a file.
+
+use axum_extra::{
+    headers::{authorization::Bearer, Authorization},
+    TypedHeader,
+};
+use quadratic_rust_shared::quadratic_api::{can_edit, can_view, get_file_perms, FilePermRole};
+use uuid::Uuid;
+
+use crate::{
+    error::{FilesError, Result},
+    state::State,
+};
+
+pub(crate) type Token = Option<TypedHeader<Authorization<Bearer>>>;
+
+/// Get the user's permissions for the file from the Quadratic API.
+pub(crate) async fn file_permissions(
+    state: &State,
+    token: Token,
+    file_id: Uuid,
+) -> Result<Vec<FilePermRole>> {
+    let TypedHeader(Authorization(bearer)) =
+        token.ok_or_else(|| FilesError::Authentication("Missing token".into()))?;
+
+    let (permissions, _) = get_file_perms(
+        &state.settings.quadratic_api_uri,
+        bearer.token().to_owned(),
+        file_id,
+    )
+    .await
+    .map_err(|e| FilesError::Authentication(e.to_string()))?;
+
+    Ok(permissions)
+}
+
+/// Return an error if the user can't view the file.
+pub(crate) async fn validate_can_view(state: &State, token: Token, file_id: Uuid) -> Result<()> {
+    match can_view(&file_permissions(state, token, file_id).await?) {
+        true => Ok(()),
+        false => Err(FilesError::Permissions(format!(
+            "Unable to view file {file_id}"
+        ))),
+    }
+}
+
+/// Return an error if the user can't edit the file.
+pub(crate) async fn validate_can_edit(state: &State, token: Token, file_id: Uuid) -> Result<()> {
+    match can_edit(&file_permissions(state, token, file_id).await?) {
+        true => Ok(()),
+        false => Err(FilesError::Permissions(format!(
+            "Unable to edit file {file_id}"
+        ))),
+    }
+}
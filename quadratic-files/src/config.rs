@@ -7,7 +7,7 @@
 
 use crate::error::{FilesError, Result};
 use dotenv::dotenv;
-use quadratic_rust_shared::environment::Environment;
+use quadratic_rust_shared::{environment::Environment, pubsub::PubSubType, storage::StorageType};
 use serde::Deserialize;
 
 #[allow(dead_code)]
@@ -21,6 +21,8 @@ pub(crate) struct Config {
     pub(crate) truncate_transaction_age_days: i64,
     pub(crate) environment: Environment,
 
+    #[serde(default)]
+    pub(crate) pubsub_type: PubSubType,
     pub(crate) pubsub_host: String,
     pub(crate) pubsub_port: String,
     pub(crate) pubsub_password: String,
@@ -30,9 +32,18 @@ pub(crate) struct Config {
     pub(crate) quadratic_api_uri: String,
     pub(crate) m2m_auth_token: String,
 
+    #[serde(default)]
+    pub(crate) storage_type: StorageType,
+    pub(crate) storage_dir: Option<String>,
+
+    // only required when STORAGE_TYPE=s3
+    #[serde(default)]
     pub(crate) aws_s3_region: String,
+    #[serde(default)]
     pub(crate) aws_s3_bucket_name: String,
+    #[serde(default)]
     pub(crate) aws_s3_access_key_id: String,
+    #[serde(default)]
     pub(crate) aws_s3_secret_access_key: String,
 }
 
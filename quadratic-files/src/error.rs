@@ -5,6 +5,10 @@
 //! Convert third party crate errors to application errors.
 //! Convert errors to responses.
 
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
 use quadratic_rust_shared::{Aws, SharedError};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -34,8 +38,11 @@ pub(crate) enum FilesError {
     #[error("Internal server error: {0}")]
     InternalServer(String),
 
-    #[error("Unable to load file {0} from bucket {1}: {2}")]
-    LoadFile(String, String, String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Permission denied: {0}")]
+    Permissions(String),
 
     #[error("PubSub error: {0}")]
     PubSub(String),
@@ -46,6 +53,15 @@ pub(crate) enum FilesError {
     #[error("Error in S3: {0}")]
     S3(String),
 
+    #[error("File {0} is at sequence number {2}, not {1}")]
+    SequenceNumConflict(String, u64, u64),
+
+    #[error("Sequence number {1} of file {0} is no longer retained")]
+    SequenceNumNotRetained(String, u64),
+
+    #[error("Error in storage: {0}")]
+    Storage(String),
+
     #[error("Error serializing or deserializing: {0}")]
     Serialization(String),
 
@@ -66,6 +82,7 @@ impl From<SharedError> for FilesError {
                 Aws::S3(error) => FilesError::S3(error),
             },
             SharedError::PubSub(error) => FilesError::PubSub(error),
+            SharedError::Storage(error) => FilesError::Storage(error),
             _ => FilesError::Unknown(format!("Unknown Quadratic API error: {error}")),
         }
     }
@@ -94,3 +111,22 @@ impl From<jsonwebtoken::errors::Error> for FilesError {
         FilesError::Authentication(error.to_string())
     }
 }
+
+// Convert FilesErrors into readable responses with appropriate status codes.
+// These are the errors that are returned to the client.
+impl IntoResponse for FilesError {
+    fn into_response(self) -> Response {
+        let (status, error) = match &self {
+            FilesError::Authentication(error) => (StatusCode::UNAUTHORIZED, error.to_owned()),
+            FilesError::Permissions(error) => (StatusCode::FORBIDDEN, error.to_owned()),
+            FilesError::NotFound(error) => (StatusCode::NOT_FOUND, error.to_owned()),
+            FilesError::SequenceNumConflict(..) => (StatusCode::CONFLICT, self.to_string()),
+            FilesError::SequenceNumNotRetained(..) => (StatusCode::GONE, self.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "Unknown".into()),
+        };
+
+        tracing::warn!("{} {}: {:?}", status, error, self);
+
+        (status, error).into_response()
+    }
+}
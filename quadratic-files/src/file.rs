@@ -15,12 +15,9 @@ use quadratic_core::{
     },
 };
 use quadratic_rust_shared::{
-    aws::{
-        s3::{download_object, upload_object},
-        Client,
-    },
     pubsub::PubSub as PubSubTrait,
     quadratic_api::{get_file_checkpoint, set_file_checkpoint},
+    storage::Storage,
 };
 
 use crate::{
@@ -46,20 +43,13 @@ pub(crate) fn apply_transaction(grid: &mut GridController, operations: Vec<Opera
     grid.server_apply_transaction(operations, None)
 }
 
-/// Exports a .grid file
+/// Load a .grid file from storage
 pub(crate) async fn get_and_load_object(
-    client: &Client,
-    bucket: &str,
+    storage: &dyn Storage,
     key: &str,
     sequence_num: u64,
 ) -> Result<GridController> {
-    let file = download_object(client, bucket, key).await?;
-    let body = file
-        .body
-        .collect()
-        .await
-        .map_err(|e| FilesError::LoadFile(key.into(), bucket.to_string(), e.to_string()))?
-        .into_bytes();
+    let body = storage.read(key).await?;
     let grid = load_file(key, body.to_vec())?;
 
     Ok(GridController::from_grid(grid, sequence_num))
@@ -69,18 +59,17 @@ pub(crate) fn key(file_id: Uuid, sequence: u64) -> String {
     format!("{file_id}-{sequence}.grid")
 }
 
-/// Load a file from S3, add it to memory, process transactions and upload it back to S3
+/// Load a file from storage, add it to memory, process transactions and write
+/// it back to storage
 pub(crate) async fn process_transactions(
-    client: &Client,
-    bucket: &str,
+    storage: &dyn Storage,
     file_id: Uuid,
     checkpoint_sequence_num: u64,
     final_sequence_num: u64,
     operations: Vec<Operation>,
 ) -> Result<u64> {
     let mut grid = get_and_load_object(
-        client,
-        bucket,
+        storage,
         &key(file_id, checkpoint_sequence_num),
         checkpoint_sequence_num,
     )
@@ -91,7 +80,7 @@ pub(crate) async fn process_transactions(
     apply_transaction(&mut grid, operations);
     let body = export_file(&key, grid.into_grid())?;
 
-    upload_object(client, bucket, &key, &body).await?;
+    storage.write(&key, &body).await?;
 
     Ok(final_sequence_num)
 }
@@ -106,8 +95,7 @@ pub(crate) async fn process_queue_for_room(
     let channel = &file_id.to_string();
 
     let Settings {
-        aws_client,
-        aws_s3_bucket_name,
+        storage,
         quadratic_api_uri,
         m2m_auth_token,
         ..
@@ -119,7 +107,7 @@ pub(crate) async fn process_queue_for_room(
             Err(_) => 0,
         };
 
-    // this is an expensive lock since we're waiting for the file to write to S3 before unlocking
+    // this is an expensive lock since we're waiting for the file to be written before unlocking
     let mut pubsub = state.pubsub.lock().await;
 
     // subscribe to the channel
@@ -172,10 +160,9 @@ pub(crate) async fn process_queue_for_room(
         .flatten()
         .collect::<Vec<Operation>>();
 
-    // process the transactions and save the file to S3
+    // process the transactions and save the file to storage
     let last_sequence_num = process_transactions(
-        aws_client,
-        aws_s3_bucket_name,
+        storage.as_ref(),
         *file_id,
         checkpoint_sequence_num,
         last_sequence_num,
@@ -207,7 +194,7 @@ pub(crate) async fn process_queue_for_room(
         last_sequence_num,
         CURRENT_VERSION.into(),
         key.to_owned(),
-        aws_s3_bucket_name.to_owned(),
+        storage.path().to_owned(),
     )
     .await?;
 
@@ -261,7 +248,7 @@ pub(crate) async fn process(state: &Arc<State>, active_channels: &str) -> Result
     Ok(())
 }
 
-fn decompress_and_deserialize<T: DeserializeOwned>(data: Vec<u8>) -> Result<T> {
+pub(crate) fn decompress_and_deserialize<T: DeserializeOwned>(data: Vec<u8>) -> Result<T> {
     Transaction::decompress_and_deserialize::<T>(&data)
         .map_err(|e| FilesError::Serialization(e.to_string()))
 }
@@ -270,6 +257,7 @@ fn decompress_and_deserialize<T: DeserializeOwned>(data: Vec<u8>) -> Result<T> {
 mod tests {
     use super::*;
     use quadratic_core::{CellValue, Pos, SheetPos};
+    use quadratic_rust_shared::storage::memory::Memory;
 
     #[test]
     fn loads_a_file_and_applies_a_transaction_and_exports_the_file() {
@@ -317,49 +305,28 @@ mod tests {
 
     #[tokio::test]
     async fn processes_a_file() {
-        // let state = new_arc_state().await;
-        // let Settings {
-        //     aws_client,
-        //     aws_s3_bucket_name,
-        //     quadratic_api_uri,
-        //     m2m_auth_token,
-        //     ..
-        // } = &state.settings;
-
-        // println!("{:?}", aws_s3_bucket_name);
-
-        // let file_id = Uuid::from_str("daf6008f-d858-4a6a-966b-928213048941").unwrap();
-        // let sequence = 0;
-        // let key = key(file_id, sequence);
-
-        // let file = get_and_load_object(&aws_client, aws_s3_bucket_name, &key, 0)
-        //     .await
-        //     .unwrap();
-        // println!("{:?}", file);
-        // return;
-
-        // let mut grid = get_and_load_object(&client, &config.aws_s3_bucket_name, &key)
-        //     .await
-        //     .unwrap();
-        // let sheet_id = grid.sheet_ids().first().unwrap().to_owned();
-        // let sheet_rect = SheetPos {
-        //     x: 0,
-        //     y: 0,
-        //     sheet_id,
-        // }
-        // .into();
-        // let value = CellValue::Text("hello".to_string());
-        // let values = Array::from(value);
-        // let operation = Operation::SetCellValues { sheet_rect, values };
-
-        // process_transactions(
-        //     &client,
-        //     &config.aws_s3_bucket_name,
-        //     file_id,
-        //     sequence,
-        //     vec![operation],
-        // )
-        // .await
-        // .unwrap();
+        let storage = Memory::new();
+        let file_id = Uuid::new_v4();
+        let file = include_bytes!("../../quadratic-rust-shared/data/grid/v1_4_simple.grid");
+        storage.write(&key(file_id, 0), file).await.unwrap();
+
+        let mut gc = GridController::from_grid(load_file("test", file.to_vec()).unwrap(), 0);
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_cell_value(SheetPos::new(sheet_id, 1, 2), "hello".to_string(), None);
+        let operations = gc.last_transaction().unwrap().operations.clone();
+
+        let sequence_num = process_transactions(&storage, file_id, 0, 1, operations)
+            .await
+            .unwrap();
+        assert_eq!(sequence_num, 1);
+
+        let gc = get_and_load_object(&storage, &key(file_id, 1), 1)
+            .await
+            .unwrap();
+        let sheet = gc.grid().try_sheet(sheet_id).unwrap();
+        assert_eq!(
+            sheet.display_value(Pos { x: 1, y: 2 }),
+            Some(CellValue::Text("hello".to_string()))
+        );
     }
 }
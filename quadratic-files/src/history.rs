@@ -0,0 +1,551 @@
+//! Version History
+//!
+//! Checkpoints are the `{file_id}-{sequence_num}.grid` files written to storage as
+//! transactions are processed.  Snapshots are named copies of the grid at a
+//! sequence number.  They are stored next to the checkpoints so that they
+//! outlive truncated transactions.
+//!
+//! A file can be materialized at any sequence number that has a checkpoint or
+//! snapshot at or before it, followed by retained transactions up to it.
+
+use axum::{extract::Path, http::header, response::IntoResponse, Extension, Json};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use quadratic_core::controller::{
+    operations::operation::Operation,
+    transaction::{Transaction, TransactionServer},
+    GridController,
+};
+use quadratic_rust_shared::{pubsub::PubSub as PubSubTrait, storage::Storage};
+
+use crate::{
+    auth::{validate_can_edit, validate_can_view, Token},
+    error::{FilesError, Result},
+    file::{apply_transaction, decompress_and_deserialize, export_file, get_and_load_object, key},
+    state::State,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Checkpoint {
+    pub(crate) sequence_num: u64,
+    pub(crate) key: String,
+    pub(crate) last_modified: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Snapshot {
+    pub(crate) id: Uuid,
+    pub(crate) name: String,
+    pub(crate) sequence_num: u64,
+    pub(crate) key: String,
+    pub(crate) created_date: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct NewSnapshot {
+    pub(crate) name: String,
+    pub(crate) sequence_num: u64,
+}
+
+/// The transaction that restores the live file to an earlier sequence
+/// number, or None if no sheets changed.  It was computed against the live
+/// file at `sequence_num`, so the client should only send it if it hasn't
+/// received a later transaction.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Restore {
+    pub(crate) file_id: Uuid,
+    pub(crate) sequence_num: u64,
+    pub(crate) restored_sequence_num: u64,
+    pub(crate) transaction: Option<RestoreTransaction>,
+}
+
+/// The operations are compressed and base64 encoded, so the client can send
+/// the transaction to the multiplayer server as-is.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct RestoreTransaction {
+    pub(crate) id: Uuid,
+    pub(crate) operations: String,
+}
+
+/// The live sequence number the client has, which the restore is computed
+/// against.
+#[derive(Deserialize, Debug)]
+pub(crate) struct NewRestore {
+    pub(crate) sequence_num: u64,
+}
+
+fn snapshot_prefix(file_id: Uuid) -> String {
+    format!("{file_id}-snapshot-")
+}
+
+pub(crate) fn snapshot_key(file_id: Uuid, snapshot_id: Uuid) -> String {
+    format!("{}{snapshot_id}.grid", snapshot_prefix(file_id))
+}
+
+fn snapshot_metadata_key(file_id: Uuid, snapshot_id: Uuid) -> String {
+    format!("{}{snapshot_id}.json", snapshot_prefix(file_id))
+}
+
+/// Parse the sequence number from a checkpoint key.  Returns None for other
+/// files, such as snapshots and thumbnails.
+pub(crate) fn checkpoint_sequence_num(file_id: Uuid, key: &str) -> Option<u64> {
+    key.strip_prefix(&format!("{file_id}-"))?
+        .strip_suffix(".grid")?
+        .parse::<u64>()
+        .ok()
+}
+
+/// List the file's checkpoints, ordered by sequence number.
+pub(crate) async fn checkpoints(storage: &dyn Storage, file_id: Uuid) -> Result<Vec<Checkpoint>> {
+    let objects = storage.list(&format!("{file_id}-")).await?;
+
+    let mut checkpoints = objects
+        .into_iter()
+        .filter_map(|object| {
+            checkpoint_sequence_num(file_id, &object.key).map(|sequence_num| Checkpoint {
+                sequence_num,
+                key: object.key,
+                last_modified: object.last_modified,
+            })
+        })
+        .collect::<Vec<_>>();
+    checkpoints.sort_by_key(|checkpoint| checkpoint.sequence_num);
+
+    Ok(checkpoints)
+}
+
+/// List the file's snapshots, ordered by sequence number.
+pub(crate) async fn snapshots(storage: &dyn Storage, file_id: Uuid) -> Result<Vec<Snapshot>> {
+    let objects = storage.list(&snapshot_prefix(file_id)).await?;
+    let mut snapshots = vec![];
+
+    for object in objects
+        .iter()
+        .filter(|object| object.key.ends_with(".json"))
+    {
+        let body = storage.read(&object.key).await?;
+        snapshots.push(serde_json::from_slice::<Snapshot>(&body)?);
+    }
+    snapshots.sort_by_key(|snapshot| snapshot.sequence_num);
+
+    Ok(snapshots)
+}
+
+/// Find the latest checkpoint or snapshot at or before the sequence number.
+/// Returns its sequence number and key.
+pub(crate) fn base_for_sequence_num(
+    checkpoints: &[Checkpoint],
+    snapshots: &[Snapshot],
+    sequence_num: u64,
+) -> Option<(u64, String)> {
+    let checkpoints = checkpoints
+        .iter()
+        .map(|checkpoint| (checkpoint.sequence_num, &checkpoint.key));
+    let snapshots = snapshots
+        .iter()
+        .map(|snapshot| (snapshot.sequence_num, &snapshot.key));
+
+    checkpoints
+        .chain(snapshots)
+        .filter(|(base, _)| *base <= sequence_num)
+        .max_by_key(|(base, _)| *base)
+        .map(|(base, key)| (base, key.to_owned()))
+}
+
+/// Whether the transactions cover every sequence number after `from` up to
+/// and including `to`.
+pub(crate) fn is_contiguous(transactions: &[TransactionServer], from: u64, to: u64) -> bool {
+    transactions.len() as u64 == to - from
+        && transactions
+            .iter()
+            .zip(from + 1..)
+            .all(|(transaction, sequence_num)| transaction.sequence_num == sequence_num)
+}
+
+/// Get the retained transactions after the `from` sequence number, up to and
+/// including `to` (or all of them if `to` is None).
+async fn transactions_after(
+    state: &State,
+    file_id: Uuid,
+    from: u64,
+    to: Option<u64>,
+) -> Result<Vec<TransactionServer>> {
+    let mut transactions = state
+        .pubsub
+        .lock()
+        .await
+        .connection
+        .get_messages_from(&file_id.to_string(), &(from + 1).to_string(), false)
+        .await?
+        .into_iter()
+        .map(|(_, message)| decompress_and_deserialize::<TransactionServer>(message))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|transaction| match to {
+            Some(to) => transaction.sequence_num > from && transaction.sequence_num <= to,
+            None => transaction.sequence_num > from,
+        })
+        .collect::<Vec<_>>();
+    transactions.sort_by_key(|transaction| transaction.sequence_num);
+    transactions.dedup_by_key(|transaction| transaction.sequence_num);
+
+    Ok(transactions)
+}
+
+/// Load the grid at the sequence number by replaying retained transactions
+/// on top of the closest checkpoint or snapshot.  If `sequence_num` is None,
+/// the latest state of the file is loaded.  Returns the grid and its
+/// sequence number.
+pub(crate) async fn materialize(
+    state: &State,
+    file_id: Uuid,
+    sequence_num: Option<u64>,
+) -> Result<(GridController, u64)> {
+    let storage = state.settings.storage.as_ref();
+    let checkpoints = checkpoints(storage, file_id).await?;
+    let snapshots = match sequence_num {
+        Some(_) => snapshots(storage, file_id).await?,
+        None => vec![],
+    };
+    let target = sequence_num.unwrap_or(u64::MAX);
+    let (base, base_key) = base_for_sequence_num(&checkpoints, &snapshots, target).ok_or_else(
+        || match sequence_num {
+            Some(sequence_num) => {
+                FilesError::SequenceNumNotRetained(file_id.to_string(), sequence_num)
+            }
+            None => FilesError::NotFound(format!("No checkpoints for file {file_id}")),
+        },
+    )?;
+
+    let transactions = transactions_after(state, file_id, base, sequence_num).await?;
+    let last = transactions
+        .last()
+        .map_or(base, |transaction| transaction.sequence_num);
+    let target = sequence_num.unwrap_or(last);
+
+    if !is_contiguous(&transactions, base, target) {
+        return Err(FilesError::SequenceNumNotRetained(
+            file_id.to_string(),
+            target,
+        ));
+    }
+
+    let mut grid = get_and_load_object(storage, &base_key, base).await?;
+
+    // a transaction that can't be decoded is an error rather than skipped,
+    // which would materialize a grid that never existed
+    let operations = transactions
+        .into_iter()
+        .map(|transaction| decompress_and_deserialize::<Vec<Operation>>(transaction.operations))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<Operation>>();
+    apply_transaction(&mut grid, operations);
+
+    Ok((grid, target))
+}
+
+/// Save a named snapshot of the grid at the sequence number.
+pub(crate) async fn save_snapshot(
+    state: &State,
+    file_id: Uuid,
+    name: String,
+    sequence_num: u64,
+) -> Result<Snapshot> {
+    let (grid, sequence_num) = materialize(state, file_id, Some(sequence_num)).await?;
+    let id = Uuid::new_v4();
+    let snapshot = Snapshot {
+        id,
+        name,
+        sequence_num,
+        key: snapshot_key(file_id, id),
+        created_date: Utc::now(),
+    };
+
+    let storage = state.settings.storage.as_ref();
+    let body = export_file(&snapshot.key, grid.into_grid())?;
+    storage.write(&snapshot.key, &body).await?;
+
+    // the metadata is written last so that listed snapshots always have a grid
+    let metadata = serde_json::to_vec(&snapshot)?;
+    storage
+        .write(&snapshot_metadata_key(file_id, id), &metadata)
+        .await?;
+
+    Ok(snapshot)
+}
+
+/// Create the transaction that brings the live file, which the client has at
+/// `live_sequence_num`, back to the sequence number.  Only the sheets that
+/// changed are restored.
+pub(crate) async fn restore_transactions(
+    state: &State,
+    file_id: Uuid,
+    sequence_num: u64,
+    live_sequence_num: u64,
+) -> Result<Restore> {
+    let (live, current_sequence_num) = materialize(state, file_id, None).await?;
+
+    if current_sequence_num != live_sequence_num {
+        return Err(FilesError::SequenceNumConflict(
+            file_id.to_string(),
+            live_sequence_num,
+            current_sequence_num,
+        ));
+    }
+
+    let (version, restored_sequence_num) = materialize(state, file_id, Some(sequence_num)).await?;
+    let operations = live.restore_grid_operations(version.into_grid());
+    let transaction = match operations.is_empty() {
+        true => None,
+        false => {
+            let compressed = Transaction::serialize_and_compress(&operations)
+                .map_err(|e| FilesError::Serialization(e.to_string()))?;
+
+            Some(RestoreTransaction {
+                id: Uuid::new_v4(),
+                operations: STANDARD.encode(compressed),
+            })
+        }
+    };
+
+    Ok(Restore {
+        file_id,
+        sequence_num: current_sequence_num,
+        restored_sequence_num,
+        transaction,
+    })
+}
+
+/// List the file's checkpoints.
+pub(crate) async fn get_checkpoints(
+    token: Token,
+    Extension(state): Extension<Arc<State>>,
+    Path(file_id): Path<Uuid>,
+) -> Result<Json<Vec<Checkpoint>>> {
+    validate_can_view(&state, token, file_id).await?;
+
+    Ok(Json(
+        checkpoints(state.settings.storage.as_ref(), file_id).await?,
+    ))
+}
+
+/// List the file's snapshots.
+pub(crate) async fn get_snapshots(
+    token: Token,
+    Extension(state): Extension<Arc<State>>,
+    Path(file_id): Path<Uuid>,
+) -> Result<Json<Vec<Snapshot>>> {
+    validate_can_view(&state, token, file_id).await?;
+
+    Ok(Json(
+        snapshots(state.settings.storage.as_ref(), file_id).await?,
+    ))
+}
+
+/// Create a named snapshot.
+pub(crate) async fn create_snapshot(
+    token: Token,
+    Extension(state): Extension<Arc<State>>,
+    Path(file_id): Path<Uuid>,
+    Json(snapshot): Json<NewSnapshot>,
+) -> Result<Json<Snapshot>> {
+    validate_can_edit(&state, token, file_id).await?;
+    let snapshot = save_snapshot(&state, file_id, snapshot.name, snapshot.sequence_num).await?;
+
+    Ok(Json(snapshot))
+}
+
+/// Download the .grid file at the sequence number.
+pub(crate) async fn get_sequence(
+    token: Token,
+    Extension(state): Extension<Arc<State>>,
+    Path((file_id, sequence_num)): Path<(Uuid, u64)>,
+) -> Result<impl IntoResponse> {
+    validate_can_view(&state, token, file_id).await?;
+    let (grid, _) = materialize(&state, file_id, Some(sequence_num)).await?;
+    let key = key(file_id, sequence_num);
+    let body = export_file(&key, grid.into_grid())?;
+
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], body))
+}
+
+/// Create the transaction that restores the file to the sequence number.
+pub(crate) async fn restore_sequence(
+    token: Token,
+    Extension(state): Extension<Arc<State>>,
+    Path((file_id, sequence_num)): Path<(Uuid, u64)>,
+    Json(restore): Json<NewRestore>,
+) -> Result<Json<Restore>> {
+    validate_can_edit(&state, token, file_id).await?;
+
+    Ok(Json(
+        restore_transactions(&state, file_id, sequence_num, restore.sequence_num).await?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use quadratic_rust_shared::storage::memory::Memory;
+
+    use super::*;
+    use crate::test_util::new_arc_state;
+
+    fn checkpoint(file_id: Uuid, sequence_num: u64) -> Checkpoint {
+        Checkpoint {
+            sequence_num,
+            key: key(file_id, sequence_num),
+            last_modified: None,
+        }
+    }
+
+    fn transaction(file_id: Uuid, sequence_num: u64) -> TransactionServer {
+        TransactionServer {
+            id: Uuid::new_v4(),
+            file_id,
+            operations: vec![],
+            sequence_num,
+        }
+    }
+
+    #[test]
+    fn parses_checkpoint_keys() {
+        let file_id = Uuid::new_v4();
+
+        assert_eq!(
+            checkpoint_sequence_num(file_id, &key(file_id, 12)),
+            Some(12)
+        );
+        assert_eq!(
+            checkpoint_sequence_num(file_id, &snapshot_key(file_id, Uuid::new_v4())),
+            None
+        );
+        assert_eq!(
+            checkpoint_sequence_num(file_id, &format!("{file_id}-thumbnail.png")),
+            None
+        );
+        assert_eq!(
+            checkpoint_sequence_num(file_id, &key(Uuid::new_v4(), 1)),
+            None
+        );
+    }
+
+    #[test]
+    fn finds_the_closest_base() {
+        let file_id = Uuid::new_v4();
+        let checkpoints = vec![checkpoint(file_id, 0), checkpoint(file_id, 10)];
+        let snapshot_id = Uuid::new_v4();
+        let snapshots = vec![Snapshot {
+            id: snapshot_id,
+            name: "before cleanup".into(),
+            sequence_num: 15,
+            key: snapshot_key(file_id, snapshot_id),
+            created_date: Utc::now(),
+        }];
+
+        assert_eq!(
+            base_for_sequence_num(&checkpoints, &snapshots, 9),
+            Some((0, key(file_id, 0)))
+        );
+        assert_eq!(
+            base_for_sequence_num(&checkpoints, &snapshots, 10),
+            Some((10, key(file_id, 10)))
+        );
+        assert_eq!(
+            base_for_sequence_num(&checkpoints, &snapshots, 20),
+            Some((15, snapshot_key(file_id, snapshot_id)))
+        );
+        assert_eq!(base_for_sequence_num(&checkpoints[1..], &[], 5), None);
+    }
+
+    #[tokio::test]
+    async fn lists_checkpoints_and_snapshots() {
+        let storage = Memory::new();
+        let file_id = Uuid::new_v4();
+        let snapshot_id = Uuid::new_v4();
+        let snapshot = Snapshot {
+            id: snapshot_id,
+            name: "before cleanup".into(),
+            sequence_num: 5,
+            key: snapshot_key(file_id, snapshot_id),
+            created_date: Utc::now(),
+        };
+
+        for sequence_num in [10, 2, 0] {
+            storage
+                .write(&key(file_id, sequence_num), b"")
+                .await
+                .unwrap();
+        }
+        storage.write(&snapshot.key, b"").await.unwrap();
+        storage
+            .write(
+                &snapshot_metadata_key(file_id, snapshot_id),
+                &serde_json::to_vec(&snapshot).unwrap(),
+            )
+            .await
+            .unwrap();
+        storage.write(&key(Uuid::new_v4(), 1), b"").await.unwrap();
+
+        let sequence_nums = checkpoints(&storage, file_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|checkpoint| checkpoint.sequence_num)
+            .collect::<Vec<_>>();
+        assert_eq!(sequence_nums, vec![0, 2, 10]);
+        assert_eq!(snapshots(&storage, file_id).await.unwrap(), vec![snapshot]);
+    }
+
+    #[test]
+    fn requires_contiguous_transactions() {
+        let file_id = Uuid::new_v4();
+        let transactions = (11..=13)
+            .map(|sequence_num| transaction(file_id, sequence_num))
+            .collect::<Vec<_>>();
+
+        assert!(is_contiguous(&transactions, 10, 13));
+        assert!(is_contiguous(&[], 10, 10));
+        assert!(!is_contiguous(&transactions, 10, 14));
+        assert!(!is_contiguous(&transactions[1..], 10, 13));
+    }
+
+    #[tokio::test]
+    async fn materialize_fails_on_undecodable_operations() {
+        let state = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        let file = include_bytes!("../../quadratic-rust-shared/data/grid/v1_4_simple.grid");
+        state
+            .settings
+            .storage
+            .write(&key(file_id, 0), file)
+            .await
+            .unwrap();
+
+        let mut invalid = transaction(file_id, 1);
+        invalid.operations = vec![0, 1, 2];
+        let message = Transaction::serialize_and_compress(&invalid).unwrap();
+        state
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .publish(&file_id.to_string(), "1", &message, None)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            materialize(&state, file_id, None).await,
+            Err(FilesError::Serialization(_))
+        ));
+
+        // the transaction isn't needed before its sequence number
+        let (_, sequence_num) = materialize(&state, file_id, Some(0)).await.unwrap();
+        assert_eq!(sequence_num, 0);
+    }
+}
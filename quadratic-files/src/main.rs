@@ -1,11 +1,13 @@
 //! Quadratic File Service
 //!
 //! A file servic for that consumes transactions from a queue, applies them to
-//! a grid and writes them to S3.
+//! a grid and writes them to S3.  Also serves the version history of files.
 
+mod auth;
 mod config;
 mod error;
 mod file;
+mod history;
 mod server;
 mod state;
 #[cfg(test)]
@@ -3,9 +3,13 @@
 //! Handle bootstrapping and starting the HTTP server.  Adds global state
 //! to be shared across all requests and threads.  Adds tracing/logging.
 
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::IntoResponse;
-use axum::{routing::get, Extension, Router};
+use axum::{
+    routing::{get, post},
+    Extension, Router,
+};
+use quadratic_rust_shared::metrics::CONTENT_TYPE;
 use std::time::Duration;
 use std::{net::SocketAddr, sync::Arc};
 use tokio::time;
@@ -17,6 +21,7 @@ use crate::{
     config::config,
     error::{FilesError, Result},
     file::process,
+    history::{create_snapshot, get_checkpoints, get_sequence, get_snapshots, restore_sequence},
     state::State,
 };
 
@@ -28,6 +33,17 @@ pub(crate) fn app(state: Arc<State>) -> Router {
     Router::new()
         // routes
         .route("/health", get(healthcheck))
+        .route("/metrics", get(metrics))
+        .route("/files/:file_id/checkpoints", get(get_checkpoints))
+        .route(
+            "/files/:file_id/snapshots",
+            get(get_snapshots).post(create_snapshot),
+        )
+        .route("/files/:file_id/sequence/:sequence_num", get(get_sequence))
+        .route(
+            "/files/:file_id/sequence/:sequence_num/restore",
+            post(restore_sequence),
+        )
         // state
         .layer(Extension(state))
         // logger
@@ -146,6 +162,13 @@ pub(crate) async fn healthcheck() -> impl IntoResponse {
     StatusCode::OK
 }
 
+pub(crate) async fn metrics(Extension(state): Extension<Arc<State>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, CONTENT_TYPE)],
+        state.stats.lock().await.to_prometheus(),
+    )
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use crate::test_util::new_arc_state;
@@ -175,4 +198,23 @@ pub(crate) mod tests {
 
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn requires_a_token_for_version_history() {
+        let state = new_arc_state().await;
+        let app = app(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri(format!("/files/{}/checkpoints", uuid::Uuid::new_v4()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
 }
@@ -7,8 +7,10 @@ pub mod pubsub;
 pub mod settings;
 pub mod stats;
 
+#[cfg(any(test, feature = "memory-pubsub"))]
+use quadratic_rust_shared::pubsub::memory::{MemoryConfig, DEFAULT_NAME};
 use quadratic_rust_shared::pubsub::redis_streams::RedisStreamsConfig;
-use quadratic_rust_shared::pubsub::Config as PubSubConfig;
+use quadratic_rust_shared::pubsub::{Config as PubSubConfig, PubSubType};
 use tokio::sync::Mutex;
 
 use crate::config::Config;
@@ -27,16 +29,29 @@ pub(crate) struct State {
 
 impl State {
     pub(crate) async fn new(config: &Config) -> Result<Self> {
-        let pubsub_config = PubSubConfig::RedisStreams(RedisStreamsConfig {
-            host: config.pubsub_host.to_owned(),
-            port: config.pubsub_port.to_owned(),
-            password: config.pubsub_password.to_owned(),
-            active_channels: config.pubsub_active_channels.to_owned(),
-        });
+        let pubsub_config = match config.pubsub_type {
+            PubSubType::RedisStreams => PubSubConfig::RedisStreams(RedisStreamsConfig {
+                host: config.pubsub_host.to_owned(),
+                port: config.pubsub_port.to_owned(),
+                password: config.pubsub_password.to_owned(),
+                active_channels: config.pubsub_active_channels.to_owned(),
+            }),
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            PubSubType::Memory => PubSubConfig::Memory(MemoryConfig {
+                name: DEFAULT_NAME.into(),
+                active_channels: config.pubsub_active_channels.to_owned(),
+            }),
+            #[cfg(not(any(test, feature = "memory-pubsub")))]
+            PubSubType::Memory => {
+                return Err(crate::error::FilesError::Config(
+                    "The memory PubSub needs the memory-pubsub feature".into(),
+                ))
+            }
+        };
 
         Ok(State {
             pubsub: Mutex::new(PubSub::new(pubsub_config).await?),
-            settings: Settings::new(config).await,
+            settings: Settings::new(config).await?,
             stats: Mutex::new(Stats::new()),
         })
     }
@@ -1,3 +1,4 @@
+use quadratic_rust_shared::metrics::Metrics;
 use serde::Serialize;
 use std::fmt::Display;
 use tokio::time::Instant;
@@ -49,4 +50,57 @@ impl Stats {
     pub(crate) fn new() -> Self {
         Stats::default()
     }
+
+    /// Render the stats in the Prometheus text format.
+    pub(crate) fn to_prometheus(&self) -> String {
+        let mut metrics = Metrics::new("quadratic_files");
+        metrics
+            .gauge(
+                "files_to_process_in_pubsub",
+                "Number of files with transactions waiting to be processed.",
+                self.files_to_process_in_pubsub,
+            )
+            .gauge(
+                "channels_to_truncate_in_pubsub",
+                "Number of channels with processed transactions to truncate.",
+                self.channels_to_truncate_in_pubsub,
+            );
+
+        if let Some(time) = self.last_processed_file_time {
+            metrics.gauge(
+                "last_processed_file_seconds",
+                "Seconds since a file was last processed.",
+                time.elapsed().as_secs(),
+            );
+        }
+
+        if let Some(time) = self.last_truncated_transaction_time {
+            metrics.gauge(
+                "last_truncated_transaction_seconds",
+                "Seconds since transactions were last truncated.",
+                time.elapsed().as_secs(),
+            );
+        }
+
+        metrics.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_prometheus_metrics() {
+        let mut stats = Stats::new();
+        stats.files_to_process_in_pubsub = 2;
+
+        let metrics = stats.to_prometheus();
+        assert!(metrics.contains("quadratic_files_files_to_process_in_pubsub 2\n"));
+        assert!(!metrics.contains("last_processed_file_seconds"));
+
+        stats.last_processed_file_time = Some(Instant::now());
+        let metrics = stats.to_prometheus();
+        assert!(metrics.contains("quadratic_files_last_processed_file_seconds 0\n"));
+    }
 }
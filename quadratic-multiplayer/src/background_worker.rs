@@ -13,7 +13,9 @@ use crate::{
 /// In a separate thread:
 ///   * Process transaction queue for the room
 ///   * Broadcast sequence number to all users in the room
+///   * Publish the room's users to the other multiplayer instances
 ///   * Check for stale users in rooms and remove them.
+///   * Update the transactions per second for metrics
 #[tracing::instrument(level = "trace")]
 pub(crate) fn start(
     state: Arc<State>,
@@ -29,6 +31,8 @@ pub(crate) fn start(
             // reconnect if pubsub connection is unhealthy
             state.pubsub.lock().await.reconnect_if_unhealthy().await;
 
+            state.stats.lock().await.update_transactions_per_second();
+
             // get all room ids
             let rooms = state
                 .rooms
@@ -52,6 +56,9 @@ pub(crate) fn start(
                         tracing::warn!("Error broadcasting sequence number: {:?}", error);
                     }
 
+                    // keep this instance's users fresh on the other instances
+                    state.publish_users(*file_id, false).await;
+
                     // remove stale users in the room
                     let removed = remove_stale_users_in_room(
                         Arc::clone(&state),
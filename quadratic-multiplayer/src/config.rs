@@ -7,7 +7,7 @@
 
 use crate::error::{MpError, Result};
 use dotenv::dotenv;
-use quadratic_rust_shared::environment::Environment;
+use quadratic_rust_shared::{environment::Environment, pubsub::PubSubType, storage::StorageType};
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug)]
@@ -19,14 +19,46 @@ pub(crate) struct Config {
     pub(crate) heartbeat_timeout_s: i64,
     pub(crate) environment: Environment,
 
+    #[serde(default)]
+    pub(crate) pubsub_type: PubSubType,
     pub(crate) pubsub_host: String,
     pub(crate) pubsub_port: String,
     pub(crate) pubsub_password: String,
     pub(crate) pubsub_active_channels: String,
+    pub(crate) pubsub_fanout_channel: String,
 
     pub(crate) auth0_jwks_uri: String,
     pub(crate) quadratic_api_uri: String,
     pub(crate) m2m_auth_token: String,
+    // the admin endpoints are disabled when this isn't set
+    #[serde(default)]
+    pub(crate) admin_auth_token: String,
+
+    #[serde(default = "default_max_transaction_size_bytes")]
+    pub(crate) max_transaction_size_bytes: usize,
+    // reject transactions that can't be applied to the room's grid, which
+    // is always checked for protections
+    #[serde(default)]
+    pub(crate) validate_transactions_dry_run: bool,
+
+    // where checkpoints are loaded from, for rooms' grids and catching up clients
+    #[serde(default)]
+    pub(crate) storage_type: StorageType,
+    pub(crate) storage_dir: Option<String>,
+
+    // only required when STORAGE_TYPE=s3
+    #[serde(default)]
+    pub(crate) aws_s3_region: String,
+    #[serde(default)]
+    pub(crate) aws_s3_bucket_name: String,
+    #[serde(default)]
+    pub(crate) aws_s3_access_key_id: String,
+    #[serde(default)]
+    pub(crate) aws_s3_secret_access_key: String,
+}
+
+fn default_max_transaction_size_bytes() -> usize {
+    10 * 1024 * 1024
 }
 
 /// Load the global configuration from the environment into Config.
@@ -60,12 +60,18 @@ pub(crate) enum MpError {
     #[error("Internal server error: {0}")]
     InternalServer(String),
 
+    #[error("Transaction {0} is invalid: {1}")]
+    InvalidTransaction(Uuid, String),
+
     #[error("Error reading MinVersion file: {0}")]
     MinVersion(String),
 
     #[error("Requested {0} transactions but only found {1}")]
     MissingTransactions(String, String),
 
+    #[error("Transaction {0} changes protected cells: {1}")]
+    ProtectedCells(Uuid, String),
+
     #[error("PubSub error: {0}")]
     PubSub(String),
 
@@ -90,9 +96,15 @@ pub(crate) enum MpError {
     #[error("Error serializing or deserializing: {0}")]
     Serialization(String),
 
+    #[error("Error in storage: {0}")]
+    Storage(String),
+
     #[error("Transaction queue error: {0}")]
     TransactionQueue(String),
 
+    #[error("Transaction {0} is {1} bytes, which exceeds the limit of {2} bytes")]
+    TransactionTooLarge(Uuid, usize, usize),
+
     #[error("unknown error: {0}")]
     Unknown(String),
 
@@ -111,6 +123,7 @@ impl From<SharedError> for MpError {
                 Aws::S3(error) => MpError::S3(error),
             },
             SharedError::PubSub(error) => MpError::PubSub(error),
+            SharedError::Storage(error) => MpError::Storage(error),
             _ => MpError::Unknown(format!("Unknown Quadratic API error: {error}")),
         }
     }
@@ -6,7 +6,6 @@
 //! to all users in a room.
 
 use axum::extract::ws::{Message, WebSocket};
-use base64::{engine::general_purpose::STANDARD, Engine};
 use futures_util::stream::SplitSink;
 use quadratic_rust_shared::quadratic_api::{get_file_perms, FilePermRole};
 use std::sync::Arc;
@@ -14,21 +13,23 @@ use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::error::{ErrorLevel, MpError, Result};
-use crate::get_mut_room;
 use crate::message::response::Transaction;
+use crate::message::validate::decode_operations;
 use crate::message::{
-    broadcast, request::MessageRequest, response::MessageResponse, send_user_message,
+    broadcast, broadcast_all, request::MessageRequest, response::MessageResponse, send_user_message,
 };
 use crate::permissions::{
-    validate_can_edit_or_view_file, validate_user_can_edit_file,
+    edit_user, validate_can_edit_or_view_file, validate_user_can_edit_file,
     validate_user_can_edit_or_view_file,
 };
 use crate::state::{
     connection::PreConnection,
+    grid::{apply_transaction, contiguous_transactions},
     pubsub::GROUP_NAME,
     user::{User, UserState},
     State,
 };
+use crate::{get_mut_room, get_room};
 
 /// Handle incoming messages.  All requests and responses are strictly typed.
 #[tracing::instrument(level = "trace")]
@@ -108,7 +109,9 @@ pub(crate) async fn handle_message(
                 permissions,
                 state: user_state,
                 socket: Some(Arc::clone(&sender)),
+                protocol: pre_connection.protocol,
                 last_heartbeat: chrono::Utc::now(),
+                instance_id: None,
 
                 // this will be properly set in the enter_room function
                 index: 0,
@@ -155,9 +158,9 @@ pub(crate) async fn handle_message(
             validate_user_can_edit_or_view_file(Arc::clone(&state), file_id, session_id).await?;
 
             let is_not_empty = state.leave_room(file_id, &session_id).await?;
-            let room = state.get_room(&file_id).await?;
 
             if is_not_empty {
+                let room = state.get_room(&file_id).await?;
                 let response = MessageResponse::from((room.users, &state.settings.min_version));
                 broadcast(vec![session_id], file_id, Arc::clone(&state), response);
             }
@@ -178,25 +181,56 @@ pub(crate) async fn handle_message(
             state.update_user_heartbeat(file_id, &session_id).await?;
 
             tracing::trace!(
-                "Transaction received for room {} from user {}, operations: {:?}",
+                "Transaction received for room {} from user {}, {} bytes of operations",
                 file_id,
                 session_id,
-                &operations
+                operations.len()
             );
 
-            // get and increment the room's sequence_num
-            let room_sequence_num = get_mut_room!(state, file_id)?.increment_sequence_num();
-            let decoded_operations = STANDARD.decode(&operations).map_err(|e| {
-                MpError::Serialization(format!(
-                    "Could not decode base64 encoded operations in transaction {id}: {:?}",
-                    e
-                ))
-            })?;
-
-            // add the transaction to the transaction queue
-            let sequence_num = state
-                .push_pubsub(id, file_id, decoded_operations, room_sequence_num)
-                .await?;
+            // reject malformed or oversized operations before they are sequenced
+            let core_operations =
+                decode_operations(id, &operations, state.settings.max_transaction_size_bytes)?;
+            let user = state.get_room(&file_id).await?.get_user(&session_id)?;
+
+            // lock the room's grid until the transaction is sequenced, so
+            // transactions are checked for protections and applied to it in
+            // sequence order
+            let grid = state.lock_grid(file_id).await?;
+
+            // mentions are compared to the grid before the transaction
+            let mentions = MessageResponse::comment_mentions(
+                file_id,
+                &user.user_id,
+                &core_operations,
+                grid.as_ref(),
+            );
+
+            let mut grid = apply_transaction(
+                id,
+                grid,
+                edit_user(&user),
+                core_operations,
+                state.settings.validate_transactions_dry_run,
+            )
+            .await?;
+
+            // add the transaction to the transaction queue, which assigns the
+            // next sequence_num across all multiplayer instances
+            let room_sequence_num = get_room!(state, file_id)?.sequence_num;
+            let pushed = state
+                .push_pubsub(id, file_id, operations.to_owned(), room_sequence_num)
+                .await;
+
+            // the cached grid no longer matches the transaction queue
+            if pushed.is_err() {
+                *grid = None;
+            }
+
+            let sequence_num = pushed?;
+            drop(grid);
+
+            get_mut_room!(state, file_id)?.update_sequence_num(sequence_num);
+            state.stats.lock().await.transactions += 1;
 
             // broadcast the transaction to all users in the room
             let response = MessageResponse::Transaction {
@@ -206,7 +240,12 @@ pub(crate) async fn handle_message(
                 sequence_num,
             };
 
-            broadcast(vec![], file_id, Arc::clone(&state), response);
+            broadcast_all(vec![], file_id, Arc::clone(&state), response);
+
+            // notify users mentioned in comments
+            for mention in mentions {
+                broadcast_all(vec![session_id], file_id, Arc::clone(&state), mention);
+            }
 
             Ok(None)
         }
@@ -224,16 +263,8 @@ pub(crate) async fn handle_message(
 
             let sequence_num = state.get_sequence_num(&file_id).await?;
 
-            // calculate the expected number of transactions to get from redis
-            // add 1 to include the min_sequence_num (inclusive range)
-            let expected_num_transactions = sequence_num
-                .checked_sub(min_sequence_num)
-                .unwrap_or_default()
-                + 1;
-
             tracing::trace!("min_sequence_num: {}", min_sequence_num);
             tracing::trace!("sequence_num: {}", sequence_num);
-            tracing::trace!("expected_num_transactions: {}", expected_num_transactions);
 
             let transactions = state
                 .get_messages_from_pubsub(&file_id, min_sequence_num)
@@ -244,17 +275,28 @@ pub(crate) async fn handle_message(
 
             tracing::trace!("got: {}", transactions.len());
 
-            // we don't have the expected number of transactions
-            // send an error to the client so they can reload
-            if transactions.len() < expected_num_transactions as usize {
-                return Ok(Some(MessageResponse::Error {
-                    error: MpError::MissingTransactions(
-                        expected_num_transactions.to_string(),
-                        transactions.len().to_string(),
-                    ),
-                    error_level: ErrorLevel::Error,
-                }));
-            }
+            // we don't have every transaction the client needs, so catch the
+            // client up from the latest checkpoint, or send an error to the
+            // client so they can reload
+            let transactions =
+                match contiguous_transactions(transactions, min_sequence_num, sequence_num) {
+                    Ok(transactions) => transactions,
+                    Err(error) => {
+                        match state.catch_up(file_id, sequence_num).await {
+                            Ok(response) => return Ok(Some(response)),
+                            Err(catch_up_error) => {
+                                tracing::warn!(
+                                    "Unable to catch up file {file_id}: {catch_up_error}"
+                                )
+                            }
+                        }
+
+                        return Ok(Some(MessageResponse::Error {
+                            error,
+                            error_level: ErrorLevel::Error,
+                        }));
+                    }
+                };
 
             let response = MessageResponse::Transactions { transactions };
 
@@ -282,7 +324,7 @@ pub(crate) async fn handle_message(
                 update,
             };
 
-            broadcast(vec![session_id], file_id, Arc::clone(&state), response);
+            broadcast_all(vec![session_id], file_id, Arc::clone(&state), response);
 
             Ok(None)
         }
@@ -305,7 +347,11 @@ pub(crate) async fn handle_message(
 pub(crate) mod tests {
     use quadratic_core::controller::operations::operation::Operation;
     use quadratic_core::controller::transaction::Transaction as CoreTransaction;
+    use quadratic_core::controller::GridController;
+    use quadratic_core::grid::sheet::comments::{Comment, CommentThread};
+    use quadratic_core::grid::sheet::protections::EditUser;
     use quadratic_core::grid::SheetId;
+    use quadratic_core::Pos;
     use tokio::net::TcpStream;
     use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
     use uuid::Uuid;
@@ -313,7 +359,7 @@ pub(crate) mod tests {
     use super::*;
     use crate::state::settings::MinVersion;
     use crate::state::user::{CellEdit, UserStateUpdate};
-    use crate::test_util::{integration_test_receive, new_user, setup};
+    use crate::test_util::{cache_grid, integration_test_receive, new_user, setup};
 
     async fn test_handle(
         socket: Arc<Mutex<WebSocketStream<MaybeTlsStream<TcpStream>>>>,
@@ -469,19 +515,18 @@ pub(crate) mod tests {
             color: Some("red".to_string()),
         }];
         let compressed_ops = CoreTransaction::serialize_and_compress(&operations).unwrap();
-        let encoded_ops = STANDARD.encode(&compressed_ops);
 
         let request = MessageRequest::Transaction {
             id,
             file_id,
             session_id,
-            operations: encoded_ops.clone(),
+            operations: compressed_ops.clone(),
         };
 
         let response = MessageResponse::Transaction {
             id,
             file_id,
-            operations: encoded_ops.clone(),
+            operations: compressed_ops.clone(),
             sequence_num: 1,
         };
 
@@ -505,7 +550,7 @@ pub(crate) mod tests {
         let transaction = Transaction {
             id,
             file_id,
-            operations: encoded_ops,
+            operations: compressed_ops,
             sequence_num: 1,
         };
         let response = MessageResponse::Transactions {
@@ -524,6 +569,132 @@ pub(crate) mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn handle_invalid_transaction() {
+        let (_, state, _, file_id, user_1, _) = setup().await;
+        let id = Uuid::new_v4();
+        let session_id = user_1.session_id;
+        let stream = state
+            ._get_user_in_room(&file_id, &session_id)
+            .await
+            .unwrap()
+            .socket
+            .unwrap();
+
+        let request = MessageRequest::Transaction {
+            id,
+            file_id,
+            session_id,
+            operations: b"not operations".to_vec(),
+        };
+
+        let error = handle_message(request, state.clone(), stream, PreConnection::new(None))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MpError::InvalidTransaction(error_id, _) if error_id == id));
+
+        // the transaction was not sequenced
+        assert_eq!(state.get_sequence_num(&file_id).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn handle_protected_transaction() {
+        let (_socket, state, _, file_id, user_1, _) = setup().await;
+        let session_id = user_1.session_id;
+        let mut grid = GridController::test();
+        let sheet_id = grid.sheet_ids()[0];
+        grid.set_edit_user(EditUser {
+            user_id: "owner".into(),
+            roles: vec![],
+        });
+        grid.add_protection(sheet_id, None, vec![], vec![], None);
+        cache_grid(&state, file_id, grid).await;
+        let stream = state
+            ._get_user_in_room(&file_id, &session_id)
+            .await
+            .unwrap()
+            .socket
+            .unwrap();
+
+        // protections are checked without VALIDATE_TRANSACTIONS_DRY_RUN
+        assert!(!state.settings.validate_transactions_dry_run);
+
+        let operations = vec![Operation::SetSheetColor {
+            sheet_id,
+            color: Some("red".to_string()),
+        }];
+        let request = MessageRequest::Transaction {
+            id: Uuid::new_v4(),
+            file_id,
+            session_id,
+            operations: CoreTransaction::serialize_and_compress(&operations).unwrap(),
+        };
+        let error = handle_message(request, state.clone(), stream, PreConnection::new(None))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MpError::ProtectedCells(_, _)));
+
+        // the transaction was not sequenced
+        assert_eq!(state.get_sequence_num(&file_id).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn handle_comment_mention() {
+        let (socket, state, _, file_id, user_1, user_2) = setup().await;
+        let session_id = user_1.session_id;
+        let sheet_id = SheetId::new();
+        // the author in the payload is ignored
+        let comment = Comment::new(
+            user_2.user_id.clone(),
+            "take a look".into(),
+            vec![user_2.user_id.clone()],
+        );
+        let thread = CommentThread::new(Pos { x: 0, y: 0 }, comment.clone());
+        let operations = vec![Operation::SetCommentThread {
+            sheet_id,
+            thread_id: thread.id,
+            thread: Some(thread.clone()),
+        }];
+        let stream = state
+            ._get_user_in_room(&file_id, &session_id)
+            .await
+            .unwrap()
+            .socket
+            .unwrap();
+
+        let request = MessageRequest::Transaction {
+            id: Uuid::new_v4(),
+            file_id,
+            session_id,
+            operations: CoreTransaction::serialize_and_compress(&operations).unwrap(),
+        };
+        handle_message(request, state.clone(), stream, PreConnection::new(None))
+            .await
+            .unwrap();
+
+        let expected = MessageResponse::CommentMention {
+            file_id,
+            sheet_id: Uuid::parse_str(&sheet_id.to_string()).unwrap(),
+            thread_id: thread.id,
+            comment_id: comment.id,
+            author: user_1.user_id,
+            body: comment.body,
+            mentions: vec![user_2.user_id],
+        };
+
+        // both users receive the transaction, only user_2 receives the mention,
+        // after the list of users that's left from setup
+        let mut received = vec![];
+        for _ in 0..4 {
+            received.push(integration_test_receive(&socket, 1).await.unwrap());
+        }
+        let mentions = received
+            .iter()
+            .filter(|response| matches!(response, MessageResponse::CommentMention { .. }))
+            .collect::<Vec<_>>();
+        assert_eq!(mentions, vec![&expected]);
+    }
+
     #[tokio::test]
     async fn handle_missing_transactions() {
         let (socket, state, _, file_id, user_1, _) = setup().await;
@@ -538,14 +709,15 @@ pub(crate) mod tests {
         // increment the sequence_num
         get_mut_room!(state, file_id)
             .unwrap()
-            .increment_sequence_num();
+            .update_sequence_num(1);
 
         let response = MessageResponse::Error {
             error: MpError::MissingTransactions("1".into(), "0".into()), // requested 1, got 0
             error_level: ErrorLevel::Error,
         };
 
-        // expect an error since we're requesting a higher sequence_num
+        // expect an error since we're requesting a higher sequence_num and
+        // there's no checkpoint to catch up from
         test_handle(
             socket,
             state,
@@ -1,6 +1,6 @@
-use axum::extract::ws::Message;
 use futures_util::SinkExt;
 use serde::{Deserialize, Serialize};
+use std::collections::{hash_map::Entry, HashMap};
 use std::sync::Arc;
 use tokio::task::JoinHandle;
 use uuid::Uuid;
@@ -10,8 +10,10 @@ use crate::message::response::MessageResponse;
 use crate::state::State;
 
 pub mod handle;
+pub mod protocol;
 pub mod request;
 pub mod response;
+pub mod validate;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub(crate) struct CellEdit {
@@ -67,23 +69,28 @@ pub(crate) fn broadcast(
     tokio::spawn(async move {
         if let Ok(room) = state.get_room(&file_id).await {
             let result = async {
-                let included_users = room
-                    .users
-                    .iter()
-                    .filter(|user| !exclude.contains(&user.session_id));
+                let included_users = room.users.iter().filter(|user| {
+                    !exclude.contains(&user.session_id) && message.is_for_user(user)
+                });
 
                 if included_users.clone().count() == 0 {
                     return Ok::<_, MpError>(());
                 }
 
-                let serialized_message = serde_json::to_string(&message)?;
+                // encode the message once for each protocol in use
+                let mut encoded_messages = HashMap::new();
 
                 for user in included_users {
                     if let Some(sender) = &user.socket {
+                        let encoded_message = match encoded_messages.entry(user.protocol) {
+                            Entry::Occupied(entry) => entry.into_mut(),
+                            Entry::Vacant(entry) => entry.insert(user.protocol.encode(&message)?),
+                        };
+
                         let sent = sender
                             .lock()
                             .await
-                            .send(Message::Text(serialized_message.clone()))
+                            .send(encoded_message.clone())
                             .await
                             .map_err(|e| MpError::SendingMessage(e.to_string()));
 
@@ -111,6 +118,31 @@ pub(crate) fn broadcast(
     })
 }
 
+/// Broadcast a message to all users in a room except the sender, including
+/// users connected to other multiplayer instances.
+#[tracing::instrument(level = "trace")]
+pub(crate) fn broadcast_all(
+    exclude: Vec<Uuid>,
+    file_id: Uuid,
+    state: Arc<State>,
+    message: MessageResponse,
+) -> JoinHandle<()> {
+    let fanout_state = Arc::clone(&state);
+    let fanout_exclude = exclude.clone();
+    let fanout_message = message.clone();
+
+    tokio::spawn(async move {
+        if let Err(e) = fanout_state
+            .publish_fanout(fanout_exclude, file_id, fanout_message)
+            .await
+        {
+            tracing::warn!("Error fanning out message: {:?}", e.to_string());
+        }
+    });
+
+    broadcast(exclude, file_id, state, message)
+}
+
 /// Send a message to a specific user in a room.
 /// All messages are sent in a separate thread.
 #[tracing::instrument(level = "trace")]
@@ -127,7 +159,7 @@ pub(crate) fn send_user_message(
                     sender
                         .lock()
                         .await
-                        .send(Message::Text(serde_json::to_string(&message)?))
+                        .send(user.protocol.encode(&message)?)
                         .await
                         .map_err(|e| MpError::SendingMessage(e.to_string()))?;
                 }
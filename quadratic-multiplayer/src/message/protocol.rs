@@ -0,0 +1,205 @@
+//! Websocket Message Protocol
+//!
+//! Messages are JSON by default, with operations base64 encoded.  Clients
+//! can negotiate binary messages by requesting the `quadratic-binary`
+//! websocket subprotocol.  Binary messages are CBOR, using the codec in
+//! `quadratic_core::compression`, and carry the raw compressed operations.
+//!
+//! Text requests are always decoded as JSON and binary requests as CBOR, so
+//! JSON remains available for debugging on any connection.
+
+use axum::extract::ws::Message;
+use axum::http::HeaderValue;
+use quadratic_core::compression::{deserialize, serialize, SerializationFormat};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::{MpError, Result};
+
+pub(crate) const BINARY_PROTOCOL: &str = "quadratic-binary";
+pub(crate) const JSON_PROTOCOL: &str = "quadratic-json";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Protocol {
+    #[default]
+    Json,
+    Binary,
+}
+
+impl Protocol {
+    /// Supported websocket subprotocols, in order of preference
+    pub(crate) const NAMES: [&'static str; 2] = [BINARY_PROTOCOL, JSON_PROTOCOL];
+
+    /// Get the protocol from the negotiated websocket subprotocol.  Clients
+    /// that don't request a subprotocol use JSON.
+    pub(crate) fn from_header(protocol: Option<&HeaderValue>) -> Self {
+        match protocol.and_then(|protocol| protocol.to_str().ok()) {
+            Some(BINARY_PROTOCOL) => Protocol::Binary,
+            _ => Protocol::Json,
+        }
+    }
+
+    /// Encode a message to send over the websocket
+    pub(crate) fn encode<T: Serialize>(&self, message: &T) -> Result<Message> {
+        match self {
+            Protocol::Json => Ok(Message::Text(serde_json::to_string(message)?)),
+            Protocol::Binary => Ok(Message::Binary(
+                serialize(&SerializationFormat::Cbor, message)
+                    .map_err(|e| MpError::Serialization(e.to_string()))?,
+            )),
+        }
+    }
+}
+
+/// Decode a binary message received over the websocket
+pub(crate) fn decode_binary<T: DeserializeOwned>(data: &[u8]) -> Result<T> {
+    deserialize(&SerializationFormat::Cbor, data).map_err(|e| MpError::Serialization(e.to_string()))
+}
+
+/// Serde helpers for operations, which are raw bytes in binary messages and
+/// base64 encoded strings in JSON.
+pub(crate) mod operations {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::de::{Error, SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+    use std::fmt;
+
+    pub(crate) fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        match serializer.is_human_readable() {
+            true => serializer.serialize_str(&STANDARD.encode(bytes)),
+            false => serializer.serialize_bytes(bytes),
+        }
+    }
+
+    // Internally tagged enums deserialize their fields as if they were human
+    // readable, so accept either representation.
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        deserializer.deserialize_any(OperationsVisitor)
+    }
+
+    struct OperationsVisitor;
+
+    impl<'de> Visitor<'de> for OperationsVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("base64 encoded operations or operation bytes")
+        }
+
+        fn visit_str<E: Error>(self, value: &str) -> Result<Vec<u8>, E> {
+            STANDARD
+                .decode(value)
+                .map_err(|e| E::custom(format!("could not decode base64 operations: {e}")))
+        }
+
+        fn visit_bytes<E: Error>(self, value: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(value.to_vec())
+        }
+
+        fn visit_byte_buf<E: Error>(self, value: Vec<u8>) -> Result<Vec<u8>, E> {
+            Ok(value)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+
+            Ok(bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::message::request::MessageRequest;
+    use crate::message::response::MessageResponse;
+
+    fn transaction() -> MessageRequest {
+        MessageRequest::Transaction {
+            id: Uuid::new_v4(),
+            session_id: Uuid::new_v4(),
+            file_id: Uuid::new_v4(),
+            operations: vec![0, 1, 2, 255],
+        }
+    }
+
+    #[test]
+    fn negotiates_the_protocol() {
+        let binary = HeaderValue::from_static(BINARY_PROTOCOL);
+        let json = HeaderValue::from_static(JSON_PROTOCOL);
+
+        assert_eq!(Protocol::from_header(Some(&binary)), Protocol::Binary);
+        assert_eq!(Protocol::from_header(Some(&json)), Protocol::Json);
+        assert_eq!(Protocol::from_header(None), Protocol::Json);
+    }
+
+    #[test]
+    fn encodes_operations_as_base64_in_json() {
+        let request = transaction();
+        let Message::Text(text) = Protocol::Json.encode(&request).unwrap() else {
+            panic!("expected a text message");
+        };
+
+        assert!(text.contains(r#""operations":"AAEC/w==""#));
+        assert_eq!(
+            serde_json::from_str::<MessageRequest>(&text).unwrap(),
+            request
+        );
+
+        let invalid = text.replace("AAEC/w==", "not base64!");
+        assert!(serde_json::from_str::<MessageRequest>(&invalid).is_err());
+    }
+
+    #[test]
+    fn encodes_raw_operations_in_binary() {
+        let request = transaction();
+        let Message::Binary(data) = Protocol::Binary.encode(&request).unwrap() else {
+            panic!("expected a binary message");
+        };
+
+        // the operations are a CBOR byte string (major type 2) of 4 bytes
+        assert!(data.windows(5).any(|bytes| bytes == [0x44, 0, 1, 2, 255]));
+        assert_eq!(decode_binary::<MessageRequest>(&data).unwrap(), request);
+
+        let response = MessageResponse::CurrentTransaction { sequence_num: 10 };
+        let Message::Binary(data) = Protocol::Binary.encode(&response).unwrap() else {
+            panic!("expected a binary message");
+        };
+        assert_eq!(decode_binary::<MessageResponse>(&data).unwrap(), response);
+    }
+
+    #[test]
+    fn decodes_requests_from_the_client_codec() {
+        // a Transaction encoded by the client's `encodeCbor` wasm binding,
+        // which writes uuids as text and operations as bytes
+        let data = concat!(
+            "a564747970656b5472616e73616374696f6e626964782430303131323233332d343435352d",
+            "363637372d383839392d6161626263636464656566666a73657373696f6e5f696478243065",
+            "3533616362362d333034352d346465662d383631312d626466333534393361343235676669",
+            "6c655f6964782430653533616362362d333034352d346465662d383631312d626466333534",
+            "3933613432356a6f7065726174696f6e7344000102ff"
+        );
+        let data = (0..data.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&data[i..i + 2], 16).unwrap())
+            .collect::<Vec<_>>();
+        let file_id = Uuid::parse_str("0e53acb6-3045-4def-8611-bdf35493a425").unwrap();
+
+        assert_eq!(
+            decode_binary::<MessageRequest>(&data).unwrap(),
+            MessageRequest::Transaction {
+                id: Uuid::parse_str("00112233-4455-6677-8899-aabbccddeeff").unwrap(),
+                session_id: file_id,
+                file_id,
+                operations: vec![0, 1, 2, 255],
+            }
+        );
+    }
+}
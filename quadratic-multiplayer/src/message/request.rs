@@ -5,6 +5,7 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::message::protocol::operations;
 use crate::state::user::{CellEdit, UserStateUpdate};
 
 // NOTE: needs to be kept in sync with multiplayerTypes.ts
@@ -38,7 +39,8 @@ pub(crate) enum MessageRequest {
         id: Uuid,
         session_id: Uuid,
         file_id: Uuid,
-        operations: String,
+        #[serde(with = "operations")]
+        operations: Vec<u8>,
     },
     GetTransactions {
         file_id: Uuid,
@@ -3,12 +3,17 @@
 //! A central place for websocket messages responses.
 
 use crate::error::{ErrorLevel, MpError};
+use crate::message::protocol::operations;
 use crate::state::settings::MinVersion;
 use crate::state::user::{User, UserStateUpdate};
-use base64::{engine::general_purpose::STANDARD, Engine};
 use dashmap::DashMap;
+use quadratic_core::controller::operations::operation::Operation;
 use quadratic_core::controller::transaction::TransactionServer;
+use quadratic_core::controller::GridController;
+use quadratic_core::grid::sheet::comments::Comment;
+use quadratic_core::grid::SheetId;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -16,7 +21,8 @@ pub(crate) struct Transaction {
     pub(crate) id: Uuid,
     pub(crate) file_id: Uuid,
     pub(crate) sequence_num: u64,
-    pub(crate) operations: String,
+    #[serde(with = "operations")]
+    pub(crate) operations: Vec<u8>,
 }
 
 // NOTE: needs to be kept in sync with multiplayerTypes.ts
@@ -36,11 +42,21 @@ pub(crate) enum MessageResponse {
         id: Uuid,
         file_id: Uuid,
         sequence_num: u64,
-        operations: String,
+        #[serde(with = "operations")]
+        operations: Vec<u8>,
     },
     Transactions {
         transactions: Vec<Transaction>,
     },
+    /// Sent instead of Transactions when the missing transactions are no
+    /// longer available.  The client downloads the checkpoint and applies
+    /// the transactions after it.
+    CatchUp {
+        file_id: Uuid,
+        checkpoint_sequence_num: u64,
+        checkpoint_url: String,
+        transactions: Vec<Transaction>,
+    },
     EnterRoom {
         file_id: Uuid,
         sequence_num: u64,
@@ -52,6 +68,99 @@ pub(crate) enum MessageResponse {
         error: MpError,
         error_level: ErrorLevel,
     },
+    /// Only sent to the users mentioned in the comment.
+    CommentMention {
+        file_id: Uuid,
+        sheet_id: Uuid,
+        thread_id: Uuid,
+        comment_id: Uuid,
+        author: String,
+        body: String,
+        mentions: Vec<String>,
+    },
+}
+
+impl MessageResponse {
+    /// Create a CommentMention for each comment that the operations add or
+    /// edit with new mentions.  Users that are already mentioned in the comment
+    /// in `previous`, the grid before the operations, aren't mentioned again.
+    /// The author is the user who sent the operations.
+    pub(crate) fn comment_mentions(
+        file_id: Uuid,
+        author: &str,
+        operations: &[Operation],
+        previous: Option<&GridController>,
+    ) -> Vec<Self> {
+        // mentions in earlier operations of the same transaction
+        let mut mentioned = HashSet::new();
+
+        operations
+            .iter()
+            .filter_map(operation_comments)
+            .flat_map(|(sheet_id, thread_id, comments)| {
+                let Ok(file_sheet_id) = Uuid::parse_str(&sheet_id.to_string()) else {
+                    return vec![];
+                };
+                let previous_thread = previous
+                    .and_then(|grid| grid.try_sheet(*sheet_id))
+                    .and_then(|sheet| sheet.comments.get(*thread_id));
+
+                comments
+                    .into_iter()
+                    .filter_map(|comment| {
+                        let previous_mentions = previous_thread
+                            .and_then(|thread| thread.comment(comment.id))
+                            .map(|previous| previous.mentions.as_slice())
+                            .unwrap_or_default();
+                        let mentions = comment
+                            .mentions
+                            .iter()
+                            .filter(|user_id| !previous_mentions.contains(user_id))
+                            .filter(|user_id| mentioned.insert((comment.id, user_id.to_string())))
+                            .cloned()
+                            .collect::<Vec<_>>();
+
+                        (!mentions.is_empty()).then(|| MessageResponse::CommentMention {
+                            file_id,
+                            sheet_id: file_sheet_id,
+                            thread_id: *thread_id,
+                            comment_id: comment.id,
+                            author: author.to_owned(),
+                            body: comment.body.to_owned(),
+                            mentions,
+                        })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Whether the message should be sent to the user when broadcasting to a
+    /// room.
+    pub(crate) fn is_for_user(&self, user: &User) -> bool {
+        match self {
+            MessageResponse::CommentMention { mentions, .. } => mentions.contains(&user.user_id),
+            _ => true,
+        }
+    }
+}
+
+/// The sheet, thread and comments that an operation adds or edits.
+fn operation_comments(operation: &Operation) -> Option<(&SheetId, &Uuid, Vec<&Comment>)> {
+    match operation {
+        Operation::SetCommentThread {
+            sheet_id,
+            thread_id,
+            thread: Some(thread),
+        } => Some((sheet_id, thread_id, thread.comments.iter().collect())),
+        Operation::SetComment {
+            sheet_id,
+            thread_id,
+            comment: Some(comment),
+            ..
+        } => Some((sheet_id, thread_id, vec![comment])),
+        _ => None,
+    }
 }
 
 impl From<TransactionServer> for Transaction {
@@ -60,7 +169,7 @@ impl From<TransactionServer> for Transaction {
             id: transaction_server.id,
             file_id: transaction_server.file_id,
             sequence_num: transaction_server.sequence_num,
-            operations: STANDARD.encode(&transaction_server.operations),
+            operations: transaction_server.operations,
         }
     }
 }
@@ -73,3 +182,65 @@ impl From<(DashMap<Uuid, User>, &MinVersion)> for MessageResponse {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use quadratic_core::grid::sheet::comments::CommentThread;
+    use quadratic_core::Pos;
+
+    use super::*;
+
+    fn mentions(responses: Vec<MessageResponse>) -> Vec<Vec<String>> {
+        responses
+            .into_iter()
+            .map(|response| match response {
+                MessageResponse::CommentMention {
+                    author, mentions, ..
+                } => {
+                    assert_eq!(author, "author");
+                    mentions
+                }
+                other => panic!("expected a CommentMention but got {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn comment_mentions_are_only_sent_for_new_mentions() {
+        let file_id = Uuid::new_v4();
+        let mut grid = GridController::test();
+        let sheet_id = grid.sheet_ids()[0];
+        let comment = Comment::new("someone else".into(), "hi".into(), vec!["a".into()]);
+        let thread = CommentThread::new(Pos { x: 0, y: 0 }, comment.clone());
+        let add_thread = vec![Operation::SetCommentThread {
+            sheet_id,
+            thread_id: thread.id,
+            thread: Some(thread.clone()),
+        }];
+
+        let added = MessageResponse::comment_mentions(file_id, "author", &add_thread, Some(&grid));
+        assert_eq!(mentions(added), vec![vec!["a".to_string()]]);
+        grid.server_apply_transaction(add_thread, None);
+
+        // editing the comment only mentions the users that weren't mentioned
+        let mut edited_comment = comment.clone();
+        edited_comment.mentions.push("b".into());
+        let edit = Operation::SetComment {
+            sheet_id,
+            thread_id: thread.id,
+            comment_id: comment.id,
+            comment: Some(edited_comment),
+        };
+        let edit_twice = vec![edit.clone(), edit];
+
+        let edited = MessageResponse::comment_mentions(file_id, "author", &edit_twice, Some(&grid));
+        assert_eq!(mentions(edited), vec![vec!["b".to_string()]]);
+
+        // without the previous grid, every mention is new
+        let edited = MessageResponse::comment_mentions(file_id, "author", &edit_twice, None);
+        assert_eq!(
+            mentions(edited),
+            vec![vec!["a".to_string(), "b".to_string()]]
+        );
+    }
+}
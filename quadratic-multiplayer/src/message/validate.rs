@@ -0,0 +1,176 @@
+//! Transaction Validation
+//!
+//! Incoming transactions are decoded and deserialized before they are
+//! sequenced so that malformed operations, and operations that change cells
+//! protected from the user, never reach a file's transaction log.
+
+use quadratic_core::controller::operations::operation::Operation;
+use quadratic_core::controller::transaction::Transaction;
+use quadratic_core::controller::GridController;
+use quadratic_core::grid::sheet::protections::EditUser;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use uuid::Uuid;
+
+use crate::error::{MpError, Result};
+
+/// Decompress and deserialize compressed operations into a vec of operations.
+/// The limit applies to both the compressed and the decompressed operations,
+/// and decompression stops at the limit.
+pub(crate) fn decode_operations(
+    id: Uuid,
+    operations: &[u8],
+    max_size_bytes: usize,
+) -> Result<Vec<Operation>> {
+    if operations.len() > max_size_bytes {
+        return Err(MpError::TransactionTooLarge(
+            id,
+            operations.len(),
+            max_size_bytes,
+        ));
+    }
+
+    Transaction::decompress_and_deserialize_limited::<Vec<Operation>>(operations, max_size_bytes)
+        .map_err(|e| {
+            MpError::InvalidTransaction(id, format!("could not deserialize operations: {e}"))
+        })
+}
+
+/// Return an error if any of the operations change cells (or sheets) that are
+/// protected from the user.
+pub(crate) fn check_protections(
+    id: Uuid,
+    grid: &GridController,
+    edit_user: &EditUser,
+    operations: &[Operation],
+) -> Result<()> {
+    match grid.blocking_protection(operations, edit_user) {
+        Some((sheet_id, protection)) => Err(MpError::ProtectedCells(
+            id,
+            format!("protection {} on sheet {sheet_id}", protection.id),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Apply operations to a grid, returning an error if applying them panics.
+/// After an error, the grid may be partially modified and should be
+/// discarded.
+pub(crate) fn dry_run(
+    id: Uuid,
+    grid: &mut GridController,
+    operations: Vec<Operation>,
+) -> Result<()> {
+    catch_unwind(AssertUnwindSafe(|| {
+        grid.server_apply_transaction(operations, None)
+    }))
+    .map_err(|panic| {
+        let reason = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".into());
+
+        MpError::InvalidTransaction(id, format!("could not apply operations: {reason}"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use quadratic_core::grid::SheetId;
+
+    use super::*;
+
+    fn encode(operations: &[Operation]) -> Vec<u8> {
+        Transaction::serialize_and_compress(operations).unwrap()
+    }
+
+    #[test]
+    fn decodes_operations() {
+        let id = Uuid::new_v4();
+        let operations = vec![Operation::SetSheetColor {
+            sheet_id: SheetId::new(),
+            color: Some("red".to_string()),
+        }];
+
+        let decoded = decode_operations(id, &encode(&operations), 1024).unwrap();
+        assert_eq!(decoded, operations);
+    }
+
+    #[test]
+    fn rejects_malformed_operations() {
+        let id = Uuid::new_v4();
+
+        let error = decode_operations(id, b"these are not operations", 1024).unwrap_err();
+        assert!(matches!(error, MpError::InvalidTransaction(error_id, _) if error_id == id));
+    }
+
+    #[test]
+    fn rejects_oversized_operations() {
+        let id = Uuid::new_v4();
+        let operations = vec![Operation::SetSheetColor {
+            sheet_id: SheetId::new(),
+            color: Some("red".repeat(100)),
+        }];
+        let encoded = encode(&operations);
+        let size = encoded.len();
+
+        let error = decode_operations(id, &encoded, size - 1).unwrap_err();
+        assert_eq!(error, MpError::TransactionTooLarge(id, size, size - 1));
+        assert!(decode_operations(id, &encoded, 1024).is_ok());
+    }
+
+    #[test]
+    fn rejects_operations_that_decompress_past_the_limit() {
+        let id = Uuid::new_v4();
+        let operations = vec![Operation::SetSheetColor {
+            sheet_id: SheetId::new(),
+            color: Some("red".repeat(10_000)),
+        }];
+        let encoded = encode(&operations);
+        assert!(encoded.len() < 1024);
+
+        let error = decode_operations(id, &encoded, 1024).unwrap_err();
+        assert!(matches!(error, MpError::InvalidTransaction(error_id, _) if error_id == id));
+    }
+
+    #[test]
+    fn rejects_operations_on_protected_cells() {
+        let id = Uuid::new_v4();
+        let mut grid = GridController::test();
+        let sheet_id = grid.sheet_ids()[0];
+        let owner = EditUser {
+            user_id: "owner".to_string(),
+            roles: vec![],
+        };
+        let editor = EditUser {
+            user_id: "editor".to_string(),
+            roles: vec!["FILE_EDIT".to_string()],
+        };
+        grid.set_edit_user(owner.clone());
+        grid.add_protection(sheet_id, None, vec![], vec![], None);
+
+        let operations = vec![Operation::SetSheetColor {
+            sheet_id,
+            color: Some("red".to_string()),
+        }];
+        let error = check_protections(id, &grid, &editor, &operations).unwrap_err();
+        assert!(matches!(error, MpError::ProtectedCells(error_id, _) if error_id == id));
+        assert!(check_protections(id, &grid, &owner, &operations).is_ok());
+    }
+
+    #[test]
+    fn dry_run_applies_operations() {
+        let mut grid = GridController::test();
+        let sheet_id = grid.sheet_ids()[0];
+        let operations = vec![Operation::SetSheetColor {
+            sheet_id,
+            color: Some("red".to_string()),
+        }];
+
+        dry_run(Uuid::new_v4(), &mut grid, operations).unwrap();
+        assert_eq!(
+            grid.try_sheet(sheet_id).unwrap().color,
+            Some("red".to_string())
+        );
+    }
+}
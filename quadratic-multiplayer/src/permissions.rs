@@ -1,11 +1,12 @@
 use std::sync::Arc;
 
+use quadratic_core::grid::sheet::protections::EditUser;
 use quadratic_rust_shared::quadratic_api::{can_edit, can_view, FilePermRole};
 use uuid::Uuid;
 
 use crate::{
     error::{MpError, Result},
-    state::State,
+    state::{user::User, State},
 };
 
 pub(crate) fn validate_can_edit_or_view_file(roles: &[FilePermRole]) -> Result<()> {
@@ -48,10 +49,25 @@ pub(crate) async fn validate_user_can_edit_file(
     validate_can_edit_file(&user.permissions)
 }
 
+/// The user and their file roles (eg, "FILE_EDIT"), as checked against
+/// protected cells.
+pub(crate) fn edit_user(user: &User) -> EditUser {
+    let roles = user
+        .permissions
+        .iter()
+        .filter_map(|role| serde_json::to_value(role).ok()?.as_str().map(String::from))
+        .collect();
+
+    EditUser {
+        user_id: user.user_id.to_owned(),
+        roles,
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
 
-    use crate::test_util::setup;
+    use crate::test_util::{new_user, setup};
 
     use super::*;
 
@@ -85,6 +101,16 @@ pub(crate) mod tests {
         assert!(matches!(result, Err(MpError::FilePermissions(_))));
     }
 
+    #[test]
+    fn converts_a_user_to_an_edit_user() {
+        let mut user = new_user();
+        user.permissions = vec![FilePermRole::FileView, FilePermRole::FileEdit];
+        let edit_user = edit_user(&user);
+
+        assert_eq!(edit_user.user_id, user.user_id);
+        assert_eq!(edit_user.roles, vec!["FILE_VIEW", "FILE_EDIT"]);
+    }
+
     #[tokio::test]
     async fn validates_user_can_edit_or_view_file() {
         let (_, state, _, file_id, user, _) = setup().await;
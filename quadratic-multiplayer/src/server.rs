@@ -8,31 +8,43 @@ use axum::{
         connect_info::ConnectInfo,
         ws::{Message, WebSocket, WebSocketUpgrade},
     },
-    http::StatusCode,
+    http::{header, StatusCode},
     response::IntoResponse,
     routing::get,
-    Extension, Router,
+    Extension, Json, Router,
 };
-use axum_extra::TypedHeader;
+use axum_extra::{
+    headers::{authorization::Bearer, Authorization},
+    TypedHeader,
+};
+use chrono::{DateTime, Utc};
 use futures::stream::StreamExt;
 use futures_util::stream::SplitSink;
 use futures_util::SinkExt;
 use quadratic_rust_shared::auth::jwt::{authorize, get_jwks};
+use quadratic_rust_shared::metrics::CONTENT_TYPE;
+use quadratic_rust_shared::quadratic_api::FilePermRole;
 use serde::{Deserialize, Serialize};
 use std::ops::ControlFlow;
 use std::{net::SocketAddr, sync::Arc};
+use subtle::ConstantTimeEq;
 use tokio::sync::Mutex;
 use tower_http::trace::{DefaultMakeSpan, TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use uuid::Uuid;
 
 use crate::{
     background_worker,
     config::config,
     error::{ErrorLevel, MpError, Result},
     message::{
-        broadcast, handle::handle_message, request::MessageRequest, response::MessageResponse,
+        broadcast,
+        handle::handle_message,
+        protocol::{decode_binary, Protocol},
+        request::MessageRequest,
+        response::MessageResponse,
     },
-    state::{connection::PreConnection, State},
+    state::{connection::PreConnection, fanout, room::Room, State},
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -49,6 +61,10 @@ pub(crate) fn app(state: Arc<State>) -> Router {
         .route("/ws", get(ws_handler))
         // healthchecks
         .route("/health", get(healthcheck))
+        // prometheus metrics
+        .route("/metrics", get(metrics))
+        // room state for on-call, requires the admin auth token
+        .route("/admin/rooms", get(admin_rooms))
         // state
         .layer(Extension(state))
         // logger
@@ -101,6 +117,9 @@ pub(crate) async fn serve() -> Result<()> {
         config.heartbeat_timeout_s,
     );
 
+    // broadcast messages from other multiplayer instances to local users
+    fanout::start(Arc::clone(&state));
+
     axum::serve(
         listener,
         app.into_make_service_with_connect_info::<SocketAddr>(),
@@ -174,8 +193,10 @@ async fn ws_handler(
         pre_connection.id
     );
 
-    // upgrade the connection
-    let ws = ws.max_message_size(1024 * 1024 * 1000); // 1GB
+    // upgrade the connection, negotiating binary or JSON messages
+    let ws = ws
+        .max_message_size(1024 * 1024 * 1000) // 1GB
+        .protocols(Protocol::NAMES);
     ws.on_upgrade(move |socket| handle_socket(socket, state, addr, pre_connection))
 }
 
@@ -187,6 +208,11 @@ async fn handle_socket(
     addr: String,
     pre_connection: PreConnection,
 ) {
+    let pre_connection = PreConnection {
+        protocol: Protocol::from_header(socket.protocol()),
+        ..pre_connection
+    };
+    let protocol = pre_connection.protocol;
     let (sender, mut receiver) = socket.split();
     let sender = Arc::new(Mutex::new(sender));
     let connection_id = pre_connection.id;
@@ -207,12 +233,12 @@ async fn handle_socket(
                 let error_level = ErrorLevel::from(&error);
                 error_level.log(&format!("Error processing message: {:?}", &error));
 
-                if let Ok(message) = serde_json::to_string(&MessageResponse::Error {
+                if let Ok(message) = protocol.encode(&MessageResponse::Error {
                     error: error.to_owned(),
                     error_level,
                 }) {
                     // send error message to the client
-                    let sent = sender.lock().await.send(Message::Text(message)).await;
+                    let sent = sender.lock().await.send(message).await;
 
                     if let Err(sent) = sent {
                         tracing::warn!("Error sending error message: {:?}", sent);
@@ -282,29 +308,10 @@ async fn process_message(
     state: Arc<State>,
     pre_connection: PreConnection,
 ) -> Result<ControlFlow<Option<MessageResponse>, ()>> {
-    match msg {
-        Message::Text(text) => {
-            let messsage_request = serde_json::from_str::<MessageRequest>(&text)?;
-            let message_response =
-                handle_message(messsage_request, state, Arc::clone(&sender), pre_connection)
-                    .await?;
-
-            if let Some(message_response) = message_response {
-                let response = Message::Text(serde_json::to_string(&message_response)?);
-
-                (*sender.lock().await)
-                    .send(response)
-                    .await
-                    .map_err(|e| MpError::SendingMessage(e.to_string()))?;
-            }
-        }
-        Message::Binary(d) => {
-            tracing::info!(
-                "Binary messages are not yet supported.  {} bytes: {:?}",
-                d.len(),
-                d
-            );
-        }
+    let protocol = pre_connection.protocol;
+    let messsage_request = match msg {
+        Message::Text(text) => serde_json::from_str::<MessageRequest>(&text)?,
+        Message::Binary(data) => decode_binary::<MessageRequest>(&data)?,
         Message::Close(c) => {
             if let Some(cf) = c {
                 tracing::info!("Close with code {} and reason `{}`", cf.code, cf.reason);
@@ -315,7 +322,20 @@ async fn process_message(
         }
         _ => {
             tracing::info!("Unhandled message type");
+            return Ok(ControlFlow::Continue(()));
         }
+    };
+
+    let message_response =
+        handle_message(messsage_request, state, Arc::clone(&sender), pre_connection).await?;
+
+    if let Some(message_response) = message_response {
+        let response = protocol.encode(&message_response)?;
+
+        (*sender.lock().await)
+            .send(response)
+            .await
+            .map_err(|e| MpError::SendingMessage(e.to_string()))?;
     }
 
     Ok(ControlFlow::Continue(()))
@@ -325,22 +345,116 @@ pub(crate) async fn healthcheck() -> impl IntoResponse {
     StatusCode::OK
 }
 
+pub(crate) async fn metrics(Extension(state): Extension<Arc<State>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, CONTENT_TYPE)],
+        state.metrics().await,
+    )
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct AdminRooms {
+    instance_id: Uuid,
+    rooms: Vec<AdminRoom>,
+}
+
+/// A room without its users' personal details or cell edits.
+#[derive(Debug, Serialize)]
+pub(crate) struct AdminRoom {
+    file_id: Uuid,
+    sequence_num: u64,
+    checkpoint_sequence_num: u64,
+    users: Vec<AdminUser>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct AdminUser {
+    session_id: Uuid,
+    user_id: String,
+    index: usize,
+    permissions: Vec<FilePermRole>,
+    last_heartbeat: DateTime<Utc>,
+    instance_id: Option<Uuid>,
+}
+
+impl From<&Room> for AdminRoom {
+    fn from(room: &Room) -> Self {
+        let users = room
+            .users
+            .iter()
+            .map(|user| AdminUser {
+                session_id: user.session_id,
+                user_id: user.user_id.to_owned(),
+                index: user.index,
+                permissions: user.permissions.to_owned(),
+                last_heartbeat: user.last_heartbeat,
+                instance_id: user.instance_id,
+            })
+            .collect();
+
+        AdminRoom {
+            file_id: room.file_id,
+            sequence_num: room.sequence_num,
+            checkpoint_sequence_num: room.checkpoint_sequence_num,
+            users,
+        }
+    }
+}
+
+/// List the rooms on this instance with their users and sequence numbers.
+/// Requires the admin auth token, and is disabled when it isn't set.
+pub(crate) async fn admin_rooms(
+    Extension(state): Extension<Arc<State>>,
+    authorization: Option<TypedHeader<Authorization<Bearer>>>,
+) -> impl IntoResponse {
+    let admin_auth_token = &state.settings.admin_auth_token;
+    let authorized = match authorization {
+        // compare in constant time so the token can't be guessed by timing
+        Some(TypedHeader(Authorization(bearer))) => {
+            !admin_auth_token.is_empty()
+                && bool::from(bearer.token().as_bytes().ct_eq(admin_auth_token.as_bytes()))
+        }
+        None => false,
+    };
+
+    if !authorized {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let rooms = state
+        .rooms
+        .lock()
+        .await
+        .iter()
+        .map(|room| AdminRoom::from(room.value()))
+        .collect::<Vec<AdminRoom>>();
+
+    Json(AdminRooms {
+        instance_id: state.instance_id,
+        rooms,
+    })
+    .into_response()
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
 
     use super::*;
+    use crate::message::protocol::BINARY_PROTOCOL;
     use crate::state::settings::MinVersion;
     use crate::state::user::{User, UserStateUpdate};
     use crate::test_util::{
-        add_user_via_ws, integration_test_send_and_receive, new_arc_state, setup,
+        add_user_via_ws, cache_grid, enter_room_request, integration_test_receive,
+        integration_test_send_and_receive, integration_test_send_binary,
+        integration_test_setup_with_protocol, new_arc_state, new_user, setup,
     };
     use axum::{
         body::Body,
         http::{self, Request},
     };
-    use base64::{engine::general_purpose::STANDARD, Engine};
     use quadratic_core::controller::operations::operation::Operation;
     use quadratic_core::controller::transaction::Transaction;
+    use quadratic_core::controller::GridController;
     use quadratic_core::grid::SheetId;
 
     use tower::ServiceExt;
@@ -401,6 +515,92 @@ pub(crate) mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn responds_with_prometheus_metrics() {
+        let (_, state, _, _, _, _) = setup().await;
+        let app = app(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/metrics")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let metrics = String::from_utf8(body.to_vec()).unwrap();
+        assert!(metrics.contains("quadratic_multiplayer_rooms 1\n"));
+        assert!(metrics.contains("quadratic_multiplayer_users 2\n"));
+    }
+
+    #[tokio::test]
+    async fn admin_rooms_requires_the_admin_auth_token() {
+        let (_, state, _, file_id, _, _) = setup().await;
+        let token = state.settings.admin_auth_token.to_owned();
+        let request = |authorization: Option<String>| {
+            let mut request = Request::builder()
+                .method(http::Method::GET)
+                .uri("/admin/rooms");
+
+            if let Some(authorization) = authorization {
+                request = request.header(http::header::AUTHORIZATION, authorization);
+            }
+
+            request.body(Body::empty()).unwrap()
+        };
+
+        let response = app(Arc::clone(&state))
+            .oneshot(request(None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app(Arc::clone(&state))
+            .oneshot(request(Some("Bearer wrong".into())))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // the M2M auth token isn't accepted
+        let m2m_auth_token = state.settings.m2m_auth_token.to_owned();
+        let response = app(Arc::clone(&state))
+            .oneshot(request(Some(format!("Bearer {m2m_auth_token}"))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app(Arc::clone(&state))
+            .oneshot(request(Some(format!("Bearer {token}"))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let rooms: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(rooms["rooms"][0]["file_id"], file_id.to_string());
+
+        // users are listed without their personal details or cell edits
+        let users = rooms["rooms"][0]["users"].as_array().unwrap();
+        assert_eq!(users.len(), 2);
+        for user in users {
+            let user = user.as_object().unwrap();
+            assert!(user.contains_key("user_id"));
+            for field in ["email", "first_name", "last_name", "image", "cell_edit"] {
+                assert!(!user.contains_key(field), "{field} isn't redacted");
+            }
+        }
+    }
+
     #[tokio::test]
     async fn test_user_enters_a_room() {
         // user_2 is created using the MessageRequest::EnterRoom message
@@ -536,17 +736,16 @@ pub(crate) mod tests {
         }];
         let id = Uuid::new_v4();
         let compressed_ops = Transaction::serialize_and_compress(&operations).unwrap();
-        let encoded_ops = STANDARD.encode(&compressed_ops);
         let request = MessageRequest::Transaction {
             id,
             session_id,
             file_id,
-            operations: encoded_ops.clone(),
+            operations: compressed_ops.clone(),
         };
         let expected = MessageResponse::Transaction {
             id,
             file_id,
-            operations: encoded_ops,
+            operations: compressed_ops,
             sequence_num: 1,
         };
 
@@ -554,4 +753,49 @@ pub(crate) mod tests {
 
         assert_eq!(response, Some(expected));
     }
+
+    #[tokio::test]
+    async fn user_shares_operations_over_binary_messages() {
+        let state = new_arc_state().await;
+        let socket =
+            integration_test_setup_with_protocol(state.clone(), Some(BINARY_PROTOCOL)).await;
+        let socket = Arc::new(Mutex::new(socket));
+        let file_id = Uuid::new_v4();
+        let user = new_user();
+        cache_grid(&state, file_id, GridController::test()).await;
+
+        // UsersInRoom and EnterRoom are sent to the client when they enter a room
+        integration_test_send_binary(&socket, enter_room_request(file_id, &user)).await;
+        integration_test_receive(&socket, 2).await;
+
+        let user_in_room = state
+            ._get_user_in_room(&file_id, &user.session_id)
+            .await
+            .unwrap();
+        assert_eq!(user_in_room.protocol, Protocol::Binary);
+
+        let operations = vec![Operation::SetSheetName {
+            sheet_id: SheetId::new(),
+            name: "test".to_string(),
+        }];
+        let id = Uuid::new_v4();
+        let compressed_ops = Transaction::serialize_and_compress(&operations).unwrap();
+        let request = MessageRequest::Transaction {
+            id,
+            session_id: user.session_id,
+            file_id,
+            operations: compressed_ops.clone(),
+        };
+        let expected = MessageResponse::Transaction {
+            id,
+            file_id,
+            operations: compressed_ops,
+            sequence_num: 1,
+        };
+
+        integration_test_send_binary(&socket, request).await;
+        let response = integration_test_receive(&socket, 1).await;
+
+        assert_eq!(response, Some(expected));
+    }
 }
@@ -1,12 +1,14 @@
 use uuid::Uuid;
 
 use crate::error::{MpError, Result};
+use crate::message::protocol::Protocol;
 use crate::state::State;
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct PreConnection {
     pub(crate) id: Uuid,
     pub(crate) jwt: Option<String>,
+    pub(crate) protocol: Protocol,
 }
 
 impl PreConnection {
@@ -14,6 +16,7 @@ impl PreConnection {
         Self {
             id: Uuid::new_v4(),
             jwt,
+            protocol: Protocol::default(),
         }
     }
 }
@@ -0,0 +1,444 @@
+//! Cross-instance Fan-out
+//!
+//! Each multiplayer instance only holds the websockets of its own users.
+//! Messages that need to reach every user in a room are also published to a
+//! shared PubSub channel.  Every instance listens on that channel and
+//! broadcasts messages from other instances to its local users.
+//!
+//! Instances also publish their users in a room when users enter or leave
+//! it, and on every background worker tick, so that every instance can send
+//! the room's full list of users to its own users.  Users from an instance
+//! that stops publishing are removed once their heartbeat is stale.
+//!
+//! With the in-memory PubSub, instances in the same process share a broadcast
+//! channel instead of Redis.
+
+use futures_util::stream::StreamExt;
+#[cfg(any(test, feature = "memory-pubsub"))]
+use quadratic_rust_shared::pubsub::memory::MemoryConnection;
+use quadratic_rust_shared::pubsub::{
+    redis::RedisConnection, Config as PubSubConfig, PubSub as PubSubTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(any(test, feature = "memory-pubsub"))]
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::error::{MpError, Result};
+use crate::message::{broadcast, response::MessageResponse};
+use crate::state::{user::User, State};
+use crate::{get_mut_room, get_room};
+
+/// Time to wait before listening again after the connection is lost.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) enum FanoutEvent {
+    /// A message for the users in the room
+    Message {
+        exclude: Vec<Uuid>,
+        message: Box<MessageResponse>,
+    },
+    /// The users in the room that are connected to the sending instance.
+    /// Instances with users in the room reply with their own users when
+    /// `reply` is set.
+    Users { users: Vec<User>, reply: bool },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct FanoutMessage {
+    pub(crate) instance_id: Uuid,
+    pub(crate) file_id: Uuid,
+    pub(crate) event: FanoutEvent,
+}
+
+#[derive(Debug)]
+pub(crate) enum FanoutConnection {
+    Redis(RedisConnection),
+    #[cfg(any(test, feature = "memory-pubsub"))]
+    Memory(MemoryConnection),
+}
+
+impl FanoutConnection {
+    async fn new(config: PubSubConfig) -> Result<Self> {
+        let connection = match config {
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            PubSubConfig::Memory(_) => {
+                FanoutConnection::Memory(MemoryConnection::new(config).await?)
+            }
+            _ => FanoutConnection::Redis(RedisConnection::new(config).await?),
+        };
+
+        Ok(connection)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Fanout {
+    pub(crate) config: PubSubConfig,
+    pub(crate) channel: String,
+    pub(crate) connection: FanoutConnection,
+}
+
+impl Fanout {
+    /// Create a new connection for publishing to the fan-out channel
+    pub(crate) async fn new(config: PubSubConfig, channel: String) -> Result<Self> {
+        let connection = FanoutConnection::new(config.to_owned()).await?;
+
+        Ok(Fanout {
+            config,
+            channel,
+            connection,
+        })
+    }
+}
+
+impl State {
+    /// Publish a message to the other multiplayer instances.
+    pub(crate) async fn publish_fanout(
+        &self,
+        exclude: Vec<Uuid>,
+        file_id: Uuid,
+        message: MessageResponse,
+    ) -> Result<()> {
+        self.publish_fanout_event(
+            file_id,
+            FanoutEvent::Message {
+                exclude,
+                message: Box::new(message),
+            },
+        )
+        .await
+    }
+
+    /// Publish this instance's users in a room to the other multiplayer
+    /// instances, which replace the users they have for this instance.
+    pub(crate) async fn publish_fanout_users(&self, file_id: Uuid, reply: bool) -> Result<()> {
+        let users = match get_room!(self, file_id) {
+            Ok(room) => room
+                .users
+                .iter()
+                .filter(|user| user.instance_id.is_none())
+                .map(|user| user.to_owned())
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        self.publish_fanout_event(file_id, FanoutEvent::Users { users, reply })
+            .await
+    }
+
+    async fn publish_fanout_event(&self, file_id: Uuid, event: FanoutEvent) -> Result<()> {
+        let fanout_message = FanoutMessage {
+            instance_id: self.instance_id,
+            file_id,
+            event,
+        };
+        let payload = serde_json::to_vec(&fanout_message)?;
+        let mut fanout = self.fanout.lock().await;
+        let channel = fanout.channel.to_owned();
+
+        match fanout.connection {
+            FanoutConnection::Redis(ref mut connection) => {
+                connection.publish(&channel, "", &payload, None).await?
+            }
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            FanoutConnection::Memory(ref connection) => connection.broadcast(&channel, &payload)?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Broadcast a message from another instance to the local users in its room.
+pub(crate) async fn receive(state: Arc<State>, payload: &[u8]) -> Result<()> {
+    let FanoutMessage {
+        instance_id,
+        file_id,
+        event,
+    } = serde_json::from_slice(payload)?;
+
+    // this instance already broadcasted its own messages
+    if instance_id == state.instance_id {
+        return Ok(());
+    }
+
+    // the room only exists if this instance has users in it
+    if !state.rooms.lock().await.contains_key(&file_id) {
+        return Ok(());
+    }
+
+    let (exclude, message) = match event {
+        FanoutEvent::Message { exclude, message } => {
+            match message.as_ref() {
+                MessageResponse::Transaction { sequence_num, .. } => {
+                    get_mut_room!(state, file_id)?.update_sequence_num(*sequence_num);
+
+                    // the cached grid is missing the other instance's transaction
+                    state.remove_grid(&file_id).await;
+                }
+                MessageResponse::UserUpdate {
+                    session_id, update, ..
+                } => {
+                    state
+                        .update_user_state(&file_id, session_id, update)
+                        .await?
+                }
+                _ => {}
+            }
+
+            (exclude, *message)
+        }
+        FanoutEvent::Users { users, reply } => {
+            state
+                .update_remote_users(file_id, instance_id, users)
+                .await?;
+
+            if reply {
+                state.publish_fanout_users(file_id, false).await?;
+            }
+
+            let users = get_room!(state, file_id)?.users.to_owned();
+
+            (
+                vec![],
+                MessageResponse::from((users, &state.settings.min_version)),
+            )
+        }
+    };
+
+    broadcast(exclude, file_id, state, message)
+        .await
+        .map_err(|e| MpError::SendingMessage(e.to_string()))
+}
+
+/// Subscribe to the fan-out channel and handle messages until the connection
+/// is lost.
+async fn listen(state: &Arc<State>) -> Result<()> {
+    let (config, channel) = {
+        let fanout = state.fanout.lock().await;
+        (fanout.config.to_owned(), fanout.channel.to_owned())
+    };
+
+    match FanoutConnection::new(config).await? {
+        FanoutConnection::Redis(mut connection) => {
+            connection.subscribe(&channel, "").await?;
+
+            let mut messages = connection.pubsub.on_message();
+
+            while let Some(message) = messages.next().await {
+                let received = match message.get_payload::<Vec<u8>>() {
+                    Ok(payload) => receive(Arc::clone(state), &payload).await,
+                    Err(e) => Err(MpError::PubSub(e.to_string())),
+                };
+
+                log_receive_error(received);
+            }
+        }
+        #[cfg(any(test, feature = "memory-pubsub"))]
+        FanoutConnection::Memory(connection) => {
+            let mut messages = connection.listen(&channel)?;
+
+            loop {
+                match messages.recv().await {
+                    Ok(payload) => log_receive_error(receive(Arc::clone(state), &payload).await),
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Skipped {skipped} fan-out messages");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn log_receive_error(received: Result<()>) {
+    if let Err(error) = received {
+        tracing::warn!("Error receiving fan-out message: {:?}", error);
+    }
+}
+
+/// In a separate thread, listen for messages from other multiplayer instances.
+#[tracing::instrument(level = "trace")]
+pub(crate) fn start(state: Arc<State>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            if let Err(error) = listen(&state).await {
+                tracing::warn!("Error listening to the fan-out channel: {:?}", error);
+            }
+
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use quadratic_core::controller::operations::operation::Operation;
+    use quadratic_core::controller::transaction::Transaction;
+    use quadratic_core::controller::GridController;
+    use quadratic_core::grid::SheetId;
+    use tokio::sync::Mutex;
+
+    use super::*;
+    use crate::message::request::MessageRequest;
+    use crate::test_util::{
+        cache_grid, integration_test_receive, integration_test_send, integration_test_setup,
+        new_arc_redis_state, new_arc_state, new_connection, new_user, setup, setup_with_state,
+    };
+
+    fn transaction_request(session_id: Uuid, file_id: Uuid) -> (Uuid, Vec<u8>, MessageRequest) {
+        let operations = vec![Operation::SetSheetName {
+            sheet_id: SheetId::new(),
+            name: "test".to_string(),
+        }];
+        let id = Uuid::new_v4();
+        let compressed_ops = Transaction::serialize_and_compress(&operations).unwrap();
+        let request = MessageRequest::Transaction {
+            id,
+            session_id,
+            file_id,
+            operations: compressed_ops.clone(),
+        };
+
+        (id, compressed_ops, request)
+    }
+
+    /// Instance 1 has user_1 and user_2, instance 2 has user_3.
+    async fn sequence_and_broadcast_across_instances(state_1: Arc<State>, state_2: Arc<State>) {
+        let (socket_1, state_1, _, file_id, user_1, _) = setup_with_state(state_1).await;
+        let socket_2 = Arc::new(Mutex::new(integration_test_setup(state_2.clone()).await));
+
+        start(state_1.clone());
+        start(state_2.clone());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        // users entering the room on instance 2 are announced on instance 1,
+        // and instance 1 replies with its users
+        let user_3 = new_user();
+        new_connection(socket_2.clone(), state_2.clone(), file_id, user_3.clone()).await;
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        assert_eq!(get_room!(state_1, file_id).unwrap().users.len(), 3);
+        assert_eq!(get_room!(state_2, file_id).unwrap().users.len(), 3);
+
+        // user_1 and user_2 share socket_1, so it receives the list of users
+        // once for each of them, on top of a message left from setup
+        let received = integration_test_receive(&socket_1, 3).await;
+        assert!(
+            matches!(received, Some(MessageResponse::UsersInRoom { users, .. }) if users.len() == 3)
+        );
+        let received = integration_test_receive(&socket_2, 1).await;
+        assert!(
+            matches!(received, Some(MessageResponse::UsersInRoom { users, .. }) if users.len() == 3)
+        );
+
+        // a transaction on instance 1 reaches the user on instance 2
+        let (id, operations, request) = transaction_request(user_1.session_id, file_id);
+        integration_test_send(&socket_1, request).await;
+        let expected = MessageResponse::Transaction {
+            id,
+            file_id,
+            operations,
+            sequence_num: 1,
+        };
+
+        assert_eq!(
+            integration_test_receive(&socket_1, 2).await,
+            Some(expected.clone())
+        );
+        assert_eq!(integration_test_receive(&socket_2, 1).await, Some(expected));
+
+        // instance 2's grid is missing the transaction, so it's reloaded
+        assert!(state_2.grids.lock().await.is_empty());
+        cache_grid(&state_2, file_id, GridController::test()).await;
+
+        // a transaction on instance 2 is sequenced after the one on instance 1
+        let (id, operations, request) = transaction_request(user_3.session_id, file_id);
+        integration_test_send(&socket_2, request).await;
+        let expected = MessageResponse::Transaction {
+            id,
+            file_id,
+            operations,
+            sequence_num: 2,
+        };
+
+        assert_eq!(
+            integration_test_receive(&socket_2, 1).await,
+            Some(expected.clone())
+        );
+        assert_eq!(integration_test_receive(&socket_1, 2).await, Some(expected));
+
+        assert_eq!(get_room!(state_1, file_id).unwrap().sequence_num, 2);
+        assert_eq!(get_room!(state_2, file_id).unwrap().sequence_num, 2);
+        assert_eq!(state_1.get_sequence_num(&file_id).await.unwrap(), 2);
+
+        // users leaving the room on instance 2 are removed on instance 1
+        state_2
+            .leave_room(file_id, &user_3.session_id)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        assert_eq!(get_room!(state_1, file_id).unwrap().users.len(), 2);
+        assert!(get_room!(state_2, file_id).is_err());
+    }
+
+    #[tokio::test]
+    async fn transactions_are_sequenced_and_broadcast_across_instances() {
+        sequence_and_broadcast_across_instances(new_arc_state().await, new_arc_state().await).await;
+    }
+
+    // run with `cargo test -- --ignored` and Redis at PUBSUB_HOST, so the
+    // sequencing and fan-out go through Redis Streams and Redis PubSub
+    #[tokio::test]
+    #[ignore = "needs Redis"]
+    async fn transactions_are_sequenced_and_broadcast_across_instances_with_redis() {
+        sequence_and_broadcast_across_instances(
+            new_arc_redis_state().await,
+            new_arc_redis_state().await,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn ignores_messages_from_the_same_instance() {
+        let (_, state, _, file_id, _, _) = setup().await;
+        let message = MessageResponse::CurrentTransaction { sequence_num: 10 };
+        let payload = serde_json::to_vec(&FanoutMessage {
+            instance_id: state.instance_id,
+            file_id,
+            event: FanoutEvent::Message {
+                exclude: vec![],
+                message: Box::new(MessageResponse::Transaction {
+                    id: Uuid::new_v4(),
+                    file_id,
+                    sequence_num: 10,
+                    operations: vec![],
+                }),
+            },
+        })
+        .unwrap();
+
+        receive(state.clone(), &payload).await.unwrap();
+        assert_eq!(get_room!(state, file_id).unwrap().sequence_num, 0);
+
+        // messages for rooms without local users are dropped
+        let payload = serde_json::to_vec(&FanoutMessage {
+            instance_id: Uuid::new_v4(),
+            file_id: Uuid::new_v4(),
+            event: FanoutEvent::Message {
+                exclude: vec![],
+                message: Box::new(message),
+            },
+        })
+        .unwrap();
+
+        receive(state, &payload).await.unwrap();
+    }
+}
@@ -0,0 +1,359 @@
+//! Grid Cache
+//!
+//! The state keeps a copy of each room's grid.  Incoming operations are
+//! checked against the copy's protected cells and applied to the copy before
+//! they are sequenced.  New comment mentions are found by comparing comments
+//! with the copy.
+//!
+//! Clients that fall too far behind are caught up from the same checkpoints.
+
+use quadratic_core::controller::operations::operation::Operation;
+use quadratic_core::controller::transaction::Transaction;
+use quadratic_core::controller::GridController;
+use quadratic_core::grid::file::import;
+use quadratic_core::grid::sheet::protections::EditUser;
+use quadratic_rust_shared::quadratic_api::get_file_checkpoint;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use uuid::Uuid;
+
+use crate::error::{MpError, Result};
+use crate::message::response::{MessageResponse, Transaction as ResponseTransaction};
+use crate::message::validate::{check_protections, dry_run};
+use crate::state::settings::Settings;
+use crate::state::State;
+
+// how long a client has to download a checkpoint when catching up
+const CHECKPOINT_URL_EXPIRATION_S: u64 = 60 * 5;
+
+/// A room's cached grid, which is `None` until it's loaded.  Each room has its
+/// own lock, so loading or applying a transaction to one room's grid doesn't
+/// block the other rooms.
+pub(crate) type CachedGrid = Arc<Mutex<Option<GridController>>>;
+
+fn checkpoint_key(file_id: Uuid, sequence_num: u64) -> String {
+    format!("{file_id}-{sequence_num}.grid")
+}
+
+impl State {
+    /// Load a file's grid from its last checkpoint, then apply the
+    /// transactions that have been sequenced since.
+    pub(crate) async fn load_grid(&self, file_id: Uuid) -> Result<GridController> {
+        let Settings {
+            quadratic_api_uri,
+            m2m_auth_token,
+            storage,
+            ..
+        } = &self.settings;
+
+        let checkpoint_sequence_num =
+            get_file_checkpoint(quadratic_api_uri, m2m_auth_token, &file_id)
+                .await?
+                .sequence_number;
+        let key = checkpoint_key(file_id, checkpoint_sequence_num);
+
+        let body = storage.read(&key).await?;
+        let grid = import(body.to_vec())
+            .map_err(|e| MpError::FileService(format!("Error importing file {key}: {e}")))?;
+        let mut grid = GridController::from_grid(grid, checkpoint_sequence_num);
+
+        let transactions = self
+            .get_messages_from_pubsub(&file_id, checkpoint_sequence_num + 1)
+            .await?;
+
+        for transaction in transactions {
+            let operations =
+                Transaction::decompress_and_deserialize::<Vec<Operation>>(&transaction.operations)
+                    .map_err(|e| MpError::Serialization(e.to_string()))?;
+            grid.server_apply_transaction(operations, None);
+        }
+
+        tracing::info!("Loaded grid for room {file_id} at checkpoint {checkpoint_sequence_num}");
+
+        Ok(grid)
+    }
+
+    /// Catch up a client that is missing transactions that are no longer in
+    /// the pubsub.  The client gets a url to download the latest checkpoint
+    /// from storage and every transaction after it, up to `sequence_num`.
+    pub(crate) async fn catch_up(
+        &self,
+        file_id: Uuid,
+        sequence_num: u64,
+    ) -> Result<MessageResponse> {
+        let Settings {
+            quadratic_api_uri,
+            m2m_auth_token,
+            storage,
+            ..
+        } = &self.settings;
+
+        let checkpoint_sequence_num =
+            get_file_checkpoint(quadratic_api_uri, m2m_auth_token, &file_id)
+                .await?
+                .sequence_number;
+
+        let transactions = self
+            .get_messages_from_pubsub(&file_id, checkpoint_sequence_num + 1)
+            .await?
+            .into_iter()
+            .map(|transaction| transaction.into())
+            .collect::<Vec<ResponseTransaction>>();
+        let transactions =
+            contiguous_transactions(transactions, checkpoint_sequence_num + 1, sequence_num)?;
+
+        let checkpoint_url = storage
+            .download_url(
+                &checkpoint_key(file_id, checkpoint_sequence_num),
+                Duration::from_secs(CHECKPOINT_URL_EXPIRATION_S),
+            )
+            .await?;
+
+        Ok(MessageResponse::CatchUp {
+            file_id,
+            checkpoint_sequence_num,
+            checkpoint_url,
+            transactions,
+        })
+    }
+
+    /// Lock a room's cached grid, loading it if it isn't cached yet.  The lock
+    /// should be held until the transaction is sequenced so that the cached
+    /// grid receives transactions in sequence order.
+    pub(crate) async fn lock_grid(
+        &self,
+        file_id: Uuid,
+    ) -> Result<OwnedMutexGuard<Option<GridController>>> {
+        let cached_grid = Arc::clone(self.grids.lock().await.entry(file_id).or_default());
+        let mut grid = cached_grid.lock_owned().await;
+
+        if grid.is_none() {
+            *grid = Some(self.load_grid(file_id).await?);
+        }
+
+        Ok(grid)
+    }
+
+    /// Remove a room's cached grid.
+    pub(crate) async fn remove_grid(&self, file_id: &Uuid) {
+        self.grids.lock().await.remove(file_id);
+    }
+}
+
+/// Keep the transactions that follow on from `from` without a gap.  Clients
+/// apply transactions in order, so every sequence number from `from` through
+/// `to` must be present, otherwise a MissingTransactions error is returned.
+pub(crate) fn contiguous_transactions(
+    mut transactions: Vec<ResponseTransaction>,
+    from: u64,
+    to: u64,
+) -> Result<Vec<ResponseTransaction>> {
+    let expected = (to + 1).saturating_sub(from);
+    let contiguous = transactions
+        .iter()
+        .zip(from..)
+        .take_while(|(transaction, sequence_num)| transaction.sequence_num == *sequence_num)
+        .count();
+
+    if (contiguous as u64) < expected {
+        return Err(MpError::MissingTransactions(
+            expected.to_string(),
+            contiguous.to_string(),
+        ));
+    }
+
+    transactions.truncate(contiguous);
+
+    Ok(transactions)
+}
+
+/// Apply a transaction's operations to a room's locked grid.  Transactions
+/// that change cells protected from the user are always rejected.  If the
+/// operations can't be applied, the grid is set to `None` so it's reloaded
+/// next time, and the transaction is rejected when `reject_invalid` is set,
+/// ie, with `VALIDATE_TRANSACTIONS_DRY_RUN`.
+///
+/// Applying operations can take a while for large transactions, so it runs on
+/// a blocking thread and the lock is handed back once it's done.
+pub(crate) async fn apply_transaction(
+    id: Uuid,
+    mut grid: OwnedMutexGuard<Option<GridController>>,
+    edit_user: EditUser,
+    operations: Vec<Operation>,
+    reject_invalid: bool,
+) -> Result<OwnedMutexGuard<Option<GridController>>> {
+    tokio::task::spawn_blocking(move || {
+        let Some(loaded) = grid.as_mut() else {
+            return Err(MpError::InvalidTransaction(id, "grid isn't loaded".into()));
+        };
+
+        check_protections(id, loaded, &edit_user, &operations)?;
+
+        if let Err(error) = dry_run(id, loaded, operations) {
+            // the grid may be partially modified, so reload it next time
+            *grid = None;
+
+            match reject_invalid {
+                true => return Err(error),
+                false => tracing::warn!("Reloading the grid for room: {error}"),
+            }
+        }
+
+        Ok(grid)
+    })
+    .await
+    .map_err(|e| MpError::InternalServer(format!("Error applying transaction {id}: {e}")))?
+}
+
+#[cfg(test)]
+mod tests {
+    use quadratic_core::grid::SheetId;
+
+    use super::*;
+    use crate::test_util::new_arc_state;
+
+    #[tokio::test]
+    async fn apply_transaction_updates_cached_grid() {
+        let state = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        let grid = GridController::test();
+        let sheet_id = grid.sheet_ids()[0];
+        state
+            .grids
+            .lock()
+            .await
+            .insert(file_id, Arc::new(Mutex::new(Some(grid))));
+
+        let operations = vec![Operation::SetSheetColor {
+            sheet_id,
+            color: Some("red".to_string()),
+        }];
+        let grid = state.lock_grid(file_id).await.unwrap();
+        let grid = apply_transaction(Uuid::new_v4(), grid, EditUser::default(), operations, true)
+            .await
+            .unwrap();
+
+        let color = &grid.as_ref().unwrap().try_sheet(sheet_id).unwrap().color;
+        assert_eq!(color, &Some("red".to_string()));
+        drop(grid);
+
+        // operations for a missing sheet are ignored by the grid
+        let operations = vec![Operation::SetSheetColor {
+            sheet_id: SheetId::new(),
+            color: Some("blue".to_string()),
+        }];
+        let grid = state.lock_grid(file_id).await.unwrap();
+        let grid = apply_transaction(Uuid::new_v4(), grid, EditUser::default(), operations, true)
+            .await
+            .unwrap();
+        drop(grid);
+
+        state.remove_grid(&file_id).await;
+        assert!(state.grids.lock().await.is_empty());
+    }
+
+    #[test]
+    fn contiguous_transactions_rejects_gaps() {
+        let file_id = Uuid::new_v4();
+        let transactions = |sequence_nums: &[u64]| {
+            sequence_nums
+                .iter()
+                .map(|&sequence_num| ResponseTransaction {
+                    id: Uuid::new_v4(),
+                    file_id,
+                    sequence_num,
+                    operations: vec![],
+                })
+                .collect::<Vec<_>>()
+        };
+        let sequence_nums = |transactions: Vec<ResponseTransaction>| {
+            transactions
+                .iter()
+                .map(|transaction| transaction.sequence_num)
+                .collect::<Vec<_>>()
+        };
+
+        let contiguous = contiguous_transactions(transactions(&[3, 4, 5]), 3, 5).unwrap();
+        assert_eq!(sequence_nums(contiguous), vec![3, 4, 5]);
+
+        // transactions after a gap are dropped
+        let contiguous = contiguous_transactions(transactions(&[3, 4, 6]), 3, 4).unwrap();
+        assert_eq!(sequence_nums(contiguous), vec![3, 4]);
+
+        // nothing is expected when the client is up to date
+        let contiguous = contiguous_transactions(vec![], 6, 5).unwrap();
+        assert!(contiguous.is_empty());
+
+        // the right number of transactions, but with a gap
+        let error = contiguous_transactions(transactions(&[3, 5, 6]), 3, 5).unwrap_err();
+        assert_eq!(error, MpError::MissingTransactions("3".into(), "1".into()));
+
+        // transactions that don't start at the first sequence number
+        let error = contiguous_transactions(transactions(&[4, 5]), 3, 5).unwrap_err();
+        assert_eq!(error, MpError::MissingTransactions("3".into(), "0".into()));
+    }
+
+    #[tokio::test]
+    async fn lock_grid_locks_each_room() {
+        let state = new_arc_state().await;
+        let (file_id, other_file_id) = (Uuid::new_v4(), Uuid::new_v4());
+        for id in [file_id, other_file_id] {
+            state
+                .grids
+                .lock()
+                .await
+                .insert(id, Arc::new(Mutex::new(Some(GridController::test()))));
+        }
+
+        // holding one room's grid doesn't block the other room
+        let grid = state.lock_grid(file_id).await.unwrap();
+        let other =
+            tokio::time::timeout(Duration::from_secs(1), state.lock_grid(other_file_id)).await;
+        assert!(other.unwrap().is_ok());
+        drop(grid);
+    }
+
+    #[tokio::test]
+    async fn apply_transaction_rejects_protected_cells() {
+        let state = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        let mut grid = GridController::test();
+        let sheet_id = grid.sheet_ids()[0];
+        let owner = EditUser {
+            user_id: "owner".to_string(),
+            roles: vec![],
+        };
+        grid.set_edit_user(owner.clone());
+        grid.add_protection(sheet_id, None, vec![], vec![], None);
+        state
+            .grids
+            .lock()
+            .await
+            .insert(file_id, Arc::new(Mutex::new(Some(grid))));
+
+        let operations = vec![Operation::SetSheetColor {
+            sheet_id,
+            color: Some("red".to_string()),
+        }];
+        let grid = state.lock_grid(file_id).await.unwrap();
+        let error = apply_transaction(
+            Uuid::new_v4(),
+            grid,
+            EditUser::default(),
+            operations.clone(),
+            false,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, MpError::ProtectedCells(_, _)));
+
+        // the cached grid is unchanged, still cached and no longer locked
+        let grid = state.lock_grid(file_id).await.unwrap();
+        let grid = apply_transaction(Uuid::new_v4(), grid, owner, operations, false)
+            .await
+            .unwrap();
+        let color = &grid.as_ref().unwrap().try_sheet(sheet_id).unwrap().color;
+        assert_eq!(color, &Some("red".to_string()));
+    }
+}
@@ -4,15 +4,21 @@
 //! struct.  All access and mutations to state should be performed here.
 
 pub mod connection;
+pub mod fanout;
+pub mod grid;
 pub mod pubsub;
 pub mod room;
 pub mod settings;
+pub mod stats;
 pub mod user;
 
 use dashmap::DashMap;
 use jsonwebtoken::jwk::JwkSet;
+#[cfg(any(test, feature = "memory-pubsub"))]
+use quadratic_rust_shared::pubsub::memory::{MemoryConfig, DEFAULT_NAME};
+use quadratic_rust_shared::pubsub::redis::RedisConfig;
 use quadratic_rust_shared::pubsub::redis_streams::RedisStreamsConfig;
-use quadratic_rust_shared::pubsub::Config as PubSubConfig;
+use quadratic_rust_shared::pubsub::{Config as PubSubConfig, PubSubType};
 use std::collections::HashMap;
 use tokio::sync::Mutex;
 use uuid::Uuid;
@@ -23,30 +29,77 @@ use crate::state::room::Room;
 use crate::state::settings::Settings;
 
 use self::connection::Connection;
+use self::fanout::Fanout;
+use self::grid::CachedGrid;
 use self::pubsub::PubSub;
+use self::stats::Stats;
 
 #[derive(Debug)]
 pub(crate) struct State {
+    pub(crate) instance_id: Uuid,
     pub(crate) rooms: Mutex<DashMap<Uuid, Room>>,
     pub(crate) connections: Mutex<HashMap<Uuid, Connection>>,
+    pub(crate) grids: Mutex<HashMap<Uuid, CachedGrid>>,
     pub(crate) pubsub: Mutex<PubSub>,
+    pub(crate) fanout: Mutex<Fanout>,
     pub(crate) settings: Settings,
+    pub(crate) stats: Mutex<Stats>,
+}
+
+/// Select the PubSub configs for transactions and fan-out from the config.
+/// In memory, which needs the `memory-pubsub` feature outside of tests, both
+/// use the same store so instances in the same process share it.
+fn pubsub_configs(config: &Config) -> Result<(PubSubConfig, PubSubConfig)> {
+    let configs = match config.pubsub_type {
+        PubSubType::RedisStreams => (
+            PubSubConfig::RedisStreams(RedisStreamsConfig {
+                host: config.pubsub_host.to_owned(),
+                port: config.pubsub_port.to_owned(),
+                password: config.pubsub_password.to_owned(),
+                active_channels: config.pubsub_active_channels.to_owned(),
+            }),
+            PubSubConfig::Redis(RedisConfig {
+                host: config.pubsub_host.to_owned(),
+                port: config.pubsub_port.to_owned(),
+                password: config.pubsub_password.to_owned(),
+                active_channels: config.pubsub_active_channels.to_owned(),
+            }),
+        ),
+        #[cfg(any(test, feature = "memory-pubsub"))]
+        PubSubType::Memory => {
+            let memory_config = PubSubConfig::Memory(MemoryConfig {
+                name: DEFAULT_NAME.into(),
+                active_channels: config.pubsub_active_channels.to_owned(),
+            });
+
+            (memory_config.to_owned(), memory_config)
+        }
+        #[cfg(not(any(test, feature = "memory-pubsub")))]
+        PubSubType::Memory => {
+            return Err(crate::error::MpError::Config(
+                "The memory PubSub needs the memory-pubsub feature".into(),
+            ))
+        }
+    };
+
+    Ok(configs)
 }
 
 impl State {
     pub(crate) async fn new(config: &Config, jwks: Option<JwkSet>) -> Result<Self> {
-        let pubsub_config = PubSubConfig::RedisStreams(RedisStreamsConfig {
-            host: config.pubsub_host.to_owned(),
-            port: config.pubsub_port.to_owned(),
-            password: config.pubsub_password.to_owned(),
-            active_channels: config.pubsub_active_channels.to_owned(),
-        });
+        let (pubsub_config, fanout_config) = pubsub_configs(config)?;
 
         Ok(State {
+            instance_id: Uuid::new_v4(),
             rooms: Mutex::new(DashMap::new()),
             connections: Mutex::new(HashMap::new()),
+            grids: Mutex::new(HashMap::new()),
             pubsub: Mutex::new(PubSub::new(pubsub_config).await?),
-            settings: Settings::new(config, jwks).await,
+            fanout: Mutex::new(
+                Fanout::new(fanout_config, config.pubsub_fanout_channel.to_owned()).await?,
+            ),
+            settings: Settings::new(config, jwks).await?,
+            stats: Mutex::new(Stats::new()),
         })
     }
 }
@@ -1,7 +1,9 @@
 use quadratic_core::controller::transaction::{Transaction, TransactionServer};
 use quadratic_rust_shared::pubsub::{
-    redis_streams::RedisConnection, Config as PubSubConfig, PubSub as PubSubTrait,
+    Config as PubSubConfig, PubSub as PubSubTrait, StreamConnection,
 };
+use quadratic_rust_shared::SharedError;
+use tokio::time::Instant;
 use uuid::Uuid;
 
 use crate::error::{MpError, Result};
@@ -10,10 +12,15 @@ use super::State;
 
 pub static GROUP_NAME: &str = "quadratic-multiplayer-1";
 
+/// Key of the file's sequence number counter in the PubSub server.
+pub(crate) fn sequence_num_key(file_id: &Uuid) -> String {
+    format!("{file_id}:sequence_num")
+}
+
 #[derive(Debug)]
 pub(crate) struct PubSub {
     pub(crate) config: PubSubConfig,
-    pub(crate) connection: RedisConnection,
+    pub(crate) connection: StreamConnection,
 }
 
 impl PubSub {
@@ -25,38 +32,42 @@ impl PubSub {
     }
 
     /// Connect to the PubSub server
-    pub(crate) async fn connect(config: &PubSubConfig) -> Result<RedisConnection> {
-        let connection = RedisConnection::new(config.to_owned()).await?;
+    pub(crate) async fn connect(config: &PubSubConfig) -> Result<StreamConnection> {
+        let connection = StreamConnection::new(config.to_owned()).await?;
 
         Ok(connection)
     }
 
+    /// Publish a transaction with the next sequence number for the file.  The
+    /// sequence number is an atomic counter in the PubSub server, so
+    /// transactions are ordered across all multiplayer instances.  Returns
+    /// the transaction's sequence number.
     pub(crate) async fn push(
         &mut self,
         id: Uuid,
         file_id: Uuid,
         operations: Vec<u8>,
-        sequence_num: u64,
+        min_sequence_num: u64,
     ) -> Result<u64> {
-        let transaction = TransactionServer {
-            id,
-            file_id,
-            operations,
-            sequence_num,
-        };
-        let transaction_compressed = Transaction::serialize_and_compress(&transaction)
-            .map_err(|e| MpError::Serialization(e.to_string()))?;
+        let active_channels = self.config.active_channels().unwrap_or("active_channels");
 
-        let active_channels = match self.config {
-            PubSubConfig::RedisStreams(ref config) => config.active_channels.as_str(),
-            _ => "active_channels",
-        };
-
-        self.connection
-            .publish(
+        let sequence_num = self
+            .connection
+            .publish_sequenced(
                 &file_id.to_string(),
-                &sequence_num.to_string(),
-                &transaction_compressed,
+                &sequence_num_key(&file_id),
+                min_sequence_num,
+                |sequence_num| {
+                    let transaction = TransactionServer {
+                        id,
+                        file_id,
+                        operations: operations.to_owned(),
+                        sequence_num,
+                    };
+
+                    Transaction::serialize_and_compress(&transaction)
+                        .map_err(|e| SharedError::Serialization(e.to_string()))
+                },
                 Some(active_channels),
             )
             .await?;
@@ -97,19 +108,36 @@ impl State {
         Ok(())
     }
 
-    /// Push a transaction to the transaction queue
+    /// Push a transaction to the transaction queue.  Returns the transaction's
+    /// sequence number, which is greater than `min_sequence_num`.
     pub(crate) async fn push_pubsub(
         &self,
         id: Uuid,
         file_id: Uuid,
         operations: Vec<u8>,
-        sequence_num: u64,
+        min_sequence_num: u64,
     ) -> Result<u64> {
-        self.pubsub
+        let started = Instant::now();
+        let sequence_num = self
+            .pubsub
             .lock()
             .await
-            .push(id, file_id, operations, sequence_num)
+            .push(id, file_id, operations, min_sequence_num)
+            .await;
+        self.stats.lock().await.record_pubsub_latency(started);
+
+        sequence_num
+    }
+
+    /// Get the file's last sequence number from the PubSub server
+    pub(crate) async fn get_sequence_num_pubsub(&self, file_id: &Uuid) -> Result<Option<u64>> {
+        Ok(self
+            .pubsub
+            .lock()
             .await
+            .connection
+            .sequence_num(&sequence_num_key(file_id))
+            .await?)
     }
 
     pub(crate) async fn get_messages_from_pubsub(
@@ -117,13 +145,17 @@ impl State {
         file_id: &Uuid,
         min_sequence_num: u64,
     ) -> Result<Vec<TransactionServer>> {
-        Ok(self
+        let started = Instant::now();
+        let messages = self
             .pubsub
             .lock()
             .await
             .connection
             .get_messages_from(&file_id.to_string(), &min_sequence_num.to_string(), false)
-            .await?
+            .await;
+        self.stats.lock().await.record_pubsub_latency(started);
+
+        Ok(messages?
             .into_iter()
             .flat_map(|(_, message)| Self::decompress_and_deserialize(message))
             .collect::<Vec<TransactionServer>>())
@@ -172,10 +204,11 @@ mod tests {
         let transaction_2 =
             Transaction::serialize_and_compress(&vec![operations_2.clone()]).unwrap();
 
-        state
-            .push_pubsub(transaction_id_1, file_id, transaction_1.clone(), 1)
+        let sequence_num = state
+            .push_pubsub(transaction_id_1, file_id, transaction_1.clone(), 0)
             .await
             .unwrap();
+        assert_eq!(sequence_num, 1);
         let transactions = state.get_messages_from_pubsub(&file_id, 0).await.unwrap();
         let expected_transaction_1 = TransactionServer {
             id: transaction_id_1,
@@ -186,10 +219,11 @@ mod tests {
 
         assert_eq!(transactions[0], expected_transaction_1);
 
-        state
-            .push_pubsub(transaction_id_2, file_id, transaction_2.clone(), 2)
+        let sequence_num = state
+            .push_pubsub(transaction_id_2, file_id, transaction_2.clone(), 0)
             .await
             .unwrap();
+        assert_eq!(sequence_num, 2);
         let transaction = state.get_messages_from_pubsub(&file_id, 0).await.unwrap();
         let expected_transaction_2 = TransactionServer {
             id: transaction_id_2,
@@ -205,5 +239,8 @@ mod tests {
         let last_transaction = state.get_last_message_pubsub(&file_id).await.unwrap();
         assert_eq!(last_transaction.0, "2".to_string());
         assert_eq!(last_transaction.1, expected_transaction_2);
+
+        let sequence_num = state.get_sequence_num_pubsub(&file_id).await.unwrap();
+        assert_eq!(sequence_num, Some(2));
     }
 }
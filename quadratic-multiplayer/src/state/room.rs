@@ -1,3 +1,4 @@
+use chrono::Utc;
 use dashmap::DashMap;
 use serde::Serialize;
 use uuid::Uuid;
@@ -37,9 +38,10 @@ impl Room {
         }
     }
 
-    pub fn increment_sequence_num(&mut self) -> u64 {
-        self.sequence_num += 1;
-        self.sequence_num
+    /// Records a sequence number that was assigned to a transaction.  The
+    /// sequence number only moves forward.
+    pub fn update_sequence_num(&mut self, sequence_num: u64) {
+        self.sequence_num = self.sequence_num.max(sequence_num);
     }
 
     pub fn get_user(&self, session_id: &Uuid) -> Result<User> {
@@ -96,15 +98,25 @@ impl State {
             .await
             .insert(connection.id, connection);
 
+        // let the other instances know, and get their users in the room
+        if is_new {
+            self.publish_users(file_id, true).await;
+        }
+
         Ok(is_new)
     }
 
-    /// Removes a user from a room. If the room is empty, it deletes the room.
-    /// Returns true if the room still exists after the user leaves.
+    /// Removes a user from a room.  If the room has no users connected to this
+    /// instance, it deletes the room.  Returns true if the room still exists
+    /// after the user leaves.
     #[tracing::instrument(level = "trace")]
     pub(crate) async fn leave_room(&self, file_id: Uuid, session_id: &Uuid) -> Result<bool> {
         get_mut_room!(self, file_id)?.users.remove(session_id);
-        let num_in_room = get_room!(self, file_id)?.users.len();
+        let num_in_room = get_room!(self, file_id)?
+            .users
+            .iter()
+            .filter(|user| user.instance_id.is_none())
+            .count();
 
         tracing::info!(
             "User {:?} is leaving room {}, {} user(s) left",
@@ -118,19 +130,66 @@ impl State {
             self.remove_room(file_id).await;
         }
 
+        self.publish_users(file_id, false).await;
+
         Ok(num_in_room != 0)
     }
 
+    /// Replaces the users in a room that are connected to another instance.
+    /// Their heartbeat is the time of the instance's last update.
+    pub(crate) async fn update_remote_users(
+        &self,
+        file_id: Uuid,
+        instance_id: Uuid,
+        users: Vec<User>,
+    ) -> Result<()> {
+        let rooms = self.rooms.lock().await;
+        let room = rooms
+            .get(&file_id)
+            .ok_or(MpError::RoomNotFound(file_id.to_string()))?;
+
+        room.users
+            .retain(|_, user| user.instance_id != Some(instance_id));
+
+        for mut user in users {
+            // a session connected to this instance takes precedence
+            if room.users.contains_key(&user.session_id) {
+                continue;
+            }
+
+            user.instance_id = Some(instance_id);
+            user.last_heartbeat = Utc::now();
+            room.users.insert(user.session_id, user);
+        }
+
+        Ok(())
+    }
+
+    /// Publishes this instance's users in a room to the other instances.
+    /// Errors are logged, since the users are published again by the
+    /// background worker.
+    pub(crate) async fn publish_users(&self, file_id: Uuid, reply: bool) {
+        if let Err(error) = self.publish_fanout_users(file_id, reply).await {
+            tracing::warn!("Error publishing users in room {file_id}: {:?}", error);
+        }
+    }
+
     /// Removes a room.
     pub(crate) async fn remove_room(&self, file_id: Uuid) {
         self.rooms.lock().await.remove(&file_id);
+        self.remove_grid(&file_id).await;
 
         tracing::info!("Room {file_id} removed");
     }
 
-    /// Get a room's current sequence number.
+    /// Get a room's current sequence number.  Transactions may have been
+    /// sequenced by other multiplayer instances, so check the PubSub server's
+    /// counter as well.
     pub(crate) async fn get_sequence_num(&self, file_id: &Uuid) -> Result<u64> {
-        Ok(get_room!(self, file_id)?.sequence_num)
+        let sequence_num = get_room!(self, file_id)?.sequence_num;
+        let pubsub_sequence_num = self.get_sequence_num_pubsub(file_id).await?;
+
+        Ok(sequence_num.max(pubsub_sequence_num.unwrap_or_default()))
     }
 }
 
@@ -229,7 +288,7 @@ mod tests {
 
         get_mut_room!(state, file_id)
             .unwrap()
-            .increment_sequence_num();
+            .update_sequence_num(1);
         let sequence_num = state.get_sequence_num(&file_id).await.unwrap();
         assert_eq!(sequence_num, 1);
 
@@ -1,4 +1,9 @@
 use jsonwebtoken::jwk::JwkSet;
+use quadratic_rust_shared::environment::Environment;
+use quadratic_rust_shared::storage::{
+    file_system::FileSystemConfig, s3::S3Config, storage, StorageConfig, StorageContainer,
+    StorageType,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::config::Config;
@@ -24,17 +29,50 @@ pub(crate) struct Settings {
     pub(crate) authenticate_jwt: bool,
     pub(crate) quadratic_api_uri: String,
     pub(crate) m2m_auth_token: String,
+    pub(crate) admin_auth_token: String,
     pub(crate) min_version: MinVersion,
+    pub(crate) max_transaction_size_bytes: usize,
+    pub(crate) validate_transactions_dry_run: bool,
+    pub(crate) storage: StorageContainer,
 }
 
 impl Settings {
-    pub(crate) async fn new(config: &Config, jwks: Option<JwkSet>) -> Self {
-        Settings {
+    pub(crate) async fn new(config: &Config, jwks: Option<JwkSet>) -> Result<Self> {
+        Ok(Settings {
             jwks,
             authenticate_jwt: config.authenticate_jwt,
             quadratic_api_uri: config.quadratic_api_uri.to_owned(),
             m2m_auth_token: config.m2m_auth_token.to_owned(),
+            admin_auth_token: config.admin_auth_token.to_owned(),
             min_version: MinVersion::new().expect("Unable to load min version file"),
-        }
+            max_transaction_size_bytes: config.max_transaction_size_bytes,
+            validate_transactions_dry_run: config.validate_transactions_dry_run,
+            storage: storage(storage_config(config)?).await,
+        })
     }
 }
+
+/// Select the storage that the files service writes checkpoints to.
+pub(crate) fn storage_config(config: &Config) -> Result<StorageConfig> {
+    let is_local =
+        config.environment == Environment::Docker || config.environment == Environment::Local;
+
+    let storage_config = match config.storage_type {
+        StorageType::S3 => StorageConfig::S3(S3Config {
+            access_key_id: config.aws_s3_access_key_id.to_owned(),
+            secret_access_key: config.aws_s3_secret_access_key.to_owned(),
+            region: config.aws_s3_region.to_owned(),
+            bucket: config.aws_s3_bucket_name.to_owned(),
+            provider_name: "Quadratic Multiplayer",
+            is_local,
+        }),
+        StorageType::FileSystem => StorageConfig::FileSystem(FileSystemConfig {
+            path: config.storage_dir.to_owned().ok_or_else(|| {
+                MpError::Config("STORAGE_DIR is required for file-system storage".into())
+            })?,
+        }),
+        StorageType::Memory => StorageConfig::Memory,
+    };
+
+    Ok(storage_config)
+}
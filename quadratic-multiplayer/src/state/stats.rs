@@ -0,0 +1,156 @@
+//! Stats
+//!
+//! Counters collected while handling messages, which are exposed in the
+//! Prometheus text format at `/metrics`.  Gauges that can be read from the
+//! state, like the number of rooms, are added when the metrics are rendered.
+
+use quadratic_rust_shared::metrics::Metrics;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::state::State;
+
+#[derive(Debug, Default)]
+pub(crate) struct Stats {
+    pub(crate) transactions: u64,
+    pub(crate) transactions_per_second: f64,
+    pub(crate) heartbeat_evictions: u64,
+    pub(crate) pubsub_requests: u64,
+    pub(crate) pubsub_latency: Duration,
+    last_rate_check: Option<(Instant, u64)>,
+}
+
+/// Values read from the state when rendering the metrics.
+#[derive(Debug, Default)]
+pub(crate) struct Gauges {
+    pub(crate) rooms: usize,
+    pub(crate) users: usize,
+    pub(crate) connections: usize,
+}
+
+impl Stats {
+    pub(crate) fn new() -> Self {
+        Stats::default()
+    }
+
+    /// Record the time taken by a request to the PubSub server.
+    pub(crate) fn record_pubsub_latency(&mut self, started: Instant) {
+        self.pubsub_requests += 1;
+        self.pubsub_latency += started.elapsed();
+    }
+
+    /// Update the transactions per second since the last update.  Called by
+    /// the background worker on each heartbeat check.
+    pub(crate) fn update_transactions_per_second(&mut self) {
+        let now = Instant::now();
+
+        if let Some((last_check, last_transactions)) = self.last_rate_check {
+            let elapsed = now.duration_since(last_check).as_secs_f64();
+
+            if elapsed > 0.0 {
+                let transactions = self.transactions.saturating_sub(last_transactions);
+                self.transactions_per_second = transactions as f64 / elapsed;
+            }
+        }
+
+        self.last_rate_check = Some((now, self.transactions));
+    }
+
+    /// Render the stats and gauges in the Prometheus text format.
+    pub(crate) fn to_prometheus(&self, gauges: &Gauges) -> String {
+        let mut metrics = Metrics::new("quadratic_multiplayer");
+        metrics
+            .gauge("rooms", "Number of rooms on this instance.", gauges.rooms)
+            .gauge(
+                "users",
+                "Number of users in rooms on this instance.",
+                gauges.users,
+            )
+            .gauge(
+                "connections",
+                "Number of websocket connections on this instance.",
+                gauges.connections,
+            )
+            .counter(
+                "transactions_total",
+                "Number of transactions sequenced by this instance.",
+                self.transactions,
+            )
+            .gauge(
+                "transactions_per_second",
+                "Transactions sequenced per second since the last heartbeat check.",
+                self.transactions_per_second,
+            )
+            .summary(
+                "pubsub_latency_seconds",
+                "Latency of requests to the PubSub server.",
+                self.pubsub_latency.as_secs_f64(),
+                self.pubsub_requests,
+            )
+            .counter(
+                "heartbeat_evictions_total",
+                "Number of users removed from rooms for missing heartbeats.",
+                self.heartbeat_evictions,
+            );
+
+        metrics.to_string()
+    }
+}
+
+impl State {
+    /// Render this instance's metrics in the Prometheus text format.
+    pub(crate) async fn metrics(&self) -> String {
+        let (rooms, users) = {
+            let rooms = self.rooms.lock().await;
+            let users = rooms.iter().map(|room| room.users.len()).sum();
+            (rooms.len(), users)
+        };
+        let gauges = Gauges {
+            rooms,
+            users,
+            connections: self.connections.lock().await.len(),
+        };
+
+        self.stats.lock().await.to_prometheus(&gauges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_prometheus_metrics() {
+        let mut stats = Stats::new();
+        stats.transactions = 3;
+        stats.heartbeat_evictions = 1;
+        stats.record_pubsub_latency(Instant::now());
+
+        let gauges = Gauges {
+            rooms: 2,
+            users: 5,
+            connections: 6,
+        };
+        let metrics = stats.to_prometheus(&gauges);
+
+        assert!(metrics.contains("# TYPE quadratic_multiplayer_rooms gauge\n"));
+        assert!(metrics.contains("quadratic_multiplayer_rooms 2\n"));
+        assert!(metrics.contains("quadratic_multiplayer_users 5\n"));
+        assert!(metrics.contains("quadratic_multiplayer_connections 6\n"));
+        assert!(metrics.contains("quadratic_multiplayer_transactions_total 3\n"));
+        assert!(metrics.contains("quadratic_multiplayer_pubsub_latency_seconds_count 1\n"));
+        assert!(metrics.contains("quadratic_multiplayer_heartbeat_evictions_total 1\n"));
+    }
+
+    #[tokio::test]
+    async fn updates_transactions_per_second() {
+        let mut stats = Stats::new();
+        stats.update_transactions_per_second();
+        assert_eq!(stats.transactions_per_second, 0.0);
+
+        stats.transactions = 10;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        stats.update_transactions_per_second();
+        assert!(stats.transactions_per_second > 0.0);
+    }
+}
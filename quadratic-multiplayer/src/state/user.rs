@@ -7,6 +7,7 @@ use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::error::{MpError, Result};
+use crate::message::protocol::Protocol;
 use crate::state::State;
 use crate::{get_mut_room, get_room};
 use quadratic_rust_shared::quadratic_api::FilePermRole;
@@ -29,7 +30,13 @@ pub(crate) struct User {
     #[serde(skip)]
     pub socket: Option<UserSocket>,
     #[serde(skip)]
+    pub protocol: Protocol,
+    #[serde(skip)]
     pub last_heartbeat: DateTime<Utc>,
+    /// The multiplayer instance that the user is connected to, or None if
+    /// they're connected to this one
+    #[serde(skip)]
+    pub instance_id: Option<Uuid>,
 }
 
 impl PartialEq for User {
@@ -134,6 +141,8 @@ impl State {
             self.connections.lock().await.remove(&user.connection_id);
         }
 
+        self.stats.lock().await.heartbeat_evictions += stale_users.len() as u64;
+
         Ok((stale_users.len(), num_active_users))
     }
 
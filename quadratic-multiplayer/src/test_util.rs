@@ -5,9 +5,11 @@ use fake::Fake;
 use futures::stream::StreamExt;
 use futures_util::SinkExt;
 use quadratic_core::cell_values::CellValues;
+use quadratic_core::compression::{serialize, SerializationFormat};
 use quadratic_core::controller::operations::operation::Operation;
 use quadratic_core::controller::GridController;
 use quadratic_core::{CellValue, SheetPos};
+use quadratic_rust_shared::pubsub::PubSubType;
 use quadratic_rust_shared::quadratic_api::FilePermRole;
 use std::sync::Arc;
 use std::{
@@ -16,10 +18,12 @@ use std::{
 };
 use tokio::net::TcpStream;
 use tokio::sync::Mutex;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use tokio_tungstenite::{tungstenite, MaybeTlsStream, WebSocketStream};
 use uuid::Uuid;
 
-use crate::config::config;
+use crate::config::{config, Config};
+use crate::message::protocol::{decode_binary, Protocol};
 use crate::message::request::MessageRequest;
 use crate::message::response::MessageResponse;
 use crate::state::connection::PreConnection;
@@ -44,7 +48,20 @@ pub(crate) async fn setup() -> (
     User,
     User,
 ) {
-    let state = new_arc_state().await;
+    setup_with_state(new_arc_state().await).await
+}
+
+/// The same setup as `setup()`, with the given state.
+pub(crate) async fn setup_with_state(
+    state: Arc<State>,
+) -> (
+    Arc<Mutex<WebSocketStream<MaybeTlsStream<TcpStream>>>>,
+    Arc<State>,
+    Uuid,
+    Uuid,
+    User,
+    User,
+) {
     let socket = integration_test_setup(state.clone()).await;
     let socket = Arc::new(Mutex::new(socket));
     let file_id = Uuid::new_v4();
@@ -58,6 +75,8 @@ pub(crate) async fn setup() -> (
         .await
         .unwrap();
 
+    cache_grid(&state, file_id, GridController::test()).await;
+
     let user_1 = new_user();
     let connection_id =
         new_connection(socket.clone(), state.clone(), file_id, user_1.clone()).await;
@@ -76,6 +95,16 @@ pub(crate) async fn setup() -> (
     )
 }
 
+/// Cache a new grid for the room, so transactions can be checked without
+/// loading its checkpoint from the API.
+pub(crate) async fn cache_grid(state: &State, file_id: Uuid, grid: GridController) {
+    state
+        .grids
+        .lock()
+        .await
+        .insert(file_id, Arc::new(Mutex::new(Some(grid))));
+}
+
 /// Create new global state
 pub(crate) async fn new_state() -> State {
     let config = config().unwrap();
@@ -87,6 +116,17 @@ pub(crate) async fn new_arc_state() -> Arc<State> {
     Arc::new(new_state().await)
 }
 
+/// Create new global state that uses Redis for the PubSub, for tests that
+/// need a Redis server at `PUBSUB_HOST`
+pub(crate) async fn new_arc_redis_state() -> Arc<State> {
+    let config = Config {
+        pubsub_type: PubSubType::RedisStreams,
+        ..config().unwrap()
+    };
+
+    Arc::new(State::new(&config, None).await.unwrap())
+}
+
 /// Create a new user with fake values
 pub(crate) fn new_user() -> User {
     User {
@@ -110,21 +150,17 @@ pub(crate) fn new_user() -> User {
         image: FilePath().fake(),
         permissions: vec![FilePermRole::FileView, FilePermRole::FileEdit],
         socket: None,
+        protocol: Protocol::default(),
         last_heartbeat: chrono::Utc::now(),
+        instance_id: None,
         index: 0,
     }
 }
 
-/// Add an existing to a room via the WebSocket.
-/// Returns a reference to the user's `receiver` WebSocket.
-pub(crate) async fn add_user_via_ws(
-    file_id: Uuid,
-    socket: Arc<Mutex<WebSocketStream<MaybeTlsStream<TcpStream>>>>,
-    user: User,
-) -> User {
-    let session_id = user.session_id;
-    let request = MessageRequest::EnterRoom {
-        session_id,
+/// Create a request for the user to enter the room
+pub(crate) fn enter_room_request(file_id: Uuid, user: &User) -> MessageRequest {
+    MessageRequest::EnterRoom {
+        session_id: user.session_id,
         user_id: user.user_id.clone(),
         file_id,
         sheet_id: user.state.sheet_id,
@@ -136,7 +172,17 @@ pub(crate) async fn add_user_via_ws(
         cell_edit: CellEdit::default(),
         viewport: "initial viewport".to_string(),
         follow: None,
-    };
+    }
+}
+
+/// Add an existing to a room via the WebSocket.
+/// Returns a reference to the user's `receiver` WebSocket.
+pub(crate) async fn add_user_via_ws(
+    file_id: Uuid,
+    socket: Arc<Mutex<WebSocketStream<MaybeTlsStream<TcpStream>>>>,
+    user: User,
+) -> User {
+    let request = enter_room_request(file_id, &user);
 
     // UsersInRoom and EnterRoom are sent to the client when they enter a room
     integration_test_send_and_receive(&socket, request, true, 1).await;
@@ -196,6 +242,14 @@ pub(crate) fn operation(grid: &mut GridController, x: i64, y: i64, value: &str)
 /// - Returns a reference to the user's `receiver` WebSocket
 pub(crate) async fn integration_test_setup(
     state: Arc<State>,
+) -> WebSocketStream<MaybeTlsStream<TcpStream>> {
+    integration_test_setup_with_protocol(state, None).await
+}
+
+/// Setup integration testing, requesting a websocket subprotocol.
+pub(crate) async fn integration_test_setup_with_protocol(
+    state: Arc<State>,
+    protocol: Option<&str>,
 ) -> WebSocketStream<MaybeTlsStream<TcpStream>> {
     let listener = tokio::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)))
         .await
@@ -205,9 +259,15 @@ pub(crate) async fn integration_test_setup(
     // run the server in a separate thread
     tokio::spawn(axum::serve(listener, crate::server::app(state)).into_future());
 
-    let (socket, _response) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
-        .await
-        .unwrap();
+    let mut request = format!("ws://{addr}/ws").into_client_request().unwrap();
+
+    if let Some(protocol) = protocol {
+        request
+            .headers_mut()
+            .insert("Sec-WebSocket-Protocol", protocol.parse().unwrap());
+    }
+
+    let (socket, _response) = tokio_tungstenite::connect_async(request).await.unwrap();
 
     socket
 }
@@ -249,6 +309,23 @@ pub(crate) async fn integration_test_send(
         println!("Error sending message: {:?}", e);
     };
 }
+
+/// Using the WebSocket created in integration_test_setup(), send a binary message.
+pub(crate) async fn integration_test_send_binary(
+    socket: &Arc<Mutex<WebSocketStream<MaybeTlsStream<TcpStream>>>>,
+    request: MessageRequest,
+) {
+    let data = serialize(&SerializationFormat::Cbor, &request).unwrap();
+
+    if let Err(e) = socket
+        .lock()
+        .await
+        .send(tungstenite::Message::binary(data))
+        .await
+    {
+        println!("Error sending message: {:?}", e);
+    };
+}
 /// Using the WebSocket created in integration_test_setup(), receive a response.
 /// Returns the optional response.
 /// `response_num` is the number of responses to receive before returning the last one.
@@ -265,7 +342,10 @@ pub(crate) async fn integration_test_receive(
             tungstenite::Message::Text(msg) => {
                 Some(serde_json::from_str::<MessageResponse>(&msg).unwrap())
             }
-            other => panic!("expected a text message but got {other:?}"),
+            tungstenite::Message::Binary(data) => {
+                Some(decode_binary::<MessageResponse>(&data).unwrap())
+            }
+            other => panic!("expected a text or binary message but got {other:?}"),
         };
 
         if count >= response_num {
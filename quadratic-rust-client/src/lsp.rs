@@ -1,5 +1,33 @@
+use std::cell::RefCell;
+
+use quadratic_core::{
+    formulas::{format_formula, lsp::LspContext, FormatOptions},
+    Pos,
+};
+
 use super::*;
 
+thread_local! {
+    /// Grid information sent from the client, used for completions,
+    /// diagnostics, and resolving table references.
+    static LSP_CONTEXT: RefCell<LspContext> = RefCell::new(LspContext::default());
+}
+
+/// Updates the grid information used by the language server. `context` is a
+/// JSON-serialized `LspContext`.
+#[wasm_bindgen(js_name = "setLspContext")]
+pub fn set_lsp_context(context: &str) -> Result<(), JsValue> {
+    let context = serde_json::from_str::<LspContext>(context)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    LSP_CONTEXT.with(|lsp_context| *lsp_context.borrow_mut() = context);
+    Ok(())
+}
+
+/// Calls `f` with the grid information most recently sent from the client.
+pub(crate) fn with_lsp_context<R>(f: impl FnOnce(&LspContext) -> R) -> R {
+    LSP_CONTEXT.with(|lsp_context| f(&lsp_context.borrow()))
+}
+
 #[wasm_bindgen(js_name = "provideCompletionItems")]
 pub fn provide_completion_items(
     _text_model: JsValue,
@@ -7,9 +35,11 @@ pub fn provide_completion_items(
     _context: JsValue,
     _token: JsValue,
 ) -> Result<JsValue, JsValue> {
-    Ok(serde_wasm_bindgen::to_value(
-        &quadratic_core::formulas::lsp::provide_completion_items(),
-    )?)
+    with_lsp_context(|lsp_context| {
+        Ok(serde_wasm_bindgen::to_value(
+            &quadratic_core::formulas::lsp::provide_completion_items(lsp_context),
+        )?)
+    })
 }
 
 #[wasm_bindgen(js_name = "provideHover")]
@@ -24,3 +54,81 @@ pub fn provide_hover(
     let result = quadratic_core::formulas::lsp::provide_hover(&partial_function_name);
     Ok(serde_wasm_bindgen::to_value(&result)?)
 }
+
+#[wasm_bindgen(js_name = "provideSignatureHelp")]
+pub fn provide_signature_help(
+    text_model: JsValue,
+    position: JsValue,
+    _token: JsValue,
+    _context: JsValue,
+) -> Result<JsValue, JsValue> {
+    let formula = jsexpr!(text_model.getValue())
+        .as_string()
+        .unwrap_or_default();
+    let offset = jsexpr!(text_model.getOffsetAt(position))
+        .as_f64()
+        .unwrap_or_default() as usize;
+    let offset = quadratic_core::formulas::lsp::byte_offset(&formula, offset);
+    let Some(signature_help) =
+        quadratic_core::formulas::lsp::provide_signature_help(&formula, offset)
+    else {
+        return Ok(JsValue::NULL);
+    };
+
+    // Monaco expects a disposable `SignatureHelpResult`.
+    let result = js_sys::Object::new();
+    js_sys::Reflect::set(
+        &result,
+        &"value".into(),
+        &serde_wasm_bindgen::to_value(&signature_help)?,
+    )?;
+    js_sys::Reflect::set(
+        &result,
+        &"dispose".into(),
+        &js_sys::Function::new_no_args(""),
+    )?;
+    Ok(result.into())
+}
+
+/// Returns Monaco markers for errors in a formula at cell (`x`, `y`).
+#[wasm_bindgen(js_name = "provideDiagnostics")]
+pub fn provide_diagnostics(formula: &str, x: f64, y: f64) -> Result<JsValue, JsValue> {
+    let pos = Pos {
+        x: x as i64,
+        y: y as i64,
+    };
+    with_lsp_context(|lsp_context| {
+        Ok(serde_wasm_bindgen::to_value(
+            &quadratic_core::formulas::lsp::provide_diagnostics(formula, pos, lsp_context),
+        )?)
+    })
+}
+
+#[wasm_bindgen(js_name = "provideDocumentFormattingEdits")]
+pub fn provide_document_formatting_edits(
+    text_model: JsValue,
+    _options: JsValue,
+    _token: JsValue,
+) -> Result<JsValue, JsValue> {
+    let formula = jsexpr!(text_model.getValue())
+        .as_string()
+        .unwrap_or_default();
+    let edits = js_sys::Array::new();
+
+    // Relative references round-trip from any position, so the formula's
+    // actual position is not needed.
+    let options = FormatOptions { multiline: true };
+    if let Ok(formatted) = format_formula(&formula, Pos::ORIGIN, options) {
+        if formatted != formula {
+            let edit = js_sys::Object::new();
+            js_sys::Reflect::set(
+                &edit,
+                &"range".into(),
+                &jsexpr!(text_model.getFullModelRange()),
+            )?;
+            js_sys::Reflect::set(&edit, &"text".into(), &formatted.into())?;
+            edits.push(&edit);
+        }
+    }
+    Ok(edits.into())
+}
@@ -77,15 +77,29 @@ pub fn parse_formula(formula_string: &str, x: f64, y: f64) -> JsValue {
         parse_error_msg: parse_error.as_ref().map(|e| e.msg.to_string()),
         parse_error_span: parse_error.and_then(|e| e.span),
 
-        cell_refs: formulas::find_cell_references(formula_string, pos)
-            .into_iter()
-            .map(|r| r.into())
-            .collect(),
+        cell_refs: crate::lsp::with_lsp_context(|lsp_context| {
+            formulas::lsp::find_references(formula_string, pos, lsp_context)
+        })
+        .into_iter()
+        .map(|r| r.into())
+        .collect(),
     };
 
     serde_wasm_bindgen::to_value(&result).unwrap()
 }
 
+/// Returns the canonical rendering of a formula, or `undefined` if it cannot
+/// be parsed. If `multiline` is true, nested `IF` calls are split across
+/// lines.
+#[wasm_bindgen(js_name = "formatFormula")]
+pub fn format_formula(formula_string: &str, x: f64, y: f64, multiline: bool) -> Option<String> {
+    let pos = Pos {
+        x: x as i64,
+        y: y as i64,
+    };
+    formulas::format_formula(formula_string, pos, formulas::FormatOptions { multiline }).ok()
+}
+
 #[wasm_bindgen(js_name = "checkFormula")]
 pub fn check_formula(formula_string: &str, x: i32, y: i32) -> bool {
     formulas::parse_and_check_formula(formula_string, x as i64, y as i64)
@@ -1,10 +1,13 @@
 use aws_sdk_s3::{
     operation::{get_object::GetObjectOutput, put_object::PutObjectOutput},
+    presigning::PresigningConfig,
     primitives::{ByteStream, SdkBody},
     Client,
 };
+use std::time::Duration;
 
 use crate::error::{Aws, Result, SharedError};
+use crate::storage::ObjectSummary;
 
 pub async fn download_object(client: &Client, bucket: &str, key: &str) -> Result<GetObjectOutput> {
     client
@@ -21,6 +24,31 @@ pub async fn download_object(client: &Client, bucket: &str, key: &str) -> Result
         })
 }
 
+/// Create a url that downloads an object without credentials until it
+/// expires.
+pub async fn presigned_download_url(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    expires_in: Duration,
+) -> Result<String> {
+    let error = |error: String| {
+        SharedError::Aws(Aws::S3(format!(
+            "Error presigning file {key} in bucket {bucket}: {error}."
+        )))
+    };
+    let config = PresigningConfig::expires_in(expires_in).map_err(|e| error(e.to_string()))?;
+    let request = client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .presigned(config)
+        .await
+        .map_err(|e| error(format!("{:?}", e)))?;
+
+    Ok(request.uri().to_string())
+}
+
 pub async fn upload_object(
     client: &Client,
     bucket: &str,
@@ -44,5 +72,47 @@ pub async fn upload_object(
         })
 }
 
+/// List all objects in a bucket whose keys start with `prefix`.
+pub async fn list_objects(
+    client: &Client,
+    bucket: &str,
+    prefix: &str,
+) -> Result<Vec<ObjectSummary>> {
+    let mut objects = vec![];
+    let mut continuation_token = None;
+
+    loop {
+        let output = client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(prefix)
+            .set_continuation_token(continuation_token)
+            .send()
+            .await
+            .map_err(|error| {
+                SharedError::Aws(Aws::S3(format!(
+                    "Error listing files with prefix {prefix} in bucket {bucket}: {:?}.",
+                    error
+                )))
+            })?;
+
+        objects.extend(output.contents().iter().filter_map(|object| {
+            object.key().map(|key| ObjectSummary {
+                key: key.to_owned(),
+                last_modified: object
+                    .last_modified()
+                    .and_then(|last_modified| last_modified.to_millis().ok()),
+            })
+        }));
+
+        match output.next_continuation_token() {
+            Some(token) => continuation_token = Some(token.to_owned()),
+            None => break,
+        }
+    }
+
+    Ok(objects)
+}
+
 #[cfg(test)]
 mod tests {}
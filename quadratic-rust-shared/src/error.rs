@@ -39,6 +39,9 @@ pub enum Sql {
     #[error("Error executing query: {0}")]
     Query(String),
 
+    #[error("Statement not allowed on a read-only connection: {0}")]
+    ReadOnly(String),
+
     #[error("Error creating schema: {0}")]
     Schema(String),
 }
@@ -69,6 +72,12 @@ pub enum SharedError {
     #[error("Error with SQL connector: {0}")]
     Sql(Sql),
 
+    #[error("Error with SSH tunnel: {0}")]
+    Ssh(String),
+
+    #[error("Error with storage: {0}")]
+    Storage(String),
+
     #[error("Error with Uuid: {0}")]
     Uuid(String),
 }
@@ -114,3 +123,15 @@ impl From<arrow::error::ArrowError> for SharedError {
         SharedError::Arrow(Arrow::External(error.to_string()))
     }
 }
+
+impl From<russh::Error> for SharedError {
+    fn from(error: russh::Error) -> Self {
+        SharedError::Ssh(error.to_string())
+    }
+}
+
+impl From<russh_keys::Error> for SharedError {
+    fn from(error: russh_keys::Error) -> Self {
+        SharedError::Ssh(error.to_string())
+    }
+}
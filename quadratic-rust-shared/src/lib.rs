@@ -3,9 +3,12 @@ pub mod auth;
 pub mod aws;
 pub mod environment;
 pub mod error;
+pub mod metrics;
 pub mod pubsub;
 pub mod quadratic_api;
 pub mod sql;
+pub mod ssh;
+pub mod storage;
 
 // pub use aws::*;
 pub use error::*;
@@ -0,0 +1,91 @@
+//! Metrics
+//!
+//! Render a service's stats in the Prometheus text format for its `/metrics`
+//! endpoint.
+
+use std::fmt::Display;
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+#[derive(Debug)]
+pub struct Metrics {
+    prefix: String,
+    text: String,
+}
+
+impl Metrics {
+    /// Metric names are prefixed with `prefix`, eg, `quadratic_files`.
+    pub fn new(prefix: &str) -> Self {
+        Metrics {
+            prefix: prefix.to_owned(),
+            text: String::new(),
+        }
+    }
+
+    fn metric(&mut self, name: &str, kind: &str, help: &str, samples: &[(&str, String)]) {
+        let name = format!("{}_{name}", self.prefix);
+        self.text.push_str(&format!("# HELP {name} {help}\n"));
+        self.text.push_str(&format!("# TYPE {name} {kind}\n"));
+
+        for (suffix, value) in samples {
+            self.text.push_str(&format!("{name}{suffix} {value}\n"));
+        }
+    }
+
+    /// A value that can go up and down.
+    pub fn gauge(&mut self, name: &str, help: &str, value: impl Display) -> &mut Self {
+        self.metric(name, "gauge", help, &[("", value.to_string())]);
+        self
+    }
+
+    /// A value that only goes up.  Names should end in `_total`.
+    pub fn counter(&mut self, name: &str, help: &str, value: u64) -> &mut Self {
+        self.metric(name, "counter", help, &[("", value.to_string())]);
+        self
+    }
+
+    /// The sum and count of observations, eg, request latencies.
+    pub fn summary(&mut self, name: &str, help: &str, sum: f64, count: u64) -> &mut Self {
+        self.metric(
+            name,
+            "summary",
+            help,
+            &[("_sum", sum.to_string()), ("_count", count.to_string())],
+        );
+        self
+    }
+}
+
+impl Display for Metrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_the_prometheus_text_format() {
+        let mut metrics = Metrics::new("quadratic_test");
+        metrics
+            .gauge("rooms", "Number of rooms.", 2)
+            .counter("requests_total", "Number of requests.", 10)
+            .summary("latency_seconds", "Latency of requests.", 1.5, 3);
+
+        let expected = "\
+# HELP quadratic_test_rooms Number of rooms.
+# TYPE quadratic_test_rooms gauge
+quadratic_test_rooms 2
+# HELP quadratic_test_requests_total Number of requests.
+# TYPE quadratic_test_requests_total counter
+quadratic_test_requests_total 10
+# HELP quadratic_test_latency_seconds Latency of requests.
+# TYPE quadratic_test_latency_seconds summary
+quadratic_test_latency_seconds_sum 1.5
+quadratic_test_latency_seconds_count 3
+";
+        assert_eq!(metrics.to_string(), expected);
+    }
+}
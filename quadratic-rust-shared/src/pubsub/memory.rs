@@ -0,0 +1,758 @@
+//! In-memory PubSub
+//!
+//! An in-process implementation of the Redis Streams semantics: streams with
+//! ordered ids, consumer groups with pending messages, sorted sets for active
+//! channels and sequence counters.  Connections with the same config name
+//! share the same store, so services' tests can talk to each other without
+//! Redis.
+//!
+//! Messages are not persisted and can't be shared between processes, so this
+//! is only compiled with the `memory-pubsub` feature, for tests and for a
+//! single instance that runs without Redis.
+
+use chrono::Utc;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use tokio::sync::broadcast;
+
+use crate::pubsub::Config;
+use crate::{error::Result, SharedError};
+
+/// Number of broadcast messages a slow listener can fall behind by before
+/// messages are dropped.
+const BROADCAST_CAPACITY: usize = 1024;
+
+/// Streams keep at most this many entries, dropping the oldest first, as
+/// nothing else trims them.
+const MAX_STREAM_LENGTH: usize = 10_000;
+
+/// Name of the store shared by services running in the same process.
+pub const DEFAULT_NAME: &str = "quadratic";
+
+#[derive(Debug, Clone)]
+pub struct MemoryConfig {
+    /// Connections with the same name share the same messages.
+    pub name: String,
+    pub active_channels: String,
+}
+
+// A message consists of a key (String) and a value (Bytes).
+type Message = (String, Vec<u8>);
+
+// Stream ids are a (milliseconds, sequence) pair, as in Redis.
+type StreamId = (u64, u64);
+
+#[derive(Debug, Default)]
+struct Group {
+    last_delivered: StreamId,
+    pending: HashMap<String, BTreeSet<StreamId>>,
+}
+
+#[derive(Debug, Default)]
+struct Stream {
+    entries: BTreeMap<StreamId, Vec<u8>>,
+    last_id: StreamId,
+    groups: HashMap<String, Group>,
+}
+
+#[derive(Debug, Default)]
+struct Store {
+    streams: HashMap<String, Stream>,
+    sorted_sets: HashMap<String, HashMap<String, i64>>,
+    values: HashMap<String, u64>,
+    broadcasts: HashMap<String, broadcast::Sender<Vec<u8>>>,
+}
+
+type SharedStore = Arc<Mutex<Store>>;
+
+/// All stores in the process, by config name.
+fn stores() -> &'static Mutex<HashMap<String, SharedStore>> {
+    static STORES: OnceLock<Mutex<HashMap<String, SharedStore>>> = OnceLock::new();
+    STORES.get_or_init(Default::default)
+}
+
+fn lock_error(error: impl ToString) -> SharedError {
+    SharedError::PubSub(format!("Error locking memory store: {}", error.to_string()))
+}
+
+fn format_id((millis, sequence): StreamId) -> String {
+    format!("{millis}-{sequence}")
+}
+
+/// Parse a stream id.  Ids without a sequence (eg, "5") use
+/// `default_sequence`, so they can be used as inclusive range bounds.
+fn parse_id(id: &str, default_sequence: u64) -> Result<StreamId> {
+    let invalid = || SharedError::PubSub(format!("Invalid stream id {id}"));
+
+    match id {
+        "-" => Ok((0, 0)),
+        "+" => Ok((u64::MAX, u64::MAX)),
+        _ => match id.split_once('-') {
+            Some((millis, sequence)) => Ok((
+                millis.parse().map_err(|_| invalid())?,
+                sequence.parse().map_err(|_| invalid())?,
+            )),
+            None => Ok((id.parse().map_err(|_| invalid())?, default_sequence)),
+        },
+    }
+}
+
+fn to_message((id, value): (&StreamId, &Vec<u8>), preserve_sequence: bool) -> Message {
+    let id = match preserve_sequence {
+        true => format_id(*id),
+        false => id.0.to_string(),
+    };
+
+    (id, value.to_owned())
+}
+
+impl Stream {
+    /// Add an entry.  Like XADD, "*" generates an id and explicit ids must be
+    /// greater than the last id.  Like XADD with MAXLEN, the oldest entries
+    /// are trimmed to keep the stream under `MAX_STREAM_LENGTH`.
+    fn add(&mut self, key: &str, value: &[u8]) -> Result<StreamId> {
+        let id = match key {
+            "*" => {
+                let millis = Utc::now().timestamp_millis() as u64;
+                match millis > self.last_id.0 {
+                    true => (millis, 0),
+                    false => (self.last_id.0, self.last_id.1 + 1),
+                }
+            }
+            _ => parse_id(key, 0)?,
+        };
+
+        if id <= self.last_id {
+            return Err(SharedError::PubSub(format!(
+                "The id {} is equal or smaller than the last id {}",
+                format_id(id),
+                format_id(self.last_id)
+            )));
+        }
+
+        self.entries.insert(id, value.to_vec());
+        self.last_id = id;
+
+        while self.entries.len() > MAX_STREAM_LENGTH {
+            self.entries.pop_first();
+        }
+
+        Ok(id)
+    }
+
+    fn range(&self, start: StreamId, end: StreamId, preserve_sequence: bool) -> Vec<Message> {
+        if start > end {
+            return vec![];
+        }
+
+        self.entries
+            .range(start..=end)
+            .map(|entry| to_message(entry, preserve_sequence))
+            .collect()
+    }
+}
+
+impl Store {
+    fn upsert_active_channel(&mut self, set_key: &str, channel: &str) {
+        self.sorted_sets
+            .entry(set_key.to_owned())
+            .or_default()
+            .insert(channel.to_owned(), Utc::now().timestamp_millis());
+    }
+
+    fn remove_active_channel(&mut self, set_key: &str, channel: &str) {
+        if let Some(set) = self.sorted_sets.get_mut(set_key) {
+            set.remove(channel);
+        }
+    }
+
+    fn broadcast_sender(&mut self, channel: &str) -> &broadcast::Sender<Vec<u8>> {
+        self.broadcasts
+            .entry(channel.to_owned())
+            .or_insert_with(|| broadcast::channel(BROADCAST_CAPACITY).0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MemoryConnection {
+    store: SharedStore,
+}
+
+impl MemoryConnection {
+    fn store(&self) -> Result<MutexGuard<'_, Store>> {
+        self.store.lock().map_err(lock_error)
+    }
+
+    /// Send a message to everyone listening to the channel.  Unlike stream
+    /// messages, broadcasts are not stored.
+    pub fn broadcast(&self, channel: &str, value: &[u8]) -> Result<()> {
+        // sending only fails if there are no listeners
+        let _ = self.store()?.broadcast_sender(channel).send(value.to_vec());
+
+        Ok(())
+    }
+
+    /// Listen for broadcasts to the channel.
+    pub fn listen(&self, channel: &str) -> Result<broadcast::Receiver<Vec<u8>>> {
+        Ok(self.store()?.broadcast_sender(channel).subscribe())
+    }
+}
+
+impl super::PubSub for MemoryConnection {
+    type Connection = MemoryConnection;
+
+    /// Create a new connection to the named in-memory store.
+    async fn new(config: Config) -> Result<MemoryConnection> {
+        Self::connect(config).await
+    }
+
+    /// Connect to the named in-memory store, creating it if needed.
+    async fn connect(config: Config) -> Result<MemoryConnection> {
+        let Config::Memory(MemoryConfig { name, .. }) = config else {
+            return Err(SharedError::PubSub(
+                "Config type must be MemoryConfig".into(),
+            ));
+        };
+
+        let store = stores()
+            .lock()
+            .map_err(lock_error)?
+            .entry(name)
+            .or_default()
+            .to_owned();
+
+        Ok(MemoryConnection { store })
+    }
+
+    /// The store is always available
+    async fn is_healthy(&mut self) -> bool {
+        true
+    }
+
+    /// Get a list of channels
+    async fn channels(&mut self) -> Result<Vec<String>> {
+        let store = self.store()?;
+        let channels = store
+            .streams
+            .keys()
+            .chain(store.sorted_sets.keys())
+            .chain(store.values.keys())
+            .cloned()
+            .collect();
+
+        Ok(channels)
+    }
+
+    /// Get a list of active channels, ordered by when they were last active
+    async fn active_channels(&mut self, set_key: &str) -> Result<Vec<String>> {
+        let store = self.store()?;
+        let mut channels = store
+            .sorted_sets
+            .get(set_key)
+            .map(|set| set.iter().collect::<Vec<_>>())
+            .unwrap_or_default();
+        channels.sort_by(|(a, a_score), (b, b_score)| a_score.cmp(b_score).then(a.cmp(b)));
+
+        Ok(channels
+            .into_iter()
+            .map(|(channel, _)| channel.to_owned())
+            .collect())
+    }
+
+    /// Insert or update a key within an active channel
+    async fn upsert_active_channel(&mut self, set_key: &str, channel: &str) -> Result<()> {
+        self.store()?.upsert_active_channel(set_key, channel);
+
+        Ok(())
+    }
+
+    /// Remove an a key within an active channel
+    async fn remove_active_channel(&mut self, set_key: &str, channel: &str) -> Result<()> {
+        self.store()?.remove_active_channel(set_key, channel);
+
+        Ok(())
+    }
+
+    /// Create a group and a key (if it doesn't already exist).  The group
+    /// only receives messages published after it was created.
+    async fn subscribe(&mut self, channel: &str, group: &str) -> Result<()> {
+        let mut store = self.store()?;
+        let stream = store.streams.entry(channel.to_owned()).or_default();
+        let last_id = stream.last_id;
+
+        stream
+            .groups
+            .entry(group.to_owned())
+            .or_insert_with(|| Group {
+                last_delivered: last_id,
+                ..Default::default()
+            });
+
+        Ok(())
+    }
+
+    /// Publish a message to a channel.
+    async fn publish(
+        &mut self,
+        channel: &str,
+        key: &str,
+        value: &[u8],
+        active_channel: Option<&str>,
+    ) -> Result<()> {
+        let mut store = self.store()?;
+
+        // add the message to the stream
+        store
+            .streams
+            .entry(channel.to_owned())
+            .or_default()
+            .add(key, value)?;
+
+        // add the channel to the active channels set
+        if let Some(active_channel) = active_channel {
+            store.upsert_active_channel(active_channel, channel);
+        }
+
+        Ok(())
+    }
+
+    /// Acknowledge that a message was processed
+    async fn ack(
+        &mut self,
+        channel: &str,
+        group: &str,
+        keys: Vec<&str>,
+        active_channel: Option<&str>,
+        _preserve_sequence: bool,
+    ) -> Result<()> {
+        if keys.is_empty() {
+            return Err(SharedError::PubSub(format!(
+                "Error acking messages for channel {channel}: no keys provided"
+            )));
+        }
+
+        let ids = keys
+            .iter()
+            .map(|key| parse_id(key, 0))
+            .collect::<Result<Vec<_>>>()?;
+        let mut store = self.store()?;
+
+        if let Some(group) = store
+            .streams
+            .get_mut(channel)
+            .and_then(|stream| stream.groups.get_mut(group))
+        {
+            for pending in group.pending.values_mut() {
+                ids.iter().for_each(|id| {
+                    pending.remove(id);
+                });
+            }
+        }
+
+        // remove the channel from the active channels set
+        if let Some(active_channel) = active_channel {
+            store.remove_active_channel(active_channel, channel);
+        }
+
+        Ok(())
+    }
+
+    /// Get the current value of a sequence counter
+    async fn sequence_num(&mut self, key: &str) -> Result<Option<u64>> {
+        Ok(self.store()?.values.get(key).copied())
+    }
+
+    /// Increment a sequence counter and publish a message keyed by the new
+    /// sequence number.  The store is locked throughout, so there are no
+    /// races between publishers.
+    async fn publish_sequenced<F>(
+        &mut self,
+        channel: &str,
+        sequence_key: &str,
+        min_sequence_num: u64,
+        value: F,
+        active_channel: Option<&str>,
+    ) -> Result<u64>
+    where
+        F: Fn(u64) -> Result<Vec<u8>> + Send,
+    {
+        let mut store = self.store()?;
+        let current = store.values.get(sequence_key).copied();
+        let stream = store.streams.entry(channel.to_owned()).or_default();
+        let last = stream.entries.keys().next_back().map(|(millis, _)| *millis);
+        let sequence_num = current
+            .unwrap_or_default()
+            .max(last.unwrap_or_default())
+            .max(min_sequence_num)
+            + 1;
+
+        stream.add(&sequence_num.to_string(), &value(sequence_num)?)?;
+        store.values.insert(sequence_key.to_owned(), sequence_num);
+
+        // add the channel to the active channels set
+        if let Some(active_channel) = active_channel {
+            store.upsert_active_channel(active_channel, channel);
+        }
+
+        Ok(sequence_num)
+    }
+
+    /// Trim messages before the key from a channel
+    async fn trim(&mut self, channel: &str, key: &str) -> Result<i64> {
+        let min_id = parse_id(key, 0)?;
+        let mut store = self.store()?;
+        let Some(stream) = store.streams.get_mut(channel) else {
+            return Ok(0);
+        };
+
+        let kept = stream.entries.split_off(&min_id);
+        let trimmed = stream.entries.len();
+        stream.entries = kept;
+
+        Ok(trimmed as i64)
+    }
+
+    /// Get unread messages from a channel.  Specify an id to get the
+    /// consumer's pending messages after it, or None to get all new messages.
+    ///
+    /// After receiving new messages, they are pending until acknowledged with
+    /// `ack`.
+    async fn messages(
+        &mut self,
+        channel: &str,
+        group: &str,
+        consumer: &str,
+        maybe_id: Option<&str>,
+        max_messages: usize,
+        preserve_sequence: bool,
+    ) -> Result<Vec<Message>> {
+        let after = maybe_id.map(|id| parse_id(id, 0)).transpose()?;
+        let mut store = self.store()?;
+        let Some(stream) = store.streams.get_mut(channel) else {
+            return Err(SharedError::PubSub(format!(
+                "Error reading messages for channel {channel}: no such channel"
+            )));
+        };
+        let entries = &stream.entries;
+        let group = stream.groups.get_mut(group).ok_or_else(|| {
+            SharedError::PubSub(format!(
+                "Error reading messages for channel {channel}: no group {group}"
+            ))
+        })?;
+        let pending = group.pending.entry(consumer.to_owned()).or_default();
+
+        let messages = match after {
+            // the consumer's pending messages
+            Some(after) => pending
+                .iter()
+                .filter(|id| **id > after)
+                .take(max_messages)
+                .filter_map(|id| entries.get_key_value(id))
+                .map(|entry| to_message(entry, preserve_sequence))
+                .collect(),
+
+            // messages that haven't been delivered to the group
+            None => {
+                let ids = entries
+                    .range((
+                        std::ops::Bound::Excluded(group.last_delivered),
+                        std::ops::Bound::Unbounded,
+                    ))
+                    .take(max_messages)
+                    .map(|(id, _)| *id)
+                    .collect::<Vec<_>>();
+
+                if let Some(last) = ids.last() {
+                    group.last_delivered = *last;
+                }
+                pending.extend(ids.iter().copied());
+
+                ids.iter()
+                    .filter_map(|id| entries.get_key_value(id))
+                    .map(|entry| to_message(entry, preserve_sequence))
+                    .collect()
+            }
+        };
+
+        Ok(messages)
+    }
+
+    /// Get messages from the beginning of a channel ending at a specific id
+    async fn get_messages_before(
+        &mut self,
+        channel: &str,
+        id: &str,
+        preserve_sequence: bool,
+    ) -> Result<Vec<Message>> {
+        let end = parse_id(id, u64::MAX)?;
+        let store = self.store()?;
+
+        Ok(store.streams.get(channel).map_or_else(Vec::new, |stream| {
+            stream.range((0, 0), end, preserve_sequence)
+        }))
+    }
+
+    /// Get messages from a channel starting from a specific id
+    async fn get_messages_from(
+        &mut self,
+        channel: &str,
+        id: &str,
+        preserve_sequence: bool,
+    ) -> Result<Vec<Message>> {
+        let start = parse_id(id, 0)?;
+        let store = self.store()?;
+
+        Ok(store.streams.get(channel).map_or_else(Vec::new, |stream| {
+            stream.range(start, (u64::MAX, u64::MAX), preserve_sequence)
+        }))
+    }
+
+    /// Get the last message in a channel
+    async fn last_message(&mut self, channel: &str, preserve_sequence: bool) -> Result<Message> {
+        let store = self.store()?;
+
+        store
+            .streams
+            .get(channel)
+            .and_then(|stream| stream.entries.last_key_value())
+            .map(|entry| to_message(entry, preserve_sequence))
+            .ok_or_else(|| {
+                SharedError::PubSub("Error getting last message: no messages found".into())
+            })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::pubsub::PubSub;
+
+    fn setup() -> (Config, String) {
+        let channel = Uuid::new_v4().to_string();
+        let config = Config::Memory(MemoryConfig {
+            name: Uuid::new_v4().to_string(),
+            active_channels: Uuid::new_v4().to_string(),
+        });
+
+        (config, channel)
+    }
+
+    async fn publish_messages(connection: &mut MemoryConnection, channel: &str) {
+        for (key, value) in ["test 1", "test 2"].iter().enumerate() {
+            connection
+                .publish(channel, &(key + 1).to_string(), value.as_bytes(), None)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn memory_subscribe_publish_get_message() {
+        let (config, channel) = setup();
+        let group = "group 1";
+        let consumer = "consumer 1";
+
+        let mut connection = MemoryConnection::new(config).await.unwrap();
+        connection.subscribe(&channel, group).await.unwrap();
+        publish_messages(&mut connection, &channel).await;
+
+        // get all new messages
+        let results = connection
+            .messages(&channel, group, consumer, None, 10, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            results,
+            vec![
+                ("1".to_string(), b"test 1".to_vec()),
+                ("2".to_string(), b"test 2".to_vec())
+            ]
+        );
+
+        // messages are only delivered to the group once
+        let results = connection
+            .messages(&channel, group, consumer, None, 10, false)
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+
+        // delivered messages are pending until acknowledged
+        let results = connection
+            .messages(&channel, group, consumer, Some("0"), 10, false)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+
+        connection
+            .ack(&channel, group, vec!["1", "2"], None, false)
+            .await
+            .unwrap();
+
+        let results = connection
+            .messages(&channel, group, consumer, Some("0"), 10, false)
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn memory_groups_start_at_the_end_of_the_channel() {
+        let (config, channel) = setup();
+        let mut connection = MemoryConnection::new(config).await.unwrap();
+        connection.subscribe(&channel, "group 1").await.unwrap();
+        publish_messages(&mut connection, &channel).await;
+
+        connection.subscribe(&channel, "group 2").await.unwrap();
+        let results = connection
+            .messages(&channel, "group 2", "consumer", None, 10, false)
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+
+        assert!(connection
+            .messages(&channel, "missing", "consumer", None, 10, false)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn memory_ranges_trim_and_last_message() {
+        let (config, channel) = setup();
+        let mut connection = MemoryConnection::new(config).await.unwrap();
+        publish_messages(&mut connection, &channel).await;
+
+        // ids must increase
+        assert!(connection
+            .publish(&channel, "2", b"test 3", None)
+            .await
+            .is_err());
+
+        let from = connection
+            .get_messages_from(&channel, "2", false)
+            .await
+            .unwrap();
+        assert_eq!(from, vec![("2".to_string(), b"test 2".to_vec())]);
+
+        let before = connection
+            .get_messages_before(&channel, "1", true)
+            .await
+            .unwrap();
+        assert_eq!(before, vec![("1-0".to_string(), b"test 1".to_vec())]);
+
+        assert_eq!(
+            connection.last_message(&channel, false).await.unwrap(),
+            ("2".to_string(), b"test 2".to_vec())
+        );
+
+        // trimming removes messages before the id
+        assert_eq!(connection.trim(&channel, "2").await.unwrap(), 1);
+        let all = connection
+            .get_messages_from(&channel, "-", false)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn memory_streams_are_capped() {
+        let (config, channel) = setup();
+        let mut connection = MemoryConnection::new(config).await.unwrap();
+
+        for key in 1..=MAX_STREAM_LENGTH + 2 {
+            connection
+                .publish(&channel, &key.to_string(), b"test", None)
+                .await
+                .unwrap();
+        }
+
+        let all = connection
+            .get_messages_from(&channel, "-", false)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), MAX_STREAM_LENGTH);
+        assert_eq!(all[0].0, "3");
+    }
+
+    #[tokio::test]
+    async fn memory_publish_sequenced() {
+        let (config, channel) = setup();
+        let sequence_key = format!("{channel}:sequence_num");
+
+        // connections with the same name share messages
+        let mut connection_1 = MemoryConnection::new(config.clone()).await.unwrap();
+        let mut connection_2 = MemoryConnection::new(config).await.unwrap();
+
+        assert_eq!(
+            connection_1.sequence_num(&sequence_key).await.unwrap(),
+            None
+        );
+
+        let sequence_num = connection_1
+            .publish_sequenced(&channel, &sequence_key, 5, |n| Ok(vec![n as u8]), None)
+            .await
+            .unwrap();
+        assert_eq!(sequence_num, 6);
+
+        let sequence_num = connection_2
+            .publish_sequenced(&channel, &sequence_key, 0, |n| Ok(vec![n as u8]), None)
+            .await
+            .unwrap();
+        assert_eq!(sequence_num, 7);
+
+        assert_eq!(
+            connection_2.sequence_num(&sequence_key).await.unwrap(),
+            Some(7)
+        );
+
+        let results = connection_1
+            .get_messages_from(&channel, "0", false)
+            .await
+            .unwrap();
+        assert_eq!(
+            results,
+            vec![("6".to_string(), vec![6]), ("7".to_string(), vec![7])]
+        );
+    }
+
+    #[tokio::test]
+    async fn memory_active_channels() {
+        let (config, _) = setup();
+        let channels = [Uuid::new_v4().to_string(), Uuid::new_v4().to_string()];
+        let active_channels = Uuid::new_v4().to_string();
+        let mut connection = MemoryConnection::new(config).await.unwrap();
+
+        for channel in channels.iter() {
+            connection
+                .publish(channel, "*", b"test", Some(&active_channels))
+                .await
+                .unwrap();
+        }
+
+        let mut results = connection.active_channels(&active_channels).await.unwrap();
+        results.sort();
+        let mut expected = channels.to_vec();
+        expected.sort();
+        assert_eq!(results, expected);
+
+        connection
+            .remove_active_channel(&active_channels, &channels[0])
+            .await
+            .unwrap();
+        let results = connection.active_channels(&active_channels).await.unwrap();
+        assert_eq!(results, vec![channels[1].clone()]);
+
+        assert!(connection.channels().await.unwrap().contains(&channels[0]));
+    }
+
+    #[tokio::test]
+    async fn memory_broadcast() {
+        let (config, channel) = setup();
+        let connection = MemoryConnection::new(config).await.unwrap();
+        let mut receiver = connection.listen(&channel).unwrap();
+
+        connection.broadcast(&channel, b"test").unwrap();
+        assert_eq!(receiver.recv().await.unwrap(), b"test".to_vec());
+    }
+}
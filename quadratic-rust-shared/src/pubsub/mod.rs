@@ -1,16 +1,46 @@
+#[cfg(any(test, feature = "memory-pubsub"))]
+pub mod memory;
 pub mod redis;
 pub mod redis_streams;
 
 use futures_util::Future;
+use serde::Deserialize;
 
 use crate::error::Result;
+#[cfg(any(test, feature = "memory-pubsub"))]
+use crate::pubsub::memory::{MemoryConfig, MemoryConnection};
 use crate::pubsub::redis::RedisConfig;
-use crate::pubsub::redis_streams::RedisStreamsConfig;
+use crate::pubsub::redis_streams::{RedisConnection, RedisStreamsConfig};
+use crate::SharedError;
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PubSubType {
+    #[default]
+    RedisStreams,
+    /// Only available with the `memory-pubsub` feature, for tests and a
+    /// single instance without Redis
+    Memory,
+}
 
 #[derive(Debug, Clone)]
 pub enum Config {
     Redis(RedisConfig),
     RedisStreams(RedisStreamsConfig),
+    #[cfg(any(test, feature = "memory-pubsub"))]
+    Memory(MemoryConfig),
+}
+
+impl Config {
+    /// The key of the sorted set that tracks active channels
+    pub fn active_channels(&self) -> Option<&str> {
+        match self {
+            Config::RedisStreams(config) => Some(&config.active_channels),
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            Config::Memory(config) => Some(&config.active_channels),
+            Config::Redis(_) => None,
+        }
+    }
 }
 
 pub trait PubSub {
@@ -60,6 +90,19 @@ pub trait PubSub {
         preserve_sequence: bool,
     ) -> impl Future<Output = Result<()>> + Send;
 
+    fn sequence_num(&mut self, key: &str) -> impl Future<Output = Result<Option<u64>>> + Send;
+
+    fn publish_sequenced<F>(
+        &mut self,
+        channel: &str,
+        sequence_key: &str,
+        min_sequence_num: u64,
+        value: F,
+        active_channel: Option<&str>,
+    ) -> impl Future<Output = Result<u64>> + Send
+    where
+        F: Fn(u64) -> Result<Vec<u8>> + Send;
+
     fn trim(&mut self, channel: &str, key: &str) -> impl Future<Output = Result<i64>> + Send;
 
     fn messages(
@@ -92,3 +135,288 @@ pub trait PubSub {
         preserve_sequence: bool,
     ) -> impl Future<Output = Result<(String, Vec<u8>)>> + Send;
 }
+
+/// A stream connection chosen by config: Redis Streams, or in-memory for
+/// tests.
+#[derive(Debug)]
+pub enum StreamConnection {
+    RedisStreams(RedisConnection),
+    #[cfg(any(test, feature = "memory-pubsub"))]
+    Memory(MemoryConnection),
+}
+
+impl PubSub for StreamConnection {
+    type Connection = StreamConnection;
+
+    async fn new(config: Config) -> Result<StreamConnection> {
+        Self::connect(config).await
+    }
+
+    async fn connect(config: Config) -> Result<StreamConnection> {
+        match config {
+            Config::RedisStreams(_) => Ok(StreamConnection::RedisStreams(
+                RedisConnection::new(config).await?,
+            )),
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            Config::Memory(_) => Ok(StreamConnection::Memory(
+                MemoryConnection::new(config).await?,
+            )),
+            Config::Redis(_) => Err(SharedError::PubSub(
+                "Config type must be RedisStreamsConfig or MemoryConfig".into(),
+            )),
+        }
+    }
+
+    async fn is_healthy(&mut self) -> bool {
+        match self {
+            StreamConnection::RedisStreams(connection) => connection.is_healthy().await,
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => connection.is_healthy().await,
+        }
+    }
+
+    async fn channels(&mut self) -> Result<Vec<String>> {
+        match self {
+            StreamConnection::RedisStreams(connection) => connection.channels().await,
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => connection.channels().await,
+        }
+    }
+
+    async fn active_channels(&mut self, channel: &str) -> Result<Vec<String>> {
+        match self {
+            StreamConnection::RedisStreams(connection) => connection.active_channels(channel).await,
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => connection.active_channels(channel).await,
+        }
+    }
+
+    async fn upsert_active_channel(&mut self, set_key: &str, channel: &str) -> Result<()> {
+        match self {
+            StreamConnection::RedisStreams(connection) => {
+                connection.upsert_active_channel(set_key, channel).await
+            }
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => {
+                connection.upsert_active_channel(set_key, channel).await
+            }
+        }
+    }
+
+    async fn remove_active_channel(&mut self, set_key: &str, channel: &str) -> Result<()> {
+        match self {
+            StreamConnection::RedisStreams(connection) => {
+                connection.remove_active_channel(set_key, channel).await
+            }
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => {
+                connection.remove_active_channel(set_key, channel).await
+            }
+        }
+    }
+
+    async fn subscribe(&mut self, channel: &str, group: &str) -> Result<()> {
+        match self {
+            StreamConnection::RedisStreams(connection) => {
+                connection.subscribe(channel, group).await
+            }
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => connection.subscribe(channel, group).await,
+        }
+    }
+
+    async fn publish(
+        &mut self,
+        channel: &str,
+        key: &str,
+        value: &[u8],
+        active_channel: Option<&str>,
+    ) -> Result<()> {
+        match self {
+            StreamConnection::RedisStreams(connection) => {
+                connection
+                    .publish(channel, key, value, active_channel)
+                    .await
+            }
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => {
+                connection
+                    .publish(channel, key, value, active_channel)
+                    .await
+            }
+        }
+    }
+
+    async fn ack(
+        &mut self,
+        channel: &str,
+        group: &str,
+        keys: Vec<&str>,
+        active_channel: Option<&str>,
+        preserve_sequence: bool,
+    ) -> Result<()> {
+        match self {
+            StreamConnection::RedisStreams(connection) => {
+                connection
+                    .ack(channel, group, keys, active_channel, preserve_sequence)
+                    .await
+            }
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => {
+                connection
+                    .ack(channel, group, keys, active_channel, preserve_sequence)
+                    .await
+            }
+        }
+    }
+
+    async fn sequence_num(&mut self, key: &str) -> Result<Option<u64>> {
+        match self {
+            StreamConnection::RedisStreams(connection) => connection.sequence_num(key).await,
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => connection.sequence_num(key).await,
+        }
+    }
+
+    async fn publish_sequenced<F>(
+        &mut self,
+        channel: &str,
+        sequence_key: &str,
+        min_sequence_num: u64,
+        value: F,
+        active_channel: Option<&str>,
+    ) -> Result<u64>
+    where
+        F: Fn(u64) -> Result<Vec<u8>> + Send,
+    {
+        match self {
+            StreamConnection::RedisStreams(connection) => {
+                connection
+                    .publish_sequenced(
+                        channel,
+                        sequence_key,
+                        min_sequence_num,
+                        value,
+                        active_channel,
+                    )
+                    .await
+            }
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => {
+                connection
+                    .publish_sequenced(
+                        channel,
+                        sequence_key,
+                        min_sequence_num,
+                        value,
+                        active_channel,
+                    )
+                    .await
+            }
+        }
+    }
+
+    async fn trim(&mut self, channel: &str, key: &str) -> Result<i64> {
+        match self {
+            StreamConnection::RedisStreams(connection) => connection.trim(channel, key).await,
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => connection.trim(channel, key).await,
+        }
+    }
+
+    async fn messages(
+        &mut self,
+        channel: &str,
+        group: &str,
+        consumer: &str,
+        keys: Option<&str>,
+        max_messages: usize,
+        preserve_sequence: bool,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        match self {
+            StreamConnection::RedisStreams(connection) => {
+                connection
+                    .messages(
+                        channel,
+                        group,
+                        consumer,
+                        keys,
+                        max_messages,
+                        preserve_sequence,
+                    )
+                    .await
+            }
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => {
+                connection
+                    .messages(
+                        channel,
+                        group,
+                        consumer,
+                        keys,
+                        max_messages,
+                        preserve_sequence,
+                    )
+                    .await
+            }
+        }
+    }
+
+    async fn get_messages_before(
+        &mut self,
+        channel: &str,
+        id: &str,
+        preserve_sequence: bool,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        match self {
+            StreamConnection::RedisStreams(connection) => {
+                connection
+                    .get_messages_before(channel, id, preserve_sequence)
+                    .await
+            }
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => {
+                connection
+                    .get_messages_before(channel, id, preserve_sequence)
+                    .await
+            }
+        }
+    }
+
+    async fn get_messages_from(
+        &mut self,
+        channel: &str,
+        id: &str,
+        preserve_sequence: bool,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        match self {
+            StreamConnection::RedisStreams(connection) => {
+                connection
+                    .get_messages_from(channel, id, preserve_sequence)
+                    .await
+            }
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => {
+                connection
+                    .get_messages_from(channel, id, preserve_sequence)
+                    .await
+            }
+        }
+    }
+
+    async fn last_message(
+        &mut self,
+        channel: &str,
+        preserve_sequence: bool,
+    ) -> Result<(String, Vec<u8>)> {
+        match self {
+            StreamConnection::RedisStreams(connection) => {
+                connection.last_message(channel, preserve_sequence).await
+            }
+            #[cfg(any(test, feature = "memory-pubsub"))]
+            StreamConnection::Memory(connection) => {
+                connection.last_message(channel, preserve_sequence).await
+            }
+        }
+    }
+}
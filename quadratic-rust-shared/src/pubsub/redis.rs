@@ -1,7 +1,8 @@
 use redis::{
     aio::{AsyncStream, MultiplexedConnection, PubSub},
-    cmd, AsyncCommands, Client,
+    cmd, AsyncCommands, Client, Script,
 };
+use std::fmt::{self, Debug};
 use std::pin::Pin;
 
 use crate::pubsub::Config;
@@ -22,6 +23,21 @@ pub struct RedisConnection {
     multiplex: MultiplexedConnection,
 }
 
+impl Debug for RedisConnection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.multiplex)
+    }
+}
+
+/// Sets the KEYS[1] counter to the next sequence number after it and ARGV[1],
+/// and returns it
+const NEXT_SEQUENCE_NUM: &str = r"
+local current = tonumber(redis.call('GET', KEYS[1]) or '0')
+local next = math.max(current, tonumber(ARGV[1])) + 1
+redis.call('SET', KEYS[1], next)
+return next
+";
+
 fn client(config: Config) -> Result<Client> {
     if let Config::Redis(RedisConfig {
         host,
@@ -113,6 +129,39 @@ impl super::PubSub for RedisConnection {
         unimplemented!()
     }
 
+    /// Get the current value of a sequence counter
+    async fn sequence_num(&mut self, key: &str) -> Result<Option<u64>> {
+        Ok(self.multiplex.get(key).await?)
+    }
+
+    /// Atomically increment a sequence counter and publish a message with
+    /// the new sequence number.  Channels don't store messages, so the
+    /// sequence number is only greater than `min_sequence_num` and the
+    /// counter.
+    async fn publish_sequenced<F>(
+        &mut self,
+        channel: &str,
+        sequence_key: &str,
+        min_sequence_num: u64,
+        value: F,
+        _active_channel: Option<&str>,
+    ) -> Result<u64>
+    where
+        F: Fn(u64) -> Result<Vec<u8>> + Send,
+    {
+        let sequence_num: u64 = Script::new(NEXT_SEQUENCE_NUM)
+            .key(sequence_key)
+            .arg(min_sequence_num)
+            .invoke_async(&mut self.multiplex)
+            .await?;
+
+        self.multiplex
+            .publish(channel, value(sequence_num)?)
+            .await?;
+
+        Ok(sequence_num)
+    }
+
     async fn trim(&mut self, _channel: &str, _key: &str) -> Result<i64> {
         unimplemented!()
     }
@@ -221,4 +270,29 @@ pub mod tests {
 
         assert_eq!(received, messages);
     }
+
+    #[tokio::test]
+    async fn publish_sequenced() {
+        let (config, channel) = setup();
+        let sequence_key = format!("{channel}:sequence_num");
+        let mut connection = RedisConnection::new(config).await.unwrap();
+
+        assert_eq!(connection.sequence_num(&sequence_key).await.unwrap(), None);
+
+        let sequence_num = connection
+            .publish_sequenced(&channel, &sequence_key, 5, |n| Ok(vec![n as u8]), None)
+            .await
+            .unwrap();
+        assert_eq!(sequence_num, 6);
+
+        let sequence_num = connection
+            .publish_sequenced(&channel, &sequence_key, 0, |n| Ok(vec![n as u8]), None)
+            .await
+            .unwrap();
+        assert_eq!(sequence_num, 7);
+        assert_eq!(
+            connection.sequence_num(&sequence_key).await.unwrap(),
+            Some(7)
+        );
+    }
 }
@@ -4,7 +4,7 @@ use redis::{
     aio::{AsyncStream, Monitor, MultiplexedConnection, PubSub},
     cmd,
     streams::{StreamId, StreamKey, StreamRangeReply, StreamReadOptions, StreamReadReply},
-    AsyncCommands, Client, Value,
+    AsyncCommands, Client, Script, Value,
 };
 use std::pin::Pin;
 use std::{
@@ -36,6 +36,26 @@ impl Debug for RedisConnection {
     }
 }
 
+/// Publishes ARGV[3] to the KEYS[2] stream as sequence number ARGV[2], and
+/// stores it in the KEYS[1] counter, if it's the next sequence number after
+/// the counter, the last message and ARGV[1].  Returns whether the message
+/// was published and the next sequence number.
+const PUBLISH_SEQUENCED: &str = r"
+local current = tonumber(redis.call('GET', KEYS[1]) or '0')
+local last = redis.call('XREVRANGE', KEYS[2], '+', '-', 'COUNT', 1)
+local last_num = 0
+if #last > 0 then
+    last_num = tonumber(string.match(last[1][1], '^%d+'))
+end
+local next = math.max(current, last_num, tonumber(ARGV[1])) + 1
+if next ~= tonumber(ARGV[2]) then
+    return {0, next}
+end
+redis.call('SET', KEYS[1], ARGV[2])
+redis.call('XADD', KEYS[2], ARGV[2], ARGV[2], ARGV[3])
+return {1, next}
+";
+
 // A message consists of a key (String) and a value (Bytes).
 type Message = (String, Vec<u8>);
 
@@ -234,6 +254,69 @@ impl super::PubSub for RedisConnection {
         Ok(())
     }
 
+    /// Get the current value of a sequence counter
+    async fn sequence_num(&mut self, key: &str) -> Result<Option<u64>> {
+        Ok(self.multiplex.get(key).await?)
+    }
+
+    /// Atomically increment a sequence counter and publish a message keyed by
+    /// the new sequence number.  The sequence number is always greater than
+    /// `min_sequence_num` and the last message in the channel.
+    ///
+    /// `value` creates the message for a sequence number.  If another
+    /// publisher wins the race for the sequence number, `value` is called
+    /// again with the next one.
+    async fn publish_sequenced<F>(
+        &mut self,
+        channel: &str,
+        sequence_key: &str,
+        min_sequence_num: u64,
+        value: F,
+        active_channel: Option<&str>,
+    ) -> Result<u64>
+    where
+        F: Fn(u64) -> Result<Vec<u8>> + Send,
+    {
+        let script = Script::new(PUBLISH_SEQUENCED);
+        let current: Option<u64> = self.multiplex.get(sequence_key).await?;
+        let last: StreamRangeReply = self.multiplex.xrevrange_count(channel, "+", "-", 1).await?;
+        let last = last
+            .ids
+            .first()
+            .and_then(|id| from_key(&id.id).parse::<u64>().ok());
+        let mut sequence_num = current
+            .unwrap_or_default()
+            .max(last.unwrap_or_default())
+            .max(min_sequence_num)
+            + 1;
+
+        loop {
+            let message = value(sequence_num)?;
+
+            // the script only publishes if `sequence_num` is still the next
+            // one, otherwise it returns the next one to try again with
+            let (published, next): (bool, u64) = script
+                .key(sequence_key)
+                .key(channel)
+                .arg(min_sequence_num)
+                .arg(sequence_num)
+                .arg(message)
+                .invoke_async(&mut self.multiplex)
+                .await?;
+
+            if published {
+                // add the channel to the active channels set
+                if let Some(active_channel) = active_channel {
+                    self.upsert_active_channel(active_channel, channel).await?
+                }
+
+                return Ok(sequence_num);
+            }
+
+            sequence_num = next;
+        }
+    }
+
     /// Trim messages from a channel
     async fn trim(&mut self, channel: &str, key: &str) -> Result<i64> {
         let xtrim = cmd("XTRIM").arg(channel).arg("MINID").arg(key).to_owned();
@@ -455,6 +538,57 @@ pub mod tests {
         assert_eq!(results, ("2".into(), messages[1].into()));
     }
 
+    #[tokio::test]
+    async fn stream_publish_sequenced() {
+        let (config, channel) = setup();
+        let sequence_key = format!("{channel}:sequence_num");
+
+        let mut connection_1 = RedisConnection::new(config.clone()).await.unwrap();
+        let mut connection_2 = RedisConnection::new(config).await.unwrap();
+
+        assert_eq!(
+            connection_1.sequence_num(&sequence_key).await.unwrap(),
+            None
+        );
+
+        // the first sequence number starts after the minimum
+        let sequence_num = connection_1
+            .publish_sequenced(&channel, &sequence_key, 5, |n| Ok(vec![n as u8]), None)
+            .await
+            .unwrap();
+        assert_eq!(sequence_num, 6);
+
+        // a second publisher continues from the shared counter
+        let sequence_num = connection_2
+            .publish_sequenced(&channel, &sequence_key, 0, |n| Ok(vec![n as u8]), None)
+            .await
+            .unwrap();
+        assert_eq!(sequence_num, 7);
+
+        assert_eq!(
+            connection_2.sequence_num(&sequence_key).await.unwrap(),
+            Some(7)
+        );
+
+        let results = connection_1
+            .get_messages_from(&channel, "0", false)
+            .await
+            .unwrap();
+        assert_eq!(
+            results,
+            vec![("6".to_string(), vec![6]), ("7".to_string(), vec![7])]
+        );
+
+        // concurrent publishers get different sequence numbers
+        let (sequence_num_1, sequence_num_2) = tokio::join!(
+            connection_1.publish_sequenced(&channel, &sequence_key, 0, |n| Ok(vec![n as u8]), None),
+            connection_2.publish_sequenced(&channel, &sequence_key, 0, |n| Ok(vec![n as u8]), None)
+        );
+        let mut sequence_nums = vec![sequence_num_1.unwrap(), sequence_num_2.unwrap()];
+        sequence_nums.sort();
+        assert_eq!(sequence_nums, vec![8, 9]);
+    }
+
     #[tokio::test]
     async fn stream_get_all_channels() {
         let (config, channel) = setup();
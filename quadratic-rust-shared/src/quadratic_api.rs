@@ -4,6 +4,7 @@ use strum_macros::Display;
 use uuid::Uuid;
 
 use crate::error::{Result, SharedError};
+use crate::ssh::SshConfig;
 
 // This is only a partial mapping as permission is all that is needed from the
 // incoming json struct.
@@ -146,6 +147,15 @@ pub struct TypeDetails {
     pub username: Option<String>,
     pub password: Option<String>,
     pub database: String,
+    /// Connect through an SSH bastion
+    #[serde(default)]
+    pub ssh: Option<SshConfig>,
+    /// Only allow statements that read, see `sql::read_only`
+    #[serde(default)]
+    pub read_only: bool,
+    /// SQL Server: accept the server's certificate without validating it
+    #[serde(default)]
+    pub trust_cert: bool,
 }
 
 /// Retrieve user's connection from the quadratic API server.
@@ -0,0 +1,647 @@
+use std::{collections::BTreeMap, path::Path, str::FromStr, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use bytes::Bytes;
+use chrono::{DateTime, NaiveDateTime, NaiveTime};
+use duckdb::{
+    arrow::datatypes::DataType,
+    params_from_iter,
+    types::{TimeUnit, Value},
+    AccessMode, Config, Connection as DuckDbConn,
+};
+use serde::{Deserialize, Serialize};
+use sqlparser::dialect::{Dialect, DuckDbDialect};
+
+use crate::error::{Result, SharedError, Sql};
+use crate::sql::{
+    columns_to_parquet, row_bytes, row_estimate, schema_comment, ArrowType, Connection,
+    DatabaseSchema, ParquetBatchWriter, ParquetChunks, ParquetQuery, QueryHandle, QueryLimits,
+    SchemaColumn, SchemaTable, SqlParameter, TableKind,
+};
+
+/// A DuckDB database file.  The file must exist, it is never created.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DuckDbConnection {
+    pub database: String,
+    /// Open the database in DuckDB's read-only access mode
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+impl DuckDbConnection {
+    pub fn new(database: String, read_only: bool) -> DuckDbConnection {
+        DuckDbConnection {
+            database,
+            read_only,
+        }
+    }
+}
+
+/// DuckDB's rows borrow their statement, so values are copied out.
+#[derive(Debug)]
+pub struct DuckDbRow {
+    pub columns: Arc<Vec<DuckDbColumn>>,
+    pub values: Vec<Value>,
+}
+
+#[derive(Debug)]
+pub struct DuckDbColumn {
+    pub name: String,
+    pub data_type: DataType,
+}
+
+#[async_trait]
+impl Connection for DuckDbConnection {
+    type Conn = DuckDbConn;
+    type Row = DuckDbRow;
+    type Column = DuckDbColumn;
+
+    async fn connect(&self) -> Result<Self::Conn> {
+        let connect_error =
+            |e: String| SharedError::Sql(Sql::Connect(format!("{:?}: {e}", self.database)));
+
+        // opening a missing file would create it
+        if !Path::new(&self.database).is_file() {
+            return Err(connect_error("Database file not found".into()));
+        }
+
+        let access_mode = match self.read_only {
+            true => AccessMode::ReadOnly,
+            false => AccessMode::Automatic,
+        };
+
+        // queries can't read or write other files, eg, with `read_csv` or
+        // `ATTACH`, and can't turn that back on with `SET`
+        let config = Config::default()
+            .access_mode(access_mode)
+            .and_then(|config| config.enable_external_access(false))
+            .and_then(|config| config.with("lock_configuration", "true"))
+            .map_err(|e| connect_error(e.to_string()))?;
+
+        let database = self.database.to_owned();
+        let pool =
+            tokio::task::spawn_blocking(move || DuckDbConn::open_with_flags(database, config))
+                .await
+                .map_err(|e| connect_error(e.to_string()))?
+                .map_err(|e| connect_error(e.to_string()))?;
+
+        Ok(pool)
+    }
+
+    async fn query(
+        &self,
+        pool: Self::Conn,
+        sql: &str,
+        parameters: &[SqlParameter],
+        max_bytes: Option<u64>,
+    ) -> Result<(Vec<Self::Row>, bool)> {
+        let sql = sql.to_owned();
+        let parameters = parameters.iter().map(to_duckdb_value).collect::<Vec<_>>();
+
+        // DuckDB's API is blocking
+        tokio::task::spawn_blocking(move || {
+            let mut rows = vec![];
+            let mut bytes = 0;
+            let mut over_the_limit = false;
+
+            query_blocking(pool, &sql, parameters, |row| {
+                if let Some(max_bytes) = max_bytes {
+                    bytes += row_bytes(row.columns.iter(), |column, index| {
+                        DuckDbConnection::to_arrow(&row, column, index)
+                    });
+
+                    if bytes > max_bytes {
+                        over_the_limit = true;
+                        return Ok(false);
+                    }
+                }
+
+                rows.push(row);
+                Ok(true)
+            })?;
+
+            Ok((rows, over_the_limit))
+        })
+        .await
+        .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?
+    }
+
+    async fn query_parquet(
+        &self,
+        pool: Self::Conn,
+        sql: &str,
+        parameters: &[SqlParameter],
+        limits: QueryLimits,
+        chunks: ParquetChunks,
+    ) -> Result<ParquetQuery> {
+        let sql = sql.to_owned();
+        let parameters = parameters.iter().map(to_duckdb_value).collect::<Vec<_>>();
+
+        tokio::task::spawn_blocking(move || {
+            let mut writer = ParquetBatchWriter::new(limits, chunks);
+
+            query_blocking(pool, &sql, parameters, |row| {
+                writer.push(
+                    || row.columns.iter().map(|col| col.name.to_owned()).collect(),
+                    || {
+                        row.columns
+                            .iter()
+                            .enumerate()
+                            .map(|(index, col)| Self::to_arrow(&row, col, index))
+                            .collect()
+                    },
+                )
+            })?;
+
+            writer.finish()
+        })
+        .await
+        .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?
+    }
+
+    /// DuckDB doesn't have a statement timeout, so the connection is
+    /// interrupted once `timeout` elapses.  Interrupting a connection that
+    /// has closed does nothing.
+    async fn set_timeout(&self, pool: &mut Self::Conn, timeout: Duration) -> Result<()> {
+        let interrupt = pool.interrupt_handle();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            interrupt.interrupt();
+        });
+
+        Ok(())
+    }
+
+    async fn query_handle(&self, pool: &mut Self::Conn) -> Result<QueryHandle> {
+        let interrupt = pool.interrupt_handle();
+
+        Ok(QueryHandle::Interrupt(Arc::new(move || {
+            interrupt.interrupt()
+        })))
+    }
+
+    fn dialect(&self) -> Box<dyn Dialect> {
+        Box::new(DuckDbDialect {})
+    }
+
+    /// A database that's already open can't be made read-only, so read-only
+    /// connections are opened in DuckDB's read-only access mode by `connect`
+    async fn set_read_only(&self, _pool: &mut Self::Conn) -> Result<()> {
+        match self.read_only {
+            true => Ok(()),
+            false => Err(SharedError::Sql(Sql::Query(
+                "The database wasn't opened read-only".into(),
+            ))),
+        }
+    }
+
+    /// DuckDB's catalog doesn't name the columns a foreign key references,
+    /// so foreign keys aren't included
+    async fn schema(&self, pool: Self::Conn) -> Result<DatabaseSchema> {
+        let sql = "
+            select c.table_catalog, c.table_schema, c.table_name, c.column_name, c.data_type, c.is_nullable,
+                t.table_type, pk.column_name is not null as is_primary_key, dc.comment as column_comment,
+                coalesce(dt.comment, dv.comment) as table_comment, dt.estimated_size as row_estimate
+            from information_schema.columns as c
+            inner join information_schema.tables as t
+                on t.table_schema = c.table_schema and t.table_name = c.table_name
+            left join duckdb_columns() as dc
+                on dc.schema_name = c.table_schema and dc.table_name = c.table_name and dc.column_name = c.column_name
+            left join duckdb_tables() as dt
+                on dt.schema_name = c.table_schema and dt.table_name = c.table_name
+            left join duckdb_views() as dv
+                on dv.schema_name = c.table_schema and dv.view_name = c.table_name
+            left join (
+                select schema_name, table_name, unnest(constraint_column_names) as column_name
+                from duckdb_constraints()
+                where constraint_type = 'PRIMARY KEY'
+            ) as pk
+                on pk.schema_name = c.table_schema and pk.table_name = c.table_name and pk.column_name = c.column_name
+            where c.table_schema not in ('information_schema', 'pg_catalog')
+            order by c.table_schema, c.table_name, c.ordinal_position";
+
+        let (rows, _) = self.query(pool, sql, &[], None).await?;
+
+        let mut schema = DatabaseSchema {
+            database: self.database.to_owned(),
+            tables: BTreeMap::new(),
+        };
+
+        for row in rows.into_iter() {
+            let optional_text = |index: usize| match &row.values[index] {
+                Value::Text(text) => Some(text.to_owned()),
+                _ => None,
+            };
+            let text = |index: usize| optional_text(index).unwrap_or_default();
+            let table_name = text(2);
+            let kind = match text(6).as_str() {
+                "VIEW" => TableKind::View,
+                _ => TableKind::Table,
+            };
+            let estimate = match row.values[10] {
+                Value::BigInt(estimate) => Some(estimate),
+                _ => None,
+            };
+
+            schema
+                .tables
+                // get or insert the table
+                .entry(table_name.to_owned())
+                .or_insert_with(|| SchemaTable {
+                    name: table_name,
+                    schema: text(1),
+                    kind,
+                    row_estimate: row_estimate(kind, estimate),
+                    comment: schema_comment(optional_text(9)),
+                    columns: vec![],
+                    foreign_keys: vec![],
+                })
+                .columns
+                // add the column to the table
+                .push(SchemaColumn {
+                    name: text(3),
+                    r#type: text(4),
+                    is_nullable: matches!(text(5).to_lowercase().as_str(), "yes"),
+                    is_primary_key: matches!(row.values[7], Value::Boolean(true)),
+                    comment: schema_comment(optional_text(8)),
+                });
+        }
+
+        Ok(schema)
+    }
+
+    fn to_arrow(row: &Self::Row, column: &Self::Column, index: usize) -> ArrowType {
+        match &row.values[index] {
+            Value::Null => null_to_arrow(&column.data_type),
+            Value::Boolean(value) => ArrowType::Boolean(*value),
+            Value::TinyInt(value) => ArrowType::Int8(*value),
+            Value::SmallInt(value) => ArrowType::Int16(*value),
+            Value::Int(value) => ArrowType::Int32(*value),
+            Value::BigInt(value) => ArrowType::Int64(*value),
+            Value::UTinyInt(value) => ArrowType::UInt8(*value),
+            Value::USmallInt(value) => ArrowType::UInt16(*value),
+            Value::UInt(value) => ArrowType::UInt32(*value),
+            Value::UBigInt(value) => ArrowType::UInt64(*value),
+            Value::Float(value) => ArrowType::Float32(*value),
+            Value::Double(value) => ArrowType::Float64(*value),
+            Value::HugeInt(value) => {
+                ArrowType::BigDecimal(BigDecimal::from_str(&value.to_string()).unwrap_or_default())
+            }
+            Value::Decimal(value) => {
+                ArrowType::BigDecimal(BigDecimal::from_str(&value.to_string()).unwrap_or_default())
+            }
+            Value::Text(value) | Value::Enum(value) => ArrowType::Utf8(value.to_owned()),
+            Value::Date32(value) => ArrowType::Date32(*value),
+            Value::Time64(unit, value) => {
+                let micros = unit.to_micros(*value);
+                let time = NaiveTime::from_num_seconds_from_midnight_opt(
+                    (micros / 1_000_000) as u32,
+                    (micros % 1_000_000) as u32 * 1_000,
+                );
+                ArrowType::Time32(time.unwrap_or_default())
+            }
+            Value::Timestamp(unit, value) => {
+                let timestamp = DateTime::from_timestamp_micros(unit.to_micros(*value));
+                ArrowType::Timestamp(timestamp.map(|t| t.naive_utc()).unwrap_or_default())
+            }
+            // TODO: support intervals, blobs and nested types
+            _ => ArrowType::Unsupported,
+        }
+    }
+
+    fn to_parquet(data: Vec<Self::Row>) -> Result<Bytes> {
+        if data.is_empty() {
+            return Ok(Bytes::new());
+        }
+
+        let columns = data[0].columns.to_owned();
+
+        // transpose columns to rows, converting to Arrow types
+        let mut transposed = vec![vec![]; columns.len()];
+
+        data.iter().for_each(|row| {
+            columns.iter().enumerate().for_each(|(col_index, col)| {
+                let value = Self::to_arrow(row, col, col_index);
+                transposed[col_index].push(value);
+            });
+        });
+
+        let names = columns.iter().map(|col| col.name.to_owned()).collect();
+
+        columns_to_parquet(names, transposed)
+    }
+}
+
+/// Run a query, passing each row to `on_row` until it returns false.
+fn query_blocking(
+    pool: DuckDbConn,
+    sql: &str,
+    parameters: Vec<Value>,
+    mut on_row: impl FnMut(DuckDbRow) -> Result<bool>,
+) -> Result<()> {
+    let query_error = |e: duckdb::Error| SharedError::Sql(Sql::Query(e.to_string()));
+    let mut statement = pool.prepare(sql).map_err(query_error)?;
+    let mut results = statement
+        .query(params_from_iter(parameters))
+        .map_err(query_error)?;
+
+    // column names and types are only known once the statement is executed
+    let columns = results.as_ref().map_or_else(Vec::new, |statement| {
+        statement
+            .column_names()
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| DuckDbColumn {
+                name,
+                data_type: statement.column_type(index),
+            })
+            .collect()
+    });
+    let columns = Arc::new(columns);
+
+    while let Some(row) = results.next().map_err(query_error)? {
+        let values = (0..columns.len())
+            .map(|index| row.get::<usize, Value>(index).unwrap_or(Value::Null))
+            .collect::<Vec<_>>();
+        let row = DuckDbRow {
+            columns: Arc::clone(&columns),
+            values,
+        };
+
+        if !on_row(row)? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn to_duckdb_value(parameter: &SqlParameter) -> Value {
+    match parameter {
+        SqlParameter::Null => Value::Null,
+        SqlParameter::Boolean(value) => Value::Boolean(*value),
+        SqlParameter::Integer(value) => Value::BigInt(*value),
+        SqlParameter::Number(value) => Value::Double(*value),
+        SqlParameter::Text(value) => Value::Text(value.to_owned()),
+        SqlParameter::Timestamp(value) => {
+            Value::Timestamp(TimeUnit::Microsecond, value.and_utc().timestamp_micros())
+        }
+    }
+}
+
+/// Nulls become the default value of the column's type, like the SQLx
+/// connections' `try_get(..).unwrap_or_default()`.
+fn null_to_arrow(data_type: &DataType) -> ArrowType {
+    match data_type {
+        DataType::Boolean => ArrowType::Boolean(false),
+        DataType::Int8 => ArrowType::Int8(0),
+        DataType::Int16 => ArrowType::Int16(0),
+        DataType::Int32 => ArrowType::Int32(0),
+        DataType::Int64 => ArrowType::Int64(0),
+        DataType::UInt8 => ArrowType::UInt8(0),
+        DataType::UInt16 => ArrowType::UInt16(0),
+        DataType::UInt32 => ArrowType::UInt32(0),
+        DataType::UInt64 => ArrowType::UInt64(0),
+        DataType::Float32 => ArrowType::Float32(0.0),
+        DataType::Float64 => ArrowType::Float64(0.0),
+        DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => {
+            ArrowType::BigDecimal(BigDecimal::default())
+        }
+        DataType::Utf8 | DataType::LargeUtf8 => ArrowType::Utf8(String::new()),
+        DataType::Date32 => ArrowType::Date32(0),
+        DataType::Time64(_) => ArrowType::Time32(NaiveTime::default()),
+        DataType::Timestamp(_, _) => ArrowType::Timestamp(NaiveDateTime::default()),
+        _ => ArrowType::Unsupported,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use arrow::datatypes::Date32Type;
+    use chrono::NaiveDate;
+    use tracing_test::traced_test;
+    use uuid::Uuid;
+
+    /// Create a database file with a table of DuckDB's common types.
+    fn new_duckdb_connection() -> DuckDbConnection {
+        let path = std::env::temp_dir().join(format!("quadratic-duckdb-{}.db", Uuid::new_v4()));
+        let pool = DuckDbConn::open(&path).unwrap();
+
+        pool.execute_batch(
+            "create table all_native_data_types (
+                id integer primary key,
+                bigint_col bigint,
+                double_col double,
+                decimal_col decimal(10, 2),
+                varchar_col varchar,
+                boolean_col boolean,
+                date_col date,
+                time_col time,
+                timestamp_col timestamp
+            );
+            insert into all_native_data_types values
+                (1, 2, 1.5, 12.34, 'Ada', true, '2024-01-02', '03:04:05', '2024-01-02 03:04:05'),
+                (2, null, null, null, null, null, null, null, null);",
+        )
+        .unwrap();
+
+        DuckDbConnection::new(path.to_string_lossy().to_string(), false)
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_duckdb_connection() {
+        let connection = new_duckdb_connection();
+
+        assert!(connection.connect().await.is_ok());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_duckdb_blocks_other_files() {
+        let connection = new_duckdb_connection();
+        let other = std::env::temp_dir().join(format!("quadratic-duckdb-{}.csv", Uuid::new_v4()));
+        std::fs::write(&other, "id\n1\n").unwrap();
+
+        for sql in [
+            format!("select * from read_csv('{}')", other.display()),
+            format!("attach '{}.db' as other", other.display()),
+            "set enable_external_access = true".to_string(),
+        ] {
+            let pool = connection.connect().await.unwrap();
+            let result = connection.query(pool, &sql, &[], None).await;
+            assert!(result.is_err(), "{sql} should fail");
+        }
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_duckdb_read_only() {
+        let DuckDbConnection { database, .. } = new_duckdb_connection();
+        let connection = DuckDbConnection::new(database, true);
+        let mut pool = connection.connect().await.unwrap();
+        connection.set_read_only(&mut pool).await.unwrap();
+
+        let sql = "delete from all_native_data_types";
+        assert!(connection.query(pool, sql, &[], None).await.is_err());
+
+        // connections that weren't opened read-only can't be made read-only
+        let connection = DuckDbConnection::new(connection.database, false);
+        let mut pool = connection.connect().await.unwrap();
+        assert!(connection.set_read_only(&mut pool).await.is_err());
+    }
+
+    /// Counts forever, until it's interrupted
+    const ENDLESS_QUERY: &str = "with recursive numbers(n) as (select 1 union all
+        select n + 1 from numbers) select count(*) from numbers";
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_duckdb_timeout() {
+        let connection = new_duckdb_connection();
+        let mut pool = connection.connect().await.unwrap();
+        connection
+            .set_timeout(&mut pool, Duration::from_millis(10))
+            .await
+            .unwrap();
+        let result = connection.query(pool, ENDLESS_QUERY, &[], None).await;
+
+        assert!(matches!(result, Err(error) if error.to_string().contains("INTERRUPT")));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_duckdb_cancel() {
+        let connection = Arc::new(new_duckdb_connection());
+        let mut pool = connection.connect().await.unwrap();
+        let handle = connection.query_handle(&mut pool).await.unwrap();
+
+        let running = tokio::spawn({
+            let connection = Arc::clone(&connection);
+            async move { connection.query(pool, ENDLESS_QUERY, &[], None).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        connection.cancel(handle).await.unwrap();
+
+        let result = running.await.unwrap();
+        assert!(matches!(result, Err(error) if error.to_string().contains("INTERRUPT")));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_duckdb_connection_missing_file() {
+        let connection = DuckDbConnection::new("/does/not/exist.db".into(), false);
+
+        assert!(connection.connect().await.is_err());
+        assert!(!Path::new("/does/not/exist.db").exists());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_duckdb_query_to_arrow() {
+        let connection = new_duckdb_connection();
+        let pool = connection.connect().await.unwrap();
+        let (rows, over_the_limit) = connection
+            .query(
+                pool,
+                "select * from all_native_data_types where id >= $1 order by id",
+                &[SqlParameter::Integer(1)],
+                None,
+            )
+            .await
+            .unwrap();
+
+        let to_arrow = |row: usize, index: usize| {
+            let row = &rows[row];
+            DuckDbConnection::to_arrow(row, &row.columns[index], index)
+        };
+
+        assert!(!over_the_limit);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(to_arrow(0, 0), ArrowType::Int32(1));
+        assert_eq!(to_arrow(0, 1), ArrowType::Int64(2));
+        assert_eq!(to_arrow(0, 2), ArrowType::Float64(1.5));
+        assert_eq!(
+            to_arrow(0, 3),
+            ArrowType::BigDecimal(BigDecimal::from_str("12.34").unwrap())
+        );
+        assert_eq!(to_arrow(0, 4), ArrowType::Utf8("Ada".into()));
+        assert_eq!(to_arrow(0, 5), ArrowType::Boolean(true));
+        assert_eq!(
+            to_arrow(0, 6),
+            ArrowType::Date32(Date32Type::from_naive_date(
+                NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()
+            ))
+        );
+        assert_eq!(
+            to_arrow(0, 7),
+            ArrowType::Time32(NaiveTime::from_hms_opt(3, 4, 5).unwrap())
+        );
+        assert_eq!(
+            to_arrow(0, 8),
+            ArrowType::Timestamp(
+                NaiveDate::from_ymd_opt(2024, 1, 2)
+                    .unwrap()
+                    .and_hms_opt(3, 4, 5)
+                    .unwrap()
+            )
+        );
+
+        // nulls are the default value of the column's type
+        assert_eq!(to_arrow(1, 1), ArrowType::Int64(0));
+        assert_eq!(to_arrow(1, 4), ArrowType::Utf8("".into()));
+
+        let parquet = DuckDbConnection::to_parquet(rows).unwrap();
+        assert!(!parquet.is_empty());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_duckdb_query_max_bytes() {
+        let connection = new_duckdb_connection();
+        let pool = connection.connect().await.unwrap();
+        let (rows, over_the_limit) = connection
+            .query(pool, "select * from all_native_data_types", &[], Some(10))
+            .await
+            .unwrap();
+
+        assert!(over_the_limit);
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_duckdb_schema() {
+        let connection = new_duckdb_connection();
+        let pool = connection.connect().await.unwrap();
+        let schema = connection.schema(pool).await.unwrap();
+        let table = &schema.tables["all_native_data_types"];
+
+        assert_eq!(table.schema, "main");
+        assert_eq!(
+            table.columns[0],
+            SchemaColumn {
+                name: "id".into(),
+                r#type: "INTEGER".into(),
+                is_nullable: false,
+                is_primary_key: true,
+                comment: None,
+            }
+        );
+        assert_eq!(
+            table.columns[4],
+            SchemaColumn {
+                name: "varchar_col".into(),
+                r#type: "VARCHAR".into(),
+                is_nullable: true,
+                is_primary_key: false,
+                comment: None,
+            }
+        );
+        assert_eq!(table.columns.len(), 9);
+    }
+}
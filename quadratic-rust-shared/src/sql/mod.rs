@@ -10,26 +10,49 @@ use async_trait::async_trait;
 use bigdecimal::BigDecimal;
 use bytes::Bytes;
 use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, Timelike};
+use futures_util::{Stream, StreamExt};
 use parquet::arrow::ArrowWriter;
-use serde::Serialize;
+use parquet::file::metadata::KeyValue;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sqlparser::dialect::Dialect;
 use sqlx::{Column, Row};
-use std::{collections::BTreeMap, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::mpsc::UnboundedSender;
 use uuid::Uuid;
 
-use self::{mysql_connection::MySqlConnection, postgres_connection::PostgresConnection};
-use crate::error::Result;
+#[cfg(feature = "duckdb")]
+use self::duckdb_connection::DuckDbConnection;
+use self::{
+    mssql_connection::MsSqlConnection, mysql_connection::MySqlConnection,
+    postgres_connection::PostgresConnection, sqlite_connection::SqliteConnection,
+};
+use crate::error::{Result, SharedError, Sql};
 use crate::{
     vec_arrow_type_to_array_ref, vec_string_arrow_type_to_array_ref,
     vec_time_arrow_type_to_array_ref,
 };
 
+#[cfg(feature = "duckdb")]
+pub mod duckdb_connection;
+pub mod mssql_connection;
 pub mod mysql_connection;
 pub mod postgres_connection;
+pub mod read_only;
+pub mod sqlite_connection;
 
 pub enum SqlConnection {
     Postgres(PostgresConnection),
     Mysql(MySqlConnection),
+    Sqlite(SqliteConnection),
+    #[cfg(feature = "duckdb")]
+    DuckDb(DuckDbConnection),
+    MsSql(MsSqlConnection),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -138,15 +161,55 @@ impl ArrowType {
             ArrowType::Jsonb(_) => vec_string_arrow_type_to_array_ref!(ArrowType::Jsonb, values),
             ArrowType::Uuid(_) => vec_string_arrow_type_to_array_ref!(ArrowType::Uuid, values),
             // ArrowType::Void => Arc::new(NullArray::new(1)),
-            ArrowType::Unsupported => Arc::new(StringArray::new_null(1)),
+            ArrowType::Unsupported => Arc::new(StringArray::new_null(values.len())),
             _ => {
                 tracing::trace!("Unsupported ArrowType: {:?}", values[0]);
                 // Arc::new(NullArray::new(0))
                 // Arc::new(StringArray::from_iter_values(["".to_string()])) as ArrayRef
-                Arc::new(StringArray::new_null(1))
+                Arc::new(StringArray::new_null(values.len()))
             }
         }
     }
+
+    /// The approximate number of bytes the value takes up in Arrow, which is
+    /// what `QueryLimits::max_bytes` is measured in
+    pub fn estimated_bytes(&self) -> u64 {
+        match self {
+            ArrowType::Int8(_) | ArrowType::UInt8(_) | ArrowType::Boolean(_) => 1,
+            ArrowType::Int16(_) | ArrowType::UInt16(_) => 2,
+            ArrowType::Int32(_)
+            | ArrowType::UInt32(_)
+            | ArrowType::Float32(_)
+            | ArrowType::Date32(_)
+            | ArrowType::Time32(_)
+            | ArrowType::TimeTz(_) => 4,
+            ArrowType::Int64(_)
+            | ArrowType::UInt64(_)
+            | ArrowType::Float64(_)
+            | ArrowType::BigDecimal(_)
+            | ArrowType::Date64(_)
+            | ArrowType::Time64(_)
+            | ArrowType::Timestamp(_)
+            | ArrowType::TimestampTz(_) => 8,
+            ArrowType::Utf8(value) => value.len() as u64,
+            ArrowType::Uuid(_) => 36,
+            ArrowType::Json(value) | ArrowType::Jsonb(value) => value.to_string().len() as u64,
+            ArrowType::Void | ArrowType::Unsupported => 0,
+        }
+    }
+}
+
+/// The approximate number of bytes a row's values take up, converting each
+/// of its columns with `to_arrow`
+pub fn row_bytes<'a, C: 'a>(
+    columns: impl IntoIterator<Item = &'a C>,
+    to_arrow: impl Fn(&C, usize) -> ArrowType,
+) -> u64 {
+    columns
+        .into_iter()
+        .enumerate()
+        .map(|(index, column)| to_arrow(column, index).estimated_bytes())
+        .sum()
 }
 
 #[macro_export]
@@ -186,18 +249,79 @@ macro_rules! vec_time_arrow_type_to_array_ref {
     }};
 }
 
-#[derive(Debug, Serialize, PartialEq)]
+/// A value bound to a placeholder in a query, eg, `$1`, `?` or `@P1`.  Values are
+/// always bound by the driver and never interpolated into the SQL.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", content = "value")]
+pub enum SqlParameter {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    Text(String),
+    /// A date and time, without a timezone
+    Timestamp(NaiveDateTime),
+}
+
+#[macro_export]
+macro_rules! bind_parameters {
+    ( $query:expr, $parameters:ident ) => {{
+        let mut query = $query;
+
+        for parameter in $parameters {
+            query = match parameter {
+                $crate::sql::SqlParameter::Null => query.bind(None::<String>),
+                $crate::sql::SqlParameter::Boolean(value) => query.bind(*value),
+                $crate::sql::SqlParameter::Integer(value) => query.bind(*value),
+                $crate::sql::SqlParameter::Number(value) => query.bind(*value),
+                $crate::sql::SqlParameter::Text(value) => query.bind(value.to_owned()),
+                $crate::sql::SqlParameter::Timestamp(value) => query.bind(*value),
+            };
+        }
+
+        query
+    }};
+}
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
 pub struct SchemaColumn {
     pub name: String,
     pub r#type: String,
     pub is_nullable: bool,
+    pub is_primary_key: bool,
+    pub comment: Option<String>,
 }
 
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TableKind {
+    #[default]
+    Table,
+    View,
+}
+
+/// A foreign key, with its columns in the same order as the columns they
+/// reference
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+pub struct ForeignKey {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub referenced_schema: String,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
 pub struct SchemaTable {
     pub name: String,
     pub schema: String,
+    pub kind: TableKind,
+    /// The database's estimate of the number of rows, which isn't kept for
+    /// views and may be stale
+    pub row_estimate: Option<i64>,
+    pub comment: Option<String>,
     pub columns: Vec<SchemaColumn>,
+    pub foreign_keys: Vec<ForeignKey>,
 }
 
 #[derive(Debug, Serialize, PartialEq)]
@@ -206,20 +330,53 @@ pub struct DatabaseSchema {
     pub tables: BTreeMap<String, SchemaTable>,
 }
 
+impl DatabaseSchema {
+    /// Add a foreign key to a table.  Catalogs that list a key's columns in
+    /// separate rows are combined, so rows for the same key must be added in
+    /// order.  Keys on tables that aren't in the schema are ignored.
+    pub fn add_foreign_key(&mut self, table: &str, foreign_key: ForeignKey) {
+        let Some(table) = self.tables.get_mut(table) else {
+            return;
+        };
+
+        match table.foreign_keys.last_mut() {
+            Some(last) if last.name == foreign_key.name => {
+                last.columns.extend(foreign_key.columns);
+                last.referenced_columns
+                    .extend(foreign_key.referenced_columns);
+            }
+            _ => table.foreign_keys.push(foreign_key),
+        }
+    }
+}
+
+/// Some catalogs return an empty string when there's no comment
+pub(crate) fn schema_comment(comment: Option<String>) -> Option<String> {
+    comment.filter(|comment| !comment.is_empty())
+}
+
+/// Views don't have row estimates, and Postgres estimates -1 rows for tables
+/// that haven't been analyzed
+pub(crate) fn row_estimate(kind: TableKind, estimate: Option<i64>) -> Option<i64> {
+    estimate.filter(|estimate| kind == TableKind::Table && *estimate >= 0)
+}
+
 #[async_trait]
 pub trait Connection {
-    type Conn: sqlx::Connection;
-    type Row: Row;
-    type Column: Column;
+    type Conn: Send;
+    type Row: Send;
+    type Column;
 
     // Connect to a database
     async fn connect(&self) -> Result<Self::Conn>;
 
-    /// Generically query a database
+    /// Generically query a database, binding `parameters` to the query's
+    /// placeholders
     async fn query(
         &self,
         pool: Self::Conn,
         sql: &str,
+        parameters: &[SqlParameter],
         max_bytes: Option<u64>,
     ) -> Result<(Vec<Self::Row>, bool)>;
 
@@ -227,80 +384,420 @@ pub trait Connection {
     async fn schema(&self, pool: Self::Conn) -> Result<DatabaseSchema>;
 
     /// Convert a database-specific column to an Arrow type
-    fn to_arrow(
-        row: &Self::Row,
-        column: &<<Self::Row as sqlx::Row>::Database as sqlx::Database>::Column,
-        index: usize,
-    ) -> ArrowType;
-
-    /// Default implementation of converting a vec of rows to a Parquet byte array
-    ///
-    /// This should work over any row/colmn SQLx vec
-    fn to_parquet(data: Vec<Self::Row>) -> Result<Bytes>
-    where
-        Self::Row: Row,
-        Self::Column: Column,
-    {
-        if data.is_empty() {
-            // return Err(SharedError::Sql(Sql::ParquetConversion(
-            //     "No data to convert".to_string(),
-            // )));
-            return Ok(Bytes::new());
+    fn to_arrow(row: &Self::Row, column: &Self::Column, index: usize) -> ArrowType;
+
+    /// Convert a vec of rows to a Parquet byte array
+    fn to_parquet(data: Vec<Self::Row>) -> Result<Bytes>;
+
+    /// Query a database, streaming the rows into Parquet row groups of
+    /// `limits.batch_size` rows so only one batch of rows is held in memory.
+    /// Each row group is sent to `chunks` as it's written.
+    async fn query_parquet(
+        &self,
+        pool: Self::Conn,
+        sql: &str,
+        parameters: &[SqlParameter],
+        limits: QueryLimits,
+        chunks: ParquetChunks,
+    ) -> Result<ParquetQuery>;
+
+    /// Have the database abort statements on this connection that run longer
+    /// than `timeout`
+    async fn set_timeout(&self, pool: &mut Self::Conn, timeout: Duration) -> Result<()>;
+
+    /// What's needed to cancel this connection's queries from another task
+    async fn query_handle(&self, pool: &mut Self::Conn) -> Result<QueryHandle>;
+
+    /// Cancel the query running on the connection `handle` was taken from.
+    /// Embedded databases are interrupted, database servers are sent a
+    /// cancel from a new connection.
+    async fn cancel(&self, handle: QueryHandle) -> Result<()> {
+        if let QueryHandle::Interrupt(interrupt) = handle {
+            interrupt();
         }
 
-        let col_count = data[0].len();
+        Ok(())
+    }
 
-        // transpose columns to rows, converting to Arrow types
-        let mut transposed = vec![vec![]; col_count];
+    /// The dialect statements are parsed with by `read_only::check_read_only`
+    fn dialect(&self) -> Box<dyn Dialect>;
 
-        data.iter().for_each(|row| {
-            row.columns()
-                .iter()
-                .enumerate()
-                .for_each(|(col_index, col)| {
-                    let value = Self::to_arrow(row, col, col_index);
-                    transposed[col_index].push(value);
-                });
-        });
+    /// Make the rest of this connection's session read-only, so writes that
+    /// `read_only::check_read_only` can't see, eg, functions with side
+    /// effects, fail in the database.  Call it after any setup statements.
+    async fn set_read_only(&self, pool: &mut Self::Conn) -> Result<()>;
+}
+
+/// Default implementation of converting a vec of SQLx rows to a Parquet byte
+/// array
+///
+/// This should work over any row/column SQLx vec
+pub fn sqlx_to_parquet<R: Row>(
+    data: Vec<R>,
+    to_arrow: impl Fn(&R, &<R::Database as sqlx::Database>::Column, usize) -> ArrowType,
+) -> Result<Bytes> {
+    if data.is_empty() {
+        // return Err(SharedError::Sql(Sql::ParquetConversion(
+        //     "No data to convert".to_string(),
+        // )));
+        return Ok(Bytes::new());
+    }
+
+    let col_count = data[0].len();
+
+    // transpose columns to rows, converting to Arrow types
+    let mut transposed = vec![vec![]; col_count];
+
+    data.iter().for_each(|row| {
+        row.columns()
+            .iter()
+            .enumerate()
+            .for_each(|(col_index, col)| {
+                let value = to_arrow(row, col, col_index);
+                transposed[col_index].push(value);
+            });
+    });
+
+    let names = data[0]
+        .columns()
+        .iter()
+        .map(|col| col.name().to_string())
+        .collect();
+
+    columns_to_parquet(names, transposed)
+}
+
+/// Write named columns of Arrow types to a Parquet byte array.  Each column
+/// must have at least one value.
+pub fn columns_to_parquet(names: Vec<String>, columns: Vec<Vec<ArrowType>>) -> Result<Bytes> {
+    let file = Vec::new();
+    let cols = columns
+        .into_iter()
+        .map(ArrowType::to_array_ref)
+        .collect::<Vec<ArrayRef>>();
+
+    // headings
+    let fields = names
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| Field::new(name, cols[index].data_type().to_owned(), true))
+        .collect::<Vec<Field>>();
+
+    let schema = ArrowSchema::new(fields);
+    let mut writer = ArrowWriter::try_new(file, Arc::new(schema.clone()), None)?;
+    writer.write(&RecordBatch::try_new(Arc::new(schema), cols)?)?;
+    let parquet = writer.into_inner()?;
+
+    Ok(parquet.into())
+}
+
+/// Identifies the query running on a connection, see `Connection::cancel`
+#[derive(Clone)]
+pub enum QueryHandle {
+    /// The id a database server gives the connection's session
+    Backend(i64),
+    /// Interrupts an embedded database's connection.  It must only be called
+    /// while the connection is open, ie, before the query's future is
+    /// dropped.
+    Interrupt(Arc<dyn Fn() + Send + Sync>),
+}
+
+/// Limits on the rows returned by a query.  `offset` rows are skipped, then at
+/// most `limit` rows are returned, stopping early if the rows' approximate
+/// size exceeds `max_bytes`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryLimits {
+    pub max_bytes: Option<u64>,
+    pub limit: Option<u64>,
+    pub offset: u64,
+    pub batch_size: usize,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        QueryLimits {
+            max_bytes: None,
+            limit: None,
+            offset: 0,
+            batch_size: 10_000,
+        }
+    }
+}
+
+/// The key in a query's Parquet footer of the number of rows returned
+pub const RECORD_COUNT_KEY: &str = "record-count";
+
+/// The key in a query's Parquet footer of whether `QueryLimits::max_bytes`
+/// stopped the query early
+pub const OVER_THE_LIMIT_KEY: &str = "over-the-limit";
+
+/// Receives a query's Parquet file as it's written, in chunks of one row
+/// group each, followed by the file's footer
+pub type ParquetChunks = UnboundedSender<Bytes>;
+
+/// The rows a query wrote to its Parquet file.  They're also in the file's
+/// footer as key-value metadata, see `RECORD_COUNT_KEY` and
+/// `OVER_THE_LIMIT_KEY`, as the file is sent before they're known.
+#[derive(Debug, Default, PartialEq)]
+pub struct ParquetQuery {
+    pub record_count: usize,
+    pub over_the_limit: bool,
+}
+
+/// The Parquet writer's output, taken after each row group is written.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Bytes {
+        let mut buffer = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::take(&mut *buffer).into()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut buffer = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes rows to Parquet as they're read, applying a query's limits.  Each
+/// batch of rows is converted to an Arrow `RecordBatch` and written as a row
+/// group.
+pub struct ParquetBatchWriter {
+    limits: QueryLimits,
+    names: Vec<String>,
+    batch: Vec<Vec<ArrowType>>,
+    batch_len: usize,
+    skipped: u64,
+    bytes: u64,
+    buffer: SharedBuffer,
+    schema: Option<SchemaRef>,
+    writer: Option<ArrowWriter<SharedBuffer>>,
+    chunks: ParquetChunks,
+    result: ParquetQuery,
+}
+
+impl ParquetBatchWriter {
+    pub fn new(limits: QueryLimits, chunks: ParquetChunks) -> Self {
+        ParquetBatchWriter {
+            limits,
+            names: vec![],
+            batch: vec![],
+            batch_len: 0,
+            skipped: 0,
+            bytes: 0,
+            buffer: SharedBuffer::default(),
+            schema: None,
+            writer: None,
+            chunks,
+            result: ParquetQuery::default(),
+        }
+    }
+
+    /// Add a row.  Returns false once the limits are reached and no more
+    /// rows are wanted.  Skipped rows aren't converted.
+    pub fn push(
+        &mut self,
+        names: impl FnOnce() -> Vec<String>,
+        values: impl FnOnce() -> Vec<ArrowType>,
+    ) -> Result<bool> {
+        if self.result.over_the_limit || self.limit_reached() {
+            return Ok(false);
+        }
+
+        if self.skipped < self.limits.offset {
+            self.skipped += 1;
+            return Ok(true);
+        }
+
+        let values = values();
+
+        if let Some(max_bytes) = self.limits.max_bytes {
+            self.bytes += values.iter().map(ArrowType::estimated_bytes).sum::<u64>();
 
-        let file = Vec::new();
-        let cols = transposed
+            if self.bytes > max_bytes {
+                self.result.over_the_limit = true;
+                return Ok(false);
+            }
+        }
+
+        if self.result.record_count == 0 {
+            self.names = names();
+        }
+
+        if self.batch.is_empty() {
+            self.batch = vec![vec![]; values.len()];
+        }
+
+        self.batch
+            .iter_mut()
+            .zip(values)
+            .for_each(|(column, value)| column.push(value));
+        self.batch_len += 1;
+        self.result.record_count += 1;
+
+        if self.batch_len >= self.limits.batch_size {
+            self.write_batch()?;
+        }
+
+        Ok(!self.limit_reached())
+    }
+
+    /// Write the last batch and the file's footer, with the rows written in
+    /// its metadata.  Queries without rows are a file without columns, so
+    /// they still have the metadata.
+    pub fn finish(mut self) -> Result<ParquetQuery> {
+        self.write_batch()?;
+
+        let mut writer = match self.writer.take() {
+            Some(writer) => writer,
+            None => {
+                ArrowWriter::try_new(self.buffer.clone(), Arc::new(ArrowSchema::empty()), None)?
+            }
+        };
+
+        writer.append_key_value_metadata(KeyValue::new(
+            RECORD_COUNT_KEY.into(),
+            self.result.record_count.to_string(),
+        ));
+        writer.append_key_value_metadata(KeyValue::new(
+            OVER_THE_LIMIT_KEY.into(),
+            self.result.over_the_limit.to_string(),
+        ));
+        writer.close()?;
+        self.send_chunk()?;
+
+        Ok(self.result)
+    }
+
+    /// Send what's been written since the last chunk.  The query stops if
+    /// nothing is receiving the file, eg, when the client disconnected.
+    fn send_chunk(&self) -> Result<()> {
+        self.chunks.send(self.buffer.take()).map_err(|_| {
+            SharedError::Sql(Sql::Query("The query's results are no longer read".into()))
+        })
+    }
+
+    fn limit_reached(&self) -> bool {
+        self.limits
+            .limit
+            .is_some_and(|limit| self.result.record_count as u64 >= limit)
+    }
+
+    fn write_batch(&mut self) -> Result<()> {
+        let batch = std::mem::take(&mut self.batch);
+        self.batch_len = 0;
+
+        // statements without columns have nothing to write
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let columns = batch
             .into_iter()
             .map(ArrowType::to_array_ref)
             .collect::<Vec<ArrayRef>>();
 
-        // headings
-        let fields = data[0]
-            .columns()
-            .iter()
-            .enumerate()
-            .map(|(index, col)| {
-                Field::new(
-                    col.name().to_string(),
-                    cols[index].data_type().to_owned(),
-                    true,
-                )
-            })
-            .collect::<Vec<Field>>();
-
-        // for (index, col) in cols.iter().enumerate() {
-        //     println!(
-        //         "{} ({}) = {:?}",
-        //         fields[index].name(),
-        //         fields[index].data_type(),
-        //         col
-        //     );
-        // }
-
-        let schema = ArrowSchema::new(fields);
-        let mut writer = ArrowWriter::try_new(file, Arc::new(schema.clone()), None)?;
-        writer.write(&RecordBatch::try_new(Arc::new(schema), cols)?)?;
-        let parquet = writer.into_inner()?;
-
-        Ok(parquet.into())
+        let schema = match self.schema {
+            Some(ref schema) => Arc::clone(schema),
+            None => {
+                let fields = self
+                    .names
+                    .iter()
+                    .zip(columns.iter())
+                    .map(|(name, column)| Field::new(name, column.data_type().to_owned(), true))
+                    .collect::<Vec<Field>>();
+                let schema = Arc::new(ArrowSchema::new(fields));
+                let writer = ArrowWriter::try_new(self.buffer.clone(), Arc::clone(&schema), None)?;
+                self.writer = Some(writer);
+                self.schema = Some(Arc::clone(&schema));
+
+                schema
+            }
+        };
+
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+
+        // a column's type is decided by its first batch, so later batches are
+        // cast to it, eg, when the first batch's values were all nulls
+        let columns = columns
+            .into_iter()
+            .zip(schema.fields().iter())
+            .map(
+                |(column, field)| match column.data_type() == field.data_type() {
+                    true => Ok(column),
+                    false => arrow::compute::cast(&column, field.data_type()),
+                },
+            )
+            .collect::<std::result::Result<Vec<ArrayRef>, _>>()?;
+
+        writer.write(&RecordBatch::try_new(schema, columns)?)?;
+        writer.flush()?;
+
+        self.send_chunk()
     }
 }
 
+/// Write a stream of rows to Parquet, stopping once the limits are reached.
+pub async fn stream_to_parquet<R: Send>(
+    mut rows: impl Stream<Item = Result<R>> + Unpin + Send,
+    limits: QueryLimits,
+    chunks: ParquetChunks,
+    names: impl Fn(&R) -> Vec<String> + Send,
+    to_arrow: impl Fn(&R) -> Vec<ArrowType> + Send,
+) -> Result<ParquetQuery> {
+    let mut writer = ParquetBatchWriter::new(limits, chunks);
+
+    while let Some(row) = rows.next().await {
+        let row = row?;
+
+        if !writer.push(|| names(&row), || to_arrow(&row))? {
+            break;
+        }
+    }
+
+    writer.finish()
+}
+
+/// Default implementation of streaming SQLx rows to Parquet
+pub async fn sqlx_stream_to_parquet<R: Row>(
+    rows: impl Stream<Item = std::result::Result<R, sqlx::Error>> + Unpin + Send,
+    limits: QueryLimits,
+    chunks: ParquetChunks,
+    to_arrow: impl Fn(&R, &<R::Database as sqlx::Database>::Column, usize) -> ArrowType + Send + Sync,
+) -> Result<ParquetQuery> {
+    let rows = rows.map(|row| row.map_err(|e| SharedError::Sql(Sql::Query(e.to_string()))));
+
+    stream_to_parquet(
+        rows,
+        limits,
+        chunks,
+        |row| {
+            row.columns()
+                .iter()
+                .map(|col| col.name().to_string())
+                .collect()
+        },
+        |row| {
+            row.columns()
+                .iter()
+                .enumerate()
+                .map(|(index, col)| to_arrow(row, col, index))
+                .collect()
+        },
+    )
+    .await
+}
+
 // async fn schema<<T: Connection>::Conn>(
 //     conn: impl Connection,
 //     sql_conn: SqlConnection,
@@ -340,3 +837,215 @@ pub trait Connection {
 
 //     Ok(schema)
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    /// Write the rows, returning the result and the chunks that were sent
+    fn write_rows(limits: QueryLimits, rows: Vec<ArrowType>) -> (ParquetQuery, Vec<Bytes>) {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut writer = ParquetBatchWriter::new(limits, sender);
+
+        for row in rows {
+            if !writer.push(|| vec!["id".into()], || vec![row]).unwrap() {
+                break;
+            }
+        }
+
+        let query = writer.finish().unwrap();
+        let mut chunks = vec![];
+
+        while let Ok(chunk) = receiver.try_recv() {
+            chunks.push(chunk);
+        }
+
+        (query, chunks)
+    }
+
+    fn int64_rows(count: i64) -> Vec<ArrowType> {
+        (1..=count).map(ArrowType::Int64).collect()
+    }
+
+    /// Read the first column of the Parquet file and its number of row groups
+    fn read_parquet(chunks: &[Bytes]) -> (Vec<ArrayRef>, usize) {
+        let bytes = Bytes::from(chunks.concat());
+        let builder = ParquetRecordBatchReaderBuilder::try_new(bytes).unwrap();
+        let row_groups = builder.metadata().num_row_groups();
+        let columns = builder
+            .build()
+            .unwrap()
+            .map(|batch| Arc::clone(batch.unwrap().column(0)))
+            .collect();
+
+        (columns, row_groups)
+    }
+
+    fn int64_values(columns: Vec<ArrayRef>) -> Vec<i64> {
+        columns
+            .iter()
+            .flat_map(|column| {
+                let column = column.as_any().downcast_ref::<Int64Array>().unwrap();
+                column.values().to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn writes_a_row_group_per_batch() {
+        let limits = QueryLimits {
+            batch_size: 2,
+            ..Default::default()
+        };
+        let (query, chunks) = write_rows(limits, int64_rows(5));
+        let (columns, row_groups) = read_parquet(&chunks);
+
+        assert_eq!(query.record_count, 5);
+        assert!(!query.over_the_limit);
+        assert_eq!(row_groups, 3);
+        // a chunk for each row group and the footer
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(int64_values(columns), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn applies_limit_and_offset() {
+        let limits = QueryLimits {
+            limit: Some(2),
+            offset: 1,
+            ..Default::default()
+        };
+        let (query, chunks) = write_rows(limits, int64_rows(5));
+        let (columns, _) = read_parquet(&chunks);
+
+        assert_eq!(query.record_count, 2);
+        assert!(!query.over_the_limit);
+        assert_eq!(int64_values(columns), vec![2, 3]);
+    }
+
+    #[test]
+    fn stops_at_max_bytes() {
+        // each Int64 is 8 bytes, so the third row goes over
+        let limits = QueryLimits {
+            max_bytes: Some(20),
+            ..Default::default()
+        };
+        let (query, chunks) = write_rows(limits, int64_rows(5));
+        let (columns, _) = read_parquet(&chunks);
+
+        assert_eq!(query.record_count, 2);
+        assert!(query.over_the_limit);
+        assert_eq!(int64_values(columns), vec![1, 2]);
+    }
+
+    #[test]
+    fn estimates_bytes_by_type() {
+        let values = [
+            ArrowType::Boolean(true),
+            ArrowType::Int32(1),
+            ArrowType::Float64(1.0),
+            ArrowType::Utf8("quadratic".into()),
+            ArrowType::Void,
+        ];
+
+        assert_eq!(ArrowType::Utf8("quadratic".into()).estimated_bytes(), 9);
+        assert_eq!(row_bytes(&values, |value, _| value.clone()), 22);
+    }
+
+    #[test]
+    fn no_rows_is_a_file_without_columns() {
+        let (query, chunks) = write_rows(QueryLimits::default(), vec![]);
+        let (columns, row_groups) = read_parquet(&chunks);
+
+        assert_eq!(query.record_count, 0);
+        assert_eq!(chunks.len(), 1);
+        assert!(columns.is_empty());
+        assert_eq!(row_groups, 0);
+    }
+
+    #[test]
+    fn writes_the_rows_to_the_footer() {
+        let limits = QueryLimits {
+            max_bytes: Some(20),
+            ..Default::default()
+        };
+        let (_, chunks) = write_rows(limits, int64_rows(5));
+        let bytes = Bytes::from(chunks.concat());
+        let builder = ParquetRecordBatchReaderBuilder::try_new(bytes).unwrap();
+        let metadata = builder.metadata().file_metadata().key_value_metadata();
+        let value = |key: &str| {
+            metadata?
+                .iter()
+                .find(|key_value| key_value.key == key)?
+                .value
+                .to_owned()
+        };
+
+        assert_eq!(value(RECORD_COUNT_KEY), Some("2".into()));
+        assert_eq!(value(OVER_THE_LIMIT_KEY), Some("true".into()));
+    }
+
+    #[test]
+    fn stops_when_the_chunks_are_no_longer_read() {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let limits = QueryLimits {
+            batch_size: 1,
+            ..Default::default()
+        };
+        let mut writer = ParquetBatchWriter::new(limits, sender);
+        drop(receiver);
+
+        let pushed = writer.push(|| vec!["id".into()], || vec![ArrowType::Int64(1)]);
+        assert!(pushed.is_err());
+    }
+
+    #[test]
+    fn later_batches_are_cast_to_the_first_batch_type() {
+        let limits = QueryLimits {
+            batch_size: 1,
+            ..Default::default()
+        };
+        let rows = vec![ArrowType::Int64(1), ArrowType::Int32(2)];
+        let (_, chunks) = write_rows(limits, rows);
+        let (columns, _) = read_parquet(&chunks);
+
+        assert_eq!(columns[0].data_type(), &DataType::Int64);
+        assert_eq!(int64_values(columns), vec![1, 2]);
+    }
+
+    #[test]
+    fn combines_foreign_key_columns() {
+        let mut schema = DatabaseSchema {
+            database: "test".into(),
+            tables: BTreeMap::from([(
+                "orders".into(),
+                SchemaTable {
+                    name: "orders".into(),
+                    ..Default::default()
+                },
+            )]),
+        };
+        let foreign_key = |name: &str, column: &str| ForeignKey {
+            name: name.into(),
+            columns: vec![column.into()],
+            referenced_schema: "public".into(),
+            referenced_table: "customers".into(),
+            referenced_columns: vec![format!("customer_{column}")],
+        };
+
+        schema.add_foreign_key("orders", foreign_key("orders_customer", "region"));
+        schema.add_foreign_key("orders", foreign_key("orders_customer", "id"));
+        schema.add_foreign_key("orders", foreign_key("orders_product", "product"));
+        schema.add_foreign_key("missing", foreign_key("missing_customer", "id"));
+
+        let foreign_keys = &schema.tables["orders"].foreign_keys;
+        assert_eq!(foreign_keys.len(), 2);
+        assert_eq!(foreign_keys[0].columns, vec!["region", "id"]);
+        assert_eq!(
+            foreign_keys[0].referenced_columns,
+            vec!["customer_region", "customer_id"]
+        );
+        assert_eq!(foreign_keys[1].columns, vec!["product"]);
+    }
+}
@@ -0,0 +1,728 @@
+use std::{collections::BTreeMap, str::FromStr, time::Duration};
+
+use arrow::datatypes::Date32Type;
+use async_trait::async_trait;
+use bigdecimal::{num_bigint::BigInt, BigDecimal};
+use bytes::Bytes;
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use futures_util::{future::ready, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlparser::dialect::{Dialect, MsSqlDialect};
+use tiberius::{AuthMethod, Client, Column, ColumnData, ColumnType, Config, Query, Row};
+use tokio::net::TcpStream;
+use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
+
+use crate::convert_mssql_type;
+use crate::error::{Result, SharedError, Sql};
+use crate::sql::{
+    columns_to_parquet, row_bytes, row_estimate, schema_comment, stream_to_parquet, ArrowType,
+    Connection, DatabaseSchema, ForeignKey, ParquetChunks, ParquetQuery, QueryHandle, QueryLimits,
+    SchemaColumn, SchemaTable, SqlParameter, TableKind,
+};
+
+/// Database permissions that let a login write, see `set_read_only`
+const WRITE_PERMISSIONS: [&str; 8] = [
+    "INSERT",
+    "UPDATE",
+    "DELETE",
+    "ALTER",
+    "CONTROL",
+    "EXECUTE",
+    "CREATE TABLE",
+    "CREATE PROCEDURE",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MsSqlConnection {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub host: String,
+    pub port: Option<String>,
+    pub database: Option<String>,
+    /// Accept the server's certificate without validating it, eg, self-signed
+    #[serde(default)]
+    pub trust_cert: bool,
+}
+
+impl MsSqlConnection {
+    pub fn new(
+        username: Option<String>,
+        password: Option<String>,
+        host: String,
+        port: Option<String>,
+        database: Option<String>,
+        trust_cert: bool,
+    ) -> MsSqlConnection {
+        MsSqlConnection {
+            username,
+            password,
+            host,
+            port,
+            database,
+            trust_cert,
+        }
+    }
+}
+
+#[async_trait]
+impl Connection for MsSqlConnection {
+    type Conn = Client<Compat<TcpStream>>;
+    type Row = Row;
+    type Column = Column;
+
+    async fn connect(&self) -> Result<Self::Conn> {
+        let connect_error = |e: String| SharedError::Sql(Sql::Connect(e));
+        let mut config = Config::new();
+        config.host(&self.host);
+        config.authentication(AuthMethod::sql_server(
+            self.username.to_owned().unwrap_or_default(),
+            self.password.to_owned().unwrap_or_default(),
+        ));
+
+        if let Some(ref port) = self.port {
+            config.port(port.parse::<u16>().map_err(|_| {
+                SharedError::Sql(Sql::Connect("Could not parse port into a number".into()))
+            })?);
+        }
+
+        if let Some(ref database) = self.database {
+            config.database(database);
+        }
+
+        if self.trust_cert {
+            config.trust_cert();
+        }
+
+        let tcp = TcpStream::connect(config.get_addr())
+            .await
+            .map_err(|e| connect_error(e.to_string()))?;
+        tcp.set_nodelay(true)
+            .map_err(|e| connect_error(e.to_string()))?;
+
+        let pool = Client::connect(config, tcp.compat_write())
+            .await
+            .map_err(|e| connect_error(e.to_string()))?;
+
+        Ok(pool)
+    }
+
+    async fn query(
+        &self,
+        mut pool: Self::Conn,
+        sql: &str,
+        parameters: &[SqlParameter],
+        max_bytes: Option<u64>,
+    ) -> Result<(Vec<Self::Row>, bool)> {
+        let mut rows = vec![];
+        let mut bytes = 0;
+        let mut over_the_limit = false;
+        let mut stream = new_query(sql, parameters)
+            .query(&mut pool)
+            .await
+            .map_err(query_error)?
+            .into_row_stream();
+
+        while let Some(row) = stream.next().await {
+            let row = row.map_err(query_error)?;
+
+            // only the first result set is returned
+            if row.result_index() > 0 {
+                break;
+            }
+
+            if let Some(max_bytes) = max_bytes {
+                bytes += row_bytes(row.columns(), |column, index| {
+                    Self::to_arrow(&row, column, index)
+                });
+
+                if bytes > max_bytes {
+                    over_the_limit = true;
+                    break;
+                }
+            }
+
+            rows.push(row);
+        }
+
+        Ok((rows, over_the_limit))
+    }
+
+    async fn query_parquet(
+        &self,
+        mut pool: Self::Conn,
+        sql: &str,
+        parameters: &[SqlParameter],
+        limits: QueryLimits,
+        chunks: ParquetChunks,
+    ) -> Result<ParquetQuery> {
+        let rows = new_query(sql, parameters)
+            .query(&mut pool)
+            .await
+            .map_err(query_error)?
+            .into_row_stream()
+            // only the first result set is returned
+            .take_while(|row| ready(!matches!(row, Ok(row) if row.result_index() > 0)))
+            .map(|row| row.map_err(query_error));
+
+        stream_to_parquet(
+            rows,
+            limits,
+            chunks,
+            |row| {
+                row.columns()
+                    .iter()
+                    .map(|col| col.name().to_owned())
+                    .collect()
+            },
+            |row| {
+                row.columns()
+                    .iter()
+                    .enumerate()
+                    .map(|(index, col)| Self::to_arrow(row, col, index))
+                    .collect()
+            },
+        )
+        .await
+    }
+
+    /// SQL Server doesn't have a statement timeout, so statements are only
+    /// stopped from waiting on locks for longer than `timeout`.  Queries that
+    /// run longer are killed by the caller with `cancel`.
+    async fn set_timeout(&self, pool: &mut Self::Conn, timeout: Duration) -> Result<()> {
+        // SET doesn't accept bind parameters
+        let sql = format!("set lock_timeout {}", timeout.as_millis());
+
+        pool.simple_query(sql)
+            .await
+            .map_err(query_error)?
+            .into_results()
+            .await
+            .map_err(query_error)?;
+
+        Ok(())
+    }
+
+    async fn query_handle(&self, pool: &mut Self::Conn) -> Result<QueryHandle> {
+        let spid = pool
+            .simple_query("select @@spid")
+            .await
+            .map_err(query_error)?
+            .into_row()
+            .await
+            .map_err(query_error)?
+            .and_then(|row| row.get::<i16, _>(0))
+            .ok_or_else(|| SharedError::Sql(Sql::Query("Could not get the session id".into())))?;
+
+        Ok(QueryHandle::Backend(spid.into()))
+    }
+
+    /// KILL ends the session, not just its query, and needs the `ALTER ANY
+    /// CONNECTION` permission
+    async fn cancel(&self, handle: QueryHandle) -> Result<()> {
+        let QueryHandle::Backend(spid) = handle else {
+            return Ok(());
+        };
+        let mut pool = self.connect().await?;
+
+        // KILL doesn't accept bind parameters
+        pool.simple_query(format!("kill {spid}"))
+            .await
+            .map_err(query_error)?
+            .into_results()
+            .await
+            .map_err(query_error)?;
+
+        Ok(())
+    }
+
+    fn dialect(&self) -> Box<dyn Dialect> {
+        Box::new(MsSqlDialect {})
+    }
+
+    /// SQL Server doesn't have read-only transactions, so read-only
+    /// connections need a login that can only read, eg, one in the
+    /// `db_datareader` role, and are rejected if the login can write to the
+    /// database.  Queries run in a snapshot transaction, which needs the
+    /// database's `ALLOW_SNAPSHOT_ISOLATION` option, so they don't take locks
+    /// that block writers.
+    async fn set_read_only(&self, pool: &mut Self::Conn) -> Result<()> {
+        let sql = format!(
+            "select count(*) from fn_my_permissions(null, 'DATABASE') \
+            where permission_name in ({})",
+            WRITE_PERMISSIONS
+                .map(|permission| format!("'{permission}'"))
+                .join(", ")
+        );
+        let write_permissions = pool
+            .simple_query(sql)
+            .await
+            .map_err(query_error)?
+            .into_row()
+            .await
+            .map_err(query_error)?
+            .and_then(|row| row.get::<i32, _>(0))
+            .unwrap_or_default();
+
+        if write_permissions > 0 {
+            return Err(SharedError::Sql(Sql::Query(
+                "Read-only connections need a login that can only read the database".into(),
+            )));
+        }
+
+        // a transaction started by sp_executesql must end in it, so BEGIN is
+        // sent as a batch
+        pool.simple_query("set transaction isolation level snapshot; begin transaction")
+            .await
+            .map_err(query_error)?
+            .into_results()
+            .await
+            .map_err(query_error)?;
+
+        Ok(())
+    }
+
+    async fn schema(&self, mut pool: Self::Conn) -> Result<DatabaseSchema> {
+        let schema_error = |e: tiberius::error::Error| SharedError::Sql(Sql::Schema(e.to_string()));
+
+        // comments are the MS_Description extended properties that SSMS edits,
+        // and a key's columns are listed in the same order as the columns they
+        // reference
+        let sql = "
+            select c.TABLE_CATALOG, c.TABLE_SCHEMA, c.TABLE_NAME, c.COLUMN_NAME, c.DATA_TYPE, c.IS_NULLABLE,
+                t.TABLE_TYPE, cast(case when pk.COLUMN_NAME is null then 0 else 1 end as bit) as is_primary_key,
+                cast(column_comment.value as nvarchar(max)) as column_comment,
+                cast(table_comment.value as nvarchar(max)) as table_comment,
+                (
+                    select sum(p.rows) from sys.partitions as p
+                    where p.object_id = object_id(quotename(c.TABLE_SCHEMA) + '.' + quotename(c.TABLE_NAME))
+                        and p.index_id in (0, 1)
+                ) as row_estimate
+            from INFORMATION_SCHEMA.COLUMNS as c
+            inner join INFORMATION_SCHEMA.TABLES as t
+                on t.TABLE_SCHEMA = c.TABLE_SCHEMA and t.TABLE_NAME = c.TABLE_NAME
+            left join (
+                select kcu.TABLE_SCHEMA, kcu.TABLE_NAME, kcu.COLUMN_NAME
+                from INFORMATION_SCHEMA.TABLE_CONSTRAINTS as tc
+                inner join INFORMATION_SCHEMA.KEY_COLUMN_USAGE as kcu
+                    on tc.CONSTRAINT_SCHEMA = kcu.CONSTRAINT_SCHEMA and tc.CONSTRAINT_NAME = kcu.CONSTRAINT_NAME
+                where tc.CONSTRAINT_TYPE = 'PRIMARY KEY'
+            ) as pk
+                on pk.TABLE_SCHEMA = c.TABLE_SCHEMA and pk.TABLE_NAME = c.TABLE_NAME and pk.COLUMN_NAME = c.COLUMN_NAME
+            left join sys.extended_properties as column_comment
+                on column_comment.class = 1 and column_comment.name = 'MS_Description'
+                and column_comment.major_id = object_id(quotename(c.TABLE_SCHEMA) + '.' + quotename(c.TABLE_NAME))
+                and column_comment.minor_id = columnproperty(column_comment.major_id, c.COLUMN_NAME, 'ColumnId')
+            left join sys.extended_properties as table_comment
+                on table_comment.class = 1 and table_comment.name = 'MS_Description'
+                and table_comment.major_id = object_id(quotename(c.TABLE_SCHEMA) + '.' + quotename(c.TABLE_NAME))
+                and table_comment.minor_id = 0
+            where t.TABLE_TYPE in ('BASE TABLE', 'VIEW')
+            order by c.TABLE_SCHEMA, c.TABLE_NAME, c.ORDINAL_POSITION;
+
+            select object_name(fk.parent_object_id) as table_name, fk.name,
+                col_name(fkc.parent_object_id, fkc.parent_column_id) as column_name,
+                object_schema_name(fk.referenced_object_id) as referenced_schema,
+                object_name(fk.referenced_object_id) as referenced_table,
+                col_name(fkc.referenced_object_id, fkc.referenced_column_id) as referenced_column
+            from sys.foreign_keys as fk
+            inner join sys.foreign_key_columns as fkc on fkc.constraint_object_id = fk.object_id
+            order by table_name, fk.name, fkc.constraint_column_id;";
+
+        let mut results = pool
+            .simple_query(sql)
+            .await
+            .map_err(schema_error)?
+            .into_results()
+            .await
+            .map_err(schema_error)?
+            .into_iter();
+        let columns = results.next().unwrap_or_default();
+        let foreign_keys = results.next().unwrap_or_default();
+
+        let mut schema = DatabaseSchema {
+            database: self.database.to_owned().unwrap_or_default(),
+            tables: BTreeMap::new(),
+        };
+
+        for row in columns.into_iter() {
+            let optional_text = |index: usize| row.get::<&str, usize>(index).map(str::to_owned);
+            let text = |index: usize| optional_text(index).unwrap_or_default();
+            let table_name = text(2);
+            let kind = match text(6).as_str() {
+                "VIEW" => TableKind::View,
+                _ => TableKind::Table,
+            };
+
+            schema
+                .tables
+                // get or insert the table
+                .entry(table_name.to_owned())
+                .or_insert_with(|| SchemaTable {
+                    name: table_name,
+                    schema: text(1),
+                    kind,
+                    row_estimate: row_estimate(kind, row.get::<i64, usize>(10)),
+                    comment: schema_comment(optional_text(9)),
+                    columns: vec![],
+                    foreign_keys: vec![],
+                })
+                .columns
+                // add the column to the table
+                .push(SchemaColumn {
+                    name: text(3),
+                    r#type: text(4),
+                    is_nullable: matches!(text(5).to_lowercase().as_str(), "yes"),
+                    is_primary_key: row.get::<bool, usize>(7).unwrap_or_default(),
+                    comment: schema_comment(optional_text(8)),
+                });
+        }
+
+        for row in foreign_keys.into_iter() {
+            let text = |index: usize| row.get::<&str, usize>(index).unwrap_or_default().to_owned();
+
+            schema.add_foreign_key(
+                &text(0),
+                ForeignKey {
+                    name: text(1),
+                    columns: vec![text(2)],
+                    referenced_schema: text(3),
+                    referenced_table: text(4),
+                    referenced_columns: vec![text(5)],
+                },
+            );
+        }
+
+        Ok(schema)
+    }
+
+    fn to_arrow(row: &Self::Row, column: &Self::Column, index: usize) -> ArrowType {
+        let Some((_, data)) = row.cells().nth(index) else {
+            return ArrowType::Void;
+        };
+
+        // money and smallmoney are integers scaled by 10,000 that are decoded as floats
+        if matches!(column.column_type(), ColumnType::Money | ColumnType::Money4) {
+            let money = |value: f64| {
+                let scaled = BigInt::from((value * 10_000.0).round() as i64);
+                ArrowType::BigDecimal(BigDecimal::new(scaled, 4))
+            };
+
+            return match data {
+                ColumnData::F32(value) => money(value.unwrap_or_default() as f64),
+                ColumnData::F64(value) => money(value.unwrap_or_default()),
+                _ => ArrowType::Unsupported,
+            };
+        }
+
+        match data {
+            ColumnData::Bit(value) => ArrowType::Boolean(value.unwrap_or_default()),
+            ColumnData::U8(value) => ArrowType::UInt8(value.unwrap_or_default()),
+            ColumnData::I16(value) => ArrowType::Int16(value.unwrap_or_default()),
+            ColumnData::I32(value) => ArrowType::Int32(value.unwrap_or_default()),
+            ColumnData::I64(value) => ArrowType::Int64(value.unwrap_or_default()),
+            ColumnData::F32(value) => ArrowType::Float32(value.unwrap_or_default()),
+            ColumnData::F64(value) => ArrowType::Float64(value.unwrap_or_default()),
+            ColumnData::Numeric(value) => ArrowType::BigDecimal(
+                value
+                    .as_ref()
+                    .and_then(|numeric| BigDecimal::from_str(&numeric.to_string()).ok())
+                    .unwrap_or_default(),
+            ),
+            ColumnData::String(value) => {
+                ArrowType::Utf8(value.as_deref().unwrap_or_default().to_owned())
+            }
+            ColumnData::Guid(value) => ArrowType::Uuid(value.unwrap_or_default()),
+            ColumnData::Date(_) => {
+                let naive_date = convert_mssql_type!(NaiveDate, row, index);
+                ArrowType::Date32(Date32Type::from_naive_date(naive_date))
+            }
+            ColumnData::Time(_) => ArrowType::Time32(convert_mssql_type!(NaiveTime, row, index)),
+            ColumnData::DateTime(_) | ColumnData::SmallDateTime(_) | ColumnData::DateTime2(_) => {
+                ArrowType::Timestamp(convert_mssql_type!(NaiveDateTime, row, index))
+            }
+            ColumnData::DateTimeOffset(_) => {
+                let timestamp = convert_mssql_type!(DateTime<FixedOffset>, row, index);
+                ArrowType::TimestampTz(timestamp.with_timezone(&Local))
+            }
+            // TODO: support binary and xml
+            _ => ArrowType::Unsupported,
+        }
+    }
+
+    fn to_parquet(data: Vec<Self::Row>) -> Result<Bytes> {
+        if data.is_empty() {
+            return Ok(Bytes::new());
+        }
+
+        let columns = data[0].columns().to_owned();
+
+        // transpose columns to rows, converting to Arrow types
+        let mut transposed = vec![vec![]; columns.len()];
+
+        data.iter().for_each(|row| {
+            columns.iter().enumerate().for_each(|(col_index, col)| {
+                let value = Self::to_arrow(row, col, col_index);
+                transposed[col_index].push(value);
+            });
+        });
+
+        let names = columns.iter().map(|col| col.name().to_owned()).collect();
+
+        columns_to_parquet(names, transposed)
+    }
+}
+
+/// A query with its parameters bound to the `@P1`, `@P2`, ... placeholders.
+fn new_query(sql: &str, parameters: &[SqlParameter]) -> Query<'static> {
+    let mut query = Query::new(sql.to_owned());
+
+    for parameter in parameters {
+        match parameter {
+            SqlParameter::Null => query.bind(None::<String>),
+            SqlParameter::Boolean(value) => query.bind(*value),
+            SqlParameter::Integer(value) => query.bind(*value),
+            SqlParameter::Number(value) => query.bind(*value),
+            SqlParameter::Text(value) => query.bind(value.to_owned()),
+            SqlParameter::Timestamp(value) => query.bind(*value),
+        }
+    }
+
+    query
+}
+
+fn query_error(error: tiberius::error::Error) -> SharedError {
+    SharedError::Sql(Sql::Query(error.to_string()))
+}
+
+#[macro_export]
+macro_rules! convert_mssql_type {
+    ( $kind:ty, $row:ident, $index:ident ) => {{
+        $row.try_get::<$kind, usize>($index)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use tracing_test::traced_test;
+    use uuid::Uuid;
+
+    fn new_mssql_connection() -> MsSqlConnection {
+        MsSqlConnection::new(
+            Some("sa".into()),
+            Some("yourStrong(!)Password".into()),
+            "0.0.0.0".into(),
+            Some("1433".into()),
+            Some("mssql-connection".into()),
+            true,
+        )
+    }
+
+    async fn setup() -> (MsSqlConnection, Result<Client<Compat<TcpStream>>>) {
+        let connection = new_mssql_connection();
+        let pool = connection.connect().await;
+
+        (connection, pool)
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_mssql_connection() {
+        let (_, pool) = setup().await;
+
+        assert!(pool.is_ok());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_mssql_read_only() {
+        // sa can write, so it can't be used for read-only connections
+        let (connection, pool) = setup().await;
+        assert!(connection.set_read_only(&mut pool.unwrap()).await.is_err());
+
+        // setup.sql creates a login that can only read
+        let connection = MsSqlConnection {
+            username: Some("reader".into()),
+            ..new_mssql_connection()
+        };
+        let mut pool = connection.connect().await.unwrap();
+        connection.set_read_only(&mut pool).await.unwrap();
+
+        let sql = "select id from all_native_data_types order by id";
+        let (rows, _) = connection.query(pool, sql, &[], None).await.unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let mut pool = connection.connect().await.unwrap();
+        connection.set_read_only(&mut pool).await.unwrap();
+        let sql = "delete from all_native_data_types";
+        assert!(connection.query(pool, sql, &[], None).await.is_err());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_mssql_cancel() {
+        let (connection, pool) = setup().await;
+        let mut pool = pool.unwrap();
+        let handle = connection.query_handle(&mut pool).await.unwrap();
+        assert!(matches!(handle, QueryHandle::Backend(_)));
+
+        let connection = std::sync::Arc::new(connection);
+        let running = tokio::spawn({
+            let connection = std::sync::Arc::clone(&connection);
+            async move {
+                connection
+                    .query(pool, "waitfor delay '00:00:10'", &[], None)
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        connection.cancel(handle).await.unwrap();
+
+        assert!(running.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_mssql_query_to_arrow() {
+        let (connection, pool) = setup().await;
+        let (rows, over_the_limit) = connection
+            .query(
+                pool.unwrap(),
+                "select * from all_native_data_types where id >= @P1 order by id",
+                &[SqlParameter::Integer(1)],
+                None,
+            )
+            .await
+            .unwrap();
+
+        let to_arrow = |row: usize, index: usize| {
+            let row = &rows[row];
+            MsSqlConnection::to_arrow(row, &row.columns()[index], index)
+        };
+        let date = NaiveDate::from_ymd_opt(2024, 5, 28).unwrap();
+        let time = NaiveTime::from_hms_opt(12, 34, 56).unwrap();
+
+        assert!(!over_the_limit);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(to_arrow(0, 0), ArrowType::Int32(1));
+        assert_eq!(to_arrow(0, 1), ArrowType::UInt8(255));
+        assert_eq!(to_arrow(0, 2), ArrowType::Int16(32767));
+        assert_eq!(to_arrow(0, 3), ArrowType::Int32(2147483647));
+        assert_eq!(to_arrow(0, 4), ArrowType::Int64(9223372036854775807));
+        assert_eq!(to_arrow(0, 5), ArrowType::Boolean(true));
+        assert_eq!(
+            to_arrow(0, 6),
+            ArrowType::BigDecimal(BigDecimal::from_str("12345.67").unwrap())
+        );
+        assert_eq!(
+            to_arrow(0, 7),
+            ArrowType::BigDecimal(BigDecimal::from_str("12345.67").unwrap())
+        );
+        assert_eq!(
+            to_arrow(0, 8),
+            ArrowType::BigDecimal(BigDecimal::new(BigInt::from(123_456_700), 4))
+        );
+        assert_eq!(
+            to_arrow(0, 9),
+            ArrowType::BigDecimal(BigDecimal::new(BigInt::from(1_234_500), 4))
+        );
+        assert_eq!(to_arrow(0, 10), ArrowType::Float64(123456789.123456));
+        assert_eq!(to_arrow(0, 11), ArrowType::Float32(123.45));
+        assert_eq!(
+            to_arrow(0, 12),
+            ArrowType::Date32(Date32Type::from_naive_date(date))
+        );
+        assert_eq!(to_arrow(0, 13), ArrowType::Time32(time));
+        assert_eq!(to_arrow(0, 14), ArrowType::Timestamp(date.and_time(time)));
+        assert_eq!(to_arrow(0, 15), ArrowType::Timestamp(date.and_time(time)));
+        assert_eq!(
+            to_arrow(0, 16),
+            ArrowType::Timestamp(date.and_hms_opt(12, 35, 0).unwrap())
+        );
+        assert_eq!(
+            to_arrow(0, 17),
+            ArrowType::TimestampTz(
+                DateTime::parse_from_rfc3339("2024-05-28T12:34:56+02:00")
+                    .unwrap()
+                    .with_timezone(&Local)
+            )
+        );
+        assert_eq!(to_arrow(0, 18), ArrowType::Utf8("char_data ".into()));
+        assert_eq!(to_arrow(0, 19), ArrowType::Utf8("varchar_data".into()));
+        assert_eq!(to_arrow(0, 20), ArrowType::Utf8("text_data".into()));
+        assert_eq!(to_arrow(0, 21), ArrowType::Utf8("nchar_data".into()));
+        assert_eq!(to_arrow(0, 22), ArrowType::Utf8("nvarchar_data".into()));
+        assert_eq!(to_arrow(0, 23), ArrowType::Utf8("ntext_data".into()));
+        assert_eq!(to_arrow(0, 24), ArrowType::Unsupported);
+        assert_eq!(to_arrow(0, 25), ArrowType::Unsupported);
+        assert_eq!(
+            to_arrow(0, 26),
+            ArrowType::Uuid(Uuid::from_str("6f9619ff-8b86-d011-b42d-00c04fc964ff").unwrap())
+        );
+        assert_eq!(to_arrow(0, 27), ArrowType::Unsupported);
+
+        // nulls are the default value of the column's type
+        assert_eq!(to_arrow(1, 4), ArrowType::Int64(0));
+        assert_eq!(to_arrow(1, 8), ArrowType::Float64(0.0));
+        assert_eq!(to_arrow(1, 19), ArrowType::Utf8("".into()));
+
+        let parquet = MsSqlConnection::to_parquet(rows).unwrap();
+        assert!(!parquet.is_empty());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_mssql_query_max_bytes() {
+        let (connection, pool) = setup().await;
+        let (rows, over_the_limit) = connection
+            .query(
+                pool.unwrap(),
+                "select * from all_native_data_types order by id",
+                &[],
+                Some(30),
+            )
+            .await
+            .unwrap();
+
+        assert!(over_the_limit);
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_mssql_schema() {
+        let connection = new_mssql_connection();
+        let pool = connection.connect().await.unwrap();
+        let schema = connection.schema(pool).await.unwrap();
+        let table = &schema.tables["all_native_data_types"];
+
+        assert_eq!(schema.database, "mssql-connection");
+        assert_eq!(table.schema, "dbo");
+        assert_eq!(
+            table.columns[0],
+            SchemaColumn {
+                name: "id".into(),
+                r#type: "int".into(),
+                is_nullable: false,
+                is_primary_key: true,
+                comment: None,
+            }
+        );
+        assert_eq!(
+            table.columns[8],
+            SchemaColumn {
+                name: "money_col".into(),
+                r#type: "money".into(),
+                is_nullable: true,
+                is_primary_key: false,
+                comment: None,
+            }
+        );
+        assert_eq!(table.columns.len(), 28);
+    }
+}
@@ -1,23 +1,31 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, time::Duration};
 
 use arrow::datatypes::Date32Type;
 use async_trait::async_trait;
 use bigdecimal::BigDecimal;
+use bytes::Bytes;
 use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime};
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sqlparser::dialect::{Dialect, MySqlDialect};
 use sqlx::{
     mysql::{MySqlColumn, MySqlConnectOptions, MySqlRow /* , MySqlTypeInfo*/},
-    Column, ConnectOptions, MySqlConnection as SqlxMySqlConnection, Row, TypeInfo,
+    Column, ConnectOptions, Executor, MySqlConnection as SqlxMySqlConnection, Row, TypeInfo,
 };
 use uuid::Uuid;
 
 use crate::error::{Result, SharedError, Sql};
-use crate::sql::{ArrowType, Connection};
+use crate::sql::{
+    row_bytes, sqlx_stream_to_parquet, sqlx_to_parquet, ArrowType, Connection, ParquetChunks,
+    ParquetQuery, QueryHandle, QueryLimits,
+};
 use crate::{
-    convert_mysql_type,
-    sql::{DatabaseSchema, SchemaColumn, SchemaTable},
+    bind_parameters, convert_mysql_type,
+    sql::{
+        row_estimate, schema_comment, DatabaseSchema, ForeignKey, SchemaColumn, SchemaTable,
+        SqlParameter, TableKind,
+    },
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -87,18 +95,22 @@ impl Connection for MySqlConnection {
         &self,
         mut pool: Self::Conn,
         sql: &str,
+        parameters: &[SqlParameter],
         max_bytes: Option<u64>,
     ) -> Result<(Vec<Self::Row>, bool)> {
         let mut rows = vec![];
         let mut over_the_limit = false;
+        let query = bind_parameters!(sqlx::query(sql), parameters);
 
         if let Some(max_bytes) = max_bytes {
             let mut bytes = 0;
-            let mut stream = sqlx::query(sql).fetch(&mut pool);
+            let mut stream = query.fetch(&mut pool);
 
             while let Some(row) = stream.next().await {
                 let row = row.map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
-                bytes += row.len() as u64;
+                bytes += row_bytes(row.columns(), |column, index| {
+                    Self::to_arrow(&row, column, index)
+                });
 
                 if bytes > max_bytes {
                     over_the_limit = true;
@@ -108,7 +120,7 @@ impl Connection for MySqlConnection {
                 rows.push(row);
             }
         } else {
-            rows = sqlx::query(sql)
+            rows = query
                 .fetch_all(&mut pool)
                 .await
                 .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
@@ -117,35 +129,120 @@ impl Connection for MySqlConnection {
         Ok((rows, over_the_limit))
     }
 
-    async fn schema(&self, pool: Self::Conn) -> Result<DatabaseSchema> {
+    async fn query_parquet(
+        &self,
+        mut pool: Self::Conn,
+        sql: &str,
+        parameters: &[SqlParameter],
+        limits: QueryLimits,
+        chunks: ParquetChunks,
+    ) -> Result<ParquetQuery> {
+        let query = bind_parameters!(sqlx::query(sql), parameters);
+
+        sqlx_stream_to_parquet(query.fetch(&mut pool), limits, chunks, Self::to_arrow).await
+    }
+
+    async fn set_timeout(&self, pool: &mut Self::Conn, timeout: Duration) -> Result<()> {
+        // MySQL only applies max_execution_time to read-only SELECT statements
+        let sql = format!("set session max_execution_time = {}", timeout.as_millis());
+
+        sqlx::query(&sql)
+            .execute(pool)
+            .await
+            .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
+
+        Ok(())
+    }
+
+    async fn query_handle(&self, pool: &mut Self::Conn) -> Result<QueryHandle> {
+        let backend_id = sqlx::query_scalar::<_, u64>("select connection_id()")
+            .fetch_one(pool)
+            .await
+            .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
+
+        Ok(QueryHandle::Backend(backend_id as i64))
+    }
+
+    async fn cancel(&self, handle: QueryHandle) -> Result<()> {
+        let QueryHandle::Backend(backend_id) = handle else {
+            return Ok(());
+        };
+        let mut pool = self.connect().await?;
+
+        // KILL doesn't accept bind parameters
+        sqlx::query(&format!("kill query {backend_id}"))
+            .execute(&mut pool)
+            .await
+            .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
+
+        Ok(())
+    }
+
+    fn dialect(&self) -> Box<dyn Dialect> {
+        Box::new(MySqlDialect {})
+    }
+
+    /// The transaction is never committed, it ends when the connection
+    /// closes.  START TRANSACTION can't be a prepared statement, so it's sent
+    /// as text.
+    async fn set_read_only(&self, pool: &mut Self::Conn) -> Result<()> {
+        pool.execute("start transaction read only")
+            .await
+            .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
+
+        Ok(())
+    }
+
+    async fn schema(&self, mut pool: Self::Conn) -> Result<DatabaseSchema> {
         let database = self.database.as_ref().ok_or_else(|| {
             SharedError::Sql(Sql::Schema("Database name is required for MySQL".into()))
         })?;
+        let schema_error = |e: sqlx::Error| SharedError::Sql(Sql::Schema(e.to_string()));
 
-        let sql = format!("
-            select c.TABLE_SCHEMA as 'database', c.TABLE_SCHEMA as 'schema', c.TABLE_NAME as 'table', 
-                c.COLUMN_NAME as 'column_name', c.DATA_TYPE as 'column_type', c.IS_NULLABLE as 'is_nullable'
+        let sql = "
+            select c.TABLE_SCHEMA as 'schema', c.TABLE_NAME as 'table', cast(t.TABLE_TYPE as char) as 'table_type',
+                c.COLUMN_NAME as 'column_name', c.DATA_TYPE as 'column_type', c.IS_NULLABLE as 'is_nullable',
+                cast(c.COLUMN_KEY = 'PRI' as signed) as 'is_primary_key', c.COLUMN_COMMENT as 'column_comment',
+                t.TABLE_COMMENT as 'table_comment', cast(t.TABLE_ROWS as signed) as 'row_estimate'
             from INFORMATION_SCHEMA.COLUMNS as c
-            where table_schema = '{database}'
-            order by c.TABLE_NAME, c.ORDINAL_POSITION, c.COLUMN_NAME");
-
-        let (rows, _) = self.query(pool, &sql, None).await?;
+            inner join INFORMATION_SCHEMA.TABLES as t
+                on t.TABLE_SCHEMA = c.TABLE_SCHEMA and t.TABLE_NAME = c.TABLE_NAME
+            where c.TABLE_SCHEMA = ?
+            order by c.TABLE_NAME, c.ORDINAL_POSITION, c.COLUMN_NAME";
+
+        let rows = sqlx::query(sql)
+            .bind(database)
+            .fetch_all(&mut pool)
+            .await
+            .map_err(schema_error)?;
 
         let mut schema = DatabaseSchema {
-            database: self.database.to_owned().unwrap_or_default(),
+            database: database.to_owned(),
             tables: BTreeMap::new(),
         };
 
         for row in rows.into_iter() {
-            let table_name = row.get::<String, usize>(2);
+            let table_name = row.get::<String, usize>(1);
+            let kind = match row.get::<String, usize>(2).as_str() {
+                "VIEW" | "SYSTEM VIEW" => TableKind::View,
+                _ => TableKind::Table,
+            };
 
             schema
                 .tables
                 .entry(table_name.to_owned())
                 .or_insert_with(|| SchemaTable {
                     name: table_name,
-                    schema: row.get::<String, usize>(1),
+                    schema: row.get::<String, usize>(0),
+                    kind,
+                    row_estimate: row_estimate(kind, row.get::<Option<i64>, usize>(9)),
+                    // the comment of every view is "VIEW"
+                    comment: match kind {
+                        TableKind::Table => schema_comment(row.get::<Option<String>, usize>(8)),
+                        TableKind::View => None,
+                    },
                     columns: vec![],
+                    foreign_keys: vec![],
                 })
                 .columns
                 .push(SchemaColumn {
@@ -155,9 +252,38 @@ impl Connection for MySqlConnection {
                         row.get::<String, usize>(5).to_lowercase().as_str(),
                         "yes"
                     ),
+                    is_primary_key: row.get::<i64, usize>(6) == 1,
+                    comment: schema_comment(row.get::<Option<String>, usize>(7)),
                 });
         }
 
+        let sql = "
+            select TABLE_NAME, CONSTRAINT_NAME, COLUMN_NAME,
+                REFERENCED_TABLE_SCHEMA, REFERENCED_TABLE_NAME, REFERENCED_COLUMN_NAME
+            from INFORMATION_SCHEMA.KEY_COLUMN_USAGE
+            where TABLE_SCHEMA = ? and REFERENCED_TABLE_NAME is not null
+            order by TABLE_NAME, CONSTRAINT_NAME, ORDINAL_POSITION";
+
+        let rows = sqlx::query(sql)
+            .bind(database)
+            .fetch_all(&mut pool)
+            .await
+            .map_err(schema_error)?;
+
+        // each column of a key is a row
+        for row in rows.into_iter() {
+            schema.add_foreign_key(
+                &row.get::<String, usize>(0),
+                ForeignKey {
+                    name: row.get::<String, usize>(1),
+                    columns: vec![row.get::<String, usize>(2)],
+                    referenced_schema: row.get::<String, usize>(3),
+                    referenced_table: row.get::<String, usize>(4),
+                    referenced_columns: vec![row.get::<String, usize>(5)],
+                },
+            );
+        }
+
         Ok(schema)
     }
 
@@ -193,6 +319,10 @@ impl Connection for MySqlConnection {
             _ => ArrowType::Unsupported,
         }
     }
+
+    fn to_parquet(data: Vec<Self::Row>) -> Result<Bytes> {
+        sqlx_to_parquet(data, Self::to_arrow)
+    }
 }
 
 #[macro_export]
@@ -248,6 +378,7 @@ mod tests {
             .query(
                 pool.unwrap(),
                 "select * from all_native_data_types order by id limit 1",
+                &[],
                 None,
             )
             .await
@@ -331,151 +462,211 @@ mod tests {
                 name: "id".into(),
                 r#type: "int".into(),
                 is_nullable: false,
+                is_primary_key: true,
+                comment: None,
             },
             SchemaColumn {
                 name: "tinyint_col".into(),
                 r#type: "tinyint".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "smallint_col".into(),
                 r#type: "smallint".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "mediumint_col".into(),
                 r#type: "mediumint".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "int_col".into(),
                 r#type: "int".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "bigint_col".into(),
                 r#type: "bigint".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "decimal_col".into(),
                 r#type: "decimal".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "float_col".into(),
                 r#type: "float".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "double_col".into(),
                 r#type: "double".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "bit_col".into(),
                 r#type: "bit".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "char_col".into(),
                 r#type: "char".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "varchar_col".into(),
                 r#type: "varchar".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "binary_col".into(),
                 r#type: "binary".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "varbinary_col".into(),
                 r#type: "varbinary".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "tinyblob_col".into(),
                 r#type: "tinyblob".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "blob_col".into(),
                 r#type: "blob".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "mediumblob_col".into(),
                 r#type: "mediumblob".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "longblob_col".into(),
                 r#type: "longblob".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "tinytext_col".into(),
                 r#type: "tinytext".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "text_col".into(),
                 r#type: "text".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "mediumtext_col".into(),
                 r#type: "mediumtext".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "longtext_col".into(),
                 r#type: "longtext".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "enum_col".into(),
                 r#type: "enum".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "set_col".into(),
                 r#type: "set".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "date_col".into(),
                 r#type: "date".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "datetime_col".into(),
                 r#type: "datetime".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "timestamp_col".into(),
                 r#type: "timestamp".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "time_col".into(),
                 r#type: "time".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "year_col".into(),
                 r#type: "year".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
             SchemaColumn {
                 name: "json_col".into(),
                 r#type: "json".into(),
                 is_nullable: true,
+                is_primary_key: false,
+                comment: None,
             },
         ];
 
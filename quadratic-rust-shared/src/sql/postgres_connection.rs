@@ -1,23 +1,31 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, time::Duration};
 
 use arrow::datatypes::Date32Type;
 use async_trait::async_trait;
 use bigdecimal::BigDecimal;
+use bytes::Bytes;
 use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime};
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sqlparser::dialect::{Dialect, PostgreSqlDialect};
 use sqlx::{
     postgres::{types::PgTimeTz, PgColumn, PgConnectOptions, PgRow, PgTypeKind},
-    Column, ConnectOptions, PgConnection, Row, TypeInfo,
+    Column, ConnectOptions, Executor, PgConnection, Row, TypeInfo,
 };
 use uuid::Uuid;
 
 use crate::error::{Result, SharedError, Sql};
-use crate::sql::{ArrowType, Connection};
+use crate::sql::{
+    row_bytes, sqlx_stream_to_parquet, sqlx_to_parquet, ArrowType, Connection, ParquetChunks,
+    ParquetQuery, QueryHandle, QueryLimits,
+};
 use crate::{
-    convert_pg_type,
-    sql::{DatabaseSchema, SchemaColumn, SchemaTable},
+    bind_parameters, convert_pg_type,
+    sql::{
+        row_estimate, schema_comment, DatabaseSchema, ForeignKey, SchemaColumn, SchemaTable,
+        SqlParameter, TableKind,
+    },
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -87,18 +95,22 @@ impl Connection for PostgresConnection {
         &self,
         mut pool: Self::Conn,
         sql: &str,
+        parameters: &[SqlParameter],
         max_bytes: Option<u64>,
     ) -> Result<(Vec<Self::Row>, bool)> {
         let mut rows = vec![];
         let mut over_the_limit = false;
+        let query = bind_parameters!(sqlx::query(sql), parameters);
 
         if let Some(max_bytes) = max_bytes {
             let mut bytes = 0;
-            let mut stream = sqlx::query(sql).fetch(&mut pool);
+            let mut stream = query.fetch(&mut pool);
 
             while let Some(row) = stream.next().await {
                 let row = row.map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
-                bytes += row.len() as u64;
+                bytes += row_bytes(row.columns(), |column, index| {
+                    Self::to_arrow(&row, column, index)
+                });
 
                 if bytes > max_bytes {
                     over_the_limit = true;
@@ -109,7 +121,7 @@ impl Connection for PostgresConnection {
             }
             tracing::info!("Query executed with {bytes} bytes");
         } else {
-            rows = sqlx::query(sql)
+            rows = query
                 .fetch_all(&mut pool)
                 .await
                 .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
@@ -118,29 +130,115 @@ impl Connection for PostgresConnection {
         Ok((rows, over_the_limit))
     }
 
-    async fn schema(&self, pool: Self::Conn) -> Result<DatabaseSchema> {
-        let database = self.database.as_ref().ok_or_else(|| {
-            SharedError::Sql(Sql::Schema("Database name is required for MySQL".into()))
-        })?;
+    async fn query_parquet(
+        &self,
+        mut pool: Self::Conn,
+        sql: &str,
+        parameters: &[SqlParameter],
+        limits: QueryLimits,
+        chunks: ParquetChunks,
+    ) -> Result<ParquetQuery> {
+        let query = bind_parameters!(sqlx::query(sql), parameters);
+
+        sqlx_stream_to_parquet(query.fetch(&mut pool), limits, chunks, Self::to_arrow).await
+    }
 
-        let sql = format!("
-            select c.table_catalog as database, c.table_schema as schema, c.table_name as table, c.column_name, c.udt_name as column_type, c.is_nullable
-            from information_schema.tables as t inner join information_schema.columns as c on t.table_name = c.table_name
-            where t.table_type = 'BASE TABLE' 
-                and c.table_schema not in 
-                    ('pg_catalog', 'information_schema')
-                    and c.table_catalog = '{database}'
-            order by c.table_name, c.ordinal_position, c.column_name");
+    async fn set_timeout(&self, pool: &mut Self::Conn, timeout: Duration) -> Result<()> {
+        // SET doesn't accept bind parameters
+        let sql = format!("set statement_timeout = {}", timeout.as_millis());
 
-        let (rows, _) = self.query(pool, &sql, None).await?;
+        sqlx::query(&sql)
+            .execute(pool)
+            .await
+            .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
+
+        Ok(())
+    }
+
+    async fn query_handle(&self, pool: &mut Self::Conn) -> Result<QueryHandle> {
+        let backend_id = sqlx::query_scalar::<_, i32>("select pg_backend_pid()")
+            .fetch_one(pool)
+            .await
+            .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
+
+        Ok(QueryHandle::Backend(backend_id.into()))
+    }
+
+    async fn cancel(&self, handle: QueryHandle) -> Result<()> {
+        let QueryHandle::Backend(backend_id) = handle else {
+            return Ok(());
+        };
+        let mut pool = self.connect().await?;
+
+        sqlx::query("select pg_cancel_backend($1)")
+            .bind(backend_id as i32)
+            .execute(&mut pool)
+            .await
+            .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
+
+        Ok(())
+    }
+
+    fn dialect(&self) -> Box<dyn Dialect> {
+        Box::new(PostgreSqlDialect {})
+    }
+
+    /// The transaction is never committed, it ends when the connection closes
+    async fn set_read_only(&self, pool: &mut Self::Conn) -> Result<()> {
+        pool.execute("start transaction read only")
+            .await
+            .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
+
+        Ok(())
+    }
+
+    async fn schema(&self, mut pool: Self::Conn) -> Result<DatabaseSchema> {
+        let database = self.database.as_ref().ok_or_else(|| {
+            SharedError::Sql(Sql::Schema("Database name is required for Postgres".into()))
+        })?;
+        let schema_error = |e: sqlx::Error| SharedError::Sql(Sql::Schema(e.to_string()));
+
+        let sql = "
+            select c.table_schema, c.table_name, t.table_type, c.column_name, c.udt_name as column_type, c.is_nullable,
+                pk.column_name is not null as is_primary_key,
+                col_description(cls.oid, c.ordinal_position::int) as column_comment,
+                obj_description(cls.oid, 'pg_class') as table_comment,
+                cls.reltuples::bigint as row_estimate
+            from information_schema.tables as t
+            inner join information_schema.columns as c
+                on t.table_schema = c.table_schema and t.table_name = c.table_name
+            inner join pg_catalog.pg_namespace as n on n.nspname = c.table_schema
+            inner join pg_catalog.pg_class as cls on cls.relnamespace = n.oid and cls.relname = c.table_name
+            left join (
+                select kcu.table_schema, kcu.table_name, kcu.column_name
+                from information_schema.table_constraints as tc
+                inner join information_schema.key_column_usage as kcu
+                    on tc.constraint_schema = kcu.constraint_schema and tc.constraint_name = kcu.constraint_name
+                where tc.constraint_type = 'PRIMARY KEY'
+            ) as pk
+                on pk.table_schema = c.table_schema and pk.table_name = c.table_name and pk.column_name = c.column_name
+            where t.table_type in ('BASE TABLE', 'VIEW')
+                and c.table_schema not in ('pg_catalog', 'information_schema')
+                and c.table_catalog = $1
+            order by c.table_name, c.ordinal_position, c.column_name";
+
+        let rows = sqlx::query(sql)
+            .bind(database)
+            .fetch_all(&mut pool)
+            .await
+            .map_err(schema_error)?;
 
         let mut schema = DatabaseSchema {
-            database: self.database.to_owned().unwrap_or_default(),
+            database: database.to_owned(),
             tables: BTreeMap::new(),
         };
 
         for row in rows.into_iter() {
-            let table_name = row.get::<String, usize>(2);
+            let table_name = row.get::<String, usize>(1);
+            let kind = match row.get::<String, usize>(2).as_str() {
+                "VIEW" => TableKind::View,
+                _ => TableKind::Table,
+            };
 
             schema
                 .tables
@@ -148,8 +246,12 @@ impl Connection for PostgresConnection {
                 .entry(table_name.to_owned())
                 .or_insert_with(|| SchemaTable {
                     name: table_name,
-                    schema: row.get::<String, usize>(1),
+                    schema: row.get::<String, usize>(0),
+                    kind,
+                    row_estimate: row_estimate(kind, row.get::<Option<i64>, usize>(9)),
+                    comment: schema_comment(row.get::<Option<String>, usize>(8)),
                     columns: vec![],
+                    foreign_keys: vec![],
                 })
                 .columns
                 // add the column to the table
@@ -160,9 +262,54 @@ impl Connection for PostgresConnection {
                         row.get::<String, usize>(5).to_lowercase().as_str(),
                         "yes"
                     ),
+                    is_primary_key: row.get::<bool, usize>(6),
+                    comment: schema_comment(row.get::<Option<String>, usize>(7)),
                 });
         }
 
+        // a key's columns are listed in the same order as the columns they
+        // reference
+        let sql = "
+            select cls.relname as table_name, con.conname as name,
+                array(
+                    select a.attname::text
+                    from unnest(con.conkey) with ordinality as k(attnum, position)
+                    inner join pg_catalog.pg_attribute as a on a.attrelid = con.conrelid and a.attnum = k.attnum
+                    order by k.position
+                ) as columns,
+                ref_n.nspname as referenced_schema, ref_cls.relname as referenced_table,
+                array(
+                    select a.attname::text
+                    from unnest(con.confkey) with ordinality as k(attnum, position)
+                    inner join pg_catalog.pg_attribute as a on a.attrelid = con.confrelid and a.attnum = k.attnum
+                    order by k.position
+                ) as referenced_columns
+            from pg_catalog.pg_constraint as con
+            inner join pg_catalog.pg_class as cls on cls.oid = con.conrelid
+            inner join pg_catalog.pg_namespace as n on n.oid = cls.relnamespace
+            inner join pg_catalog.pg_class as ref_cls on ref_cls.oid = con.confrelid
+            inner join pg_catalog.pg_namespace as ref_n on ref_n.oid = ref_cls.relnamespace
+            where con.contype = 'f' and n.nspname not in ('pg_catalog', 'information_schema')
+            order by cls.relname, con.conname";
+
+        let rows = sqlx::query(sql)
+            .fetch_all(&mut pool)
+            .await
+            .map_err(schema_error)?;
+
+        for row in rows.into_iter() {
+            schema.add_foreign_key(
+                &row.get::<String, usize>(0),
+                ForeignKey {
+                    name: row.get::<String, usize>(1),
+                    columns: row.get::<Vec<String>, usize>(2),
+                    referenced_schema: row.get::<String, usize>(3),
+                    referenced_table: row.get::<String, usize>(4),
+                    referenced_columns: row.get::<Vec<String>, usize>(5),
+                },
+            );
+        }
+
         Ok(schema)
     }
 
@@ -233,6 +380,10 @@ impl Connection for PostgresConnection {
             }
         }
     }
+
+    fn to_parquet(data: Vec<Self::Row>) -> Result<Bytes> {
+        sqlx_to_parquet(data, Self::to_arrow)
+    }
 }
 
 #[macro_export]
@@ -271,6 +422,7 @@ mod tests {
             .query(
                 pool,
                 "select * from all_native_data_types order by id limit 1",
+                &[],
                 None,
             )
             .await
@@ -288,6 +440,68 @@ mod tests {
         // println!("{:?}", _data);
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn test_postgres_query_with_parameters() {
+        let connection = new_postgres_connection();
+        let pool = connection.connect().await.unwrap();
+        let parameters = [
+            SqlParameter::Integer(1),
+            SqlParameter::Text("'; drop table all_native_data_types; --".into()),
+        ];
+        let (rows, _) = connection
+            .query(
+                pool,
+                "select id, $2 as text from all_native_data_types where id = $1",
+                &parameters,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(
+            rows[0].get::<String, _>("text"),
+            "'; drop table all_native_data_types; --"
+        );
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_postgres_statement_timeout() {
+        let connection = new_postgres_connection();
+        let mut pool = connection.connect().await.unwrap();
+
+        assert!(matches!(
+            connection.query_handle(&mut pool).await.unwrap(),
+            QueryHandle::Backend(_)
+        ));
+
+        connection
+            .set_timeout(&mut pool, Duration::from_millis(10))
+            .await
+            .unwrap();
+        let result = connection
+            .query(pool, "select pg_sleep(1)", &[], None)
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_postgres_read_only() {
+        let connection = new_postgres_connection();
+        let mut pool = connection.connect().await.unwrap();
+
+        connection.set_read_only(&mut pool).await.unwrap();
+        let result = connection
+            .query(pool, "delete from all_native_data_types", &[], None)
+            .await;
+
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn test_postgres_schema() {
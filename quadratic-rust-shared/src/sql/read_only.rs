@@ -0,0 +1,193 @@
+//! Read-only Connections
+//!
+//! Statements on read-only connections are parsed before they're run, and
+//! only statements that read are allowed.  This rejects DDL and DML with a
+//! clear error before the database sees them.  The parser can't see every
+//! write, eg, a function with side effects, so connections are also made
+//! read-only in the database with `Connection::set_read_only`.
+
+use sqlparser::{
+    ast::{Query, SetExpr, Statement},
+    dialect::{Dialect, MySqlDialect},
+    parser::Parser,
+};
+
+use crate::error::{Result, SharedError, Sql};
+
+/// Parse `sql` and return an error unless every statement only reads.
+/// Statements that can't be parsed aren't allowed, as they can't be checked.
+pub fn check_read_only(dialect: &dyn Dialect, sql: &str) -> Result<()> {
+    normalize_read_only(dialect, sql).map(|_| ())
+}
+
+/// Check that `sql` only reads, see `check_read_only`, and return it as the
+/// parser prints it.  Statements that differ only in whitespace, comments or
+/// the case of keywords are printed the same way.
+pub fn normalize_read_only(dialect: &dyn Dialect, sql: &str) -> Result<String> {
+    let statements = Parser::parse_sql(dialect, sql)
+        .map_err(|e| read_only_error(format!("Could not parse the statement: {e}")))?;
+
+    match statements.iter().find(|statement| !is_read_only(statement)) {
+        Some(statement) => Err(read_only_error(statement.to_string())),
+        None => Ok(statements
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ")),
+    }
+}
+
+/// MySQL runs the SQL in `/*! ... */` comments, which the parser skips, so
+/// statements with them can't be normalized without changing what they do.
+pub fn has_executable_comments(dialect: &dyn Dialect, sql: &str) -> bool {
+    dialect.is::<MySqlDialect>() && sql.contains("/*!")
+}
+
+fn read_only_error(message: String) -> SharedError {
+    SharedError::Sql(Sql::ReadOnly(message))
+}
+
+/// The allowlist of statements
+fn is_read_only(statement: &Statement) -> bool {
+    match statement {
+        Statement::Query(query) => is_read_only_query(query),
+        // EXPLAIN ANALYZE runs the statement
+        Statement::Explain {
+            analyze, statement, ..
+        } => !analyze && is_read_only(statement),
+        Statement::ExplainTable { .. }
+        | Statement::ShowColumns { .. }
+        | Statement::ShowCollation { .. }
+        | Statement::ShowCreate { .. }
+        | Statement::ShowFunctions { .. }
+        | Statement::ShowStatus { .. }
+        | Statement::ShowTables { .. }
+        | Statement::ShowVariable { .. }
+        | Statement::ShowVariables { .. } => true,
+        _ => false,
+    }
+}
+
+/// Postgres allows INSERT, UPDATE and DELETE in a WITH clause, and FOR UPDATE
+/// and FOR SHARE lock the rows they read
+fn is_read_only_query(query: &Query) -> bool {
+    let read_only_ctes = match &query.with {
+        Some(with) => with
+            .cte_tables
+            .iter()
+            .all(|cte| is_read_only_query(&cte.query)),
+        None => true,
+    };
+
+    read_only_ctes && query.locks.is_empty() && is_read_only_set_expr(&query.body)
+}
+
+fn is_read_only_set_expr(set_expr: &SetExpr) -> bool {
+    match set_expr {
+        // SELECT INTO creates a table
+        SetExpr::Select(select) => select.into.is_none(),
+        SetExpr::Query(query) => is_read_only_query(query),
+        SetExpr::SetOperation { left, right, .. } => {
+            is_read_only_set_expr(left) && is_read_only_set_expr(right)
+        }
+        SetExpr::Values(_) | SetExpr::Table(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlparser::dialect::{MsSqlDialect, PostgreSqlDialect};
+
+    #[test]
+    fn allows_reads() {
+        let dialect = PostgreSqlDialect {};
+
+        for sql in [
+            "select * from users",
+            "select 1; select 2",
+            "with active as (select * from users where active) select count(*) from active",
+            "select id from users union select user_id from orders",
+            "values (1), (2)",
+            "explain select * from users",
+            "show statement_timeout",
+        ] {
+            assert_eq!(check_read_only(&dialect, sql), Ok(()), "{sql}");
+        }
+
+        let dialect = MySqlDialect {};
+
+        for sql in [
+            "select * from `users`",
+            "show tables",
+            "show columns from users",
+        ] {
+            assert_eq!(check_read_only(&dialect, sql), Ok(()), "{sql}");
+        }
+    }
+
+    #[test]
+    fn rejects_writes() {
+        let dialect = PostgreSqlDialect {};
+
+        for sql in [
+            "insert into users (name) values ('Ada')",
+            "update users set name = 'Ada'",
+            "delete from users",
+            "drop table users",
+            "create table copy (id int)",
+            "truncate users",
+            "grant select on users to analyst",
+            "set transaction read write",
+            "commit",
+            "select 1; delete from users",
+            "with deleted as (delete from users returning *) select * from deleted",
+            "select * into copy from users",
+            "select * from users for update",
+            "explain analyze delete from users",
+            "copy users to '/tmp/users.csv'",
+            "not sql",
+        ] {
+            assert!(
+                matches!(
+                    check_read_only(&dialect, sql),
+                    Err(SharedError::Sql(Sql::ReadOnly(_)))
+                ),
+                "{sql}"
+            );
+        }
+
+        let dialect = MsSqlDialect {};
+        assert!(check_read_only(&dialect, "select * into #copy from users").is_err());
+    }
+
+    #[test]
+    fn normalizes_reads() {
+        let dialect = PostgreSqlDialect {};
+        let normalized = normalize_read_only(&dialect, "select *\n  from users -- all\n;");
+
+        assert_eq!(normalized, Ok("SELECT * FROM users".into()));
+        assert_eq!(
+            normalize_read_only(&dialect, "SELECT * FROM users"),
+            normalized
+        );
+        assert_eq!(
+            normalize_read_only(&dialect, "select 1;select 2"),
+            Ok("SELECT 1; SELECT 2".into())
+        );
+        assert!(normalize_read_only(&dialect, "delete from users").is_err());
+    }
+
+    #[test]
+    fn finds_executable_comments() {
+        let sql = "select 1 /*! , sleep(10) */";
+
+        assert!(has_executable_comments(&MySqlDialect {}, sql));
+        assert!(!has_executable_comments(
+            &MySqlDialect {},
+            "select 1 /* comment */"
+        ));
+        assert!(!has_executable_comments(&PostgreSqlDialect {}, sql));
+    }
+}
@@ -0,0 +1,545 @@
+use std::{
+    collections::BTreeMap,
+    ptr::NonNull,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use arrow::datatypes::Date32Type;
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use futures_util::StreamExt;
+use libsqlite3_sys::{sqlite3, sqlite3_interrupt, sqlite3_limit, SQLITE_LIMIT_ATTACHED};
+use serde::{Deserialize, Serialize};
+use sqlparser::dialect::{Dialect, SQLiteDialect};
+use sqlx::{
+    sqlite::{SqliteColumn, SqliteConnectOptions, SqliteRow},
+    Column, ConnectOptions, Executor, Row, SqliteConnection as SqlxSqliteConnection, TypeInfo,
+    ValueRef,
+};
+
+use crate::error::{Result, SharedError, Sql};
+use crate::sql::{
+    row_bytes, sqlx_stream_to_parquet, sqlx_to_parquet, ArrowType, Connection, ParquetChunks,
+    ParquetQuery, QueryHandle, QueryLimits,
+};
+use crate::{
+    bind_parameters, convert_sqlite_type,
+    sql::{DatabaseSchema, ForeignKey, SchemaColumn, SchemaTable, SqlParameter, TableKind},
+};
+
+/// A SQLite database file.  The file must exist, it is never created.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SqliteConnection {
+    pub database: String,
+}
+
+impl SqliteConnection {
+    pub fn new(database: String) -> SqliteConnection {
+        SqliteConnection { database }
+    }
+}
+
+/// A connection's raw handle, which `sqlite3_interrupt` can be called with
+/// from any thread while the connection is open
+struct SqliteInterrupt(NonNull<sqlite3>);
+
+// SAFETY: `sqlite3_interrupt` is the only call made with the handle, and it's
+// safe to call from other threads
+unsafe impl Send for SqliteInterrupt {}
+unsafe impl Sync for SqliteInterrupt {}
+
+impl SqliteInterrupt {
+    fn interrupt(&self) {
+        // SAFETY: `QueryHandle::Interrupt` is only called while the connection
+        // is open
+        unsafe { sqlite3_interrupt(self.0.as_ptr()) }
+    }
+}
+
+#[async_trait]
+impl Connection for SqliteConnection {
+    type Conn = SqlxSqliteConnection;
+    type Row = SqliteRow;
+    type Column = SqliteColumn;
+
+    async fn connect(&self) -> Result<Self::Conn> {
+        let connect_error =
+            |e: String| SharedError::Sql(Sql::Connect(format!("{:?}: {e}", self.database)));
+
+        let mut pool = SqliteConnectOptions::new()
+            .filename(&self.database)
+            .connect()
+            .await
+            .map_err(|e| connect_error(e.to_string()))?;
+
+        // no other databases can be attached, so queries can't read or create
+        // other files with `ATTACH`
+        let mut handle = pool
+            .lock_handle()
+            .await
+            .map_err(|e| connect_error(e.to_string()))?;
+
+        // SAFETY: the handle is locked and open for the duration of the call
+        unsafe {
+            sqlite3_limit(handle.as_raw_handle().as_ptr(), SQLITE_LIMIT_ATTACHED, 0);
+        }
+
+        drop(handle);
+
+        Ok(pool)
+    }
+
+    async fn query(
+        &self,
+        mut pool: Self::Conn,
+        sql: &str,
+        parameters: &[SqlParameter],
+        max_bytes: Option<u64>,
+    ) -> Result<(Vec<Self::Row>, bool)> {
+        let mut rows = vec![];
+        let mut over_the_limit = false;
+        let query = bind_parameters!(sqlx::query(sql), parameters);
+
+        if let Some(max_bytes) = max_bytes {
+            let mut bytes = 0;
+            let mut stream = query.fetch(&mut pool);
+
+            while let Some(row) = stream.next().await {
+                let row = row.map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
+                bytes += row_bytes(row.columns(), |column, index| {
+                    Self::to_arrow(&row, column, index)
+                });
+
+                if bytes > max_bytes {
+                    over_the_limit = true;
+                    break;
+                }
+
+                rows.push(row);
+            }
+        } else {
+            rows = query
+                .fetch_all(&mut pool)
+                .await
+                .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
+        }
+
+        Ok((rows, over_the_limit))
+    }
+
+    async fn query_parquet(
+        &self,
+        mut pool: Self::Conn,
+        sql: &str,
+        parameters: &[SqlParameter],
+        limits: QueryLimits,
+        chunks: ParquetChunks,
+    ) -> Result<ParquetQuery> {
+        let query = bind_parameters!(sqlx::query(sql), parameters);
+
+        sqlx_stream_to_parquet(query.fetch(&mut pool), limits, chunks, Self::to_arrow).await
+    }
+
+    /// SQLite doesn't have a statement timeout, so a progress handler
+    /// interrupts statements that are still running after `timeout`
+    async fn set_timeout(&self, pool: &mut Self::Conn, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let mut handle = pool
+            .lock_handle()
+            .await
+            .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
+
+        // the handler runs every 1,000 virtual machine instructions
+        handle.set_progress_handler(1_000, move || Instant::now() < deadline);
+
+        Ok(())
+    }
+
+    async fn query_handle(&self, pool: &mut Self::Conn) -> Result<QueryHandle> {
+        let mut handle = pool
+            .lock_handle()
+            .await
+            .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
+        let interrupt = SqliteInterrupt(handle.as_raw_handle());
+
+        Ok(QueryHandle::Interrupt(Arc::new(move || {
+            interrupt.interrupt()
+        })))
+    }
+
+    fn dialect(&self) -> Box<dyn Dialect> {
+        Box::new(SQLiteDialect {})
+    }
+
+    /// SQLite doesn't have read-only transactions, but `query_only` rejects
+    /// any statement that changes the database file
+    async fn set_read_only(&self, pool: &mut Self::Conn) -> Result<()> {
+        pool.execute("pragma query_only = true")
+            .await
+            .map_err(|e| SharedError::Sql(Sql::Query(e.to_string())))?;
+
+        Ok(())
+    }
+
+    /// SQLite doesn't keep row estimates or comments
+    async fn schema(&self, mut pool: Self::Conn) -> Result<DatabaseSchema> {
+        let schema_error = |e: sqlx::Error| SharedError::Sql(Sql::Schema(e.to_string()));
+
+        let sql = "
+            select m.name as 'table', m.type as table_type, p.name as column_name, p.type as column_type,
+                p.\"notnull\" as not_null, p.pk > 0 as is_primary_key
+            from sqlite_master as m inner join pragma_table_info(m.name) as p
+            where m.type in ('table', 'view') and m.name not like 'sqlite_%'
+            order by m.name, p.cid";
+
+        let rows = sqlx::query(sql)
+            .fetch_all(&mut pool)
+            .await
+            .map_err(schema_error)?;
+
+        let mut schema = DatabaseSchema {
+            database: self.database.to_owned(),
+            tables: BTreeMap::new(),
+        };
+
+        for row in rows.into_iter() {
+            let table_name = row.get::<String, usize>(0);
+            let kind = match row.get::<String, usize>(1).as_str() {
+                "view" => TableKind::View,
+                _ => TableKind::Table,
+            };
+
+            schema
+                .tables
+                // get or insert the table
+                .entry(table_name.to_owned())
+                .or_insert_with(|| SchemaTable {
+                    name: table_name,
+                    schema: "main".into(),
+                    kind,
+                    ..Default::default()
+                })
+                .columns
+                // add the column to the table
+                .push(SchemaColumn {
+                    name: row.get::<String, usize>(2),
+                    r#type: row.get::<String, usize>(3),
+                    is_nullable: !row.get::<bool, usize>(4),
+                    is_primary_key: row.get::<bool, usize>(5),
+                    comment: None,
+                });
+        }
+
+        // foreign keys aren't named, so they're named after their table and
+        // their position in it.  A key that references the primary key of
+        // its table doesn't list the referenced column.
+        let sql = "
+            select m.name as 'table', f.id, f.\"table\" as referenced_table, f.\"from\", f.\"to\"
+            from sqlite_master as m inner join pragma_foreign_key_list(m.name) as f
+            where m.type = 'table' and m.name not like 'sqlite_%'
+            order by m.name, f.id, f.seq";
+
+        let rows = sqlx::query(sql)
+            .fetch_all(&mut pool)
+            .await
+            .map_err(schema_error)?;
+
+        for row in rows.into_iter() {
+            let table_name = row.get::<String, usize>(0);
+
+            schema.add_foreign_key(
+                &table_name,
+                ForeignKey {
+                    name: format!("{table_name}_fkey_{}", row.get::<i64, usize>(1)),
+                    columns: vec![row.get::<String, usize>(3)],
+                    referenced_schema: "main".into(),
+                    referenced_table: row.get::<String, usize>(2),
+                    referenced_columns: vec![row
+                        .get::<Option<String>, usize>(4)
+                        .unwrap_or_default()],
+                },
+            );
+        }
+
+        Ok(schema)
+    }
+
+    fn to_arrow(row: &Self::Row, column: &Self::Column, index: usize) -> ArrowType {
+        // expressions don't have a declared type, so use the type of the value
+        let type_name = match column.type_info().name() {
+            "NULL" => row
+                .try_get_raw(index)
+                .map(|value| value.type_info().name().to_string())
+                .unwrap_or_default(),
+            name => name.to_string(),
+        };
+
+        match type_name.as_str() {
+            "TEXT" => ArrowType::Utf8(convert_sqlite_type!(String, row, index)),
+            "INTEGER" => ArrowType::Int64(convert_sqlite_type!(i64, row, index)),
+            "REAL" | "NUMERIC" => ArrowType::Float64(convert_sqlite_type!(f64, row, index)),
+            "BOOLEAN" => ArrowType::Boolean(convert_sqlite_type!(bool, row, index)),
+            "DATE" => {
+                let naive_date = convert_sqlite_type!(NaiveDate, row, index);
+                ArrowType::Date32(Date32Type::from_naive_date(naive_date))
+            }
+            "TIME" => ArrowType::Time32(convert_sqlite_type!(NaiveTime, row, index)),
+            "DATETIME" => ArrowType::Timestamp(convert_sqlite_type!(NaiveDateTime, row, index)),
+            "NULL" => ArrowType::Void,
+            // try to convert others to a string
+            _ => ArrowType::Unsupported,
+        }
+    }
+
+    fn to_parquet(data: Vec<Self::Row>) -> Result<Bytes> {
+        sqlx_to_parquet(data, Self::to_arrow)
+    }
+}
+
+#[macro_export]
+macro_rules! convert_sqlite_type {
+    ( $kind:ty, $row:ident, $index:ident ) => {{
+        $row.try_get::<$kind, usize>($index)
+            .ok()
+            .unwrap_or_default()
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use tracing_test::traced_test;
+    use uuid::Uuid;
+
+    /// Create a database file with a table of each of SQLite's types.
+    async fn new_sqlite_connection() -> SqliteConnection {
+        let path = std::env::temp_dir().join(format!("quadratic-sqlite-{}.db", Uuid::new_v4()));
+        let mut pool = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true)
+            .connect()
+            .await
+            .unwrap();
+
+        pool.execute(
+            "create table all_native_data_types (
+                id integer primary key not null,
+                text_col text,
+                real_col real,
+                boolean_col boolean,
+                date_col date,
+                time_col time,
+                datetime_col datetime
+            );
+            insert into all_native_data_types values
+                (1, 'Ada', 1.5, true, '2024-01-02', '03:04:05', '2024-01-02 03:04:05');",
+        )
+        .await
+        .unwrap();
+
+        SqliteConnection::new(path.to_string_lossy().to_string())
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_sqlite_connection() {
+        let connection = new_sqlite_connection().await;
+
+        assert!(connection.connect().await.is_ok());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_sqlite_blocks_attach() {
+        let connection = new_sqlite_connection().await;
+        let other = new_sqlite_connection().await;
+        let sql = format!("attach database '{}' as other", other.database);
+
+        let pool = connection.connect().await.unwrap();
+        let result = connection.query(pool, &sql, &[], None).await;
+
+        assert!(matches!(result, Err(error) if error.to_string().contains("too many attached")));
+    }
+
+    /// Counts forever, until it's interrupted
+    const ENDLESS_QUERY: &str = "with recursive numbers(n) as (select 1 union all
+        select n + 1 from numbers) select count(*) from numbers";
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_sqlite_timeout() {
+        let connection = new_sqlite_connection().await;
+        let mut pool = connection.connect().await.unwrap();
+        connection
+            .set_timeout(&mut pool, Duration::from_millis(10))
+            .await
+            .unwrap();
+        let result = connection.query(pool, ENDLESS_QUERY, &[], None).await;
+
+        assert!(matches!(result, Err(error) if error.to_string().contains("interrupted")));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_sqlite_cancel() {
+        let connection = Arc::new(new_sqlite_connection().await);
+        let mut pool = connection.connect().await.unwrap();
+        let handle = connection.query_handle(&mut pool).await.unwrap();
+
+        // the query holds the connection open until it's interrupted
+        let running = tokio::spawn({
+            let connection = Arc::clone(&connection);
+            async move { connection.query(pool, ENDLESS_QUERY, &[], None).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        connection.cancel(handle).await.unwrap();
+
+        let result = running.await.unwrap();
+        assert!(matches!(result, Err(error) if error.to_string().contains("interrupted")));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_sqlite_connection_missing_file() {
+        let connection = SqliteConnection::new("/does/not/exist.db".into());
+
+        assert!(connection.connect().await.is_err());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_sqlite_query_to_arrow() {
+        let connection = new_sqlite_connection().await;
+        let pool = connection.connect().await.unwrap();
+        let (rows, over_the_limit) = connection
+            .query(
+                pool,
+                "select *, id * 2 as expression_col from all_native_data_types where id = ?",
+                &[SqlParameter::Integer(1)],
+                None,
+            )
+            .await
+            .unwrap();
+
+        let row = &rows[0];
+        let columns = row.columns();
+        let to_arrow = |index: usize| SqliteConnection::to_arrow(row, &columns[index], index);
+
+        assert!(!over_the_limit);
+        assert_eq!(to_arrow(0), ArrowType::Int64(1));
+        assert_eq!(to_arrow(1), ArrowType::Utf8("Ada".into()));
+        assert_eq!(to_arrow(2), ArrowType::Float64(1.5));
+        assert_eq!(to_arrow(3), ArrowType::Boolean(true));
+        assert_eq!(
+            to_arrow(4),
+            ArrowType::Date32(Date32Type::from_naive_date(
+                NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()
+            ))
+        );
+        assert_eq!(
+            to_arrow(5),
+            ArrowType::Time32(NaiveTime::from_hms_opt(3, 4, 5).unwrap())
+        );
+        assert_eq!(
+            to_arrow(6),
+            ArrowType::Timestamp(
+                NaiveDate::from_ymd_opt(2024, 1, 2)
+                    .unwrap()
+                    .and_hms_opt(3, 4, 5)
+                    .unwrap()
+            )
+        );
+        assert_eq!(to_arrow(7), ArrowType::Int64(2));
+
+        let parquet = SqliteConnection::to_parquet(rows).unwrap();
+        assert!(!parquet.is_empty());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_sqlite_read_only() {
+        let connection = new_sqlite_connection().await;
+        let mut pool = connection.connect().await.unwrap();
+
+        connection.set_read_only(&mut pool).await.unwrap();
+        let result = connection
+            .query(pool, "delete from all_native_data_types", &[], None)
+            .await;
+
+        assert!(result.is_err());
+
+        let pool = connection.connect().await.unwrap();
+        let (rows, _) = connection
+            .query(pool, "select * from all_native_data_types", &[], None)
+            .await
+            .unwrap();
+
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_sqlite_schema() {
+        let connection = new_sqlite_connection().await;
+        let pool = connection.connect().await.unwrap();
+        let schema = connection.schema(pool).await.unwrap();
+        let table = &schema.tables["all_native_data_types"];
+
+        assert_eq!(table.schema, "main");
+        assert_eq!(
+            table.columns[0],
+            SchemaColumn {
+                name: "id".into(),
+                r#type: "INTEGER".into(),
+                is_nullable: false,
+                is_primary_key: true,
+                comment: None,
+            }
+        );
+        assert_eq!(
+            table.columns[1],
+            SchemaColumn {
+                name: "text_col".into(),
+                r#type: "TEXT".into(),
+                is_nullable: true,
+                is_primary_key: false,
+                comment: None,
+            }
+        );
+        assert_eq!(table.columns.len(), 7);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_sqlite_schema_keys_and_views() {
+        let connection = new_sqlite_connection().await;
+        let mut pool = connection.connect().await.unwrap();
+        pool.execute(
+            "create table notes (
+                id integer primary key not null,
+                data_type_id integer references all_native_data_types (id)
+            );
+            create view texts as select id, text_col from all_native_data_types;",
+        )
+        .await
+        .unwrap();
+        let schema = connection.schema(pool).await.unwrap();
+
+        assert_eq!(
+            schema.tables["notes"].foreign_keys,
+            vec![ForeignKey {
+                name: "notes_fkey_0".into(),
+                columns: vec!["data_type_id".into()],
+                referenced_schema: "main".into(),
+                referenced_table: "all_native_data_types".into(),
+                referenced_columns: vec!["id".into()],
+            }]
+        );
+        assert_eq!(schema.tables["texts"].kind, TableKind::View);
+        assert_eq!(schema.tables["texts"].columns.len(), 2);
+        assert_eq!(schema.tables["notes"].kind, TableKind::Table);
+    }
+}
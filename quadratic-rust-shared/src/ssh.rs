@@ -0,0 +1,262 @@
+//! SSH Tunnels
+//!
+//! Forward a local port through an SSH bastion to a host that is only
+//! reachable from the bastion, eg, a database in a private network.
+
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use russh::client::{self, Handle};
+use russh_keys::{decode_secret_key, key::PublicKey};
+use serde::{Deserialize, Serialize};
+use tokio::{io::copy_bidirectional, net::TcpListener, task::JoinHandle};
+
+use crate::error::{Result, SharedError};
+
+const LOCALHOST: &str = "127.0.0.1";
+const DEFAULT_SSH_PORT: u16 = 22;
+
+/// How often an idle session is checked, so a tunnel to a bastion that went
+/// away is closed and reopened
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The bastion to tunnel through, as stored in a connection's details.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct SshConfig {
+    pub host: String,
+    pub port: Option<String>,
+    pub username: String,
+    /// An OpenSSH or PEM encoded private key without a passphrase
+    pub private_key: String,
+    /// The SHA256 fingerprint of the bastion's host key, as printed by
+    /// `ssh-keygen -l`, eg, `SHA256:KoC1jnWu...`
+    pub host_key_fingerprint: String,
+}
+
+impl SshConfig {
+    fn port(&self) -> Result<u16> {
+        self.port
+            .as_ref()
+            .map_or(Ok(DEFAULT_SSH_PORT), |port| port.parse::<u16>())
+            .map_err(|_| SharedError::Ssh("Could not parse SSH port into a number".into()))
+    }
+}
+
+/// Only connects to a bastion whose host key has the configured fingerprint
+struct Client {
+    host_key_fingerprint: String,
+}
+
+#[async_trait]
+impl client::Handler for Client {
+    type Error = russh::Error;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &PublicKey,
+    ) -> std::result::Result<bool, Self::Error> {
+        let expected = self.host_key_fingerprint.trim();
+        let expected = expected.strip_prefix("SHA256:").unwrap_or(expected);
+        let matches = server_public_key.fingerprint() == expected.trim_end_matches('=');
+
+        if !matches {
+            tracing::warn!("SSH host key fingerprint doesn't match {expected}");
+        }
+
+        Ok(matches)
+    }
+}
+
+/// A local port forwarded through a bastion to `remote_host:remote_port`.
+/// Each connection to the local port opens a channel in the same SSH session.
+/// The tunnel is closed when it's dropped.
+pub struct SshTunnel {
+    session: Arc<Handle<Client>>,
+    local_port: u16,
+    listener: JoinHandle<()>,
+}
+
+impl SshTunnel {
+    /// Connect to the bastion and forward a random local port to
+    /// `remote_host:remote_port`.
+    pub async fn open(config: &SshConfig, remote_host: &str, remote_port: u16) -> Result<Self> {
+        let key = decode_secret_key(&config.private_key, None)?;
+        let client_config = client::Config {
+            keepalive_interval: Some(KEEPALIVE_INTERVAL),
+            ..Default::default()
+        };
+        let mut session = client::connect(
+            Arc::new(client_config),
+            (config.host.as_str(), config.port()?),
+            Client {
+                host_key_fingerprint: config.host_key_fingerprint.to_owned(),
+            },
+        )
+        .await?;
+
+        if !session
+            .authenticate_publickey(&config.username, Arc::new(key))
+            .await?
+        {
+            return Err(SharedError::Ssh(format!(
+                "Could not authenticate as {} on {}",
+                config.username, config.host
+            )));
+        }
+
+        let session = Arc::new(session);
+        let listener = TcpListener::bind((LOCALHOST, 0))
+            .await
+            .map_err(|e| SharedError::Ssh(e.to_string()))?;
+        let local_port = listener
+            .local_addr()
+            .map_err(|e| SharedError::Ssh(e.to_string()))?
+            .port();
+
+        let listener = tokio::spawn(forward(
+            listener,
+            Arc::clone(&session),
+            remote_host.to_owned(),
+            remote_port,
+        ));
+
+        tracing::info!(
+            "Opened SSH tunnel from port {local_port} through {} to {remote_host}:{remote_port}",
+            config.host
+        );
+
+        Ok(SshTunnel {
+            session,
+            local_port,
+            listener,
+        })
+    }
+
+    /// The port on localhost that is forwarded to the remote host
+    pub fn local_port(&self) -> u16 {
+        self.local_port
+    }
+
+    /// A closed tunnel can't forward new connections and should be reopened
+    pub fn is_closed(&self) -> bool {
+        self.session.is_closed() || self.listener.is_finished()
+    }
+}
+
+impl Drop for SshTunnel {
+    fn drop(&mut self) {
+        self.listener.abort();
+    }
+}
+
+/// Accept connections to the local port and copy each one to and from a
+/// channel to the remote host.
+async fn forward(
+    listener: TcpListener,
+    session: Arc<Handle<Client>>,
+    remote_host: String,
+    remote_port: u16,
+) {
+    loop {
+        let (mut socket, address) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                tracing::warn!("Error accepting SSH tunnel connection: {error}");
+                break;
+            }
+        };
+
+        let session = Arc::clone(&session);
+        let remote_host = remote_host.clone();
+
+        tokio::spawn(async move {
+            let channel = session
+                .channel_open_direct_tcpip(
+                    remote_host,
+                    remote_port as u32,
+                    address.ip().to_string(),
+                    address.port() as u32,
+                )
+                .await;
+
+            let mut stream = match channel {
+                Ok(channel) => channel.into_stream(),
+                Err(error) => {
+                    tracing::warn!("Error opening SSH tunnel channel: {error}");
+                    return;
+                }
+            };
+
+            if let Err(error) = copy_bidirectional(&mut socket, &mut stream).await {
+                tracing::trace!("SSH tunnel connection closed: {error}");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::{postgres_connection::PostgresConnection, Connection};
+    use tracing_test::traced_test;
+
+    /// The bastion in docker-compose.yml, which can reach the other databases
+    /// by their service names
+    fn new_ssh_config() -> SshConfig {
+        SshConfig {
+            host: "0.0.0.0".into(),
+            port: Some("2222".into()),
+            username: "user".into(),
+            private_key: include_str!("../../docker/ssh-connection/keys/id_ed25519").into(),
+            host_key_fingerprint: "SHA256:KoC1jnWuWCz5sCcjAY0LAD3Go5KdTIg42xAo3YVpUGA".into(),
+        }
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_ssh_tunnel() {
+        let tunnel = SshTunnel::open(&new_ssh_config(), "postgres-connection", 5432)
+            .await
+            .unwrap();
+        assert!(!tunnel.is_closed());
+
+        let connection = PostgresConnection::new(
+            Some("user".into()),
+            Some("password".into()),
+            LOCALHOST.into(),
+            Some(tunnel.local_port().to_string()),
+            Some("postgres-connection".into()),
+        );
+        let pool = connection.connect().await.unwrap();
+        let (rows, _) = connection.query(pool, "select 1", &[], None).await.unwrap();
+
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_ssh_tunnel_with_another_host_key() {
+        let config = SshConfig {
+            host_key_fingerprint: "SHA256:47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU".into(),
+            ..new_ssh_config()
+        };
+
+        assert!(SshTunnel::open(&config, "postgres-connection", 5432)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_ssh_tunnel_with_an_invalid_key() {
+        let config = SshConfig {
+            private_key: "not a key".into(),
+            ..new_ssh_config()
+        };
+
+        assert!(SshTunnel::open(&config, "postgres-connection", 5432)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tokio::fs;
+use uuid::Uuid;
+
+use crate::error::{Result, SharedError};
+use crate::storage::{ObjectSummary, Storage};
+
+#[derive(Debug, Clone)]
+pub struct FileSystemConfig {
+    pub path: String,
+}
+
+/// Files are written here first, so they can't be mistaken for objects
+const TEMP_DIR: &str = ".tmp";
+
+/// Stores objects as files in a local directory.  Keys are file names, so
+/// they can't contain directories.
+#[derive(Debug)]
+pub struct FileSystem {
+    pub path: String,
+}
+
+impl FileSystem {
+    pub fn new(config: FileSystemConfig) -> Self {
+        FileSystem { path: config.path }
+    }
+
+    /// Get the path of the file for a key, rejecting keys that would escape
+    /// the directory.
+    fn file_path(&self, key: &str) -> Result<PathBuf> {
+        let mut components = Path::new(key).components();
+
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) if key != TEMP_DIR => {
+                Ok(Path::new(&self.path).join(key))
+            }
+            _ => Err(SharedError::Storage(format!("Invalid key {key}"))),
+        }
+    }
+}
+
+fn storage_error(action: &str, key: &str, error: impl ToString) -> SharedError {
+    SharedError::Storage(format!("Error {action} {key}: {}", error.to_string()))
+}
+
+#[async_trait]
+impl Storage for FileSystem {
+    async fn read(&self, key: &str) -> Result<Bytes> {
+        let data = fs::read(self.file_path(key)?)
+            .await
+            .map_err(|e| storage_error("reading", key, e))?;
+
+        Ok(Bytes::from(data))
+    }
+
+    /// Write to a uniquely named temporary file and then rename it, so that
+    /// readers never see a partially written file, even with concurrent
+    /// writers.
+    async fn write(&self, key: &str, data: &[u8]) -> Result<()> {
+        let path = self.file_path(key)?;
+        let temp_dir = Path::new(&self.path).join(TEMP_DIR);
+        let temp_path = temp_dir.join(Uuid::new_v4().to_string());
+
+        fs::create_dir_all(&temp_dir)
+            .await
+            .map_err(|e| storage_error("creating the directory for", key, e))?;
+        fs::write(&temp_path, data)
+            .await
+            .map_err(|e| storage_error("writing", key, e))?;
+        if let Err(e) = fs::rename(&temp_path, &path).await {
+            fs::remove_file(&temp_path).await.ok();
+            return Err(storage_error("writing", key, e));
+        }
+
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectSummary>> {
+        let mut objects = vec![];
+        let mut entries = match fs::read_dir(&self.path).await {
+            Ok(entries) => entries,
+            // nothing has been written yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(objects),
+            Err(e) => return Err(storage_error("listing", prefix, e)),
+        };
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| storage_error("listing", prefix, e))?
+        {
+            let Ok(key) = entry.file_name().into_string() else {
+                continue;
+            };
+
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+
+            // skips the temporary directory
+            if !key.starts_with(prefix) || !metadata.is_file() {
+                continue;
+            }
+
+            let last_modified = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_millis() as i64);
+
+            objects.push(ObjectSummary { key, last_modified });
+        }
+
+        Ok(objects)
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::storage::tests::reads_writes_and_lists;
+
+    fn file_system() -> FileSystem {
+        let path = std::env::temp_dir().join(format!("quadratic-storage-{}", Uuid::new_v4()));
+
+        FileSystem::new(FileSystemConfig {
+            path: path.to_string_lossy().to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn file_system_storage() {
+        let storage = file_system();
+        assert_eq!(storage.list("file-").await.unwrap(), vec![]);
+
+        reads_writes_and_lists(&storage).await;
+
+        // keys can end in the temporary files' old extension
+        storage.write("file-2.tmp", b"two").await.unwrap();
+        let keys = storage
+            .list("file-2")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|object| object.key)
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec!["file-2.tmp"]);
+
+        // concurrent writes to the same key don't share a temporary file
+        let (first, second) = tokio::join!(
+            storage.write("file-3.grid", b"first"),
+            storage.write("file-3.grid", b"second")
+        );
+        first.unwrap();
+        second.unwrap();
+        let data = storage.read("file-3.grid").await.unwrap();
+        assert!(data == "first" || data == "second");
+
+        fs::remove_dir_all(storage.path()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn file_system_storage_rejects_paths() {
+        let storage = file_system();
+
+        assert!(storage.write("../escape.grid", b"data").await.is_err());
+        assert!(storage.write("/etc/escape.grid", b"data").await.is_err());
+        assert!(storage.read("nested/file.grid").await.is_err());
+        assert!(storage.write(TEMP_DIR, b"data").await.is_err());
+    }
+}
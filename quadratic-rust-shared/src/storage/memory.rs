@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::error::{Result, SharedError};
+use crate::storage::{ObjectSummary, Storage};
+
+/// Stores objects in memory.  Useful for tests, as nothing is persisted.
+#[derive(Debug, Default)]
+pub struct Memory {
+    objects: Mutex<HashMap<String, (Bytes, i64)>>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Memory::default()
+    }
+
+    fn lock_error(error: impl ToString) -> SharedError {
+        SharedError::Storage(format!(
+            "Error locking memory storage: {}",
+            error.to_string()
+        ))
+    }
+}
+
+#[async_trait]
+impl Storage for Memory {
+    async fn read(&self, key: &str) -> Result<Bytes> {
+        self.objects
+            .lock()
+            .map_err(Self::lock_error)?
+            .get(key)
+            .map(|(data, _)| data.to_owned())
+            .ok_or_else(|| SharedError::Storage(format!("Error reading {key}: not found")))
+    }
+
+    async fn write(&self, key: &str, data: &[u8]) -> Result<()> {
+        let last_modified = Utc::now().timestamp_millis();
+
+        self.objects.lock().map_err(Self::lock_error)?.insert(
+            key.to_owned(),
+            (Bytes::copy_from_slice(data), last_modified),
+        );
+
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectSummary>> {
+        let objects = self
+            .objects
+            .lock()
+            .map_err(Self::lock_error)?
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, (_, last_modified))| ObjectSummary {
+                key: key.to_owned(),
+                last_modified: Some(*last_modified),
+            })
+            .collect();
+
+        Ok(objects)
+    }
+
+    fn path(&self) -> &str {
+        "memory"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::reads_writes_and_lists;
+
+    #[tokio::test]
+    async fn memory_storage() {
+        reads_writes_and_lists(&Memory::new()).await;
+    }
+}
@@ -0,0 +1,124 @@
+//! Storage
+//!
+//! A key/value store for files, such as .grid checkpoints.  S3 is used in
+//! deployed environments.  The file system and memory implementations allow
+//! self-hosting and development without a bucket.
+
+pub mod file_system;
+pub mod memory;
+pub mod s3;
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use serde::Deserialize;
+use std::fmt::Debug;
+use std::time::Duration;
+
+use crate::error::Result;
+use crate::storage::file_system::{FileSystem, FileSystemConfig};
+use crate::storage::memory::Memory;
+use crate::storage::s3::{S3Config, S3};
+
+/// An object in storage.  `last_modified` is in milliseconds since the epoch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectSummary {
+    pub key: String,
+    pub last_modified: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StorageType {
+    #[default]
+    S3,
+    FileSystem,
+    Memory,
+}
+
+#[derive(Debug, Clone)]
+pub enum StorageConfig {
+    S3(S3Config),
+    FileSystem(FileSystemConfig),
+    Memory,
+}
+
+#[async_trait]
+pub trait Storage: Debug + Send + Sync {
+    /// Read the object at `key`.
+    async fn read(&self, key: &str) -> Result<Bytes>;
+
+    /// Write `data` to `key`, replacing any existing object.
+    async fn write(&self, key: &str, data: &[u8]) -> Result<()>;
+
+    /// List all objects whose keys start with `prefix`.
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectSummary>>;
+
+    /// A url that downloads the object at `key` without credentials until it
+    /// expires.  Storage that can't be reached by clients inlines the object
+    /// in a data url.
+    async fn download_url(&self, key: &str, _expires_in: Duration) -> Result<String> {
+        let data = self.read(key).await?;
+
+        Ok(format!(
+            "data:application/octet-stream;base64,{}",
+            STANDARD.encode(data)
+        ))
+    }
+
+    /// The bucket or directory that objects are stored in.
+    fn path(&self) -> &str;
+}
+
+pub type StorageContainer = Box<dyn Storage>;
+
+/// Create the storage for the config.
+pub async fn storage(config: StorageConfig) -> StorageContainer {
+    match config {
+        StorageConfig::S3(config) => Box::new(S3::new(config).await),
+        StorageConfig::FileSystem(config) => Box::new(FileSystem::new(config)),
+        StorageConfig::Memory => Box::new(Memory::new()),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Exercise a storage implementation.
+    pub(crate) async fn reads_writes_and_lists(storage: &dyn Storage) {
+        storage.write("file-1.grid", b"one").await.unwrap();
+        storage.write("file-12.grid", b"twelve").await.unwrap();
+        storage.write("other-1.grid", b"other").await.unwrap();
+        storage.write("file-1.grid", b"uno").await.unwrap();
+
+        assert_eq!(storage.read("file-1.grid").await.unwrap(), "uno");
+        assert!(storage.read("missing.grid").await.is_err());
+
+        let mut keys = storage
+            .list("file-")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|object| object.key)
+            .collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, vec!["file-1.grid", "file-12.grid"]);
+    }
+
+    #[tokio::test]
+    async fn inlines_download_urls() {
+        let storage = Memory::new();
+        storage.write("file-1.grid", b"one").await.unwrap();
+
+        let url = storage
+            .download_url("file-1.grid", Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(url, "data:application/octet-stream;base64,b25l");
+        assert!(storage
+            .download_url("missing.grid", Duration::from_secs(60))
+            .await
+            .is_err());
+    }
+}
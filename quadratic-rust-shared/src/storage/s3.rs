@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::time::Duration;
+
+use crate::aws::{
+    client,
+    s3::{download_object, list_objects, presigned_download_url, upload_object},
+    Client,
+};
+use crate::error::{Aws, Result, SharedError};
+use crate::storage::{ObjectSummary, Storage};
+
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub region: String,
+    pub bucket: String,
+    pub provider_name: &'static str,
+    pub is_local: bool,
+}
+
+#[derive(Debug)]
+pub struct S3 {
+    pub client: Client,
+    pub bucket: String,
+}
+
+impl S3 {
+    pub async fn new(config: S3Config) -> Self {
+        let client = client(
+            &config.access_key_id,
+            &config.secret_access_key,
+            &config.region,
+            config.provider_name,
+            config.is_local,
+        )
+        .await;
+
+        S3 {
+            client,
+            bucket: config.bucket,
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for S3 {
+    async fn read(&self, key: &str) -> Result<Bytes> {
+        let file = download_object(&self.client, &self.bucket, key).await?;
+        let body = file.body.collect().await.map_err(|error| {
+            SharedError::Aws(Aws::S3(format!(
+                "Error reading file {key} from bucket {}: {:?}.",
+                self.bucket, error
+            )))
+        })?;
+
+        Ok(body.into_bytes())
+    }
+
+    async fn write(&self, key: &str, data: &[u8]) -> Result<()> {
+        upload_object(&self.client, &self.bucket, key, data).await?;
+
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectSummary>> {
+        list_objects(&self.client, &self.bucket, prefix).await
+    }
+
+    async fn download_url(&self, key: &str, expires_in: Duration) -> Result<String> {
+        presigned_download_url(&self.client, &self.bucket, key, expires_in).await
+    }
+
+    fn path(&self) -> &str {
+        &self.bucket
+    }
+}
+
+#[cfg(test)]
+mod tests {}
@@ -49,6 +49,21 @@ export const ConnectionTypeDetailsPostgresSchema = z.object({
   database: z.string().min(1, { message: 'Required' }),
   username: z.string().min(1, { message: 'Required' }),
   password: z.string().optional().transform(transformEmptyStringToUndefined),
+  // connect through an SSH bastion
+  ssh: z
+    .object({
+      host: z.string().min(1, { message: 'Required' }),
+      port: z.string().optional().transform(transformEmptyStringToUndefined),
+      username: z.string().min(1, { message: 'Required' }),
+      privateKey: z.string().min(1, { message: 'Required' }),
+      // the bastion's host key, as printed by `ssh-keygen -l`
+      hostKeyFingerprint: z.string().regex(/^SHA256:[A-Za-z0-9+/]{43}=?$/, {
+        message: 'Must be a SHA256 fingerprint, eg, SHA256:KoC1...',
+      }),
+    })
+    .optional(),
+  // only allow queries that read data
+  readOnly: z.boolean().optional(),
 });
 export const ConnectionTypeDetailsMysqlSchema = ConnectionTypeDetailsPostgresSchema;
 